@@ -0,0 +1,89 @@
+# Backend messages, keyed by the codes sent alongside the rendered text.
+# Add a language by copying this file to locales/<tag>.ftl and listing it in i18n.rs.
+
+## Deletion safety checks
+
+safety-not-found = Path does not exist: { $path }
+safety-system-store = { $path } is managed by Windows. { $title }: { $command }
+safety-app-data = { $path } holds Disk Analyser's own data
+safety-protected = Cannot delete protected system path: { $path }
+safety-portable-device = { $path } is on a phone or camera, which Disk Analyser only scans
+safety-in-use = File or directory is currently in use: { $path }
+safety-large-deletion = Large deletion ({ $size }). Please confirm this action.
+
+## Cleanup suggestions for Windows system stores
+
+suggestion-component-store-title = Clean up superseded components
+suggestion-component-store-description = Removes component versions replaced by later updates. Deleting files from WinSxS directly can break Windows Update and system repair.
+suggestion-update-downloads-title = Remove downloaded update files
+suggestion-update-downloads-description = Run Disk Cleanup, choose "Clean up system files" and select Windows Update Cleanup.
+suggestion-delivery-optimization-title = Clear the Delivery Optimization cache
+suggestion-delivery-optimization-description = Deletes update files cached for sharing with other PCs.
+suggestion-freeable = Up to { $size } can be freed.
+
+## Webhook notifications
+
+notification-scan-completed = Scan of { $root } finished: { $files ->
+        [one] { $files } file
+       *[other] { $files } files
+    }, { $size }
+notification-cleanup-executed = Cleanup deleted { $deleted ->
+        [one] { $deleted } item
+       *[other] { $deleted } items
+    } and freed { $size }
+notification-cleanup-partial = Cleanup deleted { $deleted ->
+        [one] { $deleted } item
+       *[other] { $deleted } items
+    } and freed { $size }; { $failed } could not be deleted
+notification-cleanup-trashed = Cleanup moved { $deleted ->
+        [one] { $deleted } item
+       *[other] { $deleted } items
+    } ({ $trashed }) to the trash
+notification-cleanup-trashed-partial = Cleanup moved { $deleted ->
+        [one] { $deleted } item
+       *[other] { $deleted } items
+    } ({ $trashed }) to the trash; { $failed } could not be moved
+notification-low-space = { $volume } is low on space: { $available } free of { $total }
+notification-test = Test notification from Disk Analyser
+
+## Ejecting volumes
+
+eject-busy-move = A copy or move is using { $path }
+eject-busy-read = Still reading { $path }
+
+## Recommendations
+
+recommendation-trash-title = Empty the trash
+recommendation-trash-description = { $path } holds files that were already deleted.
+recommendation-cache-title = Clear the { $name } cache
+recommendation-cache-description = Apps rebuild cached files when they need them again.
+recommendation-downloads-title = Review old downloads
+recommendation-downloads-description = { $count ->
+        [one] { $count } item
+       *[other] { $count } items
+    } in Downloads not touched for more than { $days } days.
+recommendation-duplicates-title = Remove duplicate copies
+recommendation-duplicates-description = { $count ->
+        [one] { $count } copy
+       *[other] { $count } copies
+    } can go while one of each stays on this volume.
+recommendation-large-unused-title = Review { $name }
+recommendation-large-unused-description = This large file hasn't been modified for { $days } days.
+recommendation-snapshot-title = Delete the local snapshot from { $label }
+recommendation-snapshot-description = Time Machine keeps local snapshots until the space is needed; the size is an estimate.
+recommendation-vacuum-title = Vacuum { $name }
+recommendation-vacuum-description = { $percent }% of this database is free pages; vacuuming it while its app is closed gives them back without losing data.
+
+## Explaining sizes
+
+explain-cache = A cache; apps rebuild what's in it when they need it.
+explain-trash = Files already deleted, kept until the trash is emptied.
+explain-bundle = Part of an app or library that is shown as a single file.
+explain-duplicates = { $percent }% of it is extra copies of files stored here more than once.
+explain-one-child = { $name } takes up { $percent }% of it.
+explain-category = { $percent }% of it is { $category } files.
+explain-spread = Spread over { $files } files; { $old_percent }% of it hasn't changed for over a year.
+
+## VM disks
+
+vm-compact-preparation = Shut the VM down first. Space the guest freed only comes back if it was zeroed inside the guest (for example with sdelete -z on Windows or zerofree on Linux) before { $tool } compacts the disk.
@@ -0,0 +1,187 @@
+use crate::persistence::{read_json_or_default, APP_IDENTIFIER};
+use crate::sandbox;
+use crate::settings::load_settings;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Command-line flag that forces read-only mode regardless of settings
+const READ_ONLY_FLAG: &str = "--read-only";
+
+/// Prefix of errors returned when an action is refused, so the frontend can tell them apart
+pub const POLICY_DENIED: &str = "PolicyDenied";
+
+/// Set once at startup from the command line
+static READ_ONLY_FROM_CLI: AtomicBool = AtomicBool::new(false);
+
+/// Restrictions an administrator places on managed installs; empty lists restrict nothing
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ManagedPolicy {
+    /// Names of commands to refuse, as the frontend invokes them (`delete_items_command`)
+    pub disabled_commands: Vec<String>,
+    /// Scans may only start at or below one of these
+    pub allowed_scan_roots: Vec<PathBuf>,
+}
+
+/// Machine-wide location of the managed policy, writable only by administrators
+fn managed_policy_file() -> PathBuf {
+    #[cfg(target_os = "macos")]
+    let dir = PathBuf::from("/Library/Application Support").join(APP_IDENTIFIER);
+    #[cfg(target_os = "windows")]
+    let dir =
+        PathBuf::from(std::env::var_os("ProgramData").unwrap_or_else(|| "C:\\ProgramData".into()))
+            .join(APP_IDENTIFIER);
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let dir = PathBuf::from("/etc").join(APP_IDENTIFIER);
+
+    dir.join("managed-policy.json")
+}
+
+/// The managed policy, read on every check so administrators' changes apply at once
+pub fn managed_policy() -> Result<ManagedPolicy, String> {
+    read_json_or_default(&managed_policy_file())
+}
+
+fn command_allowed(policy: &ManagedPolicy, command: &str) -> bool {
+    !policy.disabled_commands.iter().any(|c| c == command)
+}
+
+/// Whether `path` is inside an allowed root; both are resolved first so `..` can't escape
+fn scan_root_allowed(policy: &ManagedPolicy, path: &Path) -> bool {
+    if policy.allowed_scan_roots.is_empty() {
+        return true;
+    }
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    policy
+        .allowed_scan_roots
+        .iter()
+        .any(|root| path.starts_with(root.canonicalize().unwrap_or_else(|_| root.clone())))
+}
+
+/// Refuse a command the managed policy disables
+///
+/// Called once per command, by the invoke handler and by the server's dispatch; commands
+/// don't check themselves.
+///
+/// A policy file that exists but can't be read refuses everything, so a broken
+/// deployment fails closed.
+pub(crate) fn ensure_command_allowed(command: &str) -> Result<(), String> {
+    let policy = managed_policy().map_err(|e| format!("{}: {}", POLICY_DENIED, e))?;
+    if !command_allowed(&policy, command) {
+        return Err(format!(
+            "{}: {} is disabled by your administrator",
+            POLICY_DENIED, command
+        ));
+    }
+    Ok(())
+}
+
+/// Refuse a scan that starts outside the roots the managed policy allows, or outside the
+/// folders a sandboxed build was granted
+pub(crate) fn ensure_scan_root_allowed(path: &Path) -> Result<(), String> {
+    sandbox::ensure_in_granted_scope(path)?;
+    let policy = managed_policy().map_err(|e| format!("{}: {}", POLICY_DENIED, e))?;
+    if !scan_root_allowed(&policy, path) {
+        return Err(format!(
+            "{}: scanning {} is not allowed by your administrator",
+            POLICY_DENIED,
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the app may change anything on disk, and why not
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AccessMode {
+    pub read_only: bool,
+    /// Read-only was forced with `--read-only`; the setting can't turn it off
+    pub forced_by_cli: bool,
+}
+
+fn has_read_only_flag(mut args: impl Iterator<Item = String>) -> bool {
+    args.any(|arg| arg == READ_ONLY_FLAG)
+}
+
+/// Read the command line; called once before the app starts
+pub fn init_from_args() {
+    READ_ONLY_FROM_CLI.store(has_read_only_flag(std::env::args()), Ordering::Relaxed);
+}
+
+pub fn access_mode() -> AccessMode {
+    let forced_by_cli = READ_ONLY_FROM_CLI.load(Ordering::Relaxed);
+    AccessMode {
+        read_only: forced_by_cli || load_settings().is_ok_and(|s| s.read_only),
+        forced_by_cli,
+    }
+}
+
+/// Refuse `action` in read-only mode
+///
+/// Every command that deletes, moves, compresses or trashes user files calls this first,
+/// so the mode holds no matter what the frontend shows.
+pub(crate) fn ensure_writable(action: &str) -> Result<(), String> {
+    if access_mode().read_only {
+        return Err(format!(
+            "{}: {} is not allowed in read-only mode",
+            POLICY_DENIED, action
+        ));
+    }
+    Ok(())
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn get_access_mode_command() -> Result<AccessMode, String> {
+    Ok(access_mode())
+}
+
+/// The managed policy in force, so the UI can hide what it disables
+#[tauri::command]
+pub async fn get_managed_policy_command() -> Result<ManagedPolicy, String> {
+    managed_policy()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_read_only_flag() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert!(has_read_only_flag(
+            args(&["disk-analyser", "--read-only"]).into_iter()
+        ));
+        assert!(!has_read_only_flag(args(&["disk-analyser"]).into_iter()));
+        assert!(!has_read_only_flag(
+            args(&["disk-analyser", "--read-only-ish"]).into_iter()
+        ));
+    }
+
+    #[test]
+    fn test_managed_policy() {
+        let root = std::env::temp_dir().join("test_managed_policy");
+        fs::create_dir_all(root.join("allowed/project")).unwrap();
+        fs::create_dir_all(root.join("other")).unwrap();
+        let policy = ManagedPolicy {
+            disabled_commands: vec!["delete_items_command".to_string()],
+            allowed_scan_roots: vec![root.join("allowed")],
+        };
+
+        let inside = scan_root_allowed(&policy, &root.join("allowed/project"));
+        let escaped = scan_root_allowed(&policy, &root.join("allowed/../other"));
+        let outside = scan_root_allowed(&policy, &root.join("other"));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(inside);
+        assert!(!escaped);
+        assert!(!outside);
+        assert!(!command_allowed(&policy, "delete_items_command"));
+        assert!(command_allowed(&policy, "find_duplicates_command"));
+        assert!(scan_root_allowed(&ManagedPolicy::default(), Path::new("/")));
+    }
+}
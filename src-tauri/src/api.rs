@@ -0,0 +1,154 @@
+use crate::events::EventSink;
+use crate::types::SCHEMA_VERSION;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// Bumped whenever a command is renamed or removed, or its arguments or result change
+/// incompatibly; additive changes keep the version
+pub const API_VERSION: u32 = 2;
+
+/// Oldest API a frontend may be built against; its deprecated commands still work
+pub const MIN_SUPPORTED_API_VERSION: u32 = API_VERSION - 1;
+
+/// A command kept under its old name for one more API version
+///
+/// Renamed argument or result fields are kept working the same way, with
+/// `#[serde(alias = "old_name")]` on the new field, and listed here under the command.
+struct Deprecation {
+    command: &'static str,
+    replacement: Option<&'static str>,
+    deprecated_in: u32,
+}
+
+const DEPRECATIONS: &[Deprecation] = &[Deprecation {
+    command: "open_full_disk_access_settings",
+    replacement: Some("open_full_disk_access_settings_command"),
+    deprecated_in: 2,
+}];
+
+/// Payload of the `api-deprecation` event, sent the first time a deprecated command is used
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeprecationWarning {
+    pub command: String,
+    pub replacement: Option<String>,
+    pub deprecated_in: u32,
+    /// The first API version without the command
+    pub removed_in: u32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ApiVersion {
+    pub api_version: u32,
+    pub min_supported: u32,
+    pub app_version: String,
+    /// Version of saved scan files, which changes independently of the API
+    pub schema_version: u32,
+    pub deprecations: Vec<DeprecationWarning>,
+}
+
+/// Deprecated commands already reported this session
+static WARNED: once_cell::sync::Lazy<Mutex<HashSet<&'static str>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashSet::new()));
+
+impl Deprecation {
+    fn warning(&self) -> DeprecationWarning {
+        let removed_in = self.deprecated_in + 1;
+        let message = match self.replacement {
+            Some(replacement) => format!(
+                "{} is deprecated and will be removed in API version {}; use {}",
+                self.command, removed_in, replacement
+            ),
+            None => format!(
+                "{} is deprecated and will be removed in API version {}",
+                self.command, removed_in
+            ),
+        };
+        DeprecationWarning {
+            command: self.command.to_string(),
+            replacement: self.replacement.map(str::to_string),
+            deprecated_in: self.deprecated_in,
+            removed_in,
+            message,
+        }
+    }
+}
+
+fn deprecation(command: &str) -> Option<&'static Deprecation> {
+    DEPRECATIONS.iter().find(|d| d.command == command)
+}
+
+/// The command to run for `command`, following renames
+pub(crate) fn current_name(command: &str) -> &str {
+    deprecation(command)
+        .and_then(|d| d.replacement)
+        .unwrap_or(command)
+}
+
+/// Report that a deprecated command was used, once per command per session
+pub(crate) fn warn_deprecated<E: EventSink>(events: &E, command: &str) {
+    let Some(deprecation) = deprecation(command) else {
+        return;
+    };
+    if WARNED.lock().unwrap().insert(deprecation.command) {
+        events.send("api-deprecation", &deprecation.warning());
+    }
+}
+
+pub fn api_version() -> ApiVersion {
+    ApiVersion {
+        api_version: API_VERSION,
+        min_supported: MIN_SUPPORTED_API_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version: SCHEMA_VERSION,
+        deprecations: DEPRECATIONS.iter().map(Deprecation::warning).collect(),
+    }
+}
+
+// Tauri commands
+
+/// API version and deprecated commands, so a frontend can check it's compatible
+#[tauri::command]
+pub async fn get_api_version_command() -> Result<ApiVersion, String> {
+    Ok(api_version())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl EventSink for Recorder {
+        fn send<S: Serialize + Clone>(&self, event: &str, _payload: &S) {
+            self.0.lock().unwrap().push(event.to_string());
+        }
+    }
+
+    #[test]
+    fn test_deprecated_commands_warn_once() {
+        assert_eq!(
+            current_name("open_full_disk_access_settings"),
+            "open_full_disk_access_settings_command"
+        );
+        assert_eq!(current_name("cancel_scan_command"), "cancel_scan_command");
+
+        let warning = deprecation("open_full_disk_access_settings")
+            .unwrap()
+            .warning();
+        assert_eq!(warning.removed_in, API_VERSION + 1);
+        assert!(warning
+            .message
+            .ends_with("use open_full_disk_access_settings_command"));
+
+        let recorder = Recorder::default();
+        warn_deprecated(&recorder, "cancel_scan_command");
+        warn_deprecated(&recorder, "open_full_disk_access_settings");
+        warn_deprecated(&recorder, "open_full_disk_access_settings");
+        assert_eq!(*recorder.0.lock().unwrap(), ["api-deprecation"]);
+    }
+}
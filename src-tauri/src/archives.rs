@@ -0,0 +1,271 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    SevenZip,
+}
+
+/// A top-level file or folder inside an archive
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub is_directory: bool,
+    /// Uncompressed size of everything under this entry
+    pub size: u64,
+    /// Stored size, when the format records it per file (zip, 7z)
+    pub compressed_size: Option<u64>,
+    pub file_count: u64,
+}
+
+/// What an archive holds, read from its index without extracting anything
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArchiveListing {
+    pub path: PathBuf,
+    pub format: ArchiveFormat,
+    /// Size of the archive file itself
+    pub archive_size: u64,
+    pub uncompressed_size: u64,
+    pub file_count: u64,
+    /// Largest first
+    pub entries: Vec<ArchiveEntry>,
+}
+
+/// One file or directory record as the archive lists it
+struct RawEntry {
+    path: String,
+    is_directory: bool,
+    size: u64,
+    compressed_size: Option<u64>,
+}
+
+fn detect_format(path: &Path) -> Option<ArchiveFormat> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveFormat::TarGz)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveFormat::Tar)
+    } else if name.ends_with(".zip") {
+        Some(ArchiveFormat::Zip)
+    } else if name.ends_with(".7z") {
+        Some(ArchiveFormat::SevenZip)
+    } else {
+        None
+    }
+}
+
+fn read_zip(file: File) -> Result<Vec<RawEntry>, String> {
+    let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+    (0..archive.len())
+        .map(|i| {
+            // Raw access reads the central directory record without decompressing
+            let entry = archive.by_index_raw(i).map_err(|e| e.to_string())?;
+            Ok(RawEntry {
+                path: entry.name().to_string(),
+                is_directory: entry.is_dir(),
+                size: entry.size(),
+                compressed_size: Some(entry.compressed_size()),
+            })
+        })
+        .collect()
+}
+
+fn read_tar<R: std::io::Read>(entries: tar::Entries<'_, R>) -> Result<Vec<RawEntry>, String> {
+    entries
+        .map(|entry| {
+            let entry = entry.map_err(|e| e.to_string())?;
+            Ok(RawEntry {
+                path: entry
+                    .path()
+                    .map_err(|e| e.to_string())?
+                    .to_string_lossy()
+                    .to_string(),
+                is_directory: entry.header().entry_type().is_dir(),
+                size: entry.header().size().map_err(|e| e.to_string())?,
+                compressed_size: None,
+            })
+        })
+        .collect()
+}
+
+fn read_seven_zip(mut file: File, length: u64) -> Result<Vec<RawEntry>, String> {
+    let archive = sevenz_rust::Archive::read(&mut file, length, &[]).map_err(|e| e.to_string())?;
+    Ok(archive
+        .files
+        .into_iter()
+        .map(|entry| RawEntry {
+            path: entry.name,
+            is_directory: entry.is_directory,
+            size: entry.size,
+            // Solid archives compress many files into one stream, so this is often 0
+            compressed_size: Some(entry.compressed_size).filter(|&s| s > 0),
+        })
+        .collect())
+}
+
+/// Fold an archive's records into top-level entries
+fn summarize(
+    path: &Path,
+    format: ArchiveFormat,
+    archive_size: u64,
+    records: Vec<RawEntry>,
+) -> ArchiveListing {
+    let mut top_level: HashMap<String, ArchiveEntry> = HashMap::new();
+    let mut uncompressed_size = 0;
+    let mut file_count = 0;
+
+    for record in records {
+        let mut parts = record
+            .path
+            .split(['/', '\\'])
+            .filter(|p| !p.is_empty() && *p != ".");
+        let Some(name) = parts.next() else {
+            continue;
+        };
+        let nested = parts.next().is_some();
+
+        let entry = top_level
+            .entry(name.to_string())
+            .or_insert_with(|| ArchiveEntry {
+                name: name.to_string(),
+                is_directory: false,
+                size: 0,
+                compressed_size: Some(0),
+                file_count: 0,
+            });
+        entry.is_directory |= nested || record.is_directory;
+        if record.is_directory {
+            continue;
+        }
+
+        entry.size += record.size;
+        entry.file_count += 1;
+        entry.compressed_size = entry
+            .compressed_size
+            .zip(record.compressed_size)
+            .map(|(a, b)| a + b);
+        uncompressed_size += record.size;
+        file_count += 1;
+    }
+
+    let mut entries: Vec<ArchiveEntry> = top_level.into_values().collect();
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    ArchiveListing {
+        path: path.to_path_buf(),
+        format,
+        archive_size,
+        uncompressed_size,
+        file_count,
+        entries,
+    }
+}
+
+/// List an archive's top-level entries from its index
+///
+/// Zip and 7z keep a directory we can read directly. Tar has no index, so the headers
+/// are walked; a compressed tarball has to be decompressed as a stream to reach them.
+pub fn inspect_archive(path: &Path) -> Result<ArchiveListing, String> {
+    let format = detect_format(path)
+        .ok_or_else(|| format!("{} is not a zip, tar or 7z archive", path.display()))?;
+    let file = File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let archive_size = file.metadata().map_err(|e| e.to_string())?.len();
+
+    let records = match format {
+        ArchiveFormat::Zip => read_zip(file)?,
+        // Plain tar can seek past file contents instead of reading them
+        ArchiveFormat::Tar => read_tar(
+            tar::Archive::new(file)
+                .entries_with_seek()
+                .map_err(|e| e.to_string())?,
+        )?,
+        ArchiveFormat::TarGz => {
+            let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(BufReader::new(file)));
+            read_tar(archive.entries().map_err(|e| e.to_string())?)?
+        }
+        ArchiveFormat::SevenZip => read_seven_zip(file, archive_size)?,
+    };
+
+    Ok(summarize(path, format, archive_size, records))
+}
+
+// Tauri commands
+
+/// Peek inside a zip, tar or 7z archive found in a scan without extracting it
+#[tauri::command]
+pub async fn inspect_archive_command(path: String) -> Result<ArchiveListing, String> {
+    tokio::task::spawn_blocking(move || inspect_archive(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn test_inspect_zip_and_tar() {
+        let dir = std::env::temp_dir().join("test_archives");
+        fs::create_dir_all(&dir).unwrap();
+
+        let zip_path = dir.join("backup.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        zip.add_directory("photos/", options).unwrap();
+        for (name, bytes) in [
+            ("photos/a.jpg", 300),
+            ("photos/b.jpg", 200),
+            ("notes.txt", 50),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(&vec![1u8; bytes]).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let tar_path = dir.join("backup.tar");
+        let mut tar = tar::Builder::new(File::create(&tar_path).unwrap());
+        for (name, bytes) in [
+            ("./src/main.rs", 120usize),
+            ("./src/lib.rs", 80),
+            ("./README", 10),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes as u64);
+            header.set_cksum();
+            tar.append_data(&mut header, name, &vec![0u8; bytes][..])
+                .unwrap();
+        }
+        tar.finish().unwrap();
+        drop(tar);
+
+        let zip_listing = inspect_archive(&zip_path).unwrap();
+        let tar_listing = inspect_archive(&tar_path).unwrap();
+        let unsupported = inspect_archive(&dir.join("disk.img"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(zip_listing.format, ArchiveFormat::Zip);
+        assert_eq!(zip_listing.file_count, 3);
+        assert_eq!(zip_listing.uncompressed_size, 550);
+        assert_eq!(zip_listing.entries[0].name, "photos");
+        assert!(zip_listing.entries[0].is_directory);
+        assert_eq!(zip_listing.entries[0].size, 500);
+        assert_eq!(zip_listing.entries[0].compressed_size, Some(500));
+
+        assert_eq!(tar_listing.entries.len(), 2);
+        assert_eq!(tar_listing.entries[0].name, "src");
+        assert_eq!(tar_listing.entries[0].file_count, 2);
+        assert_eq!(tar_listing.entries[0].compressed_size, None);
+        assert!(unsupported.is_err());
+    }
+}
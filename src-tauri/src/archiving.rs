@@ -0,0 +1,846 @@
+//! Archiving files instead of deleting them outright
+//!
+//! The chosen items are packed into one zip, which is read back in full before anything is
+//! deleted. Entries of 2 GiB or more are written as Zip64, so large media survive the
+//! 4 GiB limit of plain zip. Entries are stored uncompressed: media and other big files
+//! barely compress, and it keeps archiving as fast as copying.
+//!
+//! Next to each archive goes `<archive>.manifest.json`, listing every entry with where it
+//! came from, its size, BLAKE3 hash and modification time. Each run is recorded in the
+//! operation journal, from which an archive can later be verified against its manifest or
+//! restored, in full or in part.
+
+use crate::access;
+use crate::moves::EntryKind;
+use crate::persistence::{data_file, read_sealed_json_or_default, write_sealed_json};
+use crate::safety::{self, DeletionResult};
+use crate::space_guard::{self, PartialOutput};
+use chrono::{Datelike, Timelike};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::Window;
+use zip::write::{ExtendedFileOptions, FileOptions};
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Entries at least this large are written as Zip64; well under 4 GiB, so a file that grows
+/// a little while it is being read still fits. One that grows past 4 GiB is started over.
+const ZIP64_THRESHOLD: u64 = 2 << 30;
+
+/// Extra field holding an entry's extended attributes, as repeated
+/// (u16 name length, name, u16 value length, value), little-endian
+const XATTR_FIELD: u16 = 0x7861;
+
+/// Extended attributes as (name, value) pairs
+type Xattrs = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Operation journal: one record per archiving run, oldest first
+const JOURNAL_FILE: &str = "archive_journal.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ArchiveOptions {
+    /// Store symlinks as links; otherwise they are left out and reported as skipped
+    pub symlinks: bool,
+    /// Keep Unix permission bits
+    pub permissions: bool,
+    /// Keep extended attributes (Unix), in an extra field of each entry; they are set again
+    /// on restore
+    pub xattrs: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArchiveResult {
+    /// Id of the run in the operation journal
+    pub operation_id: u64,
+    pub archive: PathBuf,
+    /// Manifest written next to the archive
+    pub manifest: PathBuf,
+    pub files: u64,
+    pub bytes: u64,
+    /// Entries written as Zip64
+    pub zip64_entries: u64,
+    /// Entries left out of the archive, with why
+    pub skipped: Vec<String>,
+    /// Set when the originals were deleted after the archive checked out
+    pub deletion: Option<DeletionResult>,
+}
+
+/// One archived file, directory or link
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ManifestEntry {
+    /// Name inside the archive; directories end in `/`
+    pub name: String,
+    /// Where it was archived from
+    pub source: PathBuf,
+    pub kind: EntryKind,
+    pub size: u64,
+    /// BLAKE3 of the contents, for files
+    pub hash: Option<String>,
+    /// Unix timestamp (seconds)
+    pub modified: i64,
+}
+
+/// Everything in an archive, written next to it as `<archive>.manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArchiveManifest {
+    pub archive: PathBuf,
+    /// Unix timestamp (seconds)
+    pub created_at: i64,
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// One archiving run in the operation journal
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArchiveOperation {
+    pub id: u64,
+    pub archive: PathBuf,
+    pub manifest: PathBuf,
+    /// Unix timestamp (seconds)
+    pub created_at: i64,
+    pub sources: Vec<PathBuf>,
+    pub files: u64,
+    pub bytes: u64,
+    /// Whether the originals were deleted once the archive checked out
+    pub originals_deleted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RestoreFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RestoreResult {
+    /// Files and links written back, where they were written
+    pub restored: Vec<PathBuf>,
+    /// Files whose contents matched the manifest
+    pub verified: u64,
+    /// Entries that were missing, damaged or in the way of an existing file
+    pub failed: Vec<RestoreFailure>,
+}
+
+/// `path` in the `\\?\` form Windows needs to open paths beyond MAX_PATH
+#[cfg(windows)]
+fn long_path(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    if !path.is_absolute() || text.starts_with(r"\\?\") {
+        path.to_path_buf()
+    } else if let Some(share) = text.strip_prefix(r"\\") {
+        PathBuf::from(format!(r"\\?\UNC\{}", share))
+    } else {
+        PathBuf::from(format!(r"\\?\{}", text))
+    }
+}
+
+#[cfg(not(windows))]
+fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Name of an entry inside the archive, always with `/` separators
+fn entry_name(base: &str, relative: &Path) -> String {
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .fold(base.to_string(), |name, part| format!("{}/{}", name, part))
+}
+
+fn zip_time(modified: SystemTime) -> Option<zip::DateTime> {
+    let local = chrono::DateTime::<chrono::Local>::from(modified);
+    zip::DateTime::from_date_and_time(
+        u16::try_from(local.year()).ok()?,
+        local.month() as u8,
+        local.day() as u8,
+        local.hour() as u8,
+        local.minute() as u8,
+        local.second() as u8,
+    )
+    .ok()
+}
+
+#[cfg(unix)]
+fn encode_xattrs(path: &Path) -> io::Result<Vec<u8>> {
+    let mut field = Vec::new();
+    for name in xattr::list(path)? {
+        let Some(value) = xattr::get(path, &name)? else {
+            continue;
+        };
+        let name = name.as_encoded_bytes();
+        for part in [name, value.as_slice()] {
+            let len = u16::try_from(part.len())
+                .map_err(|_| io::Error::other("extended attribute too large"))?;
+            field.extend_from_slice(&len.to_le_bytes());
+            field.extend_from_slice(part);
+        }
+    }
+    Ok(field)
+}
+
+#[cfg(not(unix))]
+fn encode_xattrs(_path: &Path) -> io::Result<Vec<u8>> {
+    Ok(Vec::new())
+}
+
+/// The (name, value) pairs in an entry's `XATTR_FIELD`, if its extra data has one
+fn decode_xattrs(extra: &[u8]) -> Result<Xattrs, String> {
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+        if bytes.len() < len {
+            return Err("Damaged extra field".to_string());
+        }
+        let (taken, rest) = bytes.split_at(len);
+        *bytes = rest;
+        Ok(taken)
+    }
+    fn take_u16(bytes: &mut &[u8]) -> Result<usize, String> {
+        let raw = take(bytes, 2)?;
+        Ok(u16::from_le_bytes([raw[0], raw[1]]) as usize)
+    }
+
+    let mut fields = extra;
+    while !fields.is_empty() {
+        let id = take_u16(&mut fields)?;
+        let len = take_u16(&mut fields)?;
+        let mut field = take(&mut fields, len)?;
+        if id != XATTR_FIELD as usize {
+            continue;
+        }
+        let mut pairs = Vec::new();
+        while !field.is_empty() {
+            let len = take_u16(&mut field)?;
+            let name = take(&mut field, len)?.to_vec();
+            let len = take_u16(&mut field)?;
+            pairs.push((name, take(&mut field, len)?.to_vec()));
+        }
+        return Ok(pairs);
+    }
+    Ok(Vec::new())
+}
+
+#[cfg(unix)]
+fn apply_xattrs(path: &Path, xattrs: &Xattrs) -> Result<(), String> {
+    use std::os::unix::ffi::OsStrExt;
+    for (name, value) in xattrs {
+        let name = std::ffi::OsStr::from_bytes(name);
+        xattr::set(path, name, value).map_err(|e| {
+            format!(
+                "Cannot restore extended attribute {}: {}",
+                name.to_string_lossy(),
+                e
+            )
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn apply_xattrs(_path: &Path, _xattrs: &Xattrs) -> Result<(), String> {
+    Ok(())
+}
+
+/// Options for one entry, with whatever metadata `options` asks to keep
+fn entry_options(
+    path: &Path,
+    metadata: &fs::Metadata,
+    options: &ArchiveOptions,
+    skipped: &mut Vec<String>,
+) -> FileOptions<'static, ExtendedFileOptions> {
+    let mut entry =
+        FileOptions::<ExtendedFileOptions>::default().compression_method(CompressionMethod::Stored);
+    if let Some(time) = metadata.modified().ok().and_then(zip_time) {
+        entry = entry.last_modified_time(time);
+    }
+    #[cfg(unix)]
+    if options.permissions {
+        use std::os::unix::fs::PermissionsExt;
+        entry = entry.unix_permissions(metadata.permissions().mode());
+    }
+    if options.xattrs {
+        let added = encode_xattrs(path).and_then(|field| {
+            if !field.is_empty() {
+                entry
+                    .add_extra_data(XATTR_FIELD, field.into_boxed_slice(), false)
+                    .map_err(io::Error::other)?;
+            }
+            Ok(())
+        });
+        if let Err(e) = added {
+            skipped.push(format!("Extended attributes of {}: {}", path.display(), e));
+        }
+    }
+    entry
+}
+
+/// Whether a file is now too large for an entry without Zip64
+fn grew_past_zip32(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.len() > u32::MAX as u64)
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Copy `reader` into `writer`, returning the bytes copied and their BLAKE3 hash
+fn copy_hashed(reader: &mut impl Read, writer: &mut impl Write) -> io::Result<(u64, String)> {
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; 1 << 16];
+    let mut copied = 0;
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buffer[..n]);
+        writer.write_all(&buffer[..n])?;
+        copied += n as u64;
+    }
+    Ok((copied, hasher.finalize().to_hex().to_string()))
+}
+
+/// Pack `sources` into a zip at `writer`, each under its own name, listing every entry in
+/// `manifest`
+fn write_archive<W: Write + io::Seek>(
+    sources: &[PathBuf],
+    writer: W,
+    options: &ArchiveOptions,
+    result: &mut ArchiveResult,
+    manifest: &mut Vec<ManifestEntry>,
+) -> Result<W, String> {
+    let zip_error = |e: zip::result::ZipError| format!("Cannot write archive: {}", e);
+    let mut zip = ZipWriter::new(writer);
+    for source in sources {
+        let base = source
+            .file_name()
+            .ok_or_else(|| format!("Cannot archive {}", source.display()))?
+            .to_string_lossy()
+            .to_string();
+        let root = long_path(source);
+        for entry in walkdir::WalkDir::new(&root).follow_links(false) {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let relative = entry.path().strip_prefix(&root).unwrap_or(Path::new(""));
+            let name = entry_name(&base, relative);
+            let metadata = entry.metadata().map_err(|e| e.to_string())?;
+            let io_error = |e: io::Error| format!("Cannot archive {}: {}", name, e);
+            let mut listed = ManifestEntry {
+                name: name.clone(),
+                source: if relative.as_os_str().is_empty() {
+                    source.clone()
+                } else {
+                    source.join(relative)
+                },
+                kind: EntryKind::File,
+                size: 0,
+                hash: None,
+                modified: metadata.modified().map(unix_seconds).unwrap_or(0),
+            };
+
+            if entry.file_type().is_symlink() {
+                if !options.symlinks {
+                    result.skipped.push(format!("Symlink {}", name));
+                    continue;
+                }
+                let target = fs::read_link(entry.path()).map_err(io_error)?;
+                let link_options =
+                    entry_options(entry.path(), &metadata, options, &mut result.skipped);
+                zip.add_symlink(&name, target.to_string_lossy(), link_options)
+                    .map_err(zip_error)?;
+                listed.kind = EntryKind::Symlink;
+            } else if entry.file_type().is_dir() {
+                let dir_options =
+                    entry_options(entry.path(), &metadata, options, &mut result.skipped);
+                listed.name = format!("{}/", name);
+                listed.kind = EntryKind::Directory;
+                zip.add_directory(&listed.name, dir_options)
+                    .map_err(zip_error)?;
+            } else {
+                let file_options =
+                    entry_options(entry.path(), &metadata, options, &mut result.skipped);
+                let mut zip64 = metadata.len() >= ZIP64_THRESHOLD;
+                let (size, hash) = loop {
+                    zip.start_file(&name, file_options.clone().large_file(zip64))
+                        .map_err(zip_error)?;
+                    let mut file = File::open(entry.path()).map_err(io_error)?;
+                    match copy_hashed(&mut file, &mut zip) {
+                        // It grew past what plain zip can hold while being read; the writer
+                        // has dropped the entry, so write it again as Zip64
+                        Err(_) if !zip64 && grew_past_zip32(entry.path()) => zip64 = true,
+                        copied => break copied.map_err(io_error)?,
+                    }
+                };
+                if zip64 {
+                    result.zip64_entries += 1;
+                }
+                listed.size = size;
+                listed.hash = Some(hash);
+                result.bytes += size;
+                result.files += 1;
+            }
+            manifest.push(listed);
+        }
+    }
+    zip.finish().map_err(zip_error)
+}
+
+/// `<archive>.manifest.json`, next to the archive
+fn manifest_path(archive: &Path) -> PathBuf {
+    let mut name = archive.file_name().unwrap_or_default().to_os_string();
+    name.push(".manifest.json");
+    archive.with_file_name(name)
+}
+
+fn read_manifest(path: &Path) -> Result<ArchiveManifest, String> {
+    let json = fs::read(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    serde_json::from_slice(&json).map_err(|e| format!("Cannot read {}: {}", path.display(), e))
+}
+
+fn check_hash(listed: &ManifestEntry, hash: &str) -> Result<(), String> {
+    match &listed.hash {
+        Some(expected) if expected != hash => Err("Contents don't match the manifest".to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn make_symlink(points_to: &Path, at: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(points_to, at)
+}
+
+#[cfg(windows)]
+fn make_symlink(points_to: &Path, at: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(points_to, at)
+}
+
+/// Where an entry goes back to: where it came from, or under `destination` by its name
+fn restore_target(listed: &ManifestEntry, destination: Option<&Path>) -> PathBuf {
+    match destination {
+        Some(dir) => listed
+            .name
+            .split('/')
+            .filter(|part| !part.is_empty() && *part != "..")
+            .fold(dir.to_path_buf(), |path, part| path.join(part)),
+        None => listed.source.clone(),
+    }
+}
+
+/// What an entry carries besides its contents, as kept by `ArchiveOptions`
+#[derive(Debug, Default)]
+struct KeptMetadata {
+    mode: Option<u32>,
+    xattrs: Xattrs,
+}
+
+/// Check one entry against the manifest, writing it to `target` unless that is `None`
+///
+/// Files are written to a partial file first, so one that doesn't match is never left in
+/// place.
+fn restore_entry(
+    entry: &mut impl Read,
+    kept: &KeptMetadata,
+    listed: &ManifestEntry,
+    target: Option<&Path>,
+) -> Result<(), String> {
+    let io_error = |e: io::Error| e.to_string();
+    let Some(target) = target else {
+        // The CRC is checked once an entry has been read to its end
+        let (_, hash) = copy_hashed(entry, &mut io::sink()).map_err(io_error)?;
+        return check_hash(listed, &hash);
+    };
+    let target = long_path(target);
+    if listed.kind == EntryKind::Directory {
+        fs::create_dir_all(&target).map_err(io_error)?;
+        return apply_xattrs(&target, &kept.xattrs);
+    }
+    if fs::symlink_metadata(&target).is_ok() {
+        return Err("Already exists".to_string());
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    if listed.kind == EntryKind::Symlink {
+        let mut points_to = String::new();
+        entry.read_to_string(&mut points_to).map_err(io_error)?;
+        return make_symlink(Path::new(&points_to), &target).map_err(io_error);
+    }
+
+    let output = PartialOutput::new(&target, listed.size)?;
+    let mut file = BufWriter::new(File::create(output.path()).map_err(io_error)?);
+    let (_, hash) = copy_hashed(entry, &mut file).map_err(io_error)?;
+    check_hash(listed, &hash)?;
+    let file = file.into_inner().map_err(|e| io_error(e.into_error()))?;
+    let modified = UNIX_EPOCH + Duration::from_secs(listed.modified.max(0) as u64);
+    file.set_modified(modified).map_err(io_error)?;
+    // Before the permissions, which may make the file read-only
+    apply_xattrs(output.path(), &kept.xattrs)?;
+    #[cfg(unix)]
+    if let Some(mode) = kept.mode {
+        use std::os::unix::fs::PermissionsExt;
+        // Never setuid, setgid or sticky bits from an archive that may have been tampered with
+        file.set_permissions(fs::Permissions::from_mode(mode & 0o777))
+            .map_err(io_error)?;
+    }
+    drop(file);
+    output.commit()
+}
+
+/// Check `entries` of `archive` against the manifest, writing each back too unless
+/// `verify_only`
+fn restore_entries(
+    archive: &Path,
+    entries: &[&ManifestEntry],
+    destination: Option<&Path>,
+    verify_only: bool,
+) -> Result<RestoreResult, String> {
+    let file =
+        File::open(archive).map_err(|e| format!("Cannot open {}: {}", archive.display(), e))?;
+    let mut zip = ZipArchive::new(BufReader::new(file))
+        .map_err(|e| format!("Cannot read {}: {}", archive.display(), e))?;
+    let mut result = RestoreResult {
+        restored: Vec::new(),
+        verified: 0,
+        failed: Vec::new(),
+    };
+    for listed in entries {
+        let target = restore_target(listed, destination);
+        let written = (!verify_only).then_some(target.as_path());
+        let outcome = match zip.by_name(&listed.name) {
+            Ok(mut entry) => decode_xattrs(entry.extra_data().unwrap_or_default())
+                .map(|xattrs| KeptMetadata {
+                    mode: entry.unix_mode(),
+                    xattrs,
+                })
+                .and_then(|kept| restore_entry(&mut entry, &kept, listed, written)),
+            Err(e) => Err(format!("Not in the archive: {}", e)),
+        };
+        match outcome {
+            Ok(()) => {
+                if listed.kind == EntryKind::File {
+                    result.verified += 1;
+                }
+                if !verify_only && listed.kind != EntryKind::Directory {
+                    result.restored.push(target);
+                }
+            }
+            Err(error) => result.failed.push(RestoreFailure {
+                path: target,
+                error,
+            }),
+        }
+    }
+    Ok(result)
+}
+
+/// Read every entry back against the manifest, so a truncated or corrupt archive is caught
+/// before deleting
+fn verify_archive(archive: &Path, entries: &[ManifestEntry]) -> Result<(), String> {
+    let corrupt = |e: String| format!("The archive didn't check out, nothing was deleted: {}", e);
+    let all: Vec<&ManifestEntry> = entries.iter().collect();
+    let check = restore_entries(archive, &all, None, true).map_err(corrupt)?;
+    match check.failed.first() {
+        Some(failure) => Err(corrupt(format!(
+            "{}: {}",
+            failure.path.display(),
+            failure.error
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn archive_items(
+    sources: &[PathBuf],
+    destination: &Path,
+    options: &ArchiveOptions,
+) -> Result<ArchiveResult, String> {
+    let total: u64 = sources
+        .iter()
+        .map(|s| safety::calculate_path_size(s).unwrap_or(0))
+        .sum();
+    let output = PartialOutput::new(destination, total)?;
+    let write_error = |e: io::Error| format!("Cannot write {}: {}", destination.display(), e);
+    let file = File::create(output.path()).map_err(write_error)?;
+
+    let mut result = ArchiveResult {
+        operation_id: 0,
+        archive: destination.to_path_buf(),
+        manifest: manifest_path(destination),
+        files: 0,
+        bytes: 0,
+        zip64_entries: 0,
+        skipped: Vec::new(),
+        deletion: None,
+    };
+    let mut entries = Vec::new();
+    let writer = write_archive(
+        sources,
+        BufWriter::new(file),
+        options,
+        &mut result,
+        &mut entries,
+    )?;
+    writer
+        .into_inner()
+        .map_err(|e| write_error(e.into_error()))?
+        .sync_all()
+        .map_err(write_error)?;
+    verify_archive(output.path(), &entries)?;
+    output.commit()?;
+
+    let manifest = ArchiveManifest {
+        archive: destination.to_path_buf(),
+        created_at: chrono::Utc::now().timestamp(),
+        entries,
+    };
+    let json =
+        serde_json::to_vec_pretty(&manifest).map_err(|e| format!("Cannot serialize: {}", e))?;
+    space_guard::write_guarded(&result.manifest, &json)?;
+    Ok(result)
+}
+
+fn load_journal() -> Result<Vec<ArchiveOperation>, String> {
+    read_sealed_json_or_default(&data_file(JOURNAL_FILE)?)
+}
+
+/// Add a run to the operation journal, returning the id it was given
+fn record_operation(mut operation: ArchiveOperation) -> Result<u64, String> {
+    let mut journal = load_journal()?;
+    operation.id = journal.last().map_or(1, |last| last.id + 1);
+    let id = operation.id;
+    journal.push(operation);
+    write_sealed_json(&data_file(JOURNAL_FILE)?, &journal)?;
+    Ok(id)
+}
+
+// Tauri commands
+
+/// Archive `paths` into a zip at `destination`, then delete them if `delete_originals`
+///
+/// The originals are only deleted once the whole archive has been read back intact. The
+/// run is recorded in the operation journal whether or not they were.
+#[tauri::command]
+pub async fn archive_items_command(
+    paths: Vec<String>,
+    destination: String,
+    options: Option<ArchiveOptions>,
+    delete_originals: Option<bool>,
+    window: Window,
+) -> Result<ArchiveResult, String> {
+    access::ensure_writable("Archiving files")?;
+    let sources: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+    let destination = PathBuf::from(destination);
+    if let Some(source) = sources.iter().find(|s| destination.starts_with(s)) {
+        return Err(format!("Cannot archive {} into itself", source.display()));
+    }
+
+    let archived = sources.clone();
+    let mut result = tokio::task::spawn_blocking(move || {
+        archive_items(&archived, &destination, &options.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let deletion = if delete_originals.unwrap_or(false) {
+        Some(safety::delete_and_patch_scan(sources.clone(), false, &window).await)
+    } else {
+        None
+    };
+    result.operation_id = record_operation(ArchiveOperation {
+        id: 0,
+        archive: result.archive.clone(),
+        manifest: result.manifest.clone(),
+        created_at: chrono::Utc::now().timestamp(),
+        sources,
+        files: result.files,
+        bytes: result.bytes,
+        originals_deleted: matches!(deletion, Some(Ok(_))),
+    })?;
+    result.deletion = deletion.transpose()?;
+    Ok(result)
+}
+
+/// Archiving runs in the operation journal, newest first
+#[tauri::command]
+pub async fn get_archive_operations_command() -> Result<Vec<ArchiveOperation>, String> {
+    let mut journal = load_journal()?;
+    journal.reverse();
+    Ok(journal)
+}
+
+/// Check an archiving run's archive against its manifest and write its entries back
+///
+/// `paths` picks what to restore: entries archived from or under any of them, or all of
+/// them when omitted. Entries go back where they came from, or under `destination` by
+/// their names in the archive. Existing files are never overwritten. With `verify_only`
+/// nothing is written; the archive is only checked against the manifest.
+#[tauri::command]
+pub async fn restore_from_archive_command(
+    operation_id: u64,
+    paths: Option<Vec<String>>,
+    destination: Option<String>,
+    verify_only: Option<bool>,
+) -> Result<RestoreResult, String> {
+    let verify_only = verify_only.unwrap_or(false);
+    if !verify_only {
+        access::ensure_writable("Restoring files")?;
+    }
+    let operation = load_journal()?
+        .into_iter()
+        .find(|op| op.id == operation_id)
+        .ok_or_else(|| format!("No archiving run {} in the journal", operation_id))?;
+    let paths: Vec<PathBuf> = paths
+        .unwrap_or_default()
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    let destination = destination.map(PathBuf::from);
+
+    tokio::task::spawn_blocking(move || {
+        let manifest = read_manifest(&operation.manifest)?;
+        let selected: Vec<&ManifestEntry> = manifest
+            .entries
+            .iter()
+            .filter(|e| paths.is_empty() || paths.iter().any(|p| e.source.starts_with(p)))
+            .collect();
+        restore_entries(
+            &operation.archive,
+            &selected,
+            destination.as_deref(),
+            verify_only,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_keeps_links_and_permissions() {
+        let dir = std::env::temp_dir().join(format!("test_archiving_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let source = dir.join("photos");
+        fs::create_dir_all(source.join("2024")).unwrap();
+        fs::write(source.join("2024/beach.jpg"), vec![7u8; 4096]).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let script = source.join("sync.sh");
+            fs::write(&script, "#!/bin/sh\n").unwrap();
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).unwrap();
+            std::os::unix::fs::symlink("2024/beach.jpg", source.join("latest.jpg")).unwrap();
+        }
+        // Not every temp filesystem takes user attributes
+        #[cfg(unix)]
+        let tagged = xattr::set(source.join("2024/beach.jpg"), "user.note", b"sunset").is_ok();
+
+        let archive = dir.join("photos.zip");
+        let options = ArchiveOptions {
+            symlinks: true,
+            permissions: true,
+            xattrs: true,
+        };
+        let result = archive_items(std::slice::from_ref(&source), &archive, &options).unwrap();
+
+        let mut zip = ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+        assert!(zip.by_name("photos/2024/beach.jpg").unwrap().size() == 4096);
+        assert!(result.skipped.is_empty());
+        assert_eq!(result.zip64_entries, 0);
+        #[cfg(unix)]
+        {
+            assert_eq!(result.files, 2);
+            assert!(zip.by_name("photos/latest.jpg").unwrap().is_symlink());
+            let mode = zip.by_name("photos/sync.sh").unwrap().unix_mode().unwrap();
+            assert_eq!(mode & 0o777, 0o750);
+        }
+        let manifest = read_manifest(&dir.join("photos.zip.manifest.json")).unwrap();
+        assert!(verify_archive(&archive, &manifest.entries).is_ok());
+        #[cfg(unix)]
+        if tagged {
+            let restored = dir.join("restored");
+            let all: Vec<&ManifestEntry> = manifest.entries.iter().collect();
+            let result = restore_entries(&archive, &all, Some(&restored), false).unwrap();
+            assert!(result.failed.is_empty());
+            let note = xattr::get(restored.join("photos/2024/beach.jpg"), "user.note").unwrap();
+            assert_eq!(note.as_deref(), Some(&b"sunset"[..]));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_restore_from_manifest() {
+        let dir = std::env::temp_dir().join(format!("test_restoring_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // A hand-made archive: one good file, one escaping upwards, one not matching its hash
+        let archive = dir.join("crafted.zip");
+        let mut zip = ZipWriter::new(File::create(&archive).unwrap());
+        let mut manifest = Vec::new();
+        for (name, contents, hash) in [
+            ("docs/report.txt", "report", None),
+            ("../escape.txt", "escape", None),
+            ("docs/bad.txt", "bad", Some("0".repeat(64))),
+        ] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+            manifest.push(ManifestEntry {
+                name: name.to_string(),
+                source: dir.join("original").join(name),
+                kind: EntryKind::File,
+                size: contents.len() as u64,
+                hash: hash.or_else(|| Some(blake3::hash(contents.as_bytes()).to_hex().to_string())),
+                modified: 1_700_000_000,
+            });
+        }
+        zip.finish().unwrap();
+
+        let restored = dir.join("restored");
+        let all: Vec<&ManifestEntry> = manifest.iter().collect();
+        let result = restore_entries(&archive, &all, Some(&restored), false).unwrap();
+        assert_eq!(result.verified, 2);
+        assert_eq!(
+            fs::read_to_string(restored.join("docs/report.txt")).unwrap(),
+            "report"
+        );
+        assert_eq!(
+            fs::read_to_string(restored.join("escape.txt")).unwrap(),
+            "escape"
+        );
+        assert!(!dir.join("escape.txt").exists());
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].path, restored.join("docs/bad.txt"));
+        assert!(!restored.join("docs/bad.txt").exists());
+        assert!(verify_archive(&archive, &manifest).is_err());
+
+        // Nothing is overwritten
+        let again = restore_entries(&archive, &all[..1], Some(&restored), false).unwrap();
+        assert_eq!(again.failed[0].error, "Already exists");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let target = restored.join("setuid.txt");
+            let listed = ManifestEntry {
+                hash: None,
+                ..manifest[0].clone()
+            };
+            let kept = KeptMetadata {
+                mode: Some(0o104755),
+                xattrs: Vec::new(),
+            };
+            restore_entry(&mut "report".as_bytes(), &kept, &listed, Some(&target)).unwrap();
+            let mode = fs::metadata(&target).unwrap().permissions().mode();
+            assert_eq!(mode & 0o7777, 0o755);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+use crate::confidence::{known_location, KnownLocation};
+use crate::explain::{known_kind, KnownKind};
+use crate::reconcile::volume_for;
+use crate::registry;
+use crate::sizes::percent_of;
+use crate::storage;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Folders directly on a volume that belong to the OS or installed software
+const SYSTEM_DIR_NAMES: &[&str] = &[
+    "System",
+    "Library",
+    "Applications",
+    "private",
+    "usr",
+    "var",
+    "opt",
+    "etc",
+    "boot",
+    "snap",
+    "Windows",
+    "Program Files",
+    "Program Files (x86)",
+    "ProgramData",
+];
+
+/// What a folder on the path is, so breadcrumbs can give it an icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DirKind {
+    Volume,
+    /// The folder holding every user's profile
+    Users,
+    Home,
+    System,
+    Downloads,
+    Cache,
+    Trash,
+    Bundle,
+    Folder,
+}
+
+/// One step of a breadcrumb trail
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Ancestor {
+    pub path: PathBuf,
+    pub name: String,
+    /// From the newest retained scan containing the path; for the volume itself, the space
+    /// in use. `None` above the scan's root.
+    pub size: Option<u64>,
+    pub pct_of_volume: Option<f64>,
+    pub dir_kind: DirKind,
+}
+
+fn dir_kind(path: &Path, volume: &Path, home: Option<&Path>) -> DirKind {
+    if path == volume {
+        return DirKind::Volume;
+    }
+    if home == Some(path) {
+        return DirKind::Home;
+    }
+    if home.and_then(Path::parent) == Some(path) {
+        return DirKind::Users;
+    }
+    let on_volume_root = path.parent() == Some(volume);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if on_volume_root && SYSTEM_DIR_NAMES.contains(&name.as_str()) {
+        return DirKind::System;
+    }
+    match known_kind(path) {
+        Some(KnownKind::Cache) => DirKind::Cache,
+        Some(KnownKind::Trash) => DirKind::Trash,
+        Some(KnownKind::Bundle) => DirKind::Bundle,
+        None if known_location(path) == Some(KnownLocation::Downloads) => DirKind::Downloads,
+        None => DirKind::Folder,
+    }
+}
+
+/// Breadcrumb trail from `volume` down to `path`
+///
+/// `sizes` holds the sizes known from a scan; `volume_used` and `volume_total` come from the
+/// volume itself.
+fn ancestors(
+    path: &Path,
+    volume: &Path,
+    home: Option<&Path>,
+    sizes: &HashMap<PathBuf, u64>,
+    volume_used: Option<u64>,
+    volume_total: Option<u64>,
+) -> Vec<Ancestor> {
+    let mut trail: Vec<Ancestor> = path
+        .ancestors()
+        .take_while(|ancestor| ancestor.starts_with(volume))
+        .map(|ancestor| {
+            let size = match sizes.get(ancestor) {
+                Some(&size) => Some(size),
+                None if ancestor == volume => volume_used,
+                None => None,
+            };
+            let name = match ancestor.file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => ancestor.to_string_lossy().to_string(),
+            };
+            Ancestor {
+                path: ancestor.to_path_buf(),
+                name,
+                size,
+                pct_of_volume: size.zip(volume_total).map(|(s, t)| percent_of(s, t)),
+                dir_kind: dir_kind(ancestor, volume, home),
+            }
+        })
+        .collect();
+    trail.reverse();
+    trail
+}
+
+// Tauri commands
+
+/// Every folder from the volume down to `path`, with sizes, for breadcrumbs
+#[tauri::command]
+pub async fn get_ancestors_command(path: String) -> Result<Vec<Ancestor>, String> {
+    let path = PathBuf::from(path);
+    let locations = storage::get_storage_locations().unwrap_or_default();
+    let location = volume_for(&path, &locations);
+    let volume = location
+        .map(|l| l.path.clone())
+        .or_else(|| path.ancestors().last().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let volume_total = location.and_then(|l| l.total_space);
+    let volume_used =
+        location.and_then(|l| Some(l.total_space?.saturating_sub(l.available_space?)));
+
+    let mut scans = registry::retained_summaries().await;
+    scans.retain(|summary| path.starts_with(&summary.root));
+    let newest = scans.iter().map(|summary| summary.scan_id).max();
+    let sizes = match newest {
+        Some(scan_id) => {
+            registry::with_scan(scan_id, |nodes, _| {
+                path.ancestors()
+                    .filter_map(|a| nodes.get(a).map(|n| (a.to_path_buf(), n.size)))
+                    .collect()
+            })
+            .await?
+        }
+        None => HashMap::new(),
+    };
+
+    let home = dirs::home_dir();
+    Ok(ancestors(
+        &path,
+        &volume,
+        home.as_deref(),
+        &sizes,
+        volume_used,
+        volume_total,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ancestors_from_volume_down() {
+        let sizes = HashMap::from([
+            (PathBuf::from("/Users/alice"), 800),
+            (PathBuf::from("/Users/alice/Movies"), 500),
+        ]);
+        let trail = ancestors(
+            Path::new("/Users/alice/Movies"),
+            Path::new("/"),
+            Some(Path::new("/Users/alice")),
+            &sizes,
+            Some(1500),
+            Some(2000),
+        );
+
+        let kinds: Vec<DirKind> = trail.iter().map(|a| a.dir_kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                DirKind::Volume,
+                DirKind::Users,
+                DirKind::Home,
+                DirKind::Folder
+            ]
+        );
+        assert_eq!(trail[0].name, "/");
+        assert_eq!(trail[0].pct_of_volume, Some(75.0));
+        assert_eq!(trail[1].size, None);
+        assert_eq!(trail[3].pct_of_volume, Some(25.0));
+        assert_eq!(
+            dir_kind(Path::new("/Library"), Path::new("/"), None),
+            DirKind::System
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use crate::access;
+use crate::persistence::is_app_data_path;
+use crate::registry;
+use crate::safety::{self, DeletionResult, FailedDeletion};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Window};
+use walkdir::WalkDir;
+
+/// Partial downloads and temp files younger than this may still be in use
+const ORPHAN_MIN_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Extensions left behind by interrupted downloads
+const PARTIAL_DOWNLOAD_EXTENSIONS: [&str; 3] = ["part", "crdownload", "partial"];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BrokenLinkKind {
+    /// Symlink whose target no longer exists
+    DanglingSymlink,
+    /// Windows `.lnk` shortcut whose local target no longer exists
+    BrokenShortcut,
+    /// `.part` / `.crdownload` left by a download that never finished
+    PartialDownload,
+    /// Stale `.tmp` file
+    TempFile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BrokenLink {
+    pub path: PathBuf,
+    pub kind: BrokenLinkKind,
+    pub size: u64,
+    /// Where a symlink or shortcut pointed
+    pub target: Option<PathBuf>,
+    /// Unix timestamp (seconds) of the last modification
+    pub modified: i64,
+}
+
+/// Read the local target path out of a Windows shell link (`.lnk`)
+///
+/// Only the LinkInfo `LocalBasePath` is read; shortcuts to network shares or shell
+/// items without a path yield `None`.
+fn parse_lnk_target(data: &[u8]) -> Option<PathBuf> {
+    const HEADER_SIZE: usize = 0x4C;
+    const HAS_ID_LIST: u32 = 0x1;
+    const HAS_LINK_INFO: u32 = 0x2;
+    const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x1;
+
+    let u16_at = |offset: usize| {
+        data.get(offset..offset + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+    };
+    let u32_at = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let c_string = |offset: usize| {
+        let bytes = data.get(offset..)?;
+        let end = bytes.iter().position(|&b| b == 0)?;
+        Some(String::from_utf8_lossy(&bytes[..end]).to_string())
+    };
+
+    if u32_at(0)? as usize != HEADER_SIZE {
+        return None;
+    }
+    let flags = u32_at(20)?;
+    if flags & HAS_LINK_INFO == 0 {
+        return None;
+    }
+
+    let mut offset = HEADER_SIZE;
+    if flags & HAS_ID_LIST != 0 {
+        offset += 2 + u16_at(offset)?;
+    }
+
+    let info_flags = u32_at(offset + 8)?;
+    if info_flags & VOLUME_ID_AND_LOCAL_BASE_PATH == 0 {
+        return None;
+    }
+    let base_path = c_string(offset + u32_at(offset + 16)? as usize)?;
+    let suffix = c_string(offset + u32_at(offset + 24)? as usize).unwrap_or_default();
+    if base_path.is_empty() {
+        return None;
+    }
+
+    Some(PathBuf::from(base_path + &suffix))
+}
+
+/// Whether `target` is certainly gone: looking it up says it doesn't exist, and the directory
+/// it would be in does
+///
+/// A target that can't be read, or whose directory is missing too (as on a volume that isn't
+/// mounted right now), may still be there, so its link isn't reported.
+fn target_missing(target: &Path) -> bool {
+    match fs::metadata(target) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => target
+            .parent()
+            .is_some_and(|dir| fs::metadata(dir).is_ok_and(|m| m.is_dir())),
+        _ => false,
+    }
+}
+
+/// Classify one entry, returning its kind and link target when it is broken or orphaned
+fn classify(path: &Path, now: SystemTime) -> Option<(BrokenLinkKind, Option<PathBuf>)> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(path).ok()?;
+        // Relative targets are relative to the link's directory
+        let resolved = path.parent().unwrap_or(Path::new("")).join(&target);
+        return target_missing(&resolved)
+            .then_some((BrokenLinkKind::DanglingSymlink, Some(target)));
+    }
+    if !metadata.is_file() {
+        return None;
+    }
+
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if extension == "lnk" {
+        let target = parse_lnk_target(&fs::read(path).ok()?)?;
+        // A `C:\...` target is not absolute on other platforms and can't be checked there
+        return (target.is_absolute() && target_missing(&target))
+            .then_some((BrokenLinkKind::BrokenShortcut, Some(target)));
+    }
+
+    let kind = if PARTIAL_DOWNLOAD_EXTENSIONS.contains(&extension.as_str()) {
+        BrokenLinkKind::PartialDownload
+    } else if extension == "tmp" {
+        BrokenLinkKind::TempFile
+    } else {
+        return None;
+    };
+    let age = now
+        .duration_since(metadata.modified().ok()?)
+        .unwrap_or_default();
+    (age >= ORPHAN_MIN_AGE).then_some((kind, None))
+}
+
+/// Walk a directory for dangling links and orphaned partial files, skipping protected paths
+/// and the app's own data
+fn find_broken_links(root: &Path, now: SystemTime) -> Vec<BrokenLink> {
+    let mut found: Vec<BrokenLink> = WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| !safety::is_protected_path(e.path()) && !is_app_data_path(e.path()))
+        .filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let (kind, target) = classify(entry.path(), now)?;
+            let metadata = entry.metadata().ok()?;
+            Some(BrokenLink {
+                path: entry.path().to_path_buf(),
+                kind,
+                size: if metadata.is_file() {
+                    metadata.len()
+                } else {
+                    0
+                },
+                target,
+                modified: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs() as i64)
+                    .unwrap_or(0),
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+}
+
+// Tauri commands
+
+/// Dangling symlinks, broken shortcuts and orphaned partial files beneath `path`
+#[tauri::command]
+pub async fn find_broken_links_command(path: String) -> Result<Vec<BrokenLink>, String> {
+    let root = PathBuf::from(&path);
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", path));
+    }
+    access::ensure_scan_root_allowed(&root)?;
+    tokio::task::spawn_blocking(move || find_broken_links(&root, SystemTime::now()))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Remove entries found by `find_broken_links_command`
+///
+/// Each path is checked again first, so a link whose target came back or a download that
+/// resumed is left alone. The rest go through the usual deletion safety checks.
+#[tauri::command]
+pub async fn remove_broken_links_command(
+    paths: Vec<String>,
+    window: Window,
+) -> Result<DeletionResult, String> {
+    let now = SystemTime::now();
+    let (still_broken, recovered): (Vec<PathBuf>, Vec<PathBuf>) = paths
+        .into_iter()
+        .map(PathBuf::from)
+        .partition(|p| classify(p, now).is_some());
+
+    let mut result = safety::delete_items(still_broken, false).await?;
+    result
+        .failed
+        .extend(recovered.into_iter().map(|p| FailedDeletion {
+            path: p.to_string_lossy().to_string(),
+            error: "No longer broken or orphaned".to_string(),
+        }));
+
+    for event in registry::remove_deleted_paths(&result.deleted).await {
+        let _ = window.emit("streaming-scan-event", &event);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal shell link: header with HasLinkInfo, then a LinkInfo with a local base path
+    fn lnk_bytes(target: &str) -> Vec<u8> {
+        let mut data = vec![0u8; 0x4C];
+        data[0] = 0x4C;
+        data[20] = 0x2;
+
+        let header_size = 0x1Cu32;
+        let base_path_offset = header_size;
+        let suffix_offset = base_path_offset + target.len() as u32 + 1;
+        let size = suffix_offset + 1;
+        for value in [
+            size,
+            header_size,
+            0x1,
+            0,
+            base_path_offset,
+            0,
+            suffix_offset,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(target.as_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data
+    }
+
+    #[test]
+    fn test_parse_lnk_target() {
+        assert_eq!(
+            parse_lnk_target(&lnk_bytes("C:\\Games\\old.exe")),
+            Some(PathBuf::from("C:\\Games\\old.exe"))
+        );
+        assert_eq!(parse_lnk_target(b"not a shortcut"), None);
+    }
+
+    #[test]
+    fn test_find_broken_links() {
+        let root = std::env::temp_dir().join("test_broken_links");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("video.mp4.part"), [0u8; 10]).unwrap();
+        fs::write(root.join("fresh.tmp"), [0u8; 10]).unwrap();
+        fs::write(root.join("keep.txt"), [0u8; 10]).unwrap();
+        let missing = root.join("gone").to_string_lossy().to_string();
+        fs::write(root.join("gone.lnk"), lnk_bytes(&missing)).unwrap();
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.join("gone"), root.join("dangling")).unwrap();
+            std::os::unix::fs::symlink(root.join("keep.txt"), root.join("valid")).unwrap();
+            // On a volume that isn't mounted: can't tell, so not reported
+            std::os::unix::fs::symlink("/no-such-volume/file", root.join("offline")).unwrap();
+            std::os::unix::fs::symlink("keep.txt", root.join("relative")).unwrap();
+        }
+
+        // Two days on, the partial files count as orphaned
+        let later = SystemTime::now() + 2 * ORPHAN_MIN_AGE;
+        let found = find_broken_links(&root, later);
+        let now = find_broken_links(&root, SystemTime::now());
+        fs::remove_dir_all(&root).unwrap();
+
+        let kinds: Vec<(String, BrokenLinkKind)> = found
+            .iter()
+            .map(|b| {
+                let name = b.path.file_name().unwrap().to_string_lossy().to_string();
+                (name, b.kind)
+            })
+            .collect();
+        let mut expected = vec![
+            ("fresh.tmp".to_string(), BrokenLinkKind::TempFile),
+            ("gone.lnk".to_string(), BrokenLinkKind::BrokenShortcut),
+            (
+                "video.mp4.part".to_string(),
+                BrokenLinkKind::PartialDownload,
+            ),
+        ];
+        #[cfg(unix)]
+        expected.insert(0, ("dangling".to_string(), BrokenLinkKind::DanglingSymlink));
+        assert_eq!(kinds, expected);
+        assert!(now.iter().all(
+            |b| b.kind != BrokenLinkKind::PartialDownload && b.kind != BrokenLinkKind::TempFile
+        ));
+    }
+}
@@ -0,0 +1,333 @@
+use crate::access;
+use crate::classifier::classify_file;
+use crate::estimate::disk_size;
+use crate::persistence::is_app_data_path;
+use crate::registry::{self, DiscoveredNode};
+use crate::scanner::{
+    build_tree_from_registry_with_depth, previous_directory_sizes, validate_path, TreeShape,
+};
+use crate::storage::{get_cluster_size, get_inode_usage, get_user_quota};
+use crate::summary::{
+    build_summary, BudgetStats, ExcludedPath, ExclusionReason, ScanCounters, ScanStrategy,
+    UnexploredDir,
+};
+use crate::types::{FileNode, FileType, StreamingScanEvent, SCHEMA_VERSION};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{Emitter, Window};
+use tokio::sync::Mutex;
+
+/// Unexplored directories listed by path in the summary; the rest are only counted
+const MAX_UNEXPLORED_REPORTED: usize = 100;
+
+/// A directory waiting to be listed
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Pending {
+    /// Size the directory is expected to have; larger ones are listed first
+    expected: u64,
+    /// Among equals, shallower first, so directories nothing is known about go breadth-first
+    depth: Reverse<usize>,
+    path: PathBuf,
+}
+
+/// What a time-limited scan produced
+struct BudgetedResult {
+    nodes: HashMap<PathBuf, DiscoveredNode>,
+    counters: ScanCounters,
+    stats: BudgetStats,
+}
+
+/// State of one time-limited scan
+struct BudgetedScan<'a> {
+    /// Directory sizes from the previous scan covering the root
+    previous: &'a HashMap<PathBuf, u64>,
+    queue: BinaryHeap<Pending>,
+    nodes: HashMap<PathBuf, DiscoveredNode>,
+    counters: ScanCounters,
+    listed: u64,
+}
+
+impl<'a> BudgetedScan<'a> {
+    fn new(previous: &'a HashMap<PathBuf, u64>) -> Self {
+        Self {
+            previous,
+            queue: BinaryHeap::new(),
+            nodes: HashMap::new(),
+            counters: ScanCounters::default(),
+            listed: 0,
+        }
+    }
+
+    /// Record a directory as found but not yet listed
+    fn discover_dir(&mut self, path: PathBuf, parent: Option<PathBuf>, modified: SystemTime) {
+        self.nodes.insert(
+            path.clone(),
+            DiscoveredNode {
+                id: registry::next_node_id(),
+                name: file_name(&path),
+                path,
+                size: 0,
+                logical_size: 0,
+                is_directory: true,
+                file_type: FileType::Other,
+                modified,
+                parent_path: parent,
+                is_complete: false,
+                is_symlink: false,
+                symlink_target: None,
+                file_count: 0,
+                dropped: None,
+            },
+        );
+        self.counters.directories += 1;
+    }
+
+    /// List one directory, queueing its subdirectories
+    ///
+    /// A subdirectory is expected to be as big as it was in the previous scan; without one,
+    /// it gets an even share of what its parent was expected to hold beyond its files.
+    fn list(&mut self, pending: Pending) {
+        let Ok(entries) = fs::read_dir(&pending.path) else {
+            self.counters.errors += 1;
+            return;
+        };
+        self.listed += 1;
+
+        let mut files_size = 0u64;
+        let mut subdirs = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if is_app_data_path(&path) {
+                self.counters.excluded.push(ExcludedPath {
+                    path: path.to_string_lossy().to_string(),
+                    reason: ExclusionReason::AppData,
+                });
+                continue;
+            }
+            let Ok(metadata) = fs::symlink_metadata(&path) else {
+                self.counters.errors += 1;
+                continue;
+            };
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            if metadata.is_dir() {
+                self.discover_dir(path.clone(), Some(pending.path.clone()), modified);
+                subdirs.push(path);
+                continue;
+            }
+
+            let is_symlink = metadata.is_symlink();
+            let size = if is_symlink { 0 } else { disk_size(&metadata) };
+            self.counters.symlinks += u64::from(is_symlink);
+            files_size += size;
+            self.nodes.insert(
+                path.clone(),
+                DiscoveredNode {
+                    id: registry::next_node_id(),
+                    name: file_name(&path),
+                    size,
+                    logical_size: if is_symlink { 0 } else { metadata.len() },
+                    is_directory: false,
+                    file_type: if is_symlink {
+                        FileType::Other
+                    } else {
+                        classify_file(&path)
+                    },
+                    modified,
+                    parent_path: Some(pending.path.clone()),
+                    is_complete: true,
+                    is_symlink,
+                    symlink_target: is_symlink.then(|| fs::read_link(&path).ok()).flatten(),
+                    file_count: u64::from(!is_symlink),
+                    dropped: None,
+                    path,
+                },
+            );
+        }
+
+        if let Some(node) = self.nodes.get_mut(&pending.path) {
+            node.is_complete = true;
+        }
+        let share = pending.expected.saturating_sub(files_size) / subdirs.len().max(1) as u64;
+        for path in subdirs {
+            self.queue.push(Pending {
+                expected: self.previous.get(&path).copied().unwrap_or(share),
+                depth: Reverse(pending.depth.0 + 1),
+                path,
+            });
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Walk `root` largest directory first until `budget` runs out
+///
+/// The root is always listed. Directories still queued when time is up stay in the result
+/// unlisted, and they and every directory above them are left incomplete, so their sizes
+/// read as lower bounds.
+fn budgeted_scan(
+    root: &Path,
+    previous: &HashMap<PathBuf, u64>,
+    budget: Duration,
+) -> Result<BudgetedResult, String> {
+    let deadline = Instant::now() + budget;
+    let metadata =
+        fs::metadata(root).map_err(|e| format!("Cannot access {}: {}", root.display(), e))?;
+    if !metadata.is_dir() {
+        return Err(format!("{} is not a folder", root.display()));
+    }
+
+    let mut scan = BudgetedScan::new(previous);
+    scan.discover_dir(
+        root.to_path_buf(),
+        None,
+        metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+    );
+    scan.list(Pending {
+        expected: previous.get(root).copied().unwrap_or(u64::MAX),
+        depth: Reverse(0),
+        path: root.to_path_buf(),
+    });
+    while Instant::now() < deadline {
+        let Some(pending) = scan.queue.pop() else {
+            break;
+        };
+        scan.list(pending);
+    }
+
+    let unexplored: Vec<Pending> = scan.queue.into_sorted_vec().into_iter().rev().collect();
+    for pending in &unexplored {
+        for ancestor in pending.path.ancestors().skip(1) {
+            match scan.nodes.get_mut(ancestor) {
+                Some(node) if node.is_complete => node.is_complete = false,
+                _ => break,
+            }
+        }
+    }
+
+    let stats = BudgetStats {
+        budget_ms: budget.as_millis() as u64,
+        exhausted: !unexplored.is_empty(),
+        directories_listed: scan.listed,
+        unexplored_count: unexplored.len() as u64,
+        unexplored: unexplored
+            .iter()
+            .take(MAX_UNEXPLORED_REPORTED)
+            .map(|pending| UnexploredDir {
+                path: pending.path.to_string_lossy().to_string(),
+                previous_size: previous.get(&pending.path).copied(),
+            })
+            .collect(),
+    };
+    Ok(BudgetedResult {
+        nodes: scan.nodes,
+        counters: scan.counters,
+        stats,
+    })
+}
+
+// Tauri commands
+
+/// Scan for at most `max_minutes`, listing the directories expected to be largest first
+///
+/// Expectations come from the newest retained scan covering `path`. When time runs out the
+/// tree holds what was found: unfinished directories are marked `is_partial` and the summary
+/// lists the ones never opened.
+#[tauri::command]
+pub async fn scan_with_budget_command(
+    path: String,
+    max_minutes: f64,
+    window: Window,
+) -> Result<FileNode, String> {
+    if !max_minutes.is_finite() || max_minutes <= 0.0 {
+        return Err("The time limit must be a positive number of minutes".to_string());
+    }
+    validate_path(&path)?;
+    let root = PathBuf::from(&path);
+    access::ensure_scan_root_allowed(&root)?;
+    let budget = Duration::from_secs_f64(max_minutes * 60.0);
+    let started = Instant::now();
+
+    let previous = previous_directory_sizes(&root).await;
+
+    let scan_root = root.clone();
+    let BudgetedResult {
+        nodes,
+        counters,
+        stats,
+    } = tokio::task::spawn_blocking(move || budgeted_scan(&scan_root, &previous, budget))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let mut summary = build_summary(
+        registry::next_scan_id(),
+        &nodes,
+        &root,
+        &counters,
+        started.elapsed(),
+        ScanStrategy::Budgeted,
+    );
+    summary.inodes = get_inode_usage(&root);
+    summary.quota = get_user_quota(&root);
+    summary.cluster_size = get_cluster_size(&root);
+    summary.budget = Some(stats);
+
+    let tree = build_tree_from_registry_with_depth(&nodes, &root, 2, TreeShape::default())
+        .ok_or_else(|| "Failed to build final tree".to_string())?;
+    registry::retain_scan(Arc::new(Mutex::new(nodes)), summary.clone()).await;
+
+    let _ = window.emit(
+        "streaming-scan-event",
+        &StreamingScanEvent::Complete {
+            files_scanned: summary.files,
+            total_size: summary.total_size,
+            schema_version: SCHEMA_VERSION,
+            summary: Box::new(summary),
+        },
+    );
+
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_lists_largest_first_and_marks_the_rest() {
+        let root = std::env::temp_dir().join("test_budgeted");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("videos/2024")).unwrap();
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::write(root.join("top.bin"), [1u8; 100]).unwrap();
+        fs::write(root.join("videos/2024/a.mp4"), [1u8; 100]).unwrap();
+
+        let previous = HashMap::from([(root.join("notes"), 10), (root.join("videos"), 5_000_000)]);
+        let cut_short = budgeted_scan(&root, &previous, Duration::ZERO).unwrap();
+        let finished = budgeted_scan(&root, &previous, Duration::from_secs(60)).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        // Only the root was listed; its subfolders wait, the biggest one first
+        let stats = &cut_short.stats;
+        assert!(stats.exhausted);
+        assert_eq!(stats.directories_listed, 1);
+        assert_eq!(stats.unexplored_count, 2);
+        assert!(stats.unexplored[0].path.ends_with("videos"));
+        assert_eq!(stats.unexplored[0].previous_size, Some(5_000_000));
+        assert!(!cut_short.nodes[&root].is_complete);
+        assert!(cut_short.nodes[&root.join("top.bin")].is_complete);
+
+        assert!(!finished.stats.exhausted);
+        assert_eq!(finished.stats.directories_listed, 4);
+        assert!(finished.nodes.values().all(|n| n.is_complete));
+        assert!(finished.nodes.contains_key(&root.join("videos/2024/a.mp4")));
+    }
+}
@@ -0,0 +1,279 @@
+//! Completed scans kept on disk, so a rescan after a restart only re-walks what changed
+//!
+//! Retained scans live in memory and are gone once the app quits. Each retained scan is also
+//! written here, one file per root, with every node's size and mtime; a rescan of the root
+//! then compares directory mtimes against it the way an incremental scan of a retained scan
+//! does.
+
+use crate::access;
+use crate::change_journal::JournalCursor;
+use crate::encryption;
+use crate::incremental::{rescan_previous, PreviousScan};
+use crate::persistence::app_data_dir;
+use crate::registry::{self, DiscoveredNode, DroppedFiles, NodeRegistry};
+use crate::scanner::{scan_directory_async, TreeShape};
+use crate::space_guard::write_guarded;
+use crate::summary::ScanSummary;
+use crate::types::{FileNode, FileType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::Window;
+
+/// Directory inside the app data directory holding cached scans
+pub(crate) const SCAN_CACHE_DIR: &str = "scan_cache";
+
+/// Roots with a cached scan; the least recently written are dropped beyond this
+const MAX_CACHED_ROOTS: usize = 8;
+
+/// A node as cached; ids, names and parents are rebuilt from the path on load
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedNode {
+    path: PathBuf,
+    size: u64,
+    is_directory: bool,
+    file_type: FileType,
+    modified: SystemTime,
+    is_complete: bool,
+    is_symlink: bool,
+    symlink_target: Option<PathBuf>,
+    file_count: u64,
+    #[serde(default)]
+    dropped: Option<DroppedFiles>,
+    /// Missing from caches written before it was recorded; those fall back to `size`
+    #[serde(default)]
+    logical_size: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedScan {
+    root: PathBuf,
+    scan_id: u64,
+    journal: Option<JournalCursor>,
+    nodes: Vec<CachedNode>,
+}
+
+fn cache_dir() -> Result<PathBuf, String> {
+    let dir = app_data_dir()?.join(SCAN_CACHE_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// Cache file of `root`, named by a hash of the path
+fn cache_file(dir: &Path, root: &Path) -> PathBuf {
+    let hash = blake3::hash(root.to_string_lossy().as_bytes());
+    dir.join(format!("{}.bin", &hash.to_hex()[..16]))
+}
+
+fn encode(
+    root: &Path,
+    scan_id: u64,
+    journal: Option<JournalCursor>,
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+) -> Result<Vec<u8>, String> {
+    let scan = CachedScan {
+        root: root.to_path_buf(),
+        scan_id,
+        journal,
+        nodes: nodes
+            .values()
+            .map(|node| CachedNode {
+                path: node.path.clone(),
+                size: node.size,
+                is_directory: node.is_directory,
+                file_type: node.file_type.clone(),
+                modified: node.modified,
+                is_complete: node.is_complete,
+                is_symlink: node.is_symlink,
+                symlink_target: node.symlink_target.clone(),
+                file_count: node.file_count,
+                dropped: node.dropped.as_deref().cloned(),
+                logical_size: Some(node.logical_size),
+            })
+            .collect(),
+    };
+    rmp_serde::to_vec(&scan).map_err(|e| format!("Cannot encode scan cache: {}", e))
+}
+
+/// Decode a cached scan of `root`, giving its nodes ids from this session
+fn decode(root: &Path, bytes: &[u8]) -> Option<PreviousScan> {
+    let scan: CachedScan = rmp_serde::from_slice(bytes).ok()?;
+    if scan.root != root {
+        return None;
+    }
+    let nodes = scan
+        .nodes
+        .into_iter()
+        .map(|node| {
+            let parent_path = (node.path != root)
+                .then(|| node.path.parent().map(Path::to_path_buf))
+                .flatten();
+            let discovered = DiscoveredNode {
+                id: registry::next_node_id(),
+                name: node
+                    .path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                path: node.path.clone(),
+                size: node.size,
+                logical_size: node.logical_size.unwrap_or(if node.is_directory {
+                    0
+                } else {
+                    node.size
+                }),
+                is_directory: node.is_directory,
+                file_type: node.file_type,
+                modified: node.modified,
+                parent_path,
+                is_complete: node.is_complete,
+                is_symlink: node.is_symlink,
+                symlink_target: node.symlink_target,
+                file_count: node.file_count,
+                dropped: node.dropped.map(Box::new),
+            };
+            (node.path, discovered)
+        })
+        .collect();
+    Some(PreviousScan {
+        scan_id: scan.scan_id,
+        root: scan.root,
+        nodes,
+        journal: scan.journal,
+        from_cache: true,
+    })
+}
+
+/// Drop the least recently written cache files beyond `keep`
+fn evict(dir: &Path, keep: usize) {
+    let mut files: Vec<(PathBuf, Option<SystemTime>)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| (e.path(), e.metadata().and_then(|m| m.modified()).ok()))
+        .collect();
+    files.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+    for (file, _) in files.into_iter().skip(keep) {
+        let _ = fs::remove_file(file);
+    }
+}
+
+fn store(nodes: &HashMap<PathBuf, DiscoveredNode>, summary: &ScanSummary) -> Result<(), String> {
+    let root = PathBuf::from(&summary.root);
+    let bytes = encode(&root, summary.scan_id, summary.journal.clone(), nodes)?;
+    let dir = cache_dir()?;
+    write_guarded(&cache_file(&dir, &root), &encryption::seal(bytes)?)?;
+    evict(&dir, MAX_CACHED_ROOTS);
+    Ok(())
+}
+
+/// Write a retained scan to the cache without holding up the caller
+///
+/// Runs on a blocking thread, holding the scan's lock while it encodes; a failed write only
+/// means the next rescan after a restart walks everything.
+pub(crate) fn store_in_background(nodes: NodeRegistry, summary: ScanSummary) {
+    tokio::task::spawn_blocking(move || {
+        let nodes = nodes.blocking_lock();
+        let _ = store(&nodes, &summary);
+    });
+}
+
+/// The cached scan of `root`, if there is a readable one
+pub(crate) fn load(root: &Path) -> Option<PreviousScan> {
+    let bytes = fs::read(cache_file(&cache_dir().ok()?, root)).ok()?;
+    decode(root, &encryption::open(bytes).ok()?)
+}
+
+// Tauri commands
+
+/// Rescan `path` against the newest earlier scan of it, re-walking only what changed
+///
+/// Uses a scan retained in memory when there is one and the on-disk cache otherwise, so this
+/// also works right after a restart; with neither it runs a normal scan.
+/// `trust_directory_mtimes` is as for `incremental_scan_command`.
+#[tauri::command]
+pub async fn rescan_directory_command(
+    path: String,
+    trust_directory_mtimes: Option<bool>,
+    window: Window,
+) -> Result<FileNode, String> {
+    let root = PathBuf::from(&path);
+    access::ensure_scan_root_allowed(&root)?;
+    let retained = registry::retained_summaries()
+        .await
+        .into_iter()
+        .filter(|summary| Path::new(&summary.root) == root)
+        .map(|summary| summary.scan_id)
+        .max();
+    let previous = match retained {
+        Some(scan_id) => registry::with_scan(scan_id, |nodes, summary| PreviousScan {
+            scan_id,
+            root: root.clone(),
+            nodes: nodes.clone(),
+            journal: summary.journal.clone(),
+            from_cache: false,
+        })
+        .await
+        .ok(),
+        None => {
+            let cached_root = root.clone();
+            tokio::task::spawn_blocking(move || load(&cached_root))
+                .await
+                .map_err(|e| e.to_string())?
+        }
+    };
+
+    match previous {
+        Some(previous) => rescan_previous(previous, trust_directory_mtimes, window).await,
+        None => scan_directory_async(path, TreeShape::default(), Vec::new(), None, window).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_scan_round_trips_with_fresh_ids() {
+        let root = PathBuf::from("/data");
+        let node = |path: &str, parent: Option<&str>, is_directory| DiscoveredNode {
+            id: 7,
+            path: PathBuf::from(path),
+            name: String::new(),
+            size: 4096,
+            logical_size: 100,
+            is_directory,
+            file_type: FileType::Other,
+            modified: SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000),
+            parent_path: parent.map(PathBuf::from),
+            is_complete: true,
+            is_symlink: false,
+            symlink_target: None,
+            file_count: 1,
+            dropped: None,
+        };
+        let nodes = HashMap::from([
+            (root.clone(), node("/data", None, true)),
+            (
+                PathBuf::from("/data/a.bin"),
+                node("/data/a.bin", Some("/data"), false),
+            ),
+        ]);
+
+        let bytes = encode(&root, 3, None, &nodes).unwrap();
+        assert!(decode(Path::new("/other"), &bytes).is_none());
+        let previous = decode(&root, &bytes).unwrap();
+
+        assert_eq!(previous.scan_id, 3);
+        assert!(previous.from_cache);
+        let file = &previous.nodes[Path::new("/data/a.bin")];
+        assert_eq!(file.name, "a.bin");
+        assert_eq!(file.parent_path.as_deref(), Some(root.as_path()));
+        assert_eq!(file.modified, nodes[Path::new("/data/a.bin")].modified);
+        assert_eq!((file.size, file.logical_size), (4096, 100));
+        assert_ne!(file.id, previous.nodes[&root].id);
+        assert_eq!(previous.nodes[&root].parent_path, None);
+    }
+}
@@ -0,0 +1,573 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Where a volume's change journal stood when a scan started
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalCursor {
+    /// NTFS update sequence number (USN) journal
+    Usn {
+        /// Drive the journal belongs to, e.g. `C:`
+        volume: String,
+        /// Changes when the journal is deleted and recreated, invalidating old positions
+        journal_id: u64,
+        next_usn: i64,
+    },
+    /// macOS FSEvents history, which the system keeps per volume across reboots
+    FsEvents { event_id: u64 },
+}
+
+/// Directories whose entries changed since a cursor, and where to resume next time
+#[derive(Debug, Clone)]
+pub(crate) struct ChangeSet {
+    pub directories: HashSet<PathBuf>,
+    pub cursor: JournalCursor,
+}
+
+/// Split an `FSCTL_READ_USN_JOURNAL` output buffer into the next USN and the parent
+/// directory reference of each record
+///
+/// Whatever the reason for a record (a file created, deleted, renamed or written), its parent
+/// directory's listing is stale. Only version 2 records are read; the journal returns those
+/// unless asked for more.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn parse_usn_records(buffer: &[u8]) -> Option<(i64, Vec<u64>)> {
+    let u16_at = |at: usize| Some(u16::from_le_bytes(buffer.get(at..at + 2)?.try_into().ok()?));
+    let u32_at = |at: usize| Some(u32::from_le_bytes(buffer.get(at..at + 4)?.try_into().ok()?));
+    let u64_at = |at: usize| Some(u64::from_le_bytes(buffer.get(at..at + 8)?.try_into().ok()?));
+
+    let next_usn = u64_at(0)? as i64;
+    let mut records = Vec::new();
+    let mut offset = 8;
+    while offset + 60 <= buffer.len() {
+        let length = u32_at(offset)? as usize;
+        if length == 0 {
+            break;
+        }
+        if u16_at(offset + 4)? == 2 {
+            records.push(u64_at(offset + 16)?);
+        }
+        offset += length;
+    }
+
+    Some((next_usn, records))
+}
+
+/// `path` in a form that compares equal to every spelling of it the volume accepts
+///
+/// NTFS ignores case, and the journal reports paths in the volume's own case, which need
+/// not be how the user typed the scan root.
+pub(crate) fn comparable(path: &Path) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        PathBuf::from(path.to_string_lossy().to_lowercase())
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// The components of `path` after `base`, if it lies under it
+fn below<'a>(path: &'a Path, base: &Path) -> Option<std::path::Components<'a>> {
+    let mut rest = path.components();
+    for component in base.components() {
+        let next = rest.next()?;
+        if comparable(Path::new(next.as_os_str())) != comparable(Path::new(component.as_os_str())) {
+            return None;
+        }
+    }
+    Some(rest)
+}
+
+/// Spell directories from a journal the way the scan spells `root`
+///
+/// Journals report resolved paths: `/private/var` for a scan of `/var` on macOS, and on
+/// Windows the volume's own case, `C:\Users` for a scan of `c:\users`. Directories outside
+/// the root are left as they are.
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+fn respell_under(root: &Path, directories: HashSet<PathBuf>) -> HashSet<PathBuf> {
+    let canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    // Windows gives canonical paths the verbatim prefix that `usn::path_of` strips
+    let canonical = PathBuf::from(
+        canonical
+            .to_string_lossy()
+            .trim_start_matches("\\\\?\\")
+            .to_string(),
+    );
+    directories
+        .into_iter()
+        .map(
+            |dir| match below(&dir, &canonical).or_else(|| below(&dir, root)) {
+                Some(rest) => {
+                    let mut respelled = root.to_path_buf();
+                    respelled.extend(rest);
+                    respelled
+                }
+                None => dir,
+            },
+        )
+        .collect()
+}
+
+/// Drive letter of `root`, e.g. `C:`
+#[cfg(target_os = "windows")]
+fn volume_of(root: &Path) -> Option<String> {
+    use std::path::Component;
+    match root.components().next()? {
+        Component::Prefix(prefix) => Some(prefix.as_os_str().to_string_lossy().to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod usn {
+    use super::{parse_usn_records, JournalCursor};
+    use std::collections::HashSet;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::PathBuf;
+    use std::ptr::null_mut;
+    use winapi::shared::minwindef::{DWORD, LPVOID};
+    use winapi::um::fileapi::{CreateFileW, GetFinalPathNameByHandleW, OPEN_EXISTING};
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::ioapiset::DeviceIoControl;
+    use winapi::um::winbase::{OpenFileById, FILE_FLAG_BACKUP_SEMANTICS, FILE_ID_DESCRIPTOR};
+    use winapi::um::winioctl::{FSCTL_QUERY_USN_JOURNAL, FSCTL_READ_USN_JOURNAL};
+    use winapi::um::winnt::{
+        FILE_READ_ATTRIBUTES, FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ,
+        HANDLE,
+    };
+
+    const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+    /// `USN_JOURNAL_DATA_V0`
+    #[repr(C)]
+    #[derive(Default)]
+    pub(super) struct JournalData {
+        pub journal_id: u64,
+        _first_usn: i64,
+        pub next_usn: i64,
+        pub lowest_valid_usn: i64,
+        _max_usn: i64,
+        _maximum_size: u64,
+        _allocation_delta: u64,
+    }
+
+    /// `READ_USN_JOURNAL_DATA_V0`
+    #[repr(C)]
+    struct ReadJournalData {
+        start_usn: i64,
+        reason_mask: u32,
+        return_only_on_close: u32,
+        timeout: u64,
+        bytes_to_wait_for_more: u64,
+        journal_id: u64,
+    }
+
+    /// Closes the handle when dropped
+    pub(super) struct Handle(HANDLE);
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    /// Open `\\.\C:`; reading the journal this way needs administrator rights
+    pub(super) fn open_volume(volume: &str) -> Result<Handle, String> {
+        let path: Vec<u16> = std::ffi::OsStr::new(&format!("\\\\.\\{}", volume))
+            .encode_wide()
+            .chain(Some(0))
+            .collect();
+        let handle = unsafe {
+            CreateFileW(
+                path.as_ptr(),
+                GENERIC_READ,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                null_mut(),
+                OPEN_EXISTING,
+                0,
+                null_mut(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(format!(
+                "Cannot open volume {}: {}",
+                volume,
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(Handle(handle))
+    }
+
+    pub(super) fn query(volume: &Handle) -> Result<JournalData, String> {
+        let mut data = JournalData::default();
+        let mut returned: DWORD = 0;
+        let ok = unsafe {
+            DeviceIoControl(
+                volume.0,
+                FSCTL_QUERY_USN_JOURNAL,
+                null_mut(),
+                0,
+                &mut data as *mut JournalData as LPVOID,
+                std::mem::size_of::<JournalData>() as DWORD,
+                &mut returned,
+                null_mut(),
+            )
+        };
+        if ok == 0 {
+            return Err(format!(
+                "No change journal: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(data)
+    }
+
+    /// Parent directory references of every change from `start_usn` up to `end_usn`
+    pub(super) fn changed_parents(
+        volume: &Handle,
+        journal_id: u64,
+        mut start_usn: i64,
+        end_usn: i64,
+    ) -> Result<HashSet<u64>, String> {
+        let mut parents = HashSet::new();
+        let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+
+        while start_usn < end_usn {
+            let mut input = ReadJournalData {
+                start_usn,
+                reason_mask: u32::MAX,
+                return_only_on_close: 0,
+                timeout: 0,
+                bytes_to_wait_for_more: 0,
+                journal_id,
+            };
+            let mut returned: DWORD = 0;
+            let ok = unsafe {
+                DeviceIoControl(
+                    volume.0,
+                    FSCTL_READ_USN_JOURNAL,
+                    &mut input as *mut ReadJournalData as LPVOID,
+                    std::mem::size_of::<ReadJournalData>() as DWORD,
+                    buffer.as_mut_ptr() as LPVOID,
+                    buffer.len() as DWORD,
+                    &mut returned,
+                    null_mut(),
+                )
+            };
+            if ok == 0 {
+                return Err(format!(
+                    "Cannot read the change journal: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+
+            let (next_usn, records) = parse_usn_records(&buffer[..returned as usize])
+                .ok_or_else(|| "Malformed change journal record".to_string())?;
+            parents.extend(records.iter().copied());
+            if records.is_empty() || next_usn <= start_usn {
+                break;
+            }
+            start_usn = next_usn;
+        }
+
+        Ok(parents)
+    }
+
+    /// Current path of a file or directory from its file reference number
+    pub(super) fn path_of(volume: &Handle, reference: u64) -> Option<PathBuf> {
+        unsafe {
+            let mut descriptor: FILE_ID_DESCRIPTOR = std::mem::zeroed();
+            descriptor.dwSize = std::mem::size_of::<FILE_ID_DESCRIPTOR>() as DWORD;
+            *descriptor.u.FileId_mut().QuadPart_mut() = reference as i64;
+
+            let handle = OpenFileById(
+                volume.0,
+                &mut descriptor,
+                FILE_READ_ATTRIBUTES,
+                FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+                null_mut(),
+                FILE_FLAG_BACKUP_SEMANTICS,
+            );
+            if handle == INVALID_HANDLE_VALUE {
+                return None;
+            }
+            let handle = Handle(handle);
+
+            let mut path = vec![0u16; 1024];
+            let length =
+                GetFinalPathNameByHandleW(handle.0, path.as_mut_ptr(), path.len() as DWORD, 0)
+                    as usize;
+            if length == 0 || length > path.len() {
+                return None;
+            }
+            let path = String::from_utf16_lossy(&path[..length]);
+            Some(PathBuf::from(path.trim_start_matches("\\\\?\\")))
+        }
+    }
+
+    pub(super) fn cursor(volume: &str, data: &JournalData) -> JournalCursor {
+        JournalCursor::Usn {
+            volume: volume.to_string(),
+            journal_id: data.journal_id,
+            next_usn: data.next_usn,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod fsevents {
+    use fsevent_sys as fse;
+    use fsevent_sys::core_foundation as cf;
+    use std::collections::HashSet;
+    use std::ffi::{CStr, CString};
+    use std::os::raw::{c_char, c_void};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+
+    /// Flags meaning events were coalesced or lost, so the replay can't be trusted
+    const INCOMPLETE_HISTORY: u32 = fse::kFSEventStreamEventFlagMustScanSubDirs
+        | fse::kFSEventStreamEventFlagUserDropped
+        | fse::kFSEventStreamEventFlagKernelDropped
+        | fse::kFSEventStreamEventFlagEventIdsWrapped
+        | fse::kFSEventStreamEventFlagRootChanged;
+
+    struct Replay {
+        directories: HashSet<PathBuf>,
+        complete: bool,
+    }
+
+    extern "C" fn on_events(
+        _stream: fse::FSEventStreamRef,
+        info: *mut c_void,
+        count: usize,
+        paths: *mut c_void,
+        flags: *const fse::FSEventStreamEventFlags,
+        _ids: *const fse::FSEventStreamEventId,
+    ) {
+        let replay = unsafe { &mut *(info as *mut Replay) };
+        let paths = paths as *const *const c_char;
+
+        for i in 0..count {
+            let (flag, path) = unsafe { (*flags.add(i), CStr::from_ptr(*paths.add(i))) };
+            if flag & fse::kFSEventStreamEventFlagHistoryDone != 0 {
+                unsafe { cf::CFRunLoopStop(cf::CFRunLoopGetCurrent()) };
+                continue;
+            }
+            if flag & INCOMPLETE_HISTORY != 0 {
+                replay.complete = false;
+            }
+            let path = path.to_string_lossy();
+            replay
+                .directories
+                .insert(PathBuf::from(path.trim_end_matches('/')));
+        }
+    }
+
+    pub(super) fn current_event_id() -> u64 {
+        unsafe { fse::FSEventsGetCurrentEventId() }
+    }
+
+    /// Directories under `root` with events since `since`, read from the stored history
+    ///
+    /// Runs a run loop on the calling thread until the history has been delivered.
+    pub(super) fn replay(root: &Path, since: u64) -> Result<HashSet<PathBuf>, String> {
+        let root_c = CString::new(root.as_os_str().as_bytes()).map_err(|e| e.to_string())?;
+        let mut replay = Replay {
+            directories: HashSet::new(),
+            complete: true,
+        };
+        let context = fse::FSEventStreamContext {
+            version: 0,
+            info: &mut replay as *mut Replay as *mut c_void,
+            retain: None,
+            release: None,
+            copy_description: None,
+        };
+
+        unsafe {
+            let root_ref = cf::CFStringCreateWithCString(
+                cf::kCFAllocatorDefault,
+                root_c.as_ptr(),
+                cf::kCFStringEncodingUTF8,
+            );
+            let paths =
+                cf::CFArrayCreateMutable(cf::kCFAllocatorDefault, 1, &cf::kCFTypeArrayCallBacks);
+            cf::CFArrayAppendValue(paths, root_ref);
+            cf::CFRelease(root_ref);
+
+            let stream = fse::FSEventStreamCreate(
+                cf::kCFAllocatorDefault,
+                on_events,
+                &context,
+                paths,
+                since,
+                0.0,
+                fse::kFSEventStreamCreateFlagNoDefer,
+            );
+            cf::CFRelease(paths);
+            if stream.is_null() {
+                return Err("Cannot open the FSEvents history".to_string());
+            }
+
+            let run_loop = cf::CFRunLoopGetCurrent();
+            fse::FSEventStreamScheduleWithRunLoop(stream, run_loop, cf::kCFRunLoopDefaultMode);
+            if fse::FSEventStreamStart(stream) == 0 {
+                fse::FSEventStreamInvalidate(stream);
+                fse::FSEventStreamRelease(stream);
+                return Err("Cannot start the FSEvents replay".to_string());
+            }
+            cf::CFRunLoopRun();
+            fse::FSEventStreamStop(stream);
+            fse::FSEventStreamInvalidate(stream);
+            fse::FSEventStreamRelease(stream);
+        }
+
+        if !replay.complete {
+            return Err("The FSEvents history since the last scan is incomplete".to_string());
+        }
+
+        Ok(replay.directories)
+    }
+}
+
+/// Current journal position for the volume holding `root`, if it keeps one we can read
+#[cfg(target_os = "windows")]
+pub(crate) fn current_cursor(root: &Path) -> Option<JournalCursor> {
+    let volume = volume_of(root)?;
+    let handle = usn::open_volume(&volume).ok()?;
+    let data = usn::query(&handle).ok()?;
+    Some(usn::cursor(&volume, &data))
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn current_cursor(_root: &Path) -> Option<JournalCursor> {
+    Some(JournalCursor::FsEvents {
+        event_id: fsevents::current_event_id(),
+    })
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub(crate) fn current_cursor(_root: &Path) -> Option<JournalCursor> {
+    None
+}
+
+/// Directories under `root` changed since `cursor`
+///
+/// Fails when the journal no longer reaches back that far or was recreated, in which case
+/// the caller has to walk the tree instead.
+#[cfg(target_os = "windows")]
+pub(crate) fn changes_since(root: &Path, cursor: &JournalCursor) -> Result<ChangeSet, String> {
+    let JournalCursor::Usn {
+        volume,
+        journal_id,
+        next_usn,
+    } = cursor
+    else {
+        return Err("The last scan recorded a different kind of journal".to_string());
+    };
+
+    let handle = usn::open_volume(volume)?;
+    let data = usn::query(&handle)?;
+    if data.journal_id != *journal_id {
+        return Err(format!("The change journal on {} was recreated", volume));
+    }
+    if *next_usn < data.lowest_valid_usn {
+        return Err(format!(
+            "The change journal on {} no longer reaches back to the last scan",
+            volume
+        ));
+    }
+
+    let parents = usn::changed_parents(&handle, *journal_id, *next_usn, data.next_usn)?;
+    // Deleted directories can't be resolved; their own parents are in the set anyway
+    let directories = parents
+        .into_iter()
+        .filter_map(|reference| usn::path_of(&handle, reference))
+        .collect();
+    let directories = respell_under(root, directories);
+
+    Ok(ChangeSet {
+        directories,
+        cursor: usn::cursor(volume, &data),
+    })
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn changes_since(root: &Path, cursor: &JournalCursor) -> Result<ChangeSet, String> {
+    let JournalCursor::FsEvents { event_id } = cursor else {
+        return Err("The last scan recorded a different kind of journal".to_string());
+    };
+    // Ids restart when the volume's event database is reset, invalidating old positions
+    let current = fsevents::current_event_id();
+    if *event_id > current {
+        return Err("The FSEvents history was reset since the last scan".to_string());
+    }
+
+    Ok(ChangeSet {
+        directories: respell_under(root, fsevents::replay(root, *event_id)?),
+        cursor: JournalCursor::FsEvents { event_id: current },
+    })
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub(crate) fn changes_since(_root: &Path, _cursor: &JournalCursor) -> Result<ChangeSet, String> {
+    Err("Change journals are not supported on this platform".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(file: u64, parent: u64, name_length: usize) -> Vec<u8> {
+        let length = (60 + name_length).next_multiple_of(8);
+        let mut bytes = vec![0u8; length];
+        bytes[0..4].copy_from_slice(&(length as u32).to_le_bytes());
+        bytes[4..6].copy_from_slice(&2u16.to_le_bytes());
+        bytes[8..16].copy_from_slice(&file.to_le_bytes());
+        bytes[16..24].copy_from_slice(&parent.to_le_bytes());
+        bytes
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_journal_paths_respelled_under_root() {
+        let dir = std::env::temp_dir().join(format!("test_journal_paths_{}", std::process::id()));
+        let real = dir.join("real");
+        let link = dir.join("link");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(real.join("sub")).unwrap();
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let resolved = real.canonicalize().unwrap();
+        let respelled = respell_under(
+            &link,
+            HashSet::from([
+                resolved.join("sub"),
+                resolved.clone(),
+                PathBuf::from("/elsewhere"),
+            ]),
+        );
+
+        assert_eq!(
+            respelled,
+            HashSet::from([link.join("sub"), link.clone(), PathBuf::from("/elsewhere")])
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_usn_records() {
+        let mut buffer = 4096i64.to_le_bytes().to_vec();
+        buffer.extend(record(10, 5, 14));
+        buffer.extend(record(11, 6, 3));
+
+        let (next_usn, records) = parse_usn_records(&buffer).unwrap();
+
+        assert_eq!(next_usn, 4096);
+        assert_eq!(records, vec![5, 6]);
+        assert!(parse_usn_records(&[0u8; 4]).is_none());
+    }
+}
@@ -1,9 +1,11 @@
 use crate::types::{FileNode, FileType};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
 
 /// Statistics for a specific file category
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CategoryStats {
     pub category: FileType,
     pub total_size: u64,
@@ -181,38 +183,66 @@ mod tests {
     #[test]
     fn test_get_category_stats() {
         let root = FileNode {
+            id: 0,
             name: "root".to_string(),
             path: PathBuf::from("/root"),
             size: 3000,
             is_directory: true,
             file_type: FileType::Other,
             modified: SystemTime::now(),
+            is_symlink: false,
+            symlink_target: None,
+            synthetic: None,
+            pct_of_parent: None,
+            pct_of_volume: None,
+            is_partial: false,
             children: vec![
                 FileNode {
+                    id: 0,
                     name: "doc1.pdf".to_string(),
                     path: PathBuf::from("/root/doc1.pdf"),
                     size: 1000,
                     is_directory: false,
                     file_type: FileType::Document,
                     modified: SystemTime::now(),
+                    is_symlink: false,
+                    symlink_target: None,
+                    synthetic: None,
+                    pct_of_parent: None,
+                    pct_of_volume: None,
+                    is_partial: false,
                     children: vec![],
                 },
                 FileNode {
+                    id: 0,
                     name: "doc2.txt".to_string(),
                     path: PathBuf::from("/root/doc2.txt"),
                     size: 500,
                     is_directory: false,
                     file_type: FileType::Document,
                     modified: SystemTime::now(),
+                    is_symlink: false,
+                    symlink_target: None,
+                    synthetic: None,
+                    pct_of_parent: None,
+                    pct_of_volume: None,
+                    is_partial: false,
                     children: vec![],
                 },
                 FileNode {
+                    id: 0,
                     name: "image.jpg".to_string(),
                     path: PathBuf::from("/root/image.jpg"),
                     size: 1500,
                     is_directory: false,
                     file_type: FileType::Image,
                     modified: SystemTime::now(),
+                    is_symlink: false,
+                    symlink_target: None,
+                    synthetic: None,
+                    pct_of_parent: None,
+                    pct_of_volume: None,
+                    is_partial: false,
                     children: vec![],
                 },
             ],
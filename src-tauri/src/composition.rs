@@ -0,0 +1,343 @@
+use crate::disk_images::{parse_vhd_footer, read_at, u32_le, u64_be, TRAILER_SIZE};
+use crate::estimate::disk_size;
+use crate::registry;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Files at least this big are analyzed unless the caller asks for another threshold
+const DEFAULT_MIN_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Enough of the start of a file to recognize every format handled here
+const HEADER_SIZE: usize = 512;
+
+/// Tar archives are read one 512-byte header at a time
+const TAR_BLOCK: u64 = 512;
+
+/// Tar entries read before giving up on listing the layers of an archive
+const MAX_TAR_ENTRIES: usize = 100_000;
+
+/// VirtualBox VDI signature, at offset 0x40
+const VDI_SIGNATURE: u32 = 0xBEDA_107F;
+
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VmDiskFormat {
+    /// VirtualBox
+    Vdi,
+    /// VMware
+    Vmdk,
+    /// QEMU
+    Qcow2,
+    /// Parallels
+    Hds,
+    /// Hyper-V and Virtual PC
+    Vhd,
+    Vhdx,
+}
+
+/// Total size of one layer (or other top-level entry) of an image archive
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArchiveLayer {
+    pub name: String,
+    pub size: u64,
+}
+
+/// What a large file is made of, for the formats that can be read without a helper tool
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FileComposition {
+    /// A virtual machine disk; `provisioned` is the capacity the guest sees
+    VmDisk {
+        format: VmDiskFormat,
+        provisioned: Option<u64>,
+    },
+    /// An SQLite database; free pages are kept in the file until it is vacuumed
+    Sqlite {
+        page_size: u64,
+        page_count: u64,
+        free_pages: u64,
+    },
+    /// An image written by `docker save`, largest layer first
+    DockerArchive { layers: Vec<ArchiveLayer> },
+}
+
+/// A large file and, where its format is known, what is inside it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LargeFileAnalysis {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Bytes actually allocated on disk; less than `size` for sparse files
+    pub allocated: u64,
+    pub composition: Option<FileComposition>,
+    /// Bytes compacting the file would give back without losing data, when that is known
+    pub reclaimable_by_compaction: Option<u64>,
+}
+
+fn u16_be(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]) as u64)
+}
+
+fn u32_be(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64)
+}
+
+fn u64_le(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes: [u8; 8] = data.get(offset..offset + 8)?.try_into().ok()?;
+    Some(u64::from_le_bytes(bytes))
+}
+
+/// Page counts from an SQLite database header (the first 100 bytes)
+fn parse_sqlite_header(header: &[u8]) -> Option<FileComposition> {
+    if header.get(0..16) != Some(SQLITE_MAGIC) {
+        return None;
+    }
+    // A page size of 1 stands for 65536, which doesn't fit in the two bytes
+    let page_size = match u16_be(header, 16)? {
+        1 => 65536,
+        size => size,
+    };
+    Some(FileComposition::Sqlite {
+        page_size,
+        page_count: u32_be(header, 28)?,
+        free_pages: u32_be(header, 36)?,
+    })
+}
+
+/// Format and capacity of a VM disk recognized by its header
+fn parse_vm_header(header: &[u8]) -> Option<(VmDiskFormat, Option<u64>)> {
+    if header.get(0..4) == Some(b"KDMV") {
+        // Capacity in 512-byte sectors
+        return Some((VmDiskFormat::Vmdk, u64_le(header, 12).map(|s| s * 512)));
+    }
+    if header.get(0..4) == Some(b"QFI\xfb") {
+        return Some((VmDiskFormat::Qcow2, u64_be(header, 24)));
+    }
+    if u32_le(header, 0x40) == Some(VDI_SIGNATURE as u64) {
+        return Some((VmDiskFormat::Vdi, u64_le(header, 0x170)));
+    }
+    if matches!(
+        header.get(0..16),
+        Some(b"WithoutFreeSpace") | Some(b"WithouFreSpacExt")
+    ) {
+        return Some((VmDiskFormat::Hds, u64_le(header, 36).map(|s| s * 512)));
+    }
+    if header.get(0..8) == Some(b"vhdxfile") {
+        // The capacity lives in the metadata region, which isn't worth parsing for this
+        return Some((VmDiskFormat::Vhdx, None));
+    }
+    None
+}
+
+/// Octal number field of a tar header, which may be NUL- or space-terminated
+fn tar_number(field: &[u8]) -> Option<u64> {
+    let digits: String = field
+        .iter()
+        .take_while(|&&b| b != 0 && b != b' ')
+        .map(|&b| b as char)
+        .collect();
+    u64::from_str_radix(digits.trim(), 8).ok()
+}
+
+/// Group a tar entry by the layer it belongs to: the file itself for OCI blobs, otherwise its
+/// top-level folder (or file)
+fn layer_name(entry: &str) -> String {
+    let entry = entry.trim_start_matches("./");
+    if entry.starts_with("blobs/") {
+        return entry.to_string();
+    }
+    entry.split('/').next().unwrap_or(entry).to_string()
+}
+
+/// Layer sizes of an image written by `docker save`, or `None` for any other file
+///
+/// Only the tar headers are read; entry contents are skipped.
+fn read_docker_archive(reader: &mut (impl Read + Seek)) -> Option<Vec<ArchiveLayer>> {
+    let mut sizes: HashMap<String, u64> = HashMap::new();
+    let mut has_manifest = false;
+    let mut header = [0u8; TAR_BLOCK as usize];
+    let mut offset = 0;
+
+    for _ in 0..MAX_TAR_ENTRIES {
+        reader.seek(SeekFrom::Start(offset)).ok()?;
+        if reader.read_exact(&mut header).is_err() || header.iter().all(|&b| b == 0) {
+            break;
+        }
+        if header.get(257..262) != Some(b"ustar") {
+            return None;
+        }
+        let name_end = header[..100].iter().position(|&b| b == 0).unwrap_or(100);
+        let prefix_end = 345 + header[345..500].iter().position(|&b| b == 0).unwrap_or(155);
+        let name = String::from_utf8_lossy(&header[..name_end]);
+        let name = match String::from_utf8_lossy(&header[345..prefix_end]) {
+            prefix if prefix.is_empty() => name.to_string(),
+            prefix => format!("{}/{}", prefix, name),
+        };
+        let size = tar_number(&header[124..136])?;
+
+        if name.trim_start_matches("./") == "manifest.json" {
+            has_manifest = true;
+        }
+        *sizes.entry(layer_name(&name)).or_default() += size;
+        offset += TAR_BLOCK + size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+    }
+
+    if !has_manifest {
+        return None;
+    }
+    let mut layers: Vec<ArchiveLayer> = sizes
+        .into_iter()
+        .map(|(name, size)| ArchiveLayer { name, size })
+        .collect();
+    layers.sort_by_key(|layer| std::cmp::Reverse(layer.size));
+    Some(layers)
+}
+
+fn read_composition(file: &mut File, size: u64) -> Option<FileComposition> {
+    let header = read_at(file, 0, HEADER_SIZE.min(size as usize))?;
+    if let Some(sqlite) = parse_sqlite_header(&header) {
+        return Some(sqlite);
+    }
+    if let Some((format, provisioned)) = parse_vm_header(&header) {
+        return Some(FileComposition::VmDisk {
+            format,
+            provisioned,
+        });
+    }
+    // Fixed and dynamic VHDs both end in a footer
+    let footer = size
+        .checked_sub(TRAILER_SIZE as u64)
+        .and_then(|offset| read_at(file, offset, TRAILER_SIZE));
+    if let Some((_, provisioned)) = footer.as_deref().and_then(parse_vhd_footer) {
+        return Some(FileComposition::VmDisk {
+            format: VmDiskFormat::Vhd,
+            provisioned: Some(provisioned),
+        });
+    }
+    read_docker_archive(file).map(|layers| FileComposition::DockerArchive { layers })
+}
+
+/// Bytes compaction gives back without losing data, for formats where the file says so
+fn reclaimable_by_compaction(composition: &FileComposition) -> Option<u64> {
+    match composition {
+        FileComposition::Sqlite {
+            page_size,
+            free_pages,
+            ..
+        } => Some(page_size * free_pages),
+        // Depends on free space inside the guest, which only the guest knows
+        FileComposition::VmDisk { .. } | FileComposition::DockerArchive { .. } => None,
+    }
+}
+
+/// Read what a large file is made of, without changing it
+pub fn analyze_large_file(path: &Path) -> Result<LargeFileAnalysis, String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let metadata = file.metadata().map_err(|e| e.to_string())?;
+    let composition = read_composition(&mut file, metadata.len());
+
+    Ok(LargeFileAnalysis {
+        path: path.to_path_buf(),
+        size: metadata.len(),
+        allocated: disk_size(&metadata),
+        reclaimable_by_compaction: composition.as_ref().and_then(reclaimable_by_compaction),
+        composition,
+    })
+}
+
+// Tauri commands
+
+/// Analyze one file, whatever its size
+#[tauri::command]
+pub async fn analyze_large_file_command(path: String) -> Result<LargeFileAnalysis, String> {
+    tokio::task::spawn_blocking(move || analyze_large_file(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Analyze every file in a retained scan of at least `min_size` bytes (1 GiB by default),
+/// largest first
+#[tauri::command]
+pub async fn analyze_large_files_command(
+    scan_id: u64,
+    min_size: Option<u64>,
+) -> Result<Vec<LargeFileAnalysis>, String> {
+    let min_size = min_size.unwrap_or(DEFAULT_MIN_SIZE);
+    let mut paths = registry::with_scan(scan_id, |nodes, _| {
+        nodes
+            .values()
+            .filter(|n| !n.is_directory && !n.is_symlink && n.size >= min_size)
+            .map(|n| (n.size, n.path.clone()))
+            .collect::<Vec<_>>()
+    })
+    .await?;
+    paths.sort_by_key(|(size, _)| std::cmp::Reverse(*size));
+
+    tokio::task::spawn_blocking(move || {
+        paths
+            .iter()
+            .filter_map(|(_, path)| analyze_large_file(path).ok())
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn tar_entry(name: &str, size: usize) -> Vec<u8> {
+        let mut header = vec![0u8; TAR_BLOCK as usize];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        header[257..263].copy_from_slice(b"ustar\0");
+        header.resize(TAR_BLOCK as usize + size.div_ceil(512) * 512, 0);
+        header
+    }
+
+    #[test]
+    fn test_reads_sqlite_and_docker_archive_composition() {
+        let mut sqlite = vec![0u8; 100];
+        sqlite[..16].copy_from_slice(SQLITE_MAGIC);
+        sqlite[16..18].copy_from_slice(&4096u16.to_be_bytes());
+        sqlite[28..32].copy_from_slice(&1000u32.to_be_bytes());
+        sqlite[36..40].copy_from_slice(&250u32.to_be_bytes());
+        let composition = parse_sqlite_header(&sqlite).unwrap();
+        assert_eq!(reclaimable_by_compaction(&composition), Some(250 * 4096));
+
+        let mut vdi = vec![0u8; HEADER_SIZE];
+        vdi[0x40..0x44].copy_from_slice(&VDI_SIGNATURE.to_le_bytes());
+        vdi[0x170..0x178].copy_from_slice(&(64u64 << 30).to_le_bytes());
+        assert_eq!(
+            parse_vm_header(&vdi),
+            Some((VmDiskFormat::Vdi, Some(64 << 30)))
+        );
+
+        let mut archive = Vec::new();
+        archive.extend(tar_entry("manifest.json", 100));
+        archive.extend(tar_entry("abc123/layer.tar", 5000));
+        archive.extend(tar_entry("abc123/json", 200));
+        archive.extend(tar_entry("def456/layer.tar", 700));
+        archive.extend(vec![0u8; 1024]);
+        let layers = read_docker_archive(&mut Cursor::new(archive)).unwrap();
+        let sizes: Vec<(&str, u64)> = layers.iter().map(|l| (l.name.as_str(), l.size)).collect();
+        assert_eq!(
+            sizes,
+            vec![("abc123", 5200), ("def456", 700), ("manifest.json", 100)]
+        );
+
+        let mut plain_tar = tar_entry("notes.txt", 10);
+        plain_tar.extend(vec![0u8; 1024]);
+        assert!(read_docker_archive(&mut Cursor::new(plain_tar)).is_none());
+    }
+}
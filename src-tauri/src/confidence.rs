@@ -0,0 +1,150 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Folder names apps use for data they rebuild on demand
+const CACHE_DIR_NAMES: &[&str] = &[
+    "cache",
+    "caches",
+    ".cache",
+    "__pycache__",
+    "code cache",
+    "gpucache",
+    "shadercache",
+    "cachestorage",
+];
+
+/// Folder names of trash cans on any volume
+const TRASH_DIR_NAMES: &[&str] = &[".trash", ".trashes", "$recycle.bin"];
+
+/// How safe a cleanup suggestion is to act on without the user looking at it first
+///
+/// Ordered from least to most confident, so `confidence >= Confidence::High` reads naturally.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    /// Nothing is known about the data, e.g. a large file in a documents folder
+    #[default]
+    Low,
+    /// Probably unneeded or recoverable: old downloads, extra copies of a duplicate,
+    /// local snapshots
+    Medium,
+    /// Regenerated on demand or already thrown away: caches, the trash, system stores
+    /// cleaned through their supported tools
+    High,
+}
+
+/// Per-user folders the heuristics know about
+struct KnownDirs {
+    caches: Vec<PathBuf>,
+    trash: Vec<PathBuf>,
+    downloads: Vec<PathBuf>,
+}
+
+static KNOWN_DIRS: once_cell::sync::Lazy<KnownDirs> = once_cell::sync::Lazy::new(|| KnownDirs {
+    caches: dirs::cache_dir().into_iter().collect(),
+    trash: dirs::data_local_dir()
+        .map(|dir| dir.join("Trash"))
+        .into_iter()
+        .collect(),
+    downloads: dirs::download_dir().into_iter().collect(),
+});
+
+fn has_component(path: &Path, matches: impl Fn(&str) -> bool) -> bool {
+    path.components()
+        .any(|c| matches(&c.as_os_str().to_string_lossy().to_lowercase()))
+}
+
+/// Kinds of folder the heuristics recognize
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum KnownLocation {
+    Trash,
+    Cache,
+    Downloads,
+}
+
+fn locate(path: &Path, known: &KnownDirs) -> Option<KnownLocation> {
+    let under = |dirs: &[PathBuf]| dirs.iter().any(|dir| path.starts_with(dir));
+    if under(&known.trash)
+        || has_component(path, |name| {
+            TRASH_DIR_NAMES.contains(&name) || name.starts_with(".trash-")
+        })
+    {
+        Some(KnownLocation::Trash)
+    } else if under(&known.caches) || has_component(path, |name| CACHE_DIR_NAMES.contains(&name)) {
+        Some(KnownLocation::Cache)
+    } else if under(&known.downloads) {
+        Some(KnownLocation::Downloads)
+    } else {
+        None
+    }
+}
+
+fn classify(path: &Path, known: &KnownDirs) -> Confidence {
+    match locate(path, known) {
+        Some(KnownLocation::Trash | KnownLocation::Cache) => Confidence::High,
+        Some(KnownLocation::Downloads) => Confidence::Medium,
+        None => Confidence::Low,
+    }
+}
+
+/// Which known kind of folder `path` is in, if any
+pub(crate) fn known_location(path: &Path) -> Option<KnownLocation> {
+    locate(path, &KNOWN_DIRS)
+}
+
+/// Confidence that `path` can be deleted, judged only by where it lives
+///
+/// - High: inside the user cache folder, a folder named like a cache (`Caches`,
+///   `__pycache__`, `GPUCache`, ...) or a trash can
+/// - Medium: inside Downloads
+/// - Low: anywhere else
+///
+/// Suggestion sources raise this when they know more, e.g. that another copy is kept.
+pub(crate) fn path_confidence(path: &Path) -> Confidence {
+    classify(path, &KNOWN_DIRS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confidence_from_location() {
+        let known = KnownDirs {
+            caches: vec![PathBuf::from("/home/u/.cache")],
+            trash: vec![PathBuf::from("/home/u/.local/share/Trash")],
+            downloads: vec![PathBuf::from("/home/u/Downloads")],
+        };
+        let confidence = |path: &str| classify(Path::new(path), &known);
+        assert_eq!(confidence("/home/u/.cache/pip/wheel.whl"), Confidence::High);
+        assert_eq!(
+            confidence("/home/u/.local/share/Trash/files/a"),
+            Confidence::High
+        );
+        assert_eq!(confidence("/mnt/usb/.Trash-1000/files/a"), Confidence::High);
+        assert_eq!(
+            confidence("/home/u/.config/Code/GPUCache/data_1"),
+            Confidence::High
+        );
+        assert_eq!(
+            confidence("/home/u/Downloads/setup.iso"),
+            Confidence::Medium
+        );
+        assert_eq!(confidence("/home/u/Videos/holiday.mp4"), Confidence::Low);
+        assert!(Confidence::High > Confidence::Medium && Confidence::Medium > Confidence::Low);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::never_suggest::{self, NeverSuggestEntry};
+use crate::pins;
+use crate::policies::{self, CleanupPolicy};
+use crate::settings::{self, AppSettings};
+use crate::webhooks::{self, WebhookSettings};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Bumped when a field changes meaning; newer bundles are refused rather than half-applied
+const CONFIG_BUNDLE_VERSION: u32 = 1;
+
+/// Everything the user configured, in one file that another machine can import
+///
+/// The user's protected paths are the never-suggest list. Classification rules and profiles
+/// are built in rather than saved, so there is nothing of theirs to carry over.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConfigBundle {
+    pub version: u32,
+    /// Unix timestamp (seconds)
+    pub exported_at: i64,
+    /// Version of the app that wrote the bundle
+    pub app_version: String,
+    #[serde(default)]
+    pub settings: AppSettings,
+    #[serde(default)]
+    pub never_suggest: Vec<NeverSuggestEntry>,
+    /// Ids are reassigned on import
+    #[serde(default)]
+    pub policies: Vec<CleanupPolicy>,
+    #[serde(default)]
+    pub webhooks: WebhookSettings,
+    #[serde(default)]
+    pub pinned_paths: Vec<PathBuf>,
+}
+
+fn collect_bundle() -> Result<ConfigBundle, String> {
+    Ok(ConfigBundle {
+        version: CONFIG_BUNDLE_VERSION,
+        exported_at: chrono::Utc::now().timestamp(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        settings: settings::load_settings()?,
+        never_suggest: never_suggest::load_never_suggest()?,
+        policies: policies::load_policies()?,
+        webhooks: webhooks::load_webhooks()?,
+        pinned_paths: pins::pinned_paths()?,
+    })
+}
+
+fn parse_bundle(bytes: &[u8]) -> Result<ConfigBundle, String> {
+    let bundle: ConfigBundle =
+        serde_json::from_slice(bytes).map_err(|e| format!("Invalid configuration: {}", e))?;
+    if bundle.version > CONFIG_BUNDLE_VERSION {
+        return Err(format!(
+            "The configuration was exported by a newer version ({}); update to import it",
+            bundle.app_version
+        ));
+    }
+    Ok(bundle)
+}
+
+/// Replace every store with the bundle's contents
+fn apply_bundle(bundle: &ConfigBundle) -> Result<(), String> {
+    settings::save_settings(&bundle.settings)?;
+    never_suggest::save_never_suggest(&bundle.never_suggest)?;
+    policies::replace_policies(bundle.policies.clone())?;
+    webhooks::save_webhooks(&bundle.webhooks)?;
+    pins::replace_pins(bundle.pinned_paths.clone())
+}
+
+// Tauri commands
+
+/// Write settings, protected paths, policies, webhooks and pins to `destination` as JSON
+#[tauri::command]
+pub async fn export_config_command(destination: String) -> Result<ConfigBundle, String> {
+    let bundle = collect_bundle()?;
+    let destination = Path::new(&destination);
+    let json =
+        serde_json::to_vec_pretty(&bundle).map_err(|e| format!("Cannot serialize: {}", e))?;
+    fs::write(destination, json)
+        .map_err(|e| format!("Cannot write {}: {}", destination.display(), e))?;
+    Ok(bundle)
+}
+
+/// Replace this machine's configuration with an exported bundle
+///
+/// Sizes recorded for paths pinned on both machines are kept.
+#[tauri::command]
+pub async fn import_config_command(file: String) -> Result<ConfigBundle, String> {
+    let bytes = fs::read(&file).map_err(|e| format!("Cannot read {}: {}", file, e))?;
+    let bundle = parse_bundle(&bytes)?;
+    apply_bundle(&bundle)?;
+    collect_bundle()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bundle_checks_version_and_fills_gaps() {
+        let bundle =
+            parse_bundle(br#"{"version": 1, "exported_at": 5, "app_version": "0.1.0"}"#).unwrap();
+        assert!(bundle.policies.is_empty());
+        assert_eq!(bundle.webhooks.low_space_percent, 10);
+
+        let newer = parse_bundle(br#"{"version": 99, "exported_at": 5, "app_version": "9.0.0"}"#);
+        assert!(newer.unwrap_err().contains("9.0.0"));
+        assert!(parse_bundle(b"not json").is_err());
+    }
+}
@@ -0,0 +1,260 @@
+//! Retained scans exported as CSV, one row per entry
+//!
+//! Rows are written straight to the destination through a buffered (optionally gzipped)
+//! writer, so memory stays flat however many entries a scan has. Files a scan only kept in
+//! its directories' totals have no row; they are still counted in those directories' sizes.
+
+use crate::registry::{self, DiscoveredNode};
+use crate::space_guard::PartialOutput;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Instant, UNIX_EPOCH};
+use tauri::{Emitter, Window};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+const HEADER: &str = "path,name,size,is_directory,file_type,modified,file_count\n";
+
+/// Rows written between cancellation checks and progress events
+const PROGRESS_ROWS: u64 = 10_000;
+
+/// Token for the running CSV export
+static CSV_EXPORT: once_cell::sync::Lazy<Arc<Mutex<Option<CancellationToken>>>> =
+    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(None)));
+
+/// Progress of the running CSV export, emitted as `csv-export-progress`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CsvExportProgress {
+    pub rows_written: u64,
+    pub rows_total: u64,
+    /// Bytes written to the file so far, after compression
+    pub bytes_written: u64,
+    /// Estimated seconds left, from the pace so far
+    pub eta_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CsvExportResult {
+    pub destination: PathBuf,
+    pub rows_written: u64,
+    pub bytes_written: u64,
+    pub gzip: bool,
+}
+
+/// Passes writes through, counting the bytes
+struct CountingWriter<W> {
+    inner: W,
+    written: Rc<Cell<u64>>,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written.set(self.written.get() + n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Append `field` to `row`, quoted when it holds a separator, quote or line break
+fn push_field(row: &mut String, field: &str) {
+    if field.contains([',', '"', '\n', '\r']) {
+        row.push('"');
+        row.push_str(&field.replace('"', "\"\""));
+        row.push('"');
+    } else {
+        row.push_str(field);
+    }
+}
+
+fn push_row(row: &mut String, node: &DiscoveredNode) {
+    row.clear();
+    push_field(row, &node.path.to_string_lossy());
+    row.push(',');
+    push_field(row, &node.name);
+    let modified = node
+        .modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let _ = writeln!(
+        row,
+        ",{},{},{:?},{},{}",
+        node.size, node.is_directory, node.file_type, modified, node.file_count
+    );
+}
+
+/// Write every node as a row, in path order so directories come before their contents
+///
+/// Returns the rows written, or an error once `token` is cancelled.
+fn write_rows(
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+    writer: &mut impl Write,
+    token: &CancellationToken,
+    on_progress: &mut impl FnMut(u64),
+) -> Result<u64, String> {
+    let mut sorted: Vec<&DiscoveredNode> = nodes.values().collect();
+    sorted.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+
+    let write_error = |e: io::Error| format!("Cannot write CSV: {}", e);
+    writer.write_all(HEADER.as_bytes()).map_err(write_error)?;
+    let mut row = String::new();
+    let mut written = 0u64;
+    for node in sorted {
+        push_row(&mut row, node);
+        writer.write_all(row.as_bytes()).map_err(write_error)?;
+        written += 1;
+        if written.is_multiple_of(PROGRESS_ROWS) {
+            if token.is_cancelled() {
+                return Err("CSV export cancelled".to_string());
+            }
+            on_progress(written);
+        }
+    }
+    Ok(written)
+}
+
+fn export(
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+    destination: &Path,
+    gzip: bool,
+    token: &CancellationToken,
+    window: &Window,
+) -> Result<CsvExportResult, String> {
+    // The final size isn't known up front, so only the usual margin is checked
+    let output = PartialOutput::new(destination, 0)?;
+    let file = File::create(output.path())
+        .map_err(|e| format!("Cannot write {}: {}", destination.display(), e))?;
+    let bytes_written = Rc::new(Cell::new(0));
+    let counted = BufWriter::new(CountingWriter {
+        inner: file,
+        written: bytes_written.clone(),
+    });
+    let rows_total = nodes.len() as u64;
+    let started = Instant::now();
+    let mut on_progress = |rows_written: u64| {
+        let elapsed = started.elapsed().as_secs_f64();
+        let progress = CsvExportProgress {
+            rows_written,
+            rows_total,
+            bytes_written: bytes_written.get(),
+            eta_secs: (elapsed * (rows_total - rows_written) as f64 / rows_written as f64) as u64,
+        };
+        let _ = window.emit("csv-export-progress", &progress);
+    };
+    let write_error = |e: io::Error| format!("Cannot write {}: {}", destination.display(), e);
+    let rows_written = if gzip {
+        let mut encoder = GzEncoder::new(counted, Compression::default());
+        let rows = write_rows(nodes, &mut encoder, token, &mut on_progress)?;
+        encoder
+            .finish()
+            .and_then(|mut counted| counted.flush())
+            .map_err(write_error)?;
+        rows
+    } else {
+        let mut counted = counted;
+        let rows = write_rows(nodes, &mut counted, token, &mut on_progress)?;
+        counted.flush().map_err(write_error)?;
+        rows
+    };
+    output.commit()?;
+
+    Ok(CsvExportResult {
+        destination: destination.to_path_buf(),
+        rows_written,
+        bytes_written: bytes_written.get(),
+        gzip,
+    })
+}
+
+// Tauri commands
+
+/// Export a retained scan as CSV, emitting `csv-export-progress` events
+///
+/// `gzip` defaults to whether `destination` ends in `.gz`. The scan can't be queried while
+/// it is being written out. A cancelled or failed export leaves nothing at `destination`.
+#[tauri::command]
+pub async fn export_scan_csv_command(
+    scan_id: u64,
+    destination: String,
+    gzip: Option<bool>,
+    window: Window,
+) -> Result<CsvExportResult, String> {
+    let destination = PathBuf::from(destination);
+    let gzip = gzip.unwrap_or_else(|| destination.extension().is_some_and(|e| e == "gz"));
+    let nodes = registry::scan_nodes(scan_id).await?;
+
+    let token = CancellationToken::new();
+    {
+        let mut job = CSV_EXPORT.lock().await;
+        if job.is_some() {
+            return Err("A CSV export is already running".to_string());
+        }
+        *job = Some(token.clone());
+    }
+
+    let result = tokio::task::spawn_blocking(move || {
+        let nodes = nodes.blocking_lock();
+        export(&nodes, &destination, gzip, &token, &window)
+    })
+    .await
+    .map_err(|e| e.to_string());
+
+    *CSV_EXPORT.lock().await = None;
+    result?
+}
+
+/// Cancel the running CSV export
+#[tauri::command]
+pub async fn cancel_csv_export_command() -> Result<(), String> {
+    match CSV_EXPORT.lock().await.as_ref() {
+        Some(token) => {
+            token.cancel();
+            Ok(())
+        }
+        None => Err("No CSV export is currently running".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_are_quoted_and_ordered() {
+        let nodes: HashMap<PathBuf, DiscoveredNode> = [
+            registry::test_node("/scan/b", 20, false),
+            registry::test_node("/scan", 0, true),
+            registry::test_node("/scan/a \"quoted\", name", 10, false),
+        ]
+        .into_iter()
+        .map(|n| (n.path.clone(), n))
+        .collect();
+
+        let mut out = Vec::new();
+        let rows = write_rows(&nodes, &mut out, &CancellationToken::new(), &mut |_| {}).unwrap();
+        assert_eq!(rows, 3);
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], HEADER.trim_end());
+        assert_eq!(lines[1], "/scan,scan,0,true,Other,0,0");
+        assert_eq!(
+            lines[2],
+            "\"/scan/a \"\"quoted\"\", name\",\"a \"\"quoted\"\", name\",10,false,Other,0,1"
+        );
+        assert_eq!(lines[3], "/scan/b,b,20,false,Other,0,1");
+    }
+}
@@ -0,0 +1,139 @@
+//! Phones and cameras attached over MTP or PTP
+//!
+//! Neither protocol exposes a block device. On Linux GVFS mounts them as FUSE folders
+//! under `$XDG_RUNTIME_DIR/gvfs`, which the scanner can walk like any other folder. macOS
+//! and Windows only reach them through ImageCapture and the shell's portable device
+//! namespace, which have no paths to scan, so nothing is listed there.
+
+#[cfg(target_os = "linux")]
+use crate::storage::{get_linux_volume_stats, LocationType, StorageLocation};
+use std::path::{Component, Path};
+
+/// GVFS mount name prefixes of the protocols phones and cameras use
+const DEVICE_PREFIXES: &[&str] = &["mtp:host=", "gphoto2:host="];
+
+/// Whether `path` is on a phone or camera GVFS mounted
+///
+/// Such paths are scanned read-only: MTP deletes can't be undone and half-finished
+/// transfers leave devices in odd states.
+pub fn is_portable_device_path(path: &Path) -> bool {
+    let mut after_gvfs = false;
+    for component in path.components() {
+        let Component::Normal(name) = component else {
+            continue;
+        };
+        let name = name.to_string_lossy();
+        if after_gvfs {
+            return DEVICE_PREFIXES.iter().any(|p| name.starts_with(p));
+        }
+        after_gvfs = name == "gvfs";
+    }
+    false
+}
+
+/// Readable name of a device from its GVFS mount name, e.g.
+/// `mtp:host=Google_Pixel_7_2A111FDH2000B1` becomes `Google Pixel 7 2A111FDH2000B1`
+#[cfg(any(target_os = "linux", test))]
+fn device_name(mount_name: &str) -> Option<String> {
+    let host = DEVICE_PREFIXES
+        .iter()
+        .find_map(|prefix| mount_name.strip_prefix(prefix))?;
+    let mut bytes = Vec::with_capacity(host.len());
+    let mut rest = host.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(if byte == b'_' { b' ' } else { byte });
+                rest = tail;
+            }
+        }
+    }
+    let name = String::from_utf8_lossy(&bytes).trim().to_string();
+    Some(name).filter(|n| !n.is_empty())
+}
+
+/// Phones and cameras GVFS has mounted, one location per storage on the device
+#[cfg(target_os = "linux")]
+pub(crate) fn portable_devices() -> Vec<StorageLocation> {
+    use std::path::PathBuf;
+
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("/run/user/{}", unsafe { libc::getuid() })));
+    let Ok(mounts) = std::fs::read_dir(runtime_dir.join("gvfs")) else {
+        return Vec::new();
+    };
+
+    let mut locations = Vec::new();
+    for mount in mounts.flatten() {
+        let mount_name = mount.file_name().to_string_lossy().to_string();
+        let Some(device) = device_name(&mount_name) else {
+            continue;
+        };
+        let mount = mount.path();
+        // MTP devices list their storages (internal, SD card) as top-level folders;
+        // cameras over PTP show their files directly
+        let storages: Vec<PathBuf> = match std::fs::read_dir(&mount) {
+            Ok(entries) if mount_name.starts_with("mtp:") => entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect(),
+            _ => vec![mount.clone()],
+        };
+        for storage in storages {
+            let name = match storage.strip_prefix(&mount).ok().and_then(|s| s.to_str()) {
+                Some("") | None => device.clone(),
+                Some(storage_name) => format!("{} – {}", device, storage_name),
+            };
+            let (total, available) =
+                get_linux_volume_stats(&storage.to_string_lossy()).unwrap_or((0, 0));
+            locations.push(StorageLocation {
+                name,
+                path: storage,
+                location_type: LocationType::Device,
+                total_space: Some(total).filter(|&t| t > 0),
+                available_space: Some(available).filter(|&a| a > 0),
+                inodes: None,
+                quota: None,
+                shadow_storage: None,
+            });
+        }
+    }
+    locations.sort_by(|a, b| a.name.cmp(&b.name));
+    locations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_mounts_are_named_and_read_only() {
+        assert_eq!(
+            device_name("mtp:host=Google_Pixel_7_2A111FDH2000B1").as_deref(),
+            Some("Google Pixel 7 2A111FDH2000B1")
+        );
+        assert_eq!(
+            device_name("gphoto2:host=%5Busb%3A001%2C005%5D").as_deref(),
+            Some("[usb:001,005]")
+        );
+        assert_eq!(device_name("smb-share:server=nas,share=media"), None);
+
+        assert!(is_portable_device_path(Path::new(
+            "/run/user/1000/gvfs/mtp:host=Pixel/Internal shared storage/DCIM"
+        )));
+        assert!(!is_portable_device_path(Path::new(
+            "/run/user/1000/gvfs/smb-share:server=nas,share=media"
+        )));
+        assert!(!is_portable_device_path(Path::new("/home/u/mtp:host=x")));
+    }
+}
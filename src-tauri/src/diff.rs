@@ -0,0 +1,330 @@
+use crate::classifier::{get_category_stats, CategoryStats};
+use crate::persistence::load_scan;
+use crate::types::{FileNode, FileType};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// How a child's size changed between two scans
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeChange {
+    New,
+    Grew,
+    Shrank,
+    Removed,
+}
+
+/// One bar of the waterfall: a child and what it added to or took from its parent
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WaterfallStep {
+    pub name: String,
+    pub path: PathBuf,
+    pub change: SizeChange,
+    pub is_directory: bool,
+    pub old_size: u64,
+    pub new_size: u64,
+    pub delta: i64,
+}
+
+/// All children that changed the same way, summed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WaterfallBucket {
+    pub change: SizeChange,
+    pub count: u64,
+    pub delta: i64,
+}
+
+/// Where a directory's size change came from, child by child
+///
+/// Steps run from `old_size` to `new_size`: new children first, then those that grew,
+/// shrank and were removed, each largest change first.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DiffWaterfall {
+    pub path: PathBuf,
+    pub old_size: u64,
+    pub new_size: u64,
+    pub delta: i64,
+    pub steps: Vec<WaterfallStep>,
+    /// One per kind of change that occurred, in step order
+    pub buckets: Vec<WaterfallBucket>,
+    /// Children present in both scans at the same size
+    pub unchanged_count: u64,
+}
+
+/// How much of one kind of data there was in each scan
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CategoryChange {
+    pub category: FileType,
+    pub old_size: u64,
+    pub new_size: u64,
+    pub delta: i64,
+    pub old_file_count: u64,
+    pub new_file_count: u64,
+}
+
+/// The node at `path`, found by walking down from the root
+fn find_node<'a>(tree: &'a FileNode, path: &Path) -> Option<&'a FileNode> {
+    if tree.path == path {
+        return Some(tree);
+    }
+    tree.children
+        .iter()
+        .find(|child| path.starts_with(&child.path))
+        .and_then(|child| find_node(child, path))
+}
+
+fn delta(old: u64, new: u64) -> i64 {
+    new as i64 - old as i64
+}
+
+fn waterfall(old: Option<&FileNode>, new: Option<&FileNode>, path: &Path) -> DiffWaterfall {
+    let old_children: HashMap<&str, &FileNode> = old
+        .map(|n| n.children.iter().map(|c| (c.name.as_str(), c)).collect())
+        .unwrap_or_default();
+    let new_children = new.map(|n| n.children.as_slice()).unwrap_or_default();
+
+    let step = |node: &FileNode, change, old_size, new_size| WaterfallStep {
+        name: node.name.clone(),
+        path: node.path.clone(),
+        change,
+        is_directory: node.is_directory,
+        old_size,
+        new_size,
+        delta: delta(old_size, new_size),
+    };
+    let mut steps = Vec::new();
+    let mut unchanged_count = 0;
+    for child in new_children {
+        match old_children.get(child.name.as_str()) {
+            None => steps.push(step(child, SizeChange::New, 0, child.size)),
+            Some(before) if child.size > before.size => {
+                steps.push(step(child, SizeChange::Grew, before.size, child.size))
+            }
+            Some(before) if child.size < before.size => {
+                steps.push(step(child, SizeChange::Shrank, before.size, child.size))
+            }
+            Some(_) => unchanged_count += 1,
+        }
+    }
+    let still_there: HashSet<&str> = new_children.iter().map(|c| c.name.as_str()).collect();
+    for (name, child) in &old_children {
+        if !still_there.contains(name) {
+            steps.push(step(child, SizeChange::Removed, child.size, 0));
+        }
+    }
+    steps.sort_by_key(|s| (s.change as u8, std::cmp::Reverse(s.delta.unsigned_abs())));
+
+    let mut buckets: Vec<WaterfallBucket> = Vec::new();
+    for step in &steps {
+        match buckets.last_mut() {
+            Some(bucket) if bucket.change == step.change => {
+                bucket.count += 1;
+                bucket.delta += step.delta;
+            }
+            _ => buckets.push(WaterfallBucket {
+                change: step.change,
+                count: 1,
+                delta: step.delta,
+            }),
+        }
+    }
+
+    let old_size = old.map_or(0, |n| n.size);
+    let new_size = new.map_or(0, |n| n.size);
+    DiffWaterfall {
+        path: path.to_path_buf(),
+        old_size,
+        new_size,
+        delta: delta(old_size, new_size),
+        steps,
+        buckets,
+        unchanged_count,
+    }
+}
+
+/// Every category in either scan, largest change first
+fn category_changes(old: &FileNode, new: &FileNode) -> Vec<CategoryChange> {
+    let before: HashMap<FileType, CategoryStats> = get_category_stats(old)
+        .into_iter()
+        .map(|s| (s.category.clone(), s))
+        .collect();
+    let after: HashMap<FileType, CategoryStats> = get_category_stats(new)
+        .into_iter()
+        .map(|s| (s.category.clone(), s))
+        .collect();
+    let categories: HashSet<FileType> = before.keys().chain(after.keys()).cloned().collect();
+
+    let mut changes: Vec<CategoryChange> = categories
+        .into_iter()
+        .map(|category| {
+            let old = before.get(&category);
+            let new = after.get(&category);
+            let old_size = old.map_or(0, |s| s.total_size);
+            let new_size = new.map_or(0, |s| s.total_size);
+            CategoryChange {
+                old_size,
+                new_size,
+                delta: delta(old_size, new_size),
+                old_file_count: old.map_or(0, |s| s.file_count),
+                new_file_count: new.map_or(0, |s| s.file_count),
+                category,
+            }
+        })
+        .collect();
+    changes.sort_by_key(|c| std::cmp::Reverse(c.delta.unsigned_abs()));
+    changes
+}
+
+// Tauri commands
+
+/// Per-child contributions to how `path` changed between two saved scans
+///
+/// A directory missing from one scan counts as empty there, so all its children show
+/// as new or removed.
+#[tauri::command]
+pub async fn get_diff_waterfall_command(
+    old: String,
+    new: String,
+    path: String,
+) -> Result<DiffWaterfall, String> {
+    tokio::task::spawn_blocking(move || {
+        let old = load_scan(Path::new(&old))?;
+        let new = load_scan(Path::new(&new))?;
+        let path = PathBuf::from(path);
+        let before = find_node(&old.tree, &path);
+        let after = find_node(&new.tree, &path);
+        if before.is_none() && after.is_none() {
+            return Err(format!("{} is in neither scan", path.display()));
+        }
+        Ok(waterfall(before, after, &path))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// How each kind of data grew or shrank between two saved scans
+#[tauri::command]
+pub async fn get_category_diff_command(
+    old: String,
+    new: String,
+) -> Result<Vec<CategoryChange>, String> {
+    tokio::task::spawn_blocking(move || {
+        let old = load_scan(Path::new(&old))?;
+        let new = load_scan(Path::new(&new))?;
+        Ok(category_changes(&old.tree, &new.tree))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn node(path: &str, size: u64, children: Vec<FileNode>) -> FileNode {
+        let path = PathBuf::from(path);
+        FileNode {
+            id: 0,
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            path,
+            size,
+            is_directory: !children.is_empty(),
+            children,
+            file_type: FileType::Other,
+            modified: SystemTime::UNIX_EPOCH,
+            is_symlink: false,
+            symlink_target: None,
+            synthetic: None,
+            pct_of_parent: None,
+            pct_of_volume: None,
+            is_partial: false,
+        }
+    }
+
+    #[test]
+    fn test_waterfall_buckets_child_changes() {
+        let old = node(
+            "/data",
+            100,
+            vec![
+                node("/data/a", 40, vec![]),
+                node("/data/b", 30, vec![]),
+                node("/data/c", 20, vec![]),
+                node("/data/d", 10, vec![]),
+            ],
+        );
+        let new = node(
+            "/data",
+            135,
+            vec![
+                node("/data/a", 70, vec![]),
+                node("/data/b", 10, vec![]),
+                node("/data/c", 20, vec![]),
+                node("/data/e", 35, vec![]),
+            ],
+        );
+        let diff = waterfall(
+            find_node(&old, Path::new("/data")),
+            find_node(&new, Path::new("/data")),
+            Path::new("/data"),
+        );
+
+        let steps: Vec<(&str, SizeChange, i64)> = diff
+            .steps
+            .iter()
+            .map(|s| (s.name.as_str(), s.change, s.delta))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                ("e", SizeChange::New, 35),
+                ("a", SizeChange::Grew, 30),
+                ("b", SizeChange::Shrank, -20),
+                ("d", SizeChange::Removed, -10),
+            ]
+        );
+        assert_eq!(diff.delta, 35);
+        assert_eq!(
+            diff.buckets.iter().map(|b| b.delta).sum::<i64>(),
+            diff.delta
+        );
+        assert_eq!(diff.unchanged_count, 1);
+        assert_eq!(find_node(&new, Path::new("/data/e")).unwrap().size, 35);
+    }
+
+    #[test]
+    fn test_category_changes() {
+        let typed = |path: &str, size: u64, file_type: FileType| FileNode {
+            file_type,
+            ..node(path, size, vec![])
+        };
+        let old = node(
+            "/data",
+            50,
+            vec![
+                typed("/data/a.mp4", 20, FileType::Video),
+                typed("/data/b.zip", 30, FileType::Archive),
+            ],
+        );
+        let new = node(
+            "/data",
+            125,
+            vec![
+                typed("/data/a.mp4", 20, FileType::Video),
+                typed("/data/c.mp4", 100, FileType::Video),
+                typed("/data/b.zip", 5, FileType::Archive),
+            ],
+        );
+        let changes: Vec<(FileType, i64, u64)> = category_changes(&old, &new)
+            .into_iter()
+            .map(|c| (c.category, c.delta, c.new_file_count))
+            .collect();
+        assert_eq!(
+            changes,
+            [(FileType::Video, 100, 2), (FileType::Archive, -25, 1)]
+        );
+    }
+}
@@ -0,0 +1,432 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// ISO 9660 logical sector size
+const ISO_SECTOR: u64 = 2048;
+
+/// Offset of the ISO 9660 primary volume descriptor (sector 16)
+const ISO_PVD_OFFSET: u64 = 16 * ISO_SECTOR;
+
+/// Size of the DMG (UDIF "koly") and VHD ("conectix") trailers
+pub(crate) const TRAILER_SIZE: usize = 512;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskImageFormat {
+    Dmg,
+    Iso,
+    Vhd,
+    Vhdx,
+}
+
+/// A top-level file or folder inside a disk image
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DiskImageEntry {
+    pub name: String,
+    pub is_directory: bool,
+    /// Unknown for folders of an ISO read without mounting
+    pub size: Option<u64>,
+}
+
+/// Metadata and, where the platform allows, top-level contents of a disk image
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DiskImageInfo {
+    pub path: PathBuf,
+    pub format: DiskImageFormat,
+    /// Size of the image file itself
+    pub file_size: u64,
+    /// Capacity of the disk the image represents
+    pub virtual_size: Option<u64>,
+    pub volume_name: Option<String>,
+    /// Application that created the image (VHD/VHDX)
+    pub creator: Option<String>,
+    pub entries: Vec<DiskImageEntry>,
+    /// Why the contents could not be listed, when they couldn't
+    pub listing_error: Option<String>,
+}
+
+fn detect_format(path: &Path) -> Option<DiskImageFormat> {
+    match path.extension()?.to_string_lossy().to_lowercase().as_str() {
+        "dmg" => Some(DiskImageFormat::Dmg),
+        "iso" => Some(DiskImageFormat::Iso),
+        "vhd" => Some(DiskImageFormat::Vhd),
+        "vhdx" => Some(DiskImageFormat::Vhdx),
+        _ => None,
+    }
+}
+
+pub(crate) fn read_at(file: &mut File, offset: u64, len: usize) -> Option<Vec<u8>> {
+    let mut buffer = vec![0u8; len];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+pub(crate) fn u32_le(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64)
+}
+
+pub(crate) fn u64_be(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes: [u8; 8] = data.get(offset..offset + 8)?.try_into().ok()?;
+    Some(u64::from_be_bytes(bytes))
+}
+
+/// Volume name and size from an ISO 9660 primary volume descriptor
+fn parse_iso_volume(pvd: &[u8]) -> Option<(String, u64)> {
+    if pvd.first() != Some(&1) || pvd.get(1..6) != Some(b"CD001") {
+        return None;
+    }
+    let name = String::from_utf8_lossy(pvd.get(40..72)?).trim().to_string();
+    let blocks = u32_le(pvd, 80)?;
+    let block_size = u16::from_le_bytes([*pvd.get(128)?, *pvd.get(129)?]) as u64;
+    Some((name, blocks * block_size))
+}
+
+/// Top-level entries of an ISO 9660 image, read from the root directory extent
+fn read_iso_root(file: &mut File, pvd: &[u8]) -> Option<Vec<DiskImageEntry>> {
+    // The root directory record is embedded in the PVD at byte 156
+    let root = pvd.get(156..156 + 34)?;
+    let extent = read_at(
+        file,
+        u32_le(root, 2)? * ISO_SECTOR,
+        u32_le(root, 10)? as usize,
+    )?;
+    Some(parse_iso_directory(&extent))
+}
+
+fn parse_iso_directory(extent: &[u8]) -> Vec<DiskImageEntry> {
+    let mut entries = Vec::new();
+    let mut offset = 0usize;
+
+    while offset < extent.len() {
+        let length = extent[offset] as usize;
+        if length == 0 {
+            // Records never straddle sectors; the rest of this sector is padding
+            offset = (offset / ISO_SECTOR as usize + 1) * ISO_SECTOR as usize;
+            continue;
+        }
+        let Some(record) = extent.get(offset..offset + length) else {
+            break;
+        };
+        offset += length;
+
+        let name_length = record.get(32).copied().unwrap_or(0) as usize;
+        let Some(name) = record.get(33..33 + name_length) else {
+            continue;
+        };
+        // 0x00 and 0x01 are the "." and ".." records
+        if name == [0] || name == [1] {
+            continue;
+        }
+
+        let is_directory = record[25] & 0x02 != 0;
+        let name = String::from_utf8_lossy(name);
+        // Drop the ";1" version suffix ISO 9660 adds to file names
+        let name = name.split(';').next().unwrap_or_default().to_string();
+        entries.push(DiskImageEntry {
+            name,
+            is_directory,
+            size: (!is_directory).then(|| u32_le(record, 10).unwrap_or(0)),
+        });
+    }
+
+    entries
+}
+
+/// Virtual disk size from a DMG's UDIF trailer
+fn parse_dmg_trailer(trailer: &[u8]) -> Option<u64> {
+    if trailer.get(0..4) != Some(b"koly") {
+        return None;
+    }
+    // SectorCount, in 512-byte sectors
+    u64_be(trailer, 0x1EC).map(|sectors| sectors * 512)
+}
+
+/// Creator and virtual size from a VHD footer
+pub(crate) fn parse_vhd_footer(footer: &[u8]) -> Option<(String, u64)> {
+    if footer.get(0..8) != Some(b"conectix") {
+        return None;
+    }
+    let creator = String::from_utf8_lossy(footer.get(28..32)?)
+        .trim()
+        .to_string();
+    Some((creator, u64_be(footer, 48)?))
+}
+
+/// Creator string from a VHDX file identifier
+fn parse_vhdx_identifier(header: &[u8]) -> Option<String> {
+    if header.get(0..8) != Some(b"vhdxfile") {
+        return None;
+    }
+    let units: Vec<u16> = header
+        .get(8..520)?
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&c| c != 0)
+        .collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+/// List a mounted volume's top level, sizing folders by walking them
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn list_mounted(root: &Path) -> Result<Vec<DiskImageEntry>, String> {
+    let mut entries: Vec<DiskImageEntry> = std::fs::read_dir(root)
+        .map_err(|e| format!("Cannot read {}: {}", root.display(), e))?
+        .filter_map(|e| e.ok())
+        .map(|entry| {
+            let path = entry.path();
+            DiskImageEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                is_directory: path.is_dir(),
+                size: crate::safety::calculate_path_size(&path).ok(),
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+/// Attach the image read-only with hdiutil, list it, then detach it again
+#[cfg(target_os = "macos")]
+fn list_by_mounting(path: &Path, _format: DiskImageFormat) -> Result<Vec<DiskImageEntry>, String> {
+    use std::process::Command;
+
+    let output = Command::new("hdiutil")
+        .args([
+            "attach",
+            "-readonly",
+            "-nobrowse",
+            "-noautoopen",
+            "-noverify",
+        ])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run hdiutil: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "hdiutil attach failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // Lines look like "/dev/disk4s1 <tab> Apple_HFS <tab> /Volumes/Name"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let device = stdout
+        .split_whitespace()
+        .find(|w| w.starts_with("/dev/"))
+        .map(str::to_string);
+    let mount_point = stdout
+        .lines()
+        .filter_map(|line| line.split('\t').next_back())
+        .map(str::trim)
+        .find(|p| p.starts_with('/') && !p.starts_with("/dev/"))
+        .map(PathBuf::from);
+
+    let entries = match &mount_point {
+        Some(mount_point) => list_mounted(mount_point),
+        None => Err("The image has no mountable volume".to_string()),
+    };
+
+    if let Some(device) = device {
+        let _ = Command::new("hdiutil").args(["detach", &device]).output();
+    }
+    entries
+}
+
+/// Mount the image read-only with Mount-DiskImage, list it, then dismount it
+#[cfg(target_os = "windows")]
+fn list_by_mounting(path: &Path, format: DiskImageFormat) -> Result<Vec<DiskImageEntry>, String> {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    /// Carries the image path into the scripts, which never see it as code: PowerShell
+    /// treats curly quotes as quotes too, so no escaping of a file name is safe
+    const IMAGE_VAR: &str = "DISK_ANALYSER_IMAGE";
+
+    if format == DiskImageFormat::Dmg {
+        return Err("DMG images can only be opened on macOS".to_string());
+    }
+
+    let powershell = |script: &str| {
+        Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", script])
+            .env(IMAGE_VAR, path)
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .map_err(|e| format!("Failed to run PowerShell: {}", e))
+    };
+
+    // ISOs expose a volume directly; VHD(X) volumes hang off the attached disk's partitions
+    let output = powershell(
+        "$image = Mount-DiskImage -ImagePath $env:DISK_ANALYSER_IMAGE -Access ReadOnly -PassThru; \
+         $volume = $image | Get-Volume -ErrorAction SilentlyContinue; \
+         if (-not $volume) { $volume = $image | Get-Disk | Get-Partition | Get-Volume }; \
+         $volume | Where-Object DriveLetter | Select-Object -First 1 -ExpandProperty DriveLetter",
+    )?;
+    let letter = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    let entries = if output.status.success() && !letter.is_empty() {
+        list_mounted(Path::new(&format!("{}:\\", letter)))
+    } else {
+        Err(format!(
+            "Mount-DiskImage failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    };
+
+    let _ = powershell("Dismount-DiskImage -ImagePath $env:DISK_ANALYSER_IMAGE");
+    entries
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn list_by_mounting(_path: &Path, _format: DiskImageFormat) -> Result<Vec<DiskImageEntry>, String> {
+    Err(
+        "Listing this image type needs mounting, which is only supported on macOS and Windows"
+            .to_string(),
+    )
+}
+
+/// Read a disk image's metadata and top-level contents without modifying it
+pub fn inspect_disk_image(path: &Path) -> Result<DiskImageInfo, String> {
+    let format = detect_format(path)
+        .ok_or_else(|| format!("{} is not a DMG, ISO, VHD or VHDX image", path.display()))?;
+    let mut file =
+        File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let file_size = file.metadata().map_err(|e| e.to_string())?.len();
+    let trailer = file_size
+        .checked_sub(TRAILER_SIZE as u64)
+        .and_then(|offset| read_at(&mut file, offset, TRAILER_SIZE));
+
+    let mut info = DiskImageInfo {
+        path: path.to_path_buf(),
+        format,
+        file_size,
+        virtual_size: None,
+        volume_name: None,
+        creator: None,
+        entries: Vec::new(),
+        listing_error: None,
+    };
+
+    // ISO directories are simple enough to read in place; other formats must be mounted
+    let listing = match format {
+        DiskImageFormat::Iso => {
+            let pvd = read_at(&mut file, ISO_PVD_OFFSET, ISO_SECTOR as usize);
+            if let Some((name, size)) = pvd.as_deref().and_then(parse_iso_volume) {
+                info.volume_name = Some(name).filter(|n| !n.is_empty());
+                info.virtual_size = Some(size);
+            }
+            pvd.and_then(|pvd| read_iso_root(&mut file, &pvd))
+                .ok_or_else(|| "No ISO 9660 file system found".to_string())
+        }
+        DiskImageFormat::Dmg => {
+            info.virtual_size = trailer.as_deref().and_then(parse_dmg_trailer);
+            list_by_mounting(path, format)
+        }
+        DiskImageFormat::Vhd => {
+            if let Some((creator, size)) = trailer.as_deref().and_then(parse_vhd_footer) {
+                info.creator = Some(creator);
+                info.virtual_size = Some(size);
+            }
+            list_by_mounting(path, format)
+        }
+        DiskImageFormat::Vhdx => {
+            info.creator = read_at(&mut file, 0, 520)
+                .as_deref()
+                .and_then(parse_vhdx_identifier);
+            list_by_mounting(path, format)
+        }
+    };
+
+    match listing {
+        Ok(entries) => info.entries = entries,
+        Err(e) => info.listing_error = Some(e),
+    }
+    Ok(info)
+}
+
+// Tauri commands
+
+/// Look inside a DMG, ISO, VHD or VHDX image without changing it
+#[tauri::command]
+pub async fn inspect_disk_image_command(path: String) -> Result<DiskImageInfo, String> {
+    tokio::task::spawn_blocking(move || inspect_disk_image(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn iso_record(name: &[u8], extent: u32, size: u32, is_directory: bool) -> Vec<u8> {
+        let length = 33 + name.len() + (name.len() + 1) % 2;
+        let mut record = vec![0u8; length];
+        record[0] = length as u8;
+        record[2..6].copy_from_slice(&extent.to_le_bytes());
+        record[10..14].copy_from_slice(&size.to_le_bytes());
+        record[25] = if is_directory { 0x02 } else { 0 };
+        record[32] = name.len() as u8;
+        record[33..33 + name.len()].copy_from_slice(name);
+        record
+    }
+
+    #[test]
+    fn test_inspect_iso() {
+        let mut image = vec![0u8; 20 * ISO_SECTOR as usize];
+
+        let pvd = ISO_PVD_OFFSET as usize;
+        image[pvd] = 1;
+        image[pvd + 1..pvd + 6].copy_from_slice(b"CD001");
+        image[pvd + 40..pvd + 72].copy_from_slice(&[b' '; 32]);
+        image[pvd + 40..pvd + 47].copy_from_slice(b"INSTALL");
+        image[pvd + 80..pvd + 84].copy_from_slice(&20u32.to_le_bytes());
+        image[pvd + 128..pvd + 130].copy_from_slice(&2048u16.to_le_bytes());
+        let root_record = iso_record(&[0], 18, ISO_SECTOR as u32, true);
+        image[pvd + 156..pvd + 156 + root_record.len()].copy_from_slice(&root_record);
+
+        let mut directory = Vec::new();
+        directory.extend(iso_record(&[0], 18, ISO_SECTOR as u32, true));
+        directory.extend(iso_record(&[1], 18, ISO_SECTOR as u32, true));
+        directory.extend(iso_record(b"SETUP.EXE;1", 19, 4096, false));
+        directory.extend(iso_record(b"SOURCES", 19, ISO_SECTOR as u32, true));
+        let root = 18 * ISO_SECTOR as usize;
+        image[root..root + directory.len()].copy_from_slice(&directory);
+
+        let path = std::env::temp_dir().join("test_disk_image.iso");
+        fs::write(&path, &image).unwrap();
+        let info = inspect_disk_image(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(info.format, DiskImageFormat::Iso);
+        assert_eq!(info.volume_name.as_deref(), Some("INSTALL"));
+        assert_eq!(info.virtual_size, Some(20 * ISO_SECTOR));
+        assert_eq!(info.entries.len(), 2);
+        assert_eq!(info.entries[0].name, "SETUP.EXE");
+        assert_eq!(info.entries[0].size, Some(4096));
+        assert!(info.entries[1].is_directory);
+    }
+
+    #[test]
+    fn test_parse_trailers() {
+        let mut koly = vec![0u8; TRAILER_SIZE];
+        koly[0..4].copy_from_slice(b"koly");
+        koly[0x1EC..0x1F4].copy_from_slice(&2048u64.to_be_bytes());
+        assert_eq!(parse_dmg_trailer(&koly), Some(2048 * 512));
+
+        let mut footer = vec![0u8; TRAILER_SIZE];
+        footer[0..8].copy_from_slice(b"conectix");
+        footer[28..32].copy_from_slice(b"win ");
+        footer[48..56].copy_from_slice(&(64u64 << 30).to_be_bytes());
+        assert_eq!(
+            parse_vhd_footer(&footer),
+            Some(("win".to_string(), 64u64 << 30))
+        );
+    }
+}
@@ -0,0 +1,1023 @@
+use crate::encryption;
+use crate::never_suggest::{self, is_never_suggested};
+use crate::persistence::{app_data_dir, cache_dir_avoiding, data_file, is_app_data_path};
+use crate::reconcile::volume_for;
+use crate::registry;
+use crate::space_guard::write_guarded;
+use crate::storage::{self, StorageLocation};
+use rayon::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::Hash;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::{Emitter, Window};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+/// Bytes read from the start of each file for the cheap first-pass hash
+const PARTIAL_HASH_BYTES: u64 = 64 * 1024;
+
+/// Files hashed between pause checks and progress events
+const HASH_BATCH: usize = 256;
+
+/// How often hashing progress is written to disk while a job runs
+const CACHE_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+pub(crate) const HASH_CACHE_FILE: &str = "hash_cache.msgpack";
+
+/// Token for the running duplicate search; cancelling it pauses the job
+static DUPLICATE_JOB: once_cell::sync::Lazy<Arc<Mutex<Option<CancellationToken>>>> =
+    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(None)));
+
+/// The last search that ran to completion, for recommendations
+static LAST_REPORT: once_cell::sync::Lazy<Arc<Mutex<Option<DuplicateReport>>>> =
+    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(None)));
+
+/// Hashes remembered for a file, valid while its size and mtime are unchanged
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+struct CachedHash {
+    size: u64,
+    modified_ns: u64,
+    partial: Option<String>,
+    full: Option<String>,
+}
+
+type HashCache = HashMap<PathBuf, CachedHash>;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HashPhase {
+    /// First `PARTIAL_HASH_BYTES` of each same-size file
+    Partial,
+    /// Whole contents of files whose partial hashes matched
+    Full,
+}
+
+impl HashPhase {
+    fn cached(self, entry: &CachedHash) -> Option<&String> {
+        match self {
+            HashPhase::Partial => entry.partial.as_ref(),
+            // A file no longer than the partial window was already hashed in full
+            HashPhase::Full if entry.size <= PARTIAL_HASH_BYTES => {
+                entry.full.as_ref().or(entry.partial.as_ref())
+            }
+            HashPhase::Full => entry.full.as_ref(),
+        }
+    }
+
+    fn store(self, entry: &mut CachedHash, hash: String) {
+        match self {
+            HashPhase::Partial => entry.partial = Some(hash),
+            HashPhase::Full => entry.full = Some(hash),
+        }
+    }
+
+    fn read_limit(self) -> u64 {
+        match self {
+            HashPhase::Partial => PARTIAL_HASH_BYTES,
+            HashPhase::Full => u64::MAX,
+        }
+    }
+}
+
+/// Progress of the running duplicate search, emitted as `duplicate-progress`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DuplicateProgress {
+    pub phase: HashPhase,
+    pub files_done: u64,
+    pub files_total: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DuplicateFile {
+    pub path: PathBuf,
+    /// Unix timestamp (seconds) of the last modification
+    pub modified: i64,
+    /// On the never-suggest list: always kept, never offered for deletion
+    pub never_suggest: bool,
+}
+
+/// Copies of a duplicate that live on one volume
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VolumeCopies {
+    /// Mount point or drive of the volume
+    pub volume: String,
+    pub files: Vec<DuplicateFile>,
+}
+
+/// Files with identical contents, grouped by the volume they live on
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub volumes: Vec<VolumeCopies>,
+    /// True when a copy exists on more than one volume (e.g. a backup drive)
+    pub cross_volume: bool,
+    /// Bytes freed by keeping one copy (or every never-suggest copy)
+    pub reclaimable: u64,
+}
+
+/// A directory whose whole tree is duplicated elsewhere
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DuplicateDirectory {
+    pub path: PathBuf,
+    pub volume: String,
+    pub never_suggest: bool,
+}
+
+/// Directory trees with the same structure, names and file contents
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DuplicateDirectoryGroup {
+    pub signature: String,
+    /// Size of one copy
+    pub size: u64,
+    pub file_count: u64,
+    pub directories: Vec<DuplicateDirectory>,
+    pub reclaimable: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DuplicateReport {
+    pub scan_ids: Vec<u64>,
+    /// True when the search was paused; run it again to resume from the saved hashes
+    pub paused: bool,
+    /// Duplicate files, excluding those already covered by a duplicate directory
+    pub groups: Vec<DuplicateGroup>,
+    /// Whole duplicate trees, reported once at their topmost directory
+    pub directory_groups: Vec<DuplicateDirectoryGroup>,
+    pub total_reclaimable: u64,
+    pub files_hashed: u64,
+    pub files_from_cache: u64,
+    /// Files the disk failed to read while hashing; an early sign of a failing drive
+    #[serde(default)]
+    pub possibly_corrupt: Vec<UnreadableFile>,
+}
+
+/// A file that failed with a device-level read error rather than, say, a permission error
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UnreadableFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub error: String,
+}
+
+/// A file considered for duplicate matching
+#[derive(Debug, Clone)]
+struct Candidate {
+    path: PathBuf,
+    size: u64,
+    modified_ns: u64,
+}
+
+/// A scanned entry, used to rebuild directory structure for tree matching
+#[derive(Debug, Clone)]
+struct TreeEntry {
+    path: PathBuf,
+    parent: Option<PathBuf>,
+    is_directory: bool,
+    is_symlink: bool,
+    size: u64,
+    file_count: u64,
+    /// Holds files the scan kept only in its totals, whose names and contents are unknown
+    has_dropped_files: bool,
+}
+
+#[derive(Debug, Default)]
+struct HashStats {
+    hashed: u64,
+    from_cache: u64,
+    unreadable: Vec<UnreadableFile>,
+}
+
+/// Whether a read error comes from the device (bad sectors, a dropped drive) rather than
+/// from permissions or a file that vanished
+pub(crate) fn is_device_error(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    const DEVICE_ERRORS: &[i32] = &[libc::EIO, libc::ENXIO, libc::ENODEV];
+    // ERROR_CRC, ERROR_SECTOR_NOT_FOUND, ERROR_READ_FAULT, ERROR_IO_DEVICE,
+    // ERROR_DEVICE_NOT_CONNECTED
+    #[cfg(windows)]
+    const DEVICE_ERRORS: &[i32] = &[23, 27, 30, 1117, 1167];
+    #[cfg(not(any(unix, windows)))]
+    const DEVICE_ERRORS: &[i32] = &[];
+
+    error
+        .raw_os_error()
+        .is_some_and(|code| DEVICE_ERRORS.contains(&code))
+}
+
+fn hash_file(path: &Path, limit: u64) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut File::open(path)?.take(limit), &mut hasher)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Group items by key, keeping only groups with more than one member
+fn groups_of<T, K: Hash + Eq>(
+    items: impl IntoIterator<Item = T>,
+    key: impl Fn(&T) -> K,
+) -> Vec<Vec<T>> {
+    let mut groups: HashMap<K, Vec<T>> = HashMap::new();
+    for item in items {
+        groups.entry(key(&item)).or_default().push(item);
+    }
+    groups.into_values().filter(|g| g.len() > 1).collect()
+}
+
+/// Stat the scan's files, dropping vanished files and extra hard links to the same inode
+fn gather_candidates(paths: Vec<PathBuf>, min_size: u64) -> Vec<Candidate> {
+    #[cfg(unix)]
+    let mut seen_inodes = std::collections::HashSet::new();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            if is_app_data_path(&path) {
+                return None;
+            }
+            let metadata = fs::symlink_metadata(&path).ok()?;
+            if !metadata.is_file() || metadata.len() < min_size {
+                return None;
+            }
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                if !seen_inodes.insert((metadata.dev(), metadata.ino())) {
+                    return None;
+                }
+            }
+
+            let modified_ns = metadata
+                .modified()
+                .ok()
+                .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0);
+            Some(Candidate {
+                path,
+                size: metadata.len(),
+                modified_ns,
+            })
+        })
+        .collect()
+}
+
+/// Hash candidates for one phase, reusing cached hashes; returns `None` if paused
+fn hash_phase<'a>(
+    candidates: Vec<&'a Candidate>,
+    phase: HashPhase,
+    cache: &mut HashCache,
+    token: &CancellationToken,
+    stats: &mut HashStats,
+    on_progress: &mut dyn FnMut(DuplicateProgress, &HashCache),
+) -> Option<Vec<(&'a Candidate, String)>> {
+    let files_total = candidates.len() as u64;
+    let mut hashed = Vec::with_capacity(candidates.len());
+    let mut misses = Vec::new();
+
+    for candidate in candidates {
+        let cached = cache
+            .get(&candidate.path)
+            .filter(|e| e.size == candidate.size && e.modified_ns == candidate.modified_ns)
+            .and_then(|e| phase.cached(e));
+        match cached {
+            Some(hash) => {
+                hashed.push((candidate, hash.clone()));
+                stats.from_cache += 1;
+            }
+            None => misses.push(candidate),
+        }
+    }
+
+    for batch in misses.chunks(HASH_BATCH) {
+        if token.is_cancelled() {
+            return None;
+        }
+
+        let results: Vec<(&Candidate, io::Result<String>)> = batch
+            .par_iter()
+            .map(|c| (*c, hash_file(&c.path, phase.read_limit())))
+            .collect();
+
+        for (candidate, result) in results {
+            let hash = match result {
+                Ok(hash) => hash,
+                Err(e) => {
+                    if is_device_error(&e) {
+                        stats.unreadable.push(UnreadableFile {
+                            path: candidate.path.clone(),
+                            size: candidate.size,
+                            error: e.to_string(),
+                        });
+                    }
+                    continue;
+                }
+            };
+            let entry = cache.entry(candidate.path.clone()).or_insert(CachedHash {
+                size: candidate.size,
+                modified_ns: candidate.modified_ns,
+                partial: None,
+                full: None,
+            });
+            // Stale entry for a file that changed since it was cached
+            if entry.size != candidate.size || entry.modified_ns != candidate.modified_ns {
+                entry.size = candidate.size;
+                entry.modified_ns = candidate.modified_ns;
+                entry.partial = None;
+                entry.full = None;
+            }
+            phase.store(entry, hash.clone());
+            stats.hashed += 1;
+            hashed.push((candidate, hash));
+        }
+
+        on_progress(
+            DuplicateProgress {
+                phase,
+                files_done: hashed.len() as u64,
+                files_total,
+            },
+            cache,
+        );
+    }
+
+    Some(hashed)
+}
+
+/// Size, then partial hash, then full hash; returns `None` if paused
+fn find_duplicates<'a>(
+    candidates: &'a [Candidate],
+    cache: &mut HashCache,
+    token: &CancellationToken,
+    stats: &mut HashStats,
+    on_progress: &mut dyn FnMut(DuplicateProgress, &HashCache),
+) -> Option<Vec<(String, Vec<&'a Candidate>)>> {
+    let same_size = groups_of(candidates.iter(), |c| c.size);
+
+    let partial = hash_phase(
+        same_size.into_iter().flatten().collect(),
+        HashPhase::Partial,
+        cache,
+        token,
+        stats,
+        on_progress,
+    )?;
+    let same_partial = groups_of(partial, |(c, hash)| (c.size, hash.clone()));
+
+    let full = hash_phase(
+        same_partial.into_iter().flatten().map(|(c, _)| c).collect(),
+        HashPhase::Full,
+        cache,
+        token,
+        stats,
+        on_progress,
+    )?;
+
+    Some(
+        groups_of(full, |(c, hash)| (c.size, hash.clone()))
+            .into_iter()
+            .map(|group| {
+                let hash = group[0].1.clone();
+                (hash, group.into_iter().map(|(c, _)| c).collect())
+            })
+            .collect(),
+    )
+}
+
+fn load_cache(file: &Path) -> HashCache {
+    fs::read(file)
+        .ok()
+        .and_then(|bytes| encryption::open(bytes).ok())
+        .and_then(|bytes| rmp_serde::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// Write the cache whole or not at all, so a crash midway never corrupts it
+fn save_cache(file: &Path, cache: &HashCache) -> Result<(), String> {
+    let bytes = rmp_serde::to_vec(cache).map_err(|e| format!("Cannot serialize: {}", e))?;
+    write_guarded(file, &encryption::seal(bytes)?)
+}
+
+/// Save the cache during or after a search; failing only costs rehashing next time, so
+/// the search goes on
+fn save_cache_or_log(file: &Path, cache: &HashCache) {
+    if let Err(e) = save_cache(file, cache) {
+        eprintln!("Cannot save the hash cache: {}", e);
+    }
+}
+
+/// Drop cached hashes of files that were deleted or changed; returns the number dropped
+///
+/// Refuses to run during a duplicate search, which rewrites the cache as it goes.
+pub(crate) async fn compact_hash_cache() -> Result<usize, String> {
+    if DUPLICATE_JOB.lock().await.is_some() {
+        return Err("A duplicate search is running".to_string());
+    }
+    let file = data_file(HASH_CACHE_FILE)?;
+
+    tokio::task::spawn_blocking(move || {
+        let mut cache = load_cache(&file);
+        let before = cache.len();
+        cache.retain(|path, entry| {
+            fs::metadata(path).is_ok_and(|m| {
+                let modified_ns = m
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(0);
+                m.len() == entry.size && modified_ns == entry.modified_ns
+            })
+        });
+        if cache.len() < before {
+            save_cache(&file, &cache)?;
+        }
+        Ok(before - cache.len())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Mount point of the volume holding `path`, or an empty string if it isn't on a known volume
+fn volume_label(path: &Path, locations: &[StorageLocation]) -> String {
+    volume_for(path, locations)
+        .map(|l| l.path.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn build_group(
+    hash: String,
+    files: Vec<&Candidate>,
+    never_suggest: &[never_suggest::NeverSuggestEntry],
+    locations: &[StorageLocation],
+) -> DuplicateGroup {
+    let size = files[0].size;
+    let count = files.len();
+
+    let mut by_volume: HashMap<String, Vec<DuplicateFile>> = HashMap::new();
+    for candidate in files {
+        by_volume
+            .entry(volume_label(&candidate.path, locations))
+            .or_default()
+            .push(DuplicateFile {
+                never_suggest: is_never_suggested(never_suggest, &candidate.path),
+                modified: (candidate.modified_ns / 1_000_000_000) as i64,
+                path: candidate.path.clone(),
+            });
+    }
+
+    let mut volumes: Vec<VolumeCopies> = by_volume
+        .into_iter()
+        .map(|(volume, mut files)| {
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+            VolumeCopies { volume, files }
+        })
+        .collect();
+    volumes.sort_by(|a, b| a.volume.cmp(&b.volume));
+
+    let kept = volumes
+        .iter()
+        .flat_map(|v| &v.files)
+        .filter(|f| f.never_suggest)
+        .count()
+        .max(1);
+    DuplicateGroup {
+        hash,
+        size,
+        cross_volume: volumes.len() > 1,
+        volumes,
+        reclaimable: size * (count - kept) as u64,
+    }
+}
+
+/// Signatures for every directory whose entire contents have known hashes
+///
+/// A signature covers child names, kinds and content hashes, so two directories share one
+/// only if their trees are byte-identical. Directories holding an unhashed file (unique
+/// size, below the size threshold, symlink, or one the scan kept only in its totals) get
+/// no signature.
+fn directory_signatures(
+    entries: &HashMap<PathBuf, TreeEntry>,
+    file_hashes: &HashMap<&Path, &str>,
+) -> HashMap<PathBuf, String> {
+    let mut children: HashMap<&Path, Vec<&TreeEntry>> = HashMap::new();
+    for entry in entries.values() {
+        if let Some(parent) = &entry.parent {
+            children.entry(parent.as_path()).or_default().push(entry);
+        }
+    }
+
+    // Deepest first, so children are signed before their parents
+    let mut directories: Vec<&TreeEntry> = entries.values().filter(|e| e.is_directory).collect();
+    directories.sort_by_key(|d| std::cmp::Reverse(d.path.components().count()));
+
+    let mut signatures: HashMap<PathBuf, String> = HashMap::new();
+    'directories: for dir in directories {
+        if dir.has_dropped_files {
+            continue;
+        }
+        let mut parts: Vec<(String, String)> = Vec::new();
+        for child in children.get(dir.path.as_path()).into_iter().flatten() {
+            let signature = if child.is_symlink {
+                continue 'directories;
+            } else if child.is_directory {
+                match signatures.get(&child.path) {
+                    Some(sig) => format!("d:{}", sig),
+                    None => continue 'directories,
+                }
+            } else if child.size == 0 {
+                "f:empty".to_string()
+            } else {
+                match file_hashes.get(child.path.as_path()) {
+                    Some(hash) => format!("f:{}", hash),
+                    None => continue 'directories,
+                }
+            };
+            let name = child
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            parts.push((name, signature));
+        }
+        parts.sort();
+
+        let mut hasher = blake3::Hasher::new();
+        for (name, signature) in parts {
+            hasher.update(name.as_bytes());
+            hasher.update(b"\0");
+            hasher.update(signature.as_bytes());
+            hasher.update(b"\n");
+        }
+        signatures.insert(dir.path.clone(), hasher.finalize().to_hex().to_string());
+    }
+
+    signatures
+}
+
+/// Group directories with identical trees, keeping only the topmost duplicate of each tree
+fn find_duplicate_directories<'a>(
+    entries: &'a HashMap<PathBuf, TreeEntry>,
+    signatures: &HashMap<PathBuf, String>,
+) -> Vec<(String, Vec<&'a TreeEntry>)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for signature in signatures.values() {
+        *counts.entry(signature.as_str()).or_insert(0) += 1;
+    }
+    let duplicated = |path: &Path| {
+        signatures
+            .get(path)
+            .is_some_and(|sig| counts[sig.as_str()] > 1)
+    };
+
+    let topmost = signatures.keys().filter_map(|path| {
+        let entry = &entries[path];
+        let parent_duplicated = entry.parent.as_deref().is_some_and(duplicated);
+        (entry.file_count > 0 && duplicated(path) && !parent_duplicated).then_some(entry)
+    });
+
+    groups_of(topmost, |entry| signatures[&entry.path].clone())
+        .into_iter()
+        .map(|group| (signatures[&group[0].path].clone(), group))
+        .collect()
+}
+
+fn build_directory_group(
+    signature: String,
+    directories: Vec<&TreeEntry>,
+    never_suggest: &[never_suggest::NeverSuggestEntry],
+    locations: &[StorageLocation],
+) -> DuplicateDirectoryGroup {
+    let size = directories[0].size;
+    let file_count = directories[0].file_count;
+    let mut directories: Vec<DuplicateDirectory> = directories
+        .into_iter()
+        .map(|dir| DuplicateDirectory {
+            path: dir.path.clone(),
+            volume: volume_label(&dir.path, locations),
+            never_suggest: is_never_suggested(never_suggest, &dir.path),
+        })
+        .collect();
+    directories.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let kept = directories
+        .iter()
+        .filter(|d| d.never_suggest)
+        .count()
+        .max(1);
+    DuplicateDirectoryGroup {
+        signature,
+        size,
+        file_count,
+        reclaimable: size * (directories.len() - kept) as u64,
+        directories,
+    }
+}
+
+// Tauri commands
+
+/// Find duplicate files across one or more retained scans, emitting `duplicate-progress` events
+///
+/// Passing scans of different volumes (e.g. the internal disk and a backup drive) finds
+/// copies that exist on both. Hashes are cached by path, size and mtime, so re-runs and
+/// resumed searches only hash new or changed files.
+#[tauri::command]
+pub async fn find_duplicates_command(
+    scan_ids: Vec<u64>,
+    min_size: Option<u64>,
+    window: Window,
+) -> Result<DuplicateReport, String> {
+    let token = CancellationToken::new();
+    {
+        let mut job = DUPLICATE_JOB.lock().await;
+        if job.is_some() {
+            return Err("A duplicate search is already running".to_string());
+        }
+        *job = Some(token.clone());
+    }
+
+    let result = run_duplicate_search(scan_ids, min_size.unwrap_or(1), token, window).await;
+
+    *DUPLICATE_JOB.lock().await = None;
+    if let Some(report) = result.as_ref().ok().filter(|r| !r.paused) {
+        *LAST_REPORT.lock().await = Some(report.clone());
+    }
+    result
+}
+
+/// The last duplicate search that wasn't paused, if any ran this session
+pub(crate) async fn last_report() -> Option<DuplicateReport> {
+    LAST_REPORT.lock().await.clone()
+}
+
+async fn run_duplicate_search(
+    scan_ids: Vec<u64>,
+    min_size: u64,
+    token: CancellationToken,
+    window: Window,
+) -> Result<DuplicateReport, String> {
+    let mut entries: HashMap<PathBuf, TreeEntry> = HashMap::new();
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for scan_id in &scan_ids {
+        // Overlapping scans (a folder and its parent) list the same paths twice
+        let scan_entries = registry::with_scan(*scan_id, |nodes, summary| {
+            roots.push(PathBuf::from(&summary.root));
+            nodes
+                .values()
+                .map(|n| TreeEntry {
+                    path: n.path.clone(),
+                    parent: n.parent_path.clone(),
+                    is_directory: n.is_directory,
+                    is_symlink: n.is_symlink,
+                    size: n.size,
+                    file_count: n.file_count,
+                    has_dropped_files: n.dropped.is_some(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .await?;
+        entries.extend(scan_entries.into_iter().map(|e| (e.path.clone(), e)));
+    }
+    let paths: Vec<PathBuf> = entries
+        .values()
+        .filter(|e| !e.is_directory && !e.is_symlink && e.size >= min_size)
+        .map(|e| e.path.clone())
+        .collect();
+
+    let never_suggest = never_suggest::load_never_suggest()?;
+    let locations = storage::get_storage_locations().unwrap_or_default();
+    // The cache grows as files are hashed, so it moves off a nearly full scanned volume,
+    // starting from the usual copy
+    let home_cache = data_file(HASH_CACHE_FILE)?;
+    let scanned: Vec<&Path> = roots.iter().map(PathBuf::as_path).collect();
+    let cache_file = cache_dir_avoiding(&app_data_dir()?, &scanned)?.join(HASH_CACHE_FILE);
+
+    tokio::task::spawn_blocking(move || {
+        let candidates = gather_candidates(paths, min_size);
+        let mut cache = load_cache(if cache_file.exists() {
+            &cache_file
+        } else {
+            &home_cache
+        });
+        let mut stats = HashStats::default();
+        let mut last_save = Instant::now();
+
+        let mut on_progress = |progress: DuplicateProgress, cache: &HashCache| {
+            let _ = window.emit("duplicate-progress", &progress);
+            if last_save.elapsed() >= CACHE_SAVE_INTERVAL {
+                save_cache_or_log(&cache_file, cache);
+                last_save = Instant::now();
+            }
+        };
+        let found = find_duplicates(
+            &candidates,
+            &mut cache,
+            &token,
+            &mut stats,
+            &mut on_progress,
+        );
+        save_cache_or_log(&cache_file, &cache);
+
+        let Some(found) = found else {
+            return DuplicateReport {
+                scan_ids,
+                paused: true,
+                groups: Vec::new(),
+                directory_groups: Vec::new(),
+                total_reclaimable: 0,
+                files_hashed: stats.hashed,
+                files_from_cache: stats.from_cache,
+                possibly_corrupt: stats.unreadable,
+            };
+        };
+
+        let file_hashes: HashMap<&Path, &str> = found
+            .iter()
+            .flat_map(|(hash, files)| files.iter().map(move |c| (c.path.as_path(), hash.as_str())))
+            .collect();
+        let signatures = directory_signatures(&entries, &file_hashes);
+
+        let mut directory_groups: Vec<DuplicateDirectoryGroup> =
+            find_duplicate_directories(&entries, &signatures)
+                .into_iter()
+                .map(|(sig, dirs)| build_directory_group(sig, dirs, &never_suggest, &locations))
+                .filter(|g| g.reclaimable > 0)
+                .collect();
+        directory_groups.sort_by_key(|g| std::cmp::Reverse(g.reclaimable));
+
+        // Files whose every copy sits inside a duplicate tree are covered by that tree
+        let in_duplicate_tree = |path: &Path| {
+            path.ancestors().any(|a| {
+                directory_groups
+                    .iter()
+                    .any(|g| g.directories.iter().any(|d| d.path == a))
+            })
+        };
+        let mut groups: Vec<DuplicateGroup> = found
+            .iter()
+            .filter(|(_, files)| !files.iter().all(|c| in_duplicate_tree(&c.path)))
+            .map(|(hash, files)| {
+                build_group(hash.clone(), files.clone(), &never_suggest, &locations)
+            })
+            .filter(|g| g.reclaimable > 0)
+            .collect();
+        groups.sort_by(|a, b| {
+            b.reclaimable
+                .cmp(&a.reclaimable)
+                .then_with(|| a.hash.cmp(&b.hash))
+        });
+
+        DuplicateReport {
+            scan_ids,
+            paused: false,
+            total_reclaimable: groups.iter().map(|g| g.reclaimable).sum::<u64>()
+                + directory_groups.iter().map(|g| g.reclaimable).sum::<u64>(),
+            groups,
+            directory_groups,
+            files_hashed: stats.hashed,
+            files_from_cache: stats.from_cache,
+            possibly_corrupt: stats.unreadable,
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Pause the running duplicate search; hashes computed so far are kept
+#[tauri::command]
+pub async fn pause_duplicate_search_command() -> Result<(), String> {
+    match DUPLICATE_JOB.lock().await.as_ref() {
+        Some(token) => {
+            token.cancel();
+            Ok(())
+        }
+        None => Err("No duplicate search is currently running".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_duplicates_reuses_cache() {
+        let dir = std::env::temp_dir().join("test_duplicates");
+        fs::create_dir_all(&dir).unwrap();
+        // Same size and same first 64 KiB, different tails: only the full hash tells them apart
+        let mut a = vec![7u8; 100_000];
+        fs::write(dir.join("a.bin"), &a).unwrap();
+        fs::write(dir.join("a copy.bin"), &a).unwrap();
+        a[99_999] = 8;
+        fs::write(dir.join("a tail.bin"), &a).unwrap();
+        fs::write(dir.join("small.txt"), b"hello").unwrap();
+        fs::write(dir.join("small copy.txt"), b"hello").unwrap();
+        fs::write(dir.join("other.txt"), b"world!").unwrap();
+
+        let paths: Vec<PathBuf> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        let candidates = gather_candidates(paths, 1);
+        let token = CancellationToken::new();
+        let mut cache = HashCache::new();
+
+        let mut stats = HashStats::default();
+        let groups =
+            find_duplicates(&candidates, &mut cache, &token, &mut stats, &mut |_, _| {}).unwrap();
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().all(|(_, files)| files.len() == 2));
+        // 5 partial hashes, then only the 3 large files need a full hash
+        assert_eq!(stats.hashed, 8);
+
+        let mut rerun = HashStats::default();
+        let again =
+            find_duplicates(&candidates, &mut cache, &token, &mut rerun, &mut |_, _| {}).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(again.len(), 2);
+        assert_eq!(rerun.hashed, 0);
+        assert_eq!(rerun.from_cache, 10);
+    }
+
+    #[test]
+    fn test_paused_search_returns_none() {
+        let candidates = vec![
+            Candidate {
+                path: PathBuf::from("/nonexistent/a"),
+                size: 10,
+                modified_ns: 0,
+            },
+            Candidate {
+                path: PathBuf::from("/nonexistent/b"),
+                size: 10,
+                modified_ns: 0,
+            },
+        ];
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let found = find_duplicates(
+            &candidates,
+            &mut HashCache::new(),
+            &token,
+            &mut HashStats::default(),
+            &mut |_, _| {},
+        );
+        assert!(found.is_none());
+    }
+
+    #[test]
+    fn test_only_device_errors_count_as_corrupt() {
+        assert!(!is_device_error(&io::Error::from(
+            io::ErrorKind::PermissionDenied
+        )));
+        assert!(!is_device_error(&io::Error::from(io::ErrorKind::NotFound)));
+        #[cfg(unix)]
+        assert!(is_device_error(&io::Error::from_raw_os_error(libc::EIO)));
+    }
+
+    #[test]
+    fn test_never_suggest_copies_are_kept() {
+        let candidates = [
+            Candidate {
+                path: PathBuf::from("/photos/archive/img.jpg"),
+                size: 100,
+                modified_ns: 0,
+            },
+            Candidate {
+                path: PathBuf::from("/downloads/img.jpg"),
+                size: 100,
+                modified_ns: 0,
+            },
+        ];
+        let never_suggest = vec![never_suggest::NeverSuggestEntry {
+            path: PathBuf::from("/photos/archive"),
+            note: None,
+            added_at: 0,
+        }];
+
+        let group = build_group(
+            "h".to_string(),
+            candidates.iter().collect(),
+            &never_suggest,
+            &[],
+        );
+
+        assert_eq!(group.reclaimable, 100);
+        assert!(group.volumes[0].files[1].never_suggest);
+    }
+
+    #[test]
+    fn test_groups_copies_by_volume() {
+        let location = |path: &str| StorageLocation {
+            name: path.to_string(),
+            path: PathBuf::from(path),
+            location_type: storage::LocationType::Storage,
+            total_space: Some(1),
+            available_space: Some(0),
+            inodes: None,
+            quota: None,
+            shadow_storage: None,
+        };
+        let locations = [location("/"), location("/Volumes/Backup")];
+        let candidate = |path: &str| Candidate {
+            path: PathBuf::from(path),
+            size: 10,
+            modified_ns: 0,
+        };
+        let candidates = [
+            candidate("/Users/me/a.mov"),
+            candidate("/Users/me/old/a.mov"),
+            candidate("/Volumes/Backup/me/a.mov"),
+        ];
+
+        let group = build_group(
+            "h".to_string(),
+            candidates.iter().collect(),
+            &[],
+            &locations,
+        );
+
+        assert!(group.cross_volume);
+        assert_eq!(group.volumes.len(), 2);
+        assert_eq!(group.volumes[0].volume, "/");
+        assert_eq!(group.volumes[0].files.len(), 2);
+        assert_eq!(group.volumes[1].volume, "/Volumes/Backup");
+        assert_eq!(group.reclaimable, 20);
+    }
+
+    #[test]
+    fn test_finds_topmost_duplicate_directories() {
+        let entry = |path: &str, is_directory: bool, size: u64| {
+            let node = registry::test_node(path, size, is_directory);
+            // Folded files make a folder's contents unknowable
+            let has_dropped_files = path == "/data/Folded/kept";
+            (
+                node.path.clone(),
+                TreeEntry {
+                    parent: node.parent_path,
+                    path: node.path,
+                    is_directory,
+                    is_symlink: false,
+                    size,
+                    file_count: if is_directory { 2 } else { 1 },
+                    has_dropped_files,
+                },
+            )
+        };
+        let entries: HashMap<PathBuf, TreeEntry> = [
+            entry("/data", true, 60),
+            entry("/data/Photos", true, 30),
+            entry("/data/Photos/a.jpg", false, 10),
+            entry("/data/Photos/raw", true, 20),
+            entry("/data/Photos/raw/b.nef", false, 20),
+            entry("/data/Copy of Photos", true, 30),
+            entry("/data/Copy of Photos/a.jpg", false, 10),
+            entry("/data/Copy of Photos/raw", true, 20),
+            entry("/data/Copy of Photos/raw/b.nef", false, 20),
+            entry("/data/Other", true, 10),
+            entry("/data/Other/a.jpg", false, 10),
+            entry("/data/Folded", true, 30),
+            entry("/data/Folded/kept", true, 30),
+            entry("/data/Folded/kept/a.jpg", false, 10),
+            entry("/data/Folded/kept/raw", true, 20),
+            entry("/data/Folded/kept/raw/b.nef", false, 20),
+        ]
+        .into_iter()
+        .collect();
+        let file_hashes: HashMap<&Path, &str> = [
+            (Path::new("/data/Photos/a.jpg"), "ha"),
+            (Path::new("/data/Copy of Photos/a.jpg"), "ha"),
+            (Path::new("/data/Other/a.jpg"), "ha"),
+            (Path::new("/data/Photos/raw/b.nef"), "hb"),
+            (Path::new("/data/Copy of Photos/raw/b.nef"), "hb"),
+            (Path::new("/data/Folded/kept/a.jpg"), "ha"),
+            (Path::new("/data/Folded/kept/raw/b.nef"), "hb"),
+        ]
+        .into_iter()
+        .collect();
+
+        let signatures = directory_signatures(&entries, &file_hashes);
+        let groups = find_duplicate_directories(&entries, &signatures);
+
+        // raw/ subfolders are covered by their duplicated parents; Other/ differs in structure,
+        // and Folded/kept may differ in the files it only has totals for
+        assert!(!signatures.contains_key(Path::new("/data/Folded/kept")));
+        assert_eq!(groups.len(), 1);
+        let mut paths: Vec<&Path> = groups[0].1.iter().map(|e| e.path.as_path()).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![Path::new("/data/Copy of Photos"), Path::new("/data/Photos")]
+        );
+
+        let group = build_directory_group(groups[0].0.clone(), groups[0].1.clone(), &[], &[]);
+        assert_eq!(group.reclaimable, 30);
+    }
+}
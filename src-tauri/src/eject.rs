@@ -0,0 +1,240 @@
+use crate::i18n::{localize, LocalizedMessage};
+use crate::moves;
+use crate::persistence::app_data_dir;
+use crate::watchdog;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EjectResult {
+    pub volume: PathBuf,
+    /// Pending writes were flushed to the drive
+    pub flushed: bool,
+    pub ejected: bool,
+    /// In-app operations still using the volume; it is not ejected while there are any,
+    /// unless forced
+    pub busy: Vec<LocalizedMessage>,
+}
+
+/// What the app itself is doing on `volume`
+fn busy_on(volume: &Path, moving: &[PathBuf], reading: &[PathBuf]) -> Vec<LocalizedMessage> {
+    let path = |p: &Path| json!(p.display().to_string());
+    let mut busy: Vec<LocalizedMessage> = moving
+        .iter()
+        .filter(|p| p.starts_with(volume))
+        .map(|p| localize("eject-busy-move", &[("path", path(p))]))
+        .collect();
+    busy.extend(
+        reading
+            .iter()
+            .filter(|p| p.starts_with(volume))
+            .map(|p| localize("eject-busy-read", &[("path", path(p))])),
+    );
+    busy
+}
+
+/// Refuse anything but the mount point of a volume other than the one the app runs from
+fn ensure_ejectable(volume: &Path) -> Result<(), String> {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let mounted = disks.list().iter().any(|d| d.mount_point() == volume);
+    if !mounted {
+        return Err(format!("{} is not a mounted volume", volume.display()));
+    }
+    let system = disks
+        .list()
+        .iter()
+        .filter(|d| app_data_dir().is_ok_and(|dir| dir.starts_with(d.mount_point())))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .is_some_and(|d| d.mount_point() == volume);
+    if system {
+        return Err(format!(
+            "{} holds the system and can't be ejected",
+            volume.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Write every cached change out to its drive
+#[cfg(unix)]
+fn flush(_volume: &Path) -> Result<(), String> {
+    unsafe { libc::sync() };
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn eject(volume: &Path) -> Result<(), String> {
+    let output = crate::tools::run("diskutil", &["eject", &volume.to_string_lossy()])?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(())
+}
+
+/// Unmount with udisks, then power the drive off where it supports that
+#[cfg(all(unix, not(target_os = "macos")))]
+fn eject(volume: &Path) -> Result<(), String> {
+    let device = crate::storage::mount_device_for(volume)
+        .ok_or_else(|| format!("Cannot find the device for {}", volume.display()))?;
+    let output = crate::tools::run("udisksctl", &["unmount", "--block-device", &device])?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    // Partitions and some card readers can't be powered off; unmounted is safe to unplug
+    let _ = crate::tools::run("udisksctl", &["power-off", "--block-device", &device]);
+    Ok(())
+}
+
+/// `\\.\E:` for a volume mounted at `E:\`
+#[cfg(target_os = "windows")]
+fn volume_device(volume: &Path) -> Vec<u16> {
+    let drive = volume.to_string_lossy();
+    let drive = drive.trim_end_matches('\\');
+    format!("\\\\.\\{}", drive)
+        .encode_utf16()
+        .chain(Some(0))
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn flush(volume: &Path) -> Result<(), String> {
+    use winapi::um::fileapi::{CreateFileW, FlushFileBuffers, OPEN_EXISTING};
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::winnt::{FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ, GENERIC_WRITE};
+
+    let device = volume_device(volume);
+    unsafe {
+        let handle = CreateFileW(
+            device.as_ptr(),
+            GENERIC_READ | GENERIC_WRITE,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            std::ptr::null_mut(),
+            OPEN_EXISTING,
+            0,
+            std::ptr::null_mut(),
+        );
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(format!("Cannot open {}", volume.display()));
+        }
+        let flushed = FlushFileBuffers(handle) != 0;
+        CloseHandle(handle);
+        if !flushed {
+            return Err(format!("Cannot flush {}", volume.display()));
+        }
+    }
+    Ok(())
+}
+
+/// Lock and dismount the volume so nothing can write to it, then eject the media
+#[cfg(target_os = "windows")]
+fn eject(volume: &Path) -> Result<(), String> {
+    use winapi::um::fileapi::{CreateFileW, OPEN_EXISTING};
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::ioapiset::DeviceIoControl;
+    use winapi::um::winioctl::{
+        FSCTL_DISMOUNT_VOLUME, FSCTL_LOCK_VOLUME, IOCTL_STORAGE_EJECT_MEDIA,
+    };
+    use winapi::um::winnt::{FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ, GENERIC_WRITE};
+
+    let device = volume_device(volume);
+    unsafe {
+        let handle = CreateFileW(
+            device.as_ptr(),
+            GENERIC_READ | GENERIC_WRITE,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            std::ptr::null_mut(),
+            OPEN_EXISTING,
+            0,
+            std::ptr::null_mut(),
+        );
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(format!("Cannot open {}", volume.display()));
+        }
+        let control = |code: u32| {
+            let mut returned = 0u32;
+            DeviceIoControl(
+                handle,
+                code,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+                0,
+                &mut returned,
+                std::ptr::null_mut(),
+            ) != 0
+        };
+        let result = if !control(FSCTL_LOCK_VOLUME) {
+            Err(format!(
+                "{} is in use by another program; close it and try again",
+                volume.display()
+            ))
+        } else if !control(FSCTL_DISMOUNT_VOLUME) || !control(IOCTL_STORAGE_EJECT_MEDIA) {
+            Err(format!("Windows could not eject {}", volume.display()))
+        } else {
+            Ok(())
+        };
+        CloseHandle(handle);
+        result
+    }
+}
+
+// Tauri commands
+
+/// Flush pending writes to a volume and eject it so the drive can be unplugged
+///
+/// Nothing is ejected while a copy, move or scan in the app is still using the volume;
+/// those are listed in `busy`, and `force` ejects anyway.
+#[tauri::command]
+pub async fn flush_and_eject_command(
+    volume: String,
+    force: Option<bool>,
+) -> Result<EjectResult, String> {
+    let volume = PathBuf::from(volume);
+    tokio::task::spawn_blocking(move || {
+        ensure_ejectable(&volume)?;
+        flush(&volume)?;
+        let busy = busy_on(
+            &volume,
+            &moves::running_paths(),
+            &watchdog::in_flight_paths(),
+        );
+        let ejected = if busy.is_empty() || force.unwrap_or(false) {
+            eject(&volume)?;
+            true
+        } else {
+            false
+        };
+        Ok(EjectResult {
+            volume,
+            flushed: true,
+            ejected,
+            busy,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_busy_operations_on_volume() {
+        let volume = Path::new("/Volumes/Backup");
+        let moving = [
+            PathBuf::from("/Users/alice/Photos"),
+            PathBuf::from("/Volumes/Backup"),
+        ];
+        let reading = [PathBuf::from("/Volumes/Backup/old/cache")];
+        let busy = busy_on(volume, &moving, &reading);
+        assert_eq!(busy.len(), 2);
+        assert_eq!(busy[0].code, "eject-busy-move");
+        assert_eq!(busy[1].text, "Still reading /Volumes/Backup/old/cache");
+
+        assert!(busy_on(Path::new("/Volumes/Other"), &moving, &reading).is_empty());
+        assert!(ensure_ejectable(Path::new("/definitely/not/mounted")).is_err());
+    }
+}
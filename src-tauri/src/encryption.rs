@@ -0,0 +1,151 @@
+//! Optional encryption of app data that names the user's files
+//!
+//! With `encrypt_saved_data` on, saved scans, the scan and hash caches, fleet copies and
+//! every store of paths (pins, tags, never-suggest entries, policies, entry counts, volume
+//! estimates, folder bookmarks, move jobs and the archive journal) are written through
+//! [`seal`]. A few files stay plain: `settings.json` holds the switch itself and has to be
+//! readable first, `webhooks.json` and `first_run.json` name no files, and
+//! `relocated_dirs.json` lists only the app's own cache folders.
+
+use crate::persistence::APP_IDENTIFIER;
+use crate::settings::load_settings;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Prefix of every encrypted file, so plain files written before encryption was enabled still load
+const MAGIC: &[u8] = b"DAENC1";
+/// Keychain account holding the hex-encoded key
+const KEYCHAIN_USER: &str = "scan-encryption-key";
+const KEY_LEN: usize = 32;
+
+type Key = [u8; KEY_LEN];
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], String> {
+    let mut bytes = [0u8; N];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "No secure random source available".to_string())?;
+    Ok(bytes)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Key> {
+    let mut key = [0u8; KEY_LEN];
+    if text.len() != KEY_LEN * 2 {
+        return None;
+    }
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(text.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(key)
+}
+
+/// The key from the OS keychain, generated and stored on first use; read once per run,
+/// since many small files are sealed
+fn keychain_key() -> Result<Key, String> {
+    static KEY: once_cell::sync::OnceCell<Key> = once_cell::sync::OnceCell::new();
+    KEY.get_or_try_init(read_keychain_key).copied()
+}
+
+fn read_keychain_key() -> Result<Key, String> {
+    let entry = keyring::Entry::new(APP_IDENTIFIER, KEYCHAIN_USER)
+        .map_err(|e| format!("Cannot open the keychain: {}", e))?;
+    match entry.get_password() {
+        Ok(hex) => from_hex(&hex).ok_or_else(|| "The keychain holds an invalid key".to_string()),
+        Err(keyring::Error::NoEntry) => {
+            let key = random_bytes::<KEY_LEN>()?;
+            entry
+                .set_password(&to_hex(&key))
+                .map_err(|e| format!("Cannot store the key in the keychain: {}", e))?;
+            Ok(key)
+        }
+        Err(e) => Err(format!("Cannot read the key from the keychain: {}", e)),
+    }
+}
+
+fn cipher(key: &Key) -> LessSafeKey {
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).expect("key has the AES-256 length"))
+}
+
+/// Whether bytes read from disk were written encrypted
+pub(crate) fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Encrypt with AES-256-GCM: magic, then a random nonce, then ciphertext and tag
+fn seal_with_key(plain: &[u8], key: &Key) -> Result<Vec<u8>, String> {
+    let nonce = random_bytes::<NONCE_LEN>()?;
+    let mut data = plain.to_vec();
+    cipher(key)
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+        .map_err(|_| "Encryption failed".to_string())?;
+
+    let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + data.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&data);
+    Ok(sealed)
+}
+
+fn open_with_key(sealed: &[u8], key: &Key) -> Result<Vec<u8>, String> {
+    let body = &sealed[MAGIC.len()..];
+    if body.len() < NONCE_LEN {
+        return Err("Encrypted file is truncated".to_string());
+    }
+    let (nonce, data) = body.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| "Invalid nonce".to_string())?;
+    let mut data = data.to_vec();
+    let plain_len = cipher(key)
+        .open_in_place(nonce, Aad::empty(), &mut data)
+        .map_err(|_| "Cannot decrypt: the file was changed or the key is different".to_string())?
+        .len();
+    data.truncate(plain_len);
+    Ok(data)
+}
+
+/// Prepare scan data for disk, encrypting it when the user turned encryption on
+pub(crate) fn seal(plain: Vec<u8>) -> Result<Vec<u8>, String> {
+    if load_settings()?.encrypt_saved_data {
+        seal_with_key(&plain, &keychain_key()?)
+    } else {
+        Ok(plain)
+    }
+}
+
+/// Decrypt data read from disk if it was encrypted; plain data passes through
+///
+/// Decryption doesn't depend on the setting, so turning encryption off keeps older
+/// encrypted files readable.
+pub(crate) fn open(bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    if is_sealed(&bytes) {
+        open_with_key(&bytes, &keychain_key()?)
+    } else {
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let key = random_bytes::<KEY_LEN>().unwrap();
+        let plain = br#"{"tree":{"path":"/Users/me/secret-project"}}"#;
+
+        let mut sealed = seal_with_key(plain, &key).unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.windows(6).any(|w| w == b"secret"));
+        assert_eq!(open_with_key(&sealed, &key).unwrap(), plain);
+        assert_eq!(from_hex(&to_hex(&key)), Some(key));
+
+        let other = random_bytes::<KEY_LEN>().unwrap();
+        assert!(open_with_key(&sealed, &other).is_err());
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(open_with_key(&sealed, &key).is_err());
+    }
+}
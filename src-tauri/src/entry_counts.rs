@@ -0,0 +1,126 @@
+use crate::access;
+use crate::persistence::{
+    data_file, is_app_data_path, read_sealed_json_or_default, write_sealed_json,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ENTRY_COUNTS_FILE: &str = "entry_counts.json";
+
+/// How many entries a scan of `root` is expected to visit, the denominator of its progress
+/// percentage
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EntryCount {
+    pub root: PathBuf,
+    /// Files, folders and links below `root`, plus `root` itself
+    pub entries: u64,
+    /// Unix timestamp (seconds) of the count
+    pub counted_at: i64,
+}
+
+fn load_counts() -> Result<Vec<EntryCount>, String> {
+    read_sealed_json_or_default(&data_file(ENTRY_COUNTS_FILE)?)
+}
+
+/// Count everything below `root` from directory listings alone
+///
+/// Entry types come from the listing, which on most file systems needs no `stat`, so this
+/// is much faster than a scan. Links aren't followed and the app's own data is skipped, as
+/// the scanner does.
+fn count_entries(root: &Path) -> u64 {
+    let mut entries = 1;
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(listing) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in listing.flatten() {
+            let path = entry.path();
+            if is_app_data_path(&path) {
+                continue;
+            }
+            entries += 1;
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                pending.push(path);
+            }
+        }
+    }
+    entries
+}
+
+/// Expected entry count of a scan of `root`, from a pre-pass or an earlier scan of it
+pub(crate) fn expected_entries(root: &Path) -> Option<u64> {
+    load_counts()
+        .ok()?
+        .into_iter()
+        .find(|count| count.root == root)
+        .map(|count| count.entries)
+}
+
+/// Remember how many entries `root` had, replacing the previous count
+pub(crate) fn record_entries(root: &Path, entries: u64) -> Result<EntryCount, String> {
+    let mut counts = load_counts()?;
+    let count = EntryCount {
+        root: root.to_path_buf(),
+        entries,
+        counted_at: chrono::Utc::now().timestamp(),
+    };
+    counts.retain(|c| c.root != root);
+    counts.push(count.clone());
+    counts.sort_by(|a, b| a.root.cmp(&b.root));
+    write_sealed_json(&data_file(ENTRY_COUNTS_FILE)?, &counts)?;
+    Ok(count)
+}
+
+async fn recount(root: PathBuf) -> Result<EntryCount, String> {
+    tokio::task::spawn_blocking(move || record_entries(&root, count_entries(&root)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Run the pre-pass for `root` unless an earlier count or scan of it left one to reuse
+pub(crate) async fn ensure_counted(root: &Path) -> Result<(), String> {
+    if expected_entries(root).is_none() {
+        recount(root.to_path_buf()).await?;
+    }
+    Ok(())
+}
+
+// Tauri commands
+
+/// Count the entries below `path` without reading their metadata, so later scans of it can
+/// report a percentage
+#[tauri::command]
+pub async fn count_entries_command(path: String) -> Result<EntryCount, String> {
+    let path = PathBuf::from(path);
+    access::ensure_scan_root_allowed(&path)?;
+    recount(path).await
+}
+
+#[tauri::command]
+pub async fn get_entry_counts_command() -> Result<Vec<EntryCount>, String> {
+    load_counts()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_entries_without_stat() {
+        let root = std::env::temp_dir().join("test_entry_counts");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/one.txt"), b"1").unwrap();
+        fs::write(root.join("a/b/two.txt"), b"2").unwrap();
+        fs::write(root.join("three.txt"), b"3").unwrap();
+
+        let entries = count_entries(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        // root, a, a/b and three files
+        assert_eq!(entries, 6);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::access::{self, AccessMode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// External programs some features shell out to, per platform
+#[cfg(target_os = "macos")]
+const TOOLS: &[&str] = &[
+    "ffprobe", "docker", "smartctl", "tmutil", "diskutil", "hdiutil",
+];
+#[cfg(target_os = "windows")]
+const TOOLS: &[&str] = &[
+    "ffprobe",
+    "docker",
+    "smartctl",
+    "vssadmin",
+    "icacls",
+    "takeown",
+    "powershell",
+];
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const TOOLS: &[&str] = &["ffprobe", "docker", "smartctl"];
+
+/// An external program and where it was found on `PATH`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Integration {
+    pub name: String,
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MountedFileSystem {
+    pub mount_point: PathBuf,
+    /// e.g. `apfs`, `ntfs`, `ext4`
+    pub file_system: String,
+    pub removable: bool,
+}
+
+/// What the OS lets the app do; `None` where the question doesn't apply to this platform
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GrantedPermissions {
+    /// macOS Full Disk Access
+    pub full_disk_access: Option<bool>,
+    /// Running as root or as an elevated administrator
+    pub elevated: Option<bool>,
+}
+
+/// Whether a feature can work here, and why not
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FeatureAvailability {
+    pub feature: String,
+    pub available: bool,
+    pub reason: Option<String>,
+}
+
+/// Everything the frontend needs to decide what to offer, and what a bug report should carry
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EnvironmentReport {
+    pub app_version: String,
+    /// e.g. "macOS 14.4 Sonoma"
+    pub os: String,
+    pub kernel: Option<String>,
+    pub arch: String,
+    pub file_systems: Vec<MountedFileSystem>,
+    pub integrations: Vec<Integration>,
+    pub permissions: GrantedPermissions,
+    pub access: AccessMode,
+    pub features: Vec<FeatureAvailability>,
+}
+
+/// Find an executable on `PATH`, the way a shell would
+pub(crate) fn find_tool(name: &str) -> Option<PathBuf> {
+    let names: Vec<String> = if cfg!(windows) {
+        vec![format!("{}.exe", name), format!("{}.cmd", name)]
+    } else {
+        vec![name.to_string()]
+    };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .flat_map(|dir| names.iter().map(move |n| dir.join(n)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Full Disk Access shows as being able to read the TCC database, which needs it
+#[cfg(target_os = "macos")]
+fn full_disk_access() -> Option<bool> {
+    let tcc = std::path::Path::new("/Library/Application Support/com.apple.TCC/TCC.db");
+    Some(std::fs::File::open(tcc).is_ok())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn full_disk_access() -> Option<bool> {
+    None
+}
+
+#[cfg(unix)]
+pub(crate) fn elevated() -> Option<bool> {
+    Some(unsafe { libc::geteuid() } == 0)
+}
+
+/// `fltmc` only succeeds for elevated administrators, which is the usual check
+#[cfg(target_os = "windows")]
+pub(crate) fn elevated() -> Option<bool> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    std::process::Command::new("fltmc")
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()
+        .map(|output| output.status.success())
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+pub(crate) fn elevated() -> Option<bool> {
+    None
+}
+
+fn feature(name: &str, missing: Option<String>) -> FeatureAvailability {
+    FeatureAvailability {
+        feature: name.to_string(),
+        available: missing.is_none(),
+        reason: missing,
+    }
+}
+
+/// Why a feature that needs `tool` can't run, if it can't
+fn needs_tool(integrations: &[Integration], tool: &str) -> Option<String> {
+    match integrations.iter().find(|i| i.name == tool) {
+        Some(Integration { path: Some(_), .. }) => None,
+        Some(_) => Some(format!("{} was not found", tool)),
+        None => Some("Not supported on this platform".to_string()),
+    }
+}
+
+fn features(
+    integrations: &[Integration],
+    permissions: &GrantedPermissions,
+    access: &AccessMode,
+) -> Vec<FeatureAvailability> {
+    let read_only = access
+        .read_only
+        .then(|| "The app is in read-only mode".to_string());
+    let journal = (!cfg!(any(target_os = "macos", target_os = "windows")))
+        .then(|| "No change journal on this platform".to_string());
+    vec![
+        feature(
+            "protected_folders",
+            (permissions.full_disk_access == Some(false))
+                .then(|| "Full Disk Access has not been granted".to_string()),
+        ),
+        feature("delete", read_only.clone()),
+        feature("ownership_repair", read_only),
+        feature("change_journal", journal),
+        feature("local_snapshots", needs_tool(integrations, "tmutil")),
+        feature("shadow_storage", needs_tool(integrations, "vssadmin")),
+        feature("media_metadata", needs_tool(integrations, "ffprobe")),
+        feature("docker_usage", needs_tool(integrations, "docker")),
+        feature("smart_self_test", needs_tool(integrations, "smartctl")),
+    ]
+}
+
+pub fn environment_report() -> EnvironmentReport {
+    let integrations: Vec<Integration> = TOOLS
+        .iter()
+        .map(|name| Integration {
+            name: name.to_string(),
+            path: find_tool(name),
+        })
+        .collect();
+    let file_systems = sysinfo::Disks::new_with_refreshed_list()
+        .list()
+        .iter()
+        .map(|disk| MountedFileSystem {
+            mount_point: disk.mount_point().to_path_buf(),
+            file_system: disk.file_system().to_string_lossy().to_lowercase(),
+            removable: disk.is_removable(),
+        })
+        .collect();
+    let permissions = GrantedPermissions {
+        full_disk_access: full_disk_access(),
+        elevated: elevated(),
+    };
+    let access = access::access_mode();
+
+    EnvironmentReport {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: sysinfo::System::long_os_version().unwrap_or_else(|| std::env::consts::OS.into()),
+        kernel: sysinfo::System::kernel_version(),
+        arch: std::env::consts::ARCH.to_string(),
+        features: features(&integrations, &permissions, &access),
+        file_systems,
+        integrations,
+        permissions,
+        access,
+    }
+}
+
+// Tauri commands
+
+/// OS, file systems, external tools, permissions and which features can work here
+#[tauri::command]
+pub async fn get_environment_report_command() -> Result<EnvironmentReport, String> {
+    tokio::task::spawn_blocking(environment_report)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_features_follow_tools_and_permissions() {
+        let integrations = vec![
+            Integration {
+                name: "ffprobe".to_string(),
+                path: Some(PathBuf::from("/usr/bin/ffprobe")),
+            },
+            Integration {
+                name: "docker".to_string(),
+                path: None,
+            },
+        ];
+        let permissions = GrantedPermissions {
+            full_disk_access: Some(false),
+            elevated: None,
+        };
+        let access = AccessMode {
+            read_only: true,
+            forced_by_cli: false,
+        };
+        let features = features(&integrations, &permissions, &access);
+        let get = |name: &str| features.iter().find(|f| f.feature == name).unwrap();
+
+        assert!(get("media_metadata").available);
+        assert_eq!(
+            get("docker_usage").reason.as_deref(),
+            Some("docker was not found")
+        );
+        assert!(!get("shadow_storage").available);
+        assert!(!get("protected_folders").available);
+        assert!(!get("delete").available);
+
+        #[cfg(unix)]
+        assert!(find_tool("sh").is_some());
+        assert!(find_tool("no-such-tool-for-disk-analyser").is_none());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::persistence::is_app_data_path;
+use crate::scanner;
+use crate::types::{FileNode, StreamingScanEvent};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Window};
+
+/// How long the first pass may take before it stops descending
+const ESTIMATE_BUDGET: Duration = Duration::from_secs(3);
+/// Directory levels below the root that the first pass reads
+const ESTIMATE_DEPTH: usize = 3;
+/// Files measured per directory; the rest are assumed to share their mean size
+const SAMPLED_FILES: usize = 32;
+
+/// A node of the first-pass tree
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EstimatedNode {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub is_directory: bool,
+    /// Entries found below this node (files and directories)
+    pub entry_count: u64,
+    /// False when any part of `size` was extrapolated from samples
+    pub exact: bool,
+    pub children: Vec<EstimatedNode>,
+}
+
+/// Bytes on disk, measured the same way the exact scanner does
+pub(crate) fn disk_size(metadata: &Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.blocks() * 512
+    }
+
+    #[cfg(not(unix))]
+    {
+        metadata.len()
+    }
+}
+
+/// Read a directory's entries sorted by name, so repeated runs sample the same files
+fn sorted_entries(path: &Path) -> Vec<(PathBuf, Metadata)> {
+    let mut entries: Vec<(PathBuf, Metadata)> = fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| Some((e.path(), e.metadata().ok()?)))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+fn estimate_dir(path: &Path, depth: usize, deadline: Instant) -> EstimatedNode {
+    let entries = sorted_entries(path);
+    let (dirs, files): (Vec<_>, Vec<_>) = entries.into_iter().partition(|(_, m)| m.is_dir());
+    let files: Vec<_> = files.into_iter().filter(|(_, m)| !m.is_symlink()).collect();
+
+    // Evenly spaced files, so a sorted listing of similar names still gets a spread
+    let step = files.len().div_ceil(SAMPLED_FILES).max(1);
+    let sampled: Vec<u64> = files
+        .iter()
+        .step_by(step)
+        .map(|(_, m)| disk_size(m))
+        .collect();
+    let mean = sampled.iter().sum::<u64>() / sampled.len().max(1) as u64;
+    let mut size = if sampled.len() == files.len() {
+        sampled.iter().sum()
+    } else {
+        mean * files.len() as u64
+    };
+    let mut exact = sampled.len() == files.len();
+    let mut entry_count = (files.len() + dirs.len()) as u64;
+
+    let mut children = Vec::new();
+    for (dir, _) in dirs {
+        if is_app_data_path(&dir) {
+            continue;
+        }
+        let child = if depth < ESTIMATE_DEPTH && Instant::now() < deadline {
+            estimate_dir(&dir, depth + 1, deadline)
+        } else {
+            // Too deep or out of time: count the entries and assume the parent's mean file size
+            let count = fs::read_dir(&dir).map(|d| d.count() as u64).unwrap_or(0);
+            EstimatedNode {
+                name: file_name(&dir),
+                size: count * mean,
+                is_directory: true,
+                entry_count: count,
+                exact: count == 0,
+                children: Vec::new(),
+                path: dir,
+            }
+        };
+        size += child.size;
+        entry_count += child.entry_count;
+        exact &= child.exact;
+        children.push(child);
+    }
+    children.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    EstimatedNode {
+        name: file_name(path),
+        path: path.to_path_buf(),
+        size,
+        is_directory: true,
+        entry_count,
+        exact,
+        children,
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
+
+/// Shallow tree from entry counts and sampled file sizes
+///
+/// The same directory contents always give the same estimate: files are sampled at fixed
+/// positions of a name-sorted listing, not at random.
+pub fn estimate_tree(root: &Path, budget: Duration) -> EstimatedNode {
+    estimate_dir(root, 0, Instant::now() + budget)
+}
+
+// Tauri commands
+
+/// Scan in two passes: an estimated tree within seconds, then the exact streaming scan
+///
+/// The estimate arrives as an `estimate` event; the progress and completion events that
+/// follow carry exact figures.
+#[tauri::command]
+pub async fn scan_directory_dual_pass_command(
+    path: String,
+    window: Window,
+) -> Result<FileNode, String> {
+    scanner::validate_path(&path)?;
+
+    let root = PathBuf::from(&path);
+    let started = Instant::now();
+    let tree = tokio::task::spawn_blocking(move || estimate_tree(&root, ESTIMATE_BUDGET))
+        .await
+        .map_err(|e| e.to_string())?;
+    let _ = window.emit(
+        "streaming-scan-event",
+        &StreamingScanEvent::Estimate {
+            tree,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        },
+    );
+
+    scanner::scan_directory_async(
+        path,
+        scanner::TreeShape::default(),
+        Vec::new(),
+        None,
+        window,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tree() {
+        let root = std::env::temp_dir().join("test_estimate");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("small")).unwrap();
+        fs::create_dir_all(root.join("many")).unwrap();
+        fs::write(root.join("small/a.bin"), vec![1u8; 8192]).unwrap();
+        for i in 0..100 {
+            fs::write(root.join(format!("many/{:03}.bin", i)), vec![1u8; 4096]).unwrap();
+        }
+
+        let first = estimate_tree(&root, Duration::from_secs(10));
+        let second = estimate_tree(&root, Duration::from_secs(10));
+        fs::remove_dir_all(&root).unwrap();
+
+        let many = first.children.iter().find(|c| c.name == "many").unwrap();
+        let small = first.children.iter().find(|c| c.name == "small").unwrap();
+        assert!(!many.exact);
+        assert_eq!(many.entry_count, 100);
+        assert!(small.exact);
+        assert!(!first.exact);
+        assert_eq!(first.size, second.size);
+    }
+}
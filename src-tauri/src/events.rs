@@ -0,0 +1,14 @@
+use serde::Serialize;
+use tauri::{Emitter, Window};
+
+/// Where a long-running operation sends its events: the app window, or the clients of a
+/// `--serve` server
+pub trait EventSink: Clone + Send + Sync + 'static {
+    fn send<S: Serialize + Clone>(&self, event: &str, payload: &S);
+}
+
+impl EventSink for Window {
+    fn send<S: Serialize + Clone>(&self, event: &str, payload: &S) {
+        let _ = self.emit(event, payload);
+    }
+}
@@ -0,0 +1,134 @@
+//! Glob patterns for leaving folders and files out of a scan
+//!
+//! Patterns follow `.gitignore` conventions: one without a `/` (such as `node_modules` or
+//! `*.tmp`) matches an entry's name at any depth, one with a `/` matches the path relative
+//! to the scan root, and a trailing `/` restricts it to directories. `*` and `?` stay within
+//! one path component, `**` crosses them, and `[a-z]` / `[!0-9]` match character classes.
+//! Backslashes are read as separators so Windows-style patterns work too. Matching ignores
+//! case on Windows and macOS, whose file systems usually do.
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::{Component, Path};
+
+/// A scan's exclude patterns, compiled once before the walk starts
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExcludePatterns {
+    /// Patterns without a `/`, matched against the entry's name
+    names: GlobSet,
+    /// Patterns with a `/`, matched against the path relative to the scan root
+    paths: GlobSet,
+    /// The same two kinds, for patterns ending in `/`
+    dir_names: GlobSet,
+    dir_paths: GlobSet,
+    count: usize,
+}
+
+fn build(builder: GlobSetBuilder) -> Result<GlobSet, String> {
+    builder
+        .build()
+        .map_err(|e| format!("Invalid exclude patterns: {}", e))
+}
+
+impl ExcludePatterns {
+    /// Compile `patterns`, rejecting the whole set if any of them is malformed
+    pub(crate) fn new(patterns: &[String]) -> Result<Self, String> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        let mut dir_names = GlobSetBuilder::new();
+        let mut dir_paths = GlobSetBuilder::new();
+        let mut count = 0;
+
+        for source in patterns.iter().filter(|p| !p.trim().is_empty()) {
+            let normalized = source.trim().replace('\\', "/");
+            let dirs_only = normalized.ends_with('/');
+            let body = normalized.trim_end_matches('/');
+            let anchored = body.contains('/');
+            let body = body.trim_start_matches('/');
+            if body.is_empty() {
+                return Err(format!("Invalid exclude pattern: \"{}\"", source));
+            }
+
+            let glob = GlobBuilder::new(body)
+                .literal_separator(true)
+                .backslash_escape(false)
+                .case_insensitive(cfg!(any(windows, target_os = "macos")))
+                .build()
+                .map_err(|e| format!("Invalid exclude pattern: \"{}\": {}", source, e.kind()))?;
+            match (dirs_only, anchored) {
+                (false, false) => names.add(glob),
+                (false, true) => paths.add(glob),
+                (true, false) => dir_names.add(glob),
+                (true, true) => dir_paths.add(glob),
+            };
+            count += 1;
+        }
+
+        Ok(Self {
+            names: build(names)?,
+            paths: build(paths)?,
+            dir_names: build(dir_names)?,
+            dir_paths: build(dir_paths)?,
+            count,
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Whether the entry at `relative` (to the scan root) is left out of the scan
+    pub(crate) fn is_excluded(&self, relative: &Path, is_dir: bool) -> bool {
+        let components: Vec<String> = relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+        let Some(name) = components.last() else {
+            return false;
+        };
+        let path = components.join("/");
+
+        self.names.is_match(name)
+            || self.paths.is_match(&path)
+            || (is_dir && (self.dir_names.is_match(name) || self.dir_paths.is_match(&path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_match_names_paths_and_directories() {
+        let patterns: Vec<String> = [
+            "node_modules",
+            ".git/",
+            "*.tmp",
+            "build/**/cache",
+            "log[0-9]",
+            "*a*a*a*a*a*a*a*a*a*a*a*a*b",
+        ]
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+        let exclude = ExcludePatterns::new(&patterns).unwrap();
+        let excluded = |path: &str, is_dir| exclude.is_excluded(Path::new(path), is_dir);
+
+        assert!(excluded("web/app/node_modules", true));
+        assert!(excluded(".git", true));
+        assert!(!excluded(".git", false));
+        assert!(excluded("a/b/session.tmp", false));
+        assert!(!excluded("a/b/session.tmp.bak", false));
+        assert!(excluded("build/cache", true));
+        assert!(excluded("build/x/y/cache", true));
+        assert!(!excluded("src/build/cache", true));
+        assert!(excluded("log7", false));
+        assert!(!excluded("logs", false));
+        assert!(!excluded(&"a".repeat(60), false));
+
+        assert!(ExcludePatterns::new(&["[abc".to_string()]).is_err());
+        assert!(ExcludePatterns::new(&["/".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,388 @@
+use crate::confidence::{known_location, KnownLocation};
+use crate::duplicates::{self, DuplicateReport};
+use crate::i18n::{localize, LocalizedMessage};
+use crate::registry::{self, DiscoveredNode};
+use crate::types::FileType;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Entries listed in each of the largest-children and largest-files lists
+const TOP_COUNT: usize = 5;
+
+/// Share (in percent) above which one child or category is said to dominate
+const DOMINANT_PERCENT: u64 = 50;
+
+/// Share of reclaimable duplicates above which they lead the explanation
+const DUPLICATES_PERCENT: u64 = 25;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Extensions of folders macOS shows as a single file
+const BUNDLE_EXTENSIONS: &[&str] = &[
+    "app",
+    "bundle",
+    "framework",
+    "photoslibrary",
+    "musiclibrary",
+    "tvlibrary",
+    "lrlibrary",
+    "fcpbundle",
+    "xcarchive",
+    "pvm",
+    "vmwarevm",
+];
+
+/// A descendant that accounts for part of a directory's size
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Contributor {
+    pub path: PathBuf,
+    pub size: u64,
+    pub is_directory: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CategoryShare {
+    pub category: FileType,
+    pub bytes: u64,
+    pub percent: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AgeRange {
+    /// Modified in the last 30 days
+    LastMonth,
+    /// Modified in the last year, but not the last 30 days
+    LastYear,
+    Older,
+}
+
+/// Bytes and files last modified within one age range
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AgeBucket {
+    pub range: AgeRange,
+    pub bytes: u64,
+    pub files: u64,
+}
+
+/// What the folder is known to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KnownKind {
+    Cache,
+    Trash,
+    /// An app, library or other package macOS shows as one file
+    Bundle,
+}
+
+/// Duplicate files with at least two copies inside the folder
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct DuplicatesInside {
+    pub groups: u64,
+    /// Bytes freed by keeping one copy of each inside the folder
+    pub reclaimable: u64,
+}
+
+/// Why a file or folder is as big as it is
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NodeExplanation {
+    pub path: PathBuf,
+    pub size: u64,
+    pub file_count: u64,
+    /// One sentence naming the main reason, for showing inline
+    pub summary: LocalizedMessage,
+    /// Largest direct children, largest first
+    pub largest_children: Vec<Contributor>,
+    /// Largest files anywhere below, largest first
+    pub largest_files: Vec<Contributor>,
+    pub dominant_category: Option<CategoryShare>,
+    /// Every range, newest first
+    pub age: Vec<AgeBucket>,
+    /// From the last duplicate search; empty when none has run
+    pub duplicates: DuplicatesInside,
+    pub known_kind: Option<KnownKind>,
+}
+
+fn percent(part: u64, whole: u64) -> u64 {
+    (part * 100).checked_div(whole).unwrap_or(0)
+}
+
+pub(crate) fn known_kind(path: &Path) -> Option<KnownKind> {
+    let in_bundle = path.ancestors().any(|ancestor| {
+        ancestor.extension().is_some_and(|ext| {
+            BUNDLE_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+        })
+    });
+    match known_location(path) {
+        Some(KnownLocation::Cache) => Some(KnownKind::Cache),
+        Some(KnownLocation::Trash) => Some(KnownKind::Trash),
+        _ if in_bundle => Some(KnownKind::Bundle),
+        _ => None,
+    }
+}
+
+fn age_range(modified: SystemTime, now: SystemTime) -> AgeRange {
+    let age = now.duration_since(modified).unwrap_or(Duration::ZERO);
+    if age < Duration::from_secs(30 * SECONDS_PER_DAY) {
+        AgeRange::LastMonth
+    } else if age < Duration::from_secs(365 * SECONDS_PER_DAY) {
+        AgeRange::LastYear
+    } else {
+        AgeRange::Older
+    }
+}
+
+fn contributor(node: &DiscoveredNode) -> Contributor {
+    Contributor {
+        path: node.path.clone(),
+        size: node.size,
+        is_directory: node.is_directory,
+    }
+}
+
+fn largest<'a>(nodes: impl Iterator<Item = &'a DiscoveredNode>) -> Vec<Contributor> {
+    let mut nodes: Vec<&DiscoveredNode> = nodes.collect();
+    nodes.sort_by_key(|n| std::cmp::Reverse(n.size));
+    nodes.into_iter().take(TOP_COUNT).map(contributor).collect()
+}
+
+fn duplicates_inside(report: &DuplicateReport, path: &Path) -> DuplicatesInside {
+    let mut inside = DuplicatesInside::default();
+    for group in &report.groups {
+        let copies = group
+            .volumes
+            .iter()
+            .flat_map(|v| &v.files)
+            .filter(|f| f.path.starts_with(path))
+            .count() as u64;
+        if copies >= 2 {
+            inside.groups += 1;
+            inside.reclaimable += group.size * (copies - 1);
+        }
+    }
+    inside
+}
+
+/// What the summary sentence is chosen from
+struct Reasons<'a> {
+    size: u64,
+    file_count: u64,
+    known_kind: Option<KnownKind>,
+    duplicates: &'a DuplicatesInside,
+    largest_child: Option<&'a Contributor>,
+    category: Option<&'a CategoryShare>,
+    older_bytes: u64,
+}
+
+/// The sentence for the strongest reason: what the folder is, then duplicates, then one
+/// child or one kind of file taking most of it, then its age
+fn summary(reasons: &Reasons) -> LocalizedMessage {
+    let size = reasons.size;
+    if let Some(kind) = reasons.known_kind {
+        let code = match kind {
+            KnownKind::Cache => "explain-cache",
+            KnownKind::Trash => "explain-trash",
+            KnownKind::Bundle => "explain-bundle",
+        };
+        return localize(code, &[]);
+    }
+    let duplicated = percent(reasons.duplicates.reclaimable, size);
+    if duplicated >= DUPLICATES_PERCENT {
+        return localize("explain-duplicates", &[("percent", json!(duplicated))]);
+    }
+    if let Some(child) = reasons
+        .largest_child
+        .filter(|c| percent(c.size, size) >= DOMINANT_PERCENT)
+    {
+        let name = child
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        return localize(
+            "explain-one-child",
+            &[
+                ("name", json!(name)),
+                ("percent", json!(percent(child.size, size))),
+            ],
+        );
+    }
+    if let Some(share) = reasons.category.filter(|s| s.percent >= DOMINANT_PERCENT) {
+        return localize(
+            "explain-category",
+            &[
+                ("category", json!(share.category)),
+                ("percent", json!(share.percent)),
+            ],
+        );
+    }
+    localize(
+        "explain-spread",
+        &[
+            ("files", json!(reasons.file_count)),
+            ("old_percent", json!(percent(reasons.older_bytes, size))),
+        ],
+    )
+}
+
+/// Explain `path` from a scan's nodes, or `None` when the scan doesn't contain it
+fn explain(
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+    path: &Path,
+    now: SystemTime,
+    report: Option<&DuplicateReport>,
+) -> Option<NodeExplanation> {
+    let node = nodes.get(path)?;
+    let below: Vec<&DiscoveredNode> = nodes
+        .values()
+        .filter(|n| n.path != path && n.path.starts_with(path) && !n.is_symlink)
+        .collect();
+    let files: Vec<&DiscoveredNode> = if node.is_directory {
+        below.iter().copied().filter(|n| !n.is_directory).collect()
+    } else {
+        vec![node]
+    };
+
+    let mut categories: HashMap<FileType, u64> = HashMap::new();
+    let mut age: Vec<AgeBucket> = [AgeRange::LastMonth, AgeRange::LastYear, AgeRange::Older]
+        .into_iter()
+        .map(|range| AgeBucket {
+            range,
+            bytes: 0,
+            files: 0,
+        })
+        .collect();
+    for file in &files {
+        *categories.entry(file.file_type.clone()).or_default() += file.size;
+        let range = age_range(file.modified, now);
+        if let Some(bucket) = age.iter_mut().find(|b| b.range == range) {
+            bucket.bytes += file.size;
+            bucket.files += 1;
+        }
+    }
+    let dominant_category =
+        categories
+            .into_iter()
+            .max_by_key(|(_, bytes)| *bytes)
+            .map(|(category, bytes)| CategoryShare {
+                category,
+                bytes,
+                percent: percent(bytes, node.size),
+            });
+
+    let largest_children = largest(
+        below
+            .iter()
+            .copied()
+            .filter(|n| n.parent_path.as_deref() == Some(path)),
+    );
+    let duplicates = report
+        .map(|report| duplicates_inside(report, path))
+        .unwrap_or_default();
+    let known_kind = known_kind(path);
+    let summary = summary(&Reasons {
+        size: node.size,
+        file_count: files.len() as u64,
+        known_kind,
+        duplicates: &duplicates,
+        largest_child: largest_children.first(),
+        category: dominant_category.as_ref(),
+        older_bytes: age
+            .iter()
+            .find(|bucket| bucket.range == AgeRange::Older)
+            .map_or(0, |bucket| bucket.bytes),
+    });
+
+    Some(NodeExplanation {
+        path: path.to_path_buf(),
+        size: node.size,
+        file_count: files.len() as u64,
+        summary,
+        largest_children,
+        largest_files: largest(files.iter().copied()),
+        dominant_category,
+        age,
+        duplicates,
+        known_kind,
+    })
+}
+
+// Tauri commands
+
+/// Explain what makes a file or folder big, from the newest retained scan that contains it
+///
+/// Duplicates come from the last completed `find_duplicates_command` run; nothing is hashed
+/// or scanned here.
+#[tauri::command]
+pub async fn explain_node_command(path: String) -> Result<NodeExplanation, String> {
+    let path = PathBuf::from(path);
+    let mut scans = registry::retained_summaries().await;
+    scans.retain(|summary| path.starts_with(&summary.root));
+    scans.sort_by_key(|summary| std::cmp::Reverse(summary.scan_id));
+    let report = duplicates::last_report().await;
+
+    for summary in scans {
+        let found = registry::with_scan(summary.scan_id, |nodes, _| {
+            explain(nodes, &path, SystemTime::now(), report.as_ref())
+        })
+        .await?;
+        if let Some(explanation) = found {
+            return Ok(explanation);
+        }
+    }
+    Err(format!("{} is not in any retained scan", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::test_node;
+
+    #[test]
+    fn test_explain_names_the_dominant_child() {
+        let now = SystemTime::now();
+        let mut nodes: HashMap<PathBuf, DiscoveredNode> = [
+            test_node("/p", 1000, true),
+            test_node("/p/movies", 800, true),
+            test_node("/p/movies/a.mp4", 800, false),
+            test_node("/p/notes", 200, true),
+            test_node("/p/notes/a.txt", 150, false),
+            test_node("/p/notes/b.txt", 50, false),
+        ]
+        .into_iter()
+        .map(|n| (n.path.clone(), n))
+        .collect();
+        for node in nodes.values_mut() {
+            node.modified = now;
+        }
+        nodes
+            .get_mut(Path::new("/p/movies/a.mp4"))
+            .unwrap()
+            .file_type = FileType::Video;
+
+        let explanation = explain(&nodes, Path::new("/p"), now, None).unwrap();
+        assert_eq!(explanation.file_count, 3);
+        assert_eq!(
+            explanation.largest_children[0].path,
+            PathBuf::from("/p/movies")
+        );
+        assert_eq!(explanation.largest_files.len(), 3);
+        let category = explanation.dominant_category.as_ref().unwrap();
+        assert_eq!(
+            (category.category.clone(), category.percent),
+            (FileType::Video, 80)
+        );
+        assert_eq!(explanation.age[0].bytes, 1000);
+        assert_eq!(explanation.summary.code, "explain-one-child");
+
+        assert_eq!(
+            known_kind(Path::new("/Applications/Xcode.app/Contents")),
+            Some(KnownKind::Bundle)
+        );
+        assert!(explain(&nodes, Path::new("/elsewhere"), now, None).is_none());
+    }
+}
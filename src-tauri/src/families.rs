@@ -0,0 +1,224 @@
+use crate::never_suggest::{self, is_never_suggested, NeverSuggestEntry};
+use crate::persistence::is_app_data_path;
+use crate::registry::{self, DiscoveredNode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Trailing name parts that mark a revision rather than a different document
+const VERSION_WORDS: [&str; 10] = [
+    "final", "draft", "old", "new", "latest", "copy", "backup", "bak", "orig", "edited",
+];
+
+/// One file of a versioned family
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FamilyMember {
+    pub id: u64,
+    pub path: PathBuf,
+    pub name: String,
+    pub size: u64,
+    /// Unix timestamp (seconds) of the last modification
+    pub modified: i64,
+    /// On the never-suggest list: always kept, never offered for deletion
+    pub never_suggest: bool,
+}
+
+/// Revisions of the same file side by side in one directory
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VersionFamily {
+    pub directory: PathBuf,
+    /// Name the revisions share once version markers are removed, e.g. `report.docx`
+    pub base_name: String,
+    /// Newest first
+    pub members: Vec<FamilyMember>,
+    /// Bytes freed by keeping only the newest revision (and never-suggest members)
+    pub reclaimable: u64,
+}
+
+/// Whether a name part is a version marker such as `v2`, `rev3` or `FINAL`
+fn is_version_marker(part: &str) -> bool {
+    let part = part.to_lowercase();
+    if VERSION_WORDS.contains(&part.as_str()) {
+        return true;
+    }
+    ["version", "rev", "v"].iter().any(|prefix| {
+        part.strip_prefix(prefix)
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    })
+}
+
+/// Strip copy counters and version markers from a file stem
+///
+/// `report_v2`, `report_final_FINAL` and `Report (1)` all reduce to `report`, while
+/// `IMG_1234` keeps its number since a bare number names a different photo.
+fn family_key(stem: &str) -> String {
+    let mut key = stem.trim().to_string();
+    if let Some(rest) = key.to_lowercase().strip_prefix("copy of ") {
+        key = rest.to_string();
+    }
+
+    loop {
+        let trimmed = key.trim_end();
+        // " (1)" style counters added by browsers and file managers
+        if let Some(open) = trimmed.rfind('(').filter(|_| trimmed.ends_with(')')) {
+            let counter = &trimmed[open + 1..trimmed.len() - 1];
+            if open > 0 && !counter.is_empty() && counter.chars().all(|c| c.is_ascii_digit()) {
+                key = trimmed[..open].to_string();
+                continue;
+            }
+        }
+
+        match trimmed.rfind(['_', '-', ' ', '.']) {
+            Some(split) if split > 0 && is_version_marker(&trimmed[split + 1..]) => {
+                key = trimmed[..split].to_string();
+            }
+            _ => break,
+        }
+    }
+
+    key.trim_end_matches(['_', '-', ' ', '.']).to_lowercase()
+}
+
+/// Split a file name into its stem and lowercase extension
+fn split_name(name: &str) -> (&str, String) {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => (&name[..dot], name[dot + 1..].to_lowercase()),
+        _ => (name, String::new()),
+    }
+}
+
+/// Group files in the same directory whose names differ only by version markers
+fn find_families(
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+    never_suggest: &[NeverSuggestEntry],
+) -> Vec<VersionFamily> {
+    let mut groups: HashMap<(&Path, String, String), Vec<&DiscoveredNode>> = HashMap::new();
+    let files = nodes
+        .values()
+        .filter(|n| !n.is_directory && !n.is_symlink && !is_app_data_path(&n.path));
+    for node in files {
+        let Some(parent) = node.parent_path.as_deref() else {
+            continue;
+        };
+        let (stem, extension) = split_name(&node.name);
+        let key = family_key(stem);
+        if !key.is_empty() {
+            groups
+                .entry((parent, key, extension))
+                .or_default()
+                .push(node);
+        }
+    }
+
+    let mut families: Vec<VersionFamily> = groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|((directory, key, extension), members)| {
+            let mut members: Vec<FamilyMember> = members
+                .into_iter()
+                .map(|n| FamilyMember {
+                    id: n.id,
+                    path: n.path.clone(),
+                    name: n.name.clone(),
+                    size: n.size,
+                    modified: n
+                        .modified
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs() as i64)
+                        .unwrap_or(0),
+                    never_suggest: is_never_suggested(never_suggest, &n.path),
+                })
+                .collect();
+            members.sort_by(|a, b| {
+                b.modified
+                    .cmp(&a.modified)
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+
+            let reclaimable = members
+                .iter()
+                .skip(1)
+                .filter(|m| !m.never_suggest)
+                .map(|m| m.size)
+                .sum();
+            VersionFamily {
+                directory: directory.to_path_buf(),
+                base_name: if extension.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", key, extension)
+                },
+                members,
+                reclaimable,
+            }
+        })
+        .collect();
+
+    families.sort_by(|a, b| {
+        b.reclaimable
+            .cmp(&a.reclaimable)
+            .then_with(|| a.directory.cmp(&b.directory))
+            .then_with(|| a.base_name.cmp(&b.base_name))
+    });
+    families
+}
+
+// Tauri commands
+
+/// Versioned-file families in a retained scan, largest savings first
+#[tauri::command]
+pub async fn find_version_families_command(scan_id: u64) -> Result<Vec<VersionFamily>, String> {
+    let never_suggest = never_suggest::load_never_suggest()?;
+    registry::with_scan(scan_id, |nodes, _| find_families(nodes, &never_suggest)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_family_key() {
+        assert_eq!(family_key("report_v1"), "report");
+        assert_eq!(family_key("report_v2"), "report");
+        assert_eq!(family_key("report_final_FINAL"), "report");
+        assert_eq!(family_key("Report (1)"), "report");
+        assert_eq!(family_key("Copy of report - rev3"), "report");
+        assert_eq!(family_key("IMG_1234 (1)"), "img_1234");
+        assert_eq!(family_key("IMG_1234"), "img_1234");
+        assert_eq!(family_key("IMG_1235"), "img_1235");
+        assert_eq!(family_key("final"), "final");
+    }
+
+    #[test]
+    fn test_find_families_keeps_newest() {
+        let file = |path: &str, size: u64, age_days: u64| {
+            let mut node = registry::test_node(path, size, false);
+            node.modified = UNIX_EPOCH + Duration::from_secs((100 - age_days) * 86_400);
+            (node.path.clone(), node)
+        };
+        let nodes: HashMap<PathBuf, DiscoveredNode> = [
+            file("/docs/report_v1.docx", 100, 30),
+            file("/docs/report_v2.docx", 120, 20),
+            file("/docs/report_final_FINAL.docx", 130, 1),
+            file("/docs/report_v1.pdf", 50, 30),
+            file("/photos/IMG_1234.jpg", 400, 10),
+            file("/photos/IMG_1234 (1).jpg", 400, 5),
+            file("/photos/IMG_1235.jpg", 400, 10),
+            file("/other/report_v1.docx", 100, 30),
+        ]
+        .into_iter()
+        .collect();
+
+        let families = find_families(&nodes, &[]);
+
+        assert_eq!(families.len(), 2);
+        assert_eq!(families[0].base_name, "img_1234.jpg");
+        assert_eq!(families[0].reclaimable, 400);
+        assert_eq!(families[1].base_name, "report.docx");
+        assert_eq!(families[1].members[0].name, "report_final_FINAL.docx");
+        assert_eq!(families[1].reclaimable, 220);
+    }
+}
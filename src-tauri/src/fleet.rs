@@ -0,0 +1,250 @@
+use crate::encryption;
+use crate::persistence::{
+    app_data_dir, data_file, list_saved_scans, load_scan, machine_name,
+    read_sealed_json_or_default, write_sealed_json, SavedScan,
+};
+use crate::space_guard::write_guarded;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FLEET_FILE: &str = "fleet.json";
+
+/// A scan imported from another machine into the workspace
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FleetScan {
+    pub id: u64,
+    pub machine: String,
+    pub root: PathBuf,
+    pub saved_at: i64,
+    pub total_size: u64,
+    /// Copy of the scan inside the app data directory
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+struct FleetStore {
+    next_id: u64,
+    scans: Vec<FleetScan>,
+}
+
+/// The newest scan of one root on a machine
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FleetVolume {
+    pub root: PathBuf,
+    pub used: u64,
+    pub saved_at: i64,
+    /// Imported scan to open for details; `None` for scans saved on this machine
+    pub fleet_scan_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FleetMachine {
+    pub machine: String,
+    pub is_local: bool,
+    /// Sum over the machine's volumes, not counting roots nested in another scanned root
+    pub used: u64,
+    pub last_saved_at: i64,
+    /// Largest first
+    pub volumes: Vec<FleetVolume>,
+}
+
+/// Usage of every machine with scans in the workspace, largest first
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FleetOverview {
+    pub machines: Vec<FleetMachine>,
+    pub total_used: u64,
+}
+
+fn load_store() -> Result<FleetStore, String> {
+    read_sealed_json_or_default(&data_file(FLEET_FILE)?)
+}
+
+fn save_store(store: &FleetStore) -> Result<(), String> {
+    write_sealed_json(&data_file(FLEET_FILE)?, store)
+}
+
+fn fleet_dir() -> Result<PathBuf, String> {
+    let dir = app_data_dir()?.join("fleet");
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// Name an imported scan is filed under: the one given, the one it recorded, or its file name
+fn machine_for(file: &Path, saved: &SavedScan, machine: Option<String>) -> String {
+    machine
+        .filter(|m| !m.trim().is_empty())
+        .or_else(|| saved.machine.clone())
+        .or_else(|| file.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+/// Group scans by machine, keeping the newest scan per root and dropping roots that
+/// another kept root of the same machine contains
+fn overview(entries: Vec<(String, bool, FleetVolume)>) -> FleetOverview {
+    let mut by_machine: BTreeMap<String, (bool, Vec<FleetVolume>)> = BTreeMap::new();
+    for (machine, is_local, volume) in entries {
+        let (_, volumes) = by_machine.entry(machine).or_insert((is_local, Vec::new()));
+        match volumes.iter_mut().find(|v| v.root == volume.root) {
+            Some(existing) if existing.saved_at >= volume.saved_at => {}
+            Some(existing) => *existing = volume,
+            None => volumes.push(volume),
+        }
+    }
+
+    let mut machines: Vec<FleetMachine> = by_machine
+        .into_iter()
+        .map(|(machine, (is_local, mut volumes))| {
+            let outer: Vec<PathBuf> = volumes
+                .iter()
+                .filter(|v| {
+                    !volumes
+                        .iter()
+                        .any(|other| other.root != v.root && v.root.starts_with(&other.root))
+                })
+                .map(|v| v.root.clone())
+                .collect();
+            volumes.sort_by_key(|v| std::cmp::Reverse(v.used));
+            FleetMachine {
+                machine,
+                is_local,
+                used: volumes
+                    .iter()
+                    .filter(|v| outer.contains(&v.root))
+                    .map(|v| v.used)
+                    .sum(),
+                last_saved_at: volumes.iter().map(|v| v.saved_at).max().unwrap_or(0),
+                volumes,
+            }
+        })
+        .collect();
+    machines.sort_by_key(|m| std::cmp::Reverse(m.used));
+
+    FleetOverview {
+        total_used: machines.iter().map(|m| m.used).sum(),
+        machines,
+    }
+}
+
+// Tauri commands
+
+/// Add a scan saved or exported on another machine to the workspace
+///
+/// `machine` overrides the host name recorded in the scan, for scans that predate it.
+#[tauri::command]
+pub async fn import_fleet_scan_command(
+    file: String,
+    machine: Option<String>,
+) -> Result<FleetScan, String> {
+    let file = PathBuf::from(file);
+    let saved = load_scan(&file)?;
+    let machine = machine_for(&file, &saved, machine);
+
+    let mut store = load_store()?;
+    store.next_id += 1;
+    let copy = fleet_dir()?.join(format!("fleet-{}.json", store.next_id));
+    let json = serde_json::to_vec(&saved).map_err(|e| format!("Cannot serialize scan: {}", e))?;
+    write_guarded(&copy, &encryption::seal(json)?)?;
+
+    let scan = FleetScan {
+        id: store.next_id,
+        machine,
+        root: saved.tree.path.clone(),
+        saved_at: saved.saved_at,
+        total_size: saved.tree.size,
+        file: copy,
+    };
+    store.scans.push(scan.clone());
+    save_store(&store)?;
+    Ok(scan)
+}
+
+#[tauri::command]
+pub async fn list_fleet_scans_command() -> Result<Vec<FleetScan>, String> {
+    Ok(load_store()?.scans)
+}
+
+#[tauri::command]
+pub async fn load_fleet_scan_command(id: u64) -> Result<SavedScan, String> {
+    let store = load_store()?;
+    let scan = store
+        .scans
+        .iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| format!("Unknown fleet scan: {}", id))?;
+    load_scan(&scan.file)
+}
+
+#[tauri::command]
+pub async fn remove_fleet_scan_command(id: u64) -> Result<(), String> {
+    let mut store = load_store()?;
+    if let Some(scan) = store.scans.iter().find(|s| s.id == id) {
+        let _ = fs::remove_file(&scan.file);
+    }
+    store.scans.retain(|s| s.id != id);
+    save_store(&store)
+}
+
+/// Usage per machine and volume, from this machine's saved scans and the imported ones
+#[tauri::command]
+pub async fn get_fleet_overview_command() -> Result<FleetOverview, String> {
+    let local = machine_name().unwrap_or_else(|| "This computer".to_string());
+    let mut entries: Vec<(String, bool, FleetVolume)> = list_saved_scans()?
+        .into_iter()
+        .map(|info| {
+            let volume = FleetVolume {
+                root: info.root,
+                used: info.total_size,
+                saved_at: info.saved_at,
+                fleet_scan_id: None,
+            };
+            (local.clone(), true, volume)
+        })
+        .collect();
+    entries.extend(load_store()?.scans.into_iter().map(|scan| {
+        let volume = FleetVolume {
+            root: scan.root,
+            used: scan.total_size,
+            saved_at: scan.saved_at,
+            fleet_scan_id: Some(scan.id),
+        };
+        (scan.machine, false, volume)
+    }));
+    Ok(overview(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(root: &str, used: u64, saved_at: i64) -> FleetVolume {
+        FleetVolume {
+            root: PathBuf::from(root),
+            used,
+            saved_at,
+            fleet_scan_id: None,
+        }
+    }
+
+    #[test]
+    fn test_overview_keeps_newest_outer_roots() {
+        let fleet = overview(vec![
+            ("laptop".into(), true, volume("/", 100, 1)),
+            ("laptop".into(), true, volume("/", 120, 2)),
+            ("laptop".into(), true, volume("/home", 80, 3)),
+            ("desktop".into(), false, volume("C:\\", 500, 1)),
+            ("desktop".into(), false, volume("D:\\", 300, 1)),
+        ]);
+
+        assert_eq!(fleet.total_used, 920);
+        assert_eq!(fleet.machines[0].machine, "desktop");
+        assert_eq!(fleet.machines[0].used, 800);
+        let laptop = &fleet.machines[1];
+        assert!(laptop.is_local);
+        assert_eq!(laptop.used, 120);
+        assert_eq!(laptop.volumes.len(), 2);
+        assert_eq!(laptop.last_saved_at, 3);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::persistence::{list_saved_scans, SavedScanInfo};
+use crate::reconcile::volume_for;
+use crate::settings::load_settings;
+use crate::storage::{get_storage_locations, StorageLocation};
+use chrono::Datelike;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How much scan history to keep; nothing is pruned until `keep_per_volume` is set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HistoryRetention {
+    /// Newest saved scans kept per volume; `None` keeps everything
+    pub keep_per_volume: Option<usize>,
+    /// Also keep the first scan of every month, so long-term trends survive pruning
+    pub keep_monthly_baselines: bool,
+    /// Prune after every saved scan instead of only on request
+    pub auto_compact: bool,
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self {
+            keep_per_volume: None,
+            keep_monthly_baselines: true,
+            auto_compact: false,
+        }
+    }
+}
+
+/// Saved scans of one volume
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VolumeHistory {
+    pub volume: PathBuf,
+    pub scan_count: u64,
+    pub bytes: u64,
+    pub oldest_saved_at: i64,
+    pub newest_saved_at: i64,
+    /// Scans the retention settings would delete
+    pub prunable_count: u64,
+    pub prunable_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HistoryStorageUsage {
+    pub scan_count: u64,
+    pub bytes: u64,
+    pub volumes: Vec<VolumeHistory>,
+    pub retention: HistoryRetention,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct HistoryPruneResult {
+    pub scans_removed: u64,
+    pub freed_bytes: u64,
+    /// Files that could not be removed, with the reason
+    pub errors: Vec<String>,
+}
+
+/// A saved scan with the volume it was taken of and its size on disk
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+    file: PathBuf,
+    volume: PathBuf,
+    saved_at: i64,
+    bytes: u64,
+}
+
+fn history_entries(scans: Vec<SavedScanInfo>, locations: &[StorageLocation]) -> Vec<HistoryEntry> {
+    scans
+        .into_iter()
+        .map(|scan| HistoryEntry {
+            // A volume that isn't mounted now is grouped by the scan root instead
+            volume: volume_for(&scan.root, locations)
+                .map(|l| l.path.clone())
+                .unwrap_or_else(|| scan.root.clone()),
+            bytes: fs::metadata(&scan.file).map(|m| m.len()).unwrap_or(0),
+            file: scan.file,
+            saved_at: scan.saved_at,
+        })
+        .collect()
+}
+
+fn by_volume(entries: &[HistoryEntry]) -> BTreeMap<&Path, Vec<&HistoryEntry>> {
+    let mut volumes: BTreeMap<&Path, Vec<&HistoryEntry>> = BTreeMap::new();
+    for entry in entries {
+        volumes.entry(&entry.volume).or_default().push(entry);
+    }
+    for scans in volumes.values_mut() {
+        scans.sort_by_key(|e| std::cmp::Reverse(e.saved_at));
+    }
+    volumes
+}
+
+/// Scans of one volume (newest first) that the retention settings would delete
+fn prunable<'a>(scans: &[&'a HistoryEntry], retention: &HistoryRetention) -> Vec<&'a HistoryEntry> {
+    let Some(keep) = retention.keep_per_volume else {
+        return Vec::new();
+    };
+    let mut baselines: HashSet<&Path> = HashSet::new();
+    if retention.keep_monthly_baselines {
+        let mut months = HashSet::new();
+        // Oldest first, so the first scan seen in a month is its baseline
+        for scan in scans.iter().rev() {
+            let month =
+                chrono::DateTime::from_timestamp(scan.saved_at, 0).map(|d| (d.year(), d.month()));
+            if months.insert(month) {
+                baselines.insert(&scan.file);
+            }
+        }
+    }
+    scans
+        .iter()
+        .skip(keep)
+        .filter(|scan| !baselines.contains(scan.file.as_path()))
+        .copied()
+        .collect()
+}
+
+fn storage_usage(entries: &[HistoryEntry], retention: HistoryRetention) -> HistoryStorageUsage {
+    let volumes: Vec<VolumeHistory> = by_volume(entries)
+        .into_iter()
+        .map(|(volume, scans)| {
+            let prune = prunable(&scans, &retention);
+            VolumeHistory {
+                volume: volume.to_path_buf(),
+                scan_count: scans.len() as u64,
+                bytes: scans.iter().map(|s| s.bytes).sum(),
+                oldest_saved_at: scans.last().map(|s| s.saved_at).unwrap_or(0),
+                newest_saved_at: scans.first().map(|s| s.saved_at).unwrap_or(0),
+                prunable_count: prune.len() as u64,
+                prunable_bytes: prune.iter().map(|s| s.bytes).sum(),
+            }
+        })
+        .collect();
+    HistoryStorageUsage {
+        scan_count: entries.len() as u64,
+        bytes: entries.iter().map(|e| e.bytes).sum(),
+        volumes,
+        retention,
+    }
+}
+
+/// Delete the saved scans the retention settings no longer keep
+pub(crate) fn prune_history() -> Result<HistoryPruneResult, String> {
+    let retention = load_settings()?.history_retention;
+    let locations = get_storage_locations().unwrap_or_default();
+    let entries = history_entries(list_saved_scans()?, &locations);
+
+    let mut result = HistoryPruneResult::default();
+    for scans in by_volume(&entries).values() {
+        for scan in prunable(scans, &retention) {
+            match fs::remove_file(&scan.file) {
+                Ok(()) => {
+                    result.scans_removed += 1;
+                    result.freed_bytes += scan.bytes;
+                }
+                Err(e) => result
+                    .errors
+                    .push(format!("{}: {}", scan.file.display(), e)),
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Prune after a scan is saved, when the settings ask for it
+pub(crate) fn auto_compact() {
+    if load_settings().is_ok_and(|s| s.history_retention.auto_compact) {
+        let _ = prune_history();
+    }
+}
+
+// Tauri commands
+
+/// Disk used by saved scans per volume, and what the retention settings would free
+#[tauri::command]
+pub async fn get_history_storage_usage_command() -> Result<HistoryStorageUsage, String> {
+    let retention = load_settings()?.history_retention;
+    tokio::task::spawn_blocking(move || {
+        let locations = get_storage_locations().unwrap_or_default();
+        let entries = history_entries(list_saved_scans()?, &locations);
+        Ok(storage_usage(&entries, retention))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn prune_history_command() -> Result<HistoryPruneResult, String> {
+    tokio::task::spawn_blocking(prune_history)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retention_keeps_newest_and_monthly_baselines() {
+        let day = 24 * 60 * 60;
+        // 2024-01-01, then one scan every ten days
+        let start = 1_704_067_200;
+        let entry = |volume: &str, n: i64| HistoryEntry {
+            file: Path::new(volume).join(format!("scan-{}.json", n)),
+            volume: PathBuf::from(volume),
+            saved_at: start + n * 10 * day,
+            bytes: 100,
+        };
+        let mut entries: Vec<HistoryEntry> = (0..9).map(|n| entry("/", n)).collect();
+        entries.push(entry("/Volumes/Backup", 0));
+
+        let mut retention = HistoryRetention {
+            keep_per_volume: Some(2),
+            keep_monthly_baselines: false,
+            auto_compact: false,
+        };
+        let usage = storage_usage(&entries, retention.clone());
+        assert_eq!(usage.scan_count, 10);
+        assert_eq!(usage.volumes[0].volume, Path::new("/"));
+        assert_eq!(usage.volumes[0].prunable_count, 7);
+        assert_eq!(usage.volumes[1].prunable_count, 0);
+
+        // Scans 0, 4 and 6 open January, February and March
+        retention.keep_monthly_baselines = true;
+        let volumes = by_volume(&entries);
+        let pruned: Vec<PathBuf> = prunable(&volumes[Path::new("/")], &retention)
+            .iter()
+            .map(|e| e.file.clone())
+            .collect();
+        assert_eq!(
+            pruned,
+            [5, 3, 2, 1].map(|n| PathBuf::from(format!("/scan-{}.json", n)))
+        );
+
+        retention.keep_per_volume = None;
+        assert!(prunable(&volumes[Path::new("/")], &retention).is_empty());
+    }
+}
@@ -0,0 +1,179 @@
+use crate::settings::load_settings;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use unic_langid::LanguageIdentifier;
+
+/// Used for codes a catalog is missing, and when no catalog matches the user's language
+const FALLBACK_LANGUAGE: &str = "en-US";
+
+/// Message catalogs compiled into the app, by language tag
+const CATALOGS: &[(&str, &str)] = &[("en-US", include_str!("../locales/en-US.ftl"))];
+
+/// A user-facing message from the backend
+///
+/// `code` and `params` let the frontend render it from its own translations; `text` is
+/// the backend's rendering in the user's language, for places that just show it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LocalizedMessage {
+    pub code: String,
+    pub params: BTreeMap<String, Value>,
+    pub text: String,
+}
+
+static BUNDLES: once_cell::sync::Lazy<HashMap<&'static str, FluentBundle<FluentResource>>> =
+    once_cell::sync::Lazy::new(|| {
+        CATALOGS
+            .iter()
+            .filter_map(|(tag, source)| {
+                let language: LanguageIdentifier = tag.parse().ok()?;
+                let mut bundle = FluentBundle::new_concurrent(vec![language]);
+                // Isolation marks would end up in logs, exports and webhook messages
+                bundle.set_use_isolating(false);
+                // A syntax error drops only the broken entries, which then fall back
+                let resource = FluentResource::try_new(source.to_string())
+                    .unwrap_or_else(|(resource, _)| resource);
+                bundle.add_resource(resource).ok()?;
+                Some((*tag, bundle))
+            })
+            .collect()
+    });
+
+/// The catalog for a language tag: an exact match, else one for the same language
+fn catalog_for(tag: &str) -> &'static str {
+    let tag = tag.split('.').next().unwrap_or_default().replace('_', "-");
+    let language = tag.split('-').next().unwrap_or_default();
+    CATALOGS
+        .iter()
+        .map(|(catalog, _)| *catalog)
+        .find(|c| c.eq_ignore_ascii_case(&tag))
+        .or_else(|| {
+            CATALOGS
+                .iter()
+                .map(|(catalog, _)| *catalog)
+                .find(|c| c.split('-').next() == Some(language))
+        })
+        .unwrap_or(FALLBACK_LANGUAGE)
+}
+
+/// The language chosen in settings, else the process locale
+fn current_language() -> &'static str {
+    let chosen = load_settings().ok().and_then(|s| s.language);
+    let locale = chosen.or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+    });
+    catalog_for(&locale.unwrap_or_default())
+}
+
+fn fluent_value(value: &Value) -> FluentValue<'_> {
+    match value {
+        Value::Number(n) => match n.as_u64() {
+            Some(n) => FluentValue::from(n),
+            None => FluentValue::from(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => FluentValue::from(s.as_str()),
+        other => FluentValue::from(other.to_string()),
+    }
+}
+
+fn render(language: &str, code: &str, params: &BTreeMap<String, Value>) -> Option<String> {
+    let bundle = BUNDLES.get(language)?;
+    let pattern = bundle.get_message(code)?.value()?;
+    let mut args = FluentArgs::new();
+    for (name, value) in params {
+        args.set(name.as_str(), fluent_value(value));
+    }
+    let mut errors = Vec::new();
+    Some(
+        bundle
+            .format_pattern(pattern, Some(&args), &mut errors)
+            .into_owned(),
+    )
+}
+
+fn localize_in(language: &str, code: &str, params: &[(&str, Value)]) -> LocalizedMessage {
+    let params: BTreeMap<String, Value> = params
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.clone()))
+        .collect();
+    let text = render(language, code, &params)
+        .or_else(|| render(FALLBACK_LANGUAGE, code, &params))
+        .unwrap_or_else(|| code.to_string());
+    LocalizedMessage {
+        code: code.to_string(),
+        params,
+        text,
+    }
+}
+
+/// Build the message `code` with `params`, rendered in the user's language
+pub(crate) fn localize(code: &str, params: &[(&str, Value)]) -> LocalizedMessage {
+    localize_in(current_language(), code, params)
+}
+
+// Tauri commands
+
+/// Languages the backend can render messages in
+#[tauri::command]
+pub async fn get_message_languages_command() -> Result<Vec<String>, String> {
+    Ok(CATALOGS.iter().map(|(tag, _)| tag.to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_messages_render_from_the_catalog() {
+        for (tag, source) in CATALOGS {
+            assert!(
+                FluentResource::try_new(source.to_string()).is_ok(),
+                "{}",
+                tag
+            );
+        }
+        assert_eq!(catalog_for("en_GB.UTF-8"), "en-US");
+        assert_eq!(catalog_for("xx-YY"), FALLBACK_LANGUAGE);
+
+        let message = localize_in("en-US", "safety-in-use", &[("path", json!("/tmp/a"))]);
+        assert_eq!(
+            message.text,
+            "File or directory is currently in use: /tmp/a"
+        );
+        assert_eq!(message.params["path"], "/tmp/a");
+
+        let one = localize_in(
+            "en-US",
+            "notification-scan-completed",
+            &[
+                ("root", json!("/")),
+                ("files", json!(1)),
+                ("size", json!("4 KiB")),
+            ],
+        );
+        assert_eq!(one.text, "Scan of / finished: 1 file, 4 KiB");
+        let many = localize_in(
+            "en-US",
+            "notification-scan-completed",
+            &[
+                ("root", json!("/")),
+                ("files", json!(12)),
+                ("size", json!("4 KiB")),
+            ],
+        );
+        assert_eq!(many.text, "Scan of / finished: 12 files, 4 KiB");
+
+        // Unknown codes are passed through rather than failing
+        assert_eq!(
+            localize_in("en-US", "no-such-code", &[]).text,
+            "no-such-code"
+        );
+    }
+}
@@ -0,0 +1,478 @@
+use crate::access;
+use crate::cache;
+use crate::change_journal::{self, JournalCursor};
+use crate::classifier::classify_file;
+use crate::estimate::disk_size;
+use crate::persistence::is_app_data_path;
+use crate::registry::{self, DiscoveredNode};
+use crate::scanner::{build_tree_from_registry_with_depth, TreeShape};
+use crate::settings;
+use crate::storage::{get_cluster_size, get_filesystem_type, get_inode_usage, get_user_quota};
+use crate::summary::{
+    build_summary, ExcludedPath, ExclusionReason, IncrementalStats, ScanCounters, ScanStrategy,
+};
+use crate::types::{FileNode, FileType, StreamingScanEvent, SCHEMA_VERSION};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+use tauri::{Emitter, Window};
+use tokio::sync::Mutex;
+
+/// File systems whose directory mtimes don't reliably change when entries are added or removed
+const UNRELIABLE_MTIME_FILESYSTEMS: [&str; 12] = [
+    "vfat", "msdos", "fat", "fat32", "exfat", "smbfs", "cifs", "smb2", "nfs", "nfs4", "afpfs",
+    "webdav",
+];
+
+/// Whether directory mtimes on the volume holding `root` can be trusted
+///
+/// Even where they can, an mtime only changes when entries are added, removed or renamed;
+/// a file rewritten in place keeps its directory's mtime, so its new size is missed until the
+/// next full scan.
+fn mtimes_reliable(root: &Path) -> bool {
+    get_filesystem_type(root).is_none_or(|fs| {
+        !UNRELIABLE_MTIME_FILESYSTEMS.contains(&fs.as_str()) && !fs.starts_with("fuse")
+    })
+}
+
+/// Directories a change journal reported as changed, kept in `change_journal::comparable`
+/// form so they match the scan's paths however either side is spelled
+struct JournalChanges {
+    changed: HashSet<PathBuf>,
+    /// The changed directories and every directory above them
+    dirty: HashSet<PathBuf>,
+}
+
+impl JournalChanges {
+    fn new(changed: HashSet<PathBuf>) -> Self {
+        let changed: HashSet<PathBuf> = changed
+            .iter()
+            .map(|p| change_journal::comparable(p))
+            .collect();
+        let dirty = changed
+            .iter()
+            .flat_map(|p| p.ancestors().map(Path::to_path_buf))
+            .collect();
+        Self { changed, dirty }
+    }
+
+    fn changed(&self, path: &Path) -> bool {
+        self.changed.contains(&change_journal::comparable(path))
+    }
+
+    fn dirty(&self, path: &Path) -> bool {
+        self.dirty.contains(&change_journal::comparable(path))
+    }
+}
+
+/// How much of a directory's previous scan can be reused
+enum Listing {
+    /// List it again
+    Relist,
+    /// Reuse its files but check its subdirectories
+    ReuseFiles,
+    /// Nothing below it changed (only known from a change journal)
+    ReuseSubtree,
+}
+
+/// What a rescan produced
+struct RescanResult {
+    nodes: HashMap<PathBuf, DiscoveredNode>,
+    counters: ScanCounters,
+    reused: u64,
+    rewalked: u64,
+}
+
+/// State of one incremental rescan
+struct Rescan<'a> {
+    previous: &'a HashMap<PathBuf, DiscoveredNode>,
+    /// Previous scan's children of each directory
+    children: HashMap<&'a Path, Vec<&'a DiscoveredNode>>,
+    trust_mtimes: bool,
+    journal: Option<JournalChanges>,
+    nodes: HashMap<PathBuf, DiscoveredNode>,
+    counters: ScanCounters,
+    reused: u64,
+    rewalked: u64,
+}
+
+impl<'a> Rescan<'a> {
+    fn new(
+        previous: &'a HashMap<PathBuf, DiscoveredNode>,
+        trust_mtimes: bool,
+        journal: Option<JournalChanges>,
+    ) -> Self {
+        let mut children: HashMap<&Path, Vec<&DiscoveredNode>> = HashMap::new();
+        for node in previous.values() {
+            if let Some(parent) = &node.parent_path {
+                children.entry(parent.as_path()).or_default().push(node);
+            }
+        }
+
+        Self {
+            previous,
+            children,
+            trust_mtimes,
+            journal,
+            nodes: HashMap::new(),
+            counters: ScanCounters::default(),
+            reused: 0,
+            rewalked: 0,
+        }
+    }
+
+    /// Keep a node's id across rescans so the frontend can match it up
+    fn id_for(&self, path: &Path) -> u64 {
+        self.previous
+            .get(path)
+            .map(|n| n.id)
+            .unwrap_or_else(registry::next_node_id)
+    }
+
+    fn visit(&mut self, path: &Path, parent: Option<PathBuf>) {
+        if parent.is_some() && is_app_data_path(path) {
+            self.counters.excluded.push(ExcludedPath {
+                path: path.to_string_lossy().to_string(),
+                reason: ExclusionReason::AppData,
+            });
+            return;
+        }
+
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            self.counters.errors += 1;
+            return;
+        };
+        if metadata.is_dir() {
+            self.visit_dir(path, parent, &metadata);
+            return;
+        }
+
+        let is_symlink = metadata.is_symlink();
+        if is_symlink {
+            self.counters.symlinks += 1;
+        }
+        let node = DiscoveredNode {
+            id: self.id_for(path),
+            path: path.to_path_buf(),
+            name: file_name(path),
+            size: if is_symlink { 0 } else { disk_size(&metadata) },
+            logical_size: if is_symlink { 0 } else { metadata.len() },
+            is_directory: false,
+            file_type: if is_symlink {
+                FileType::Other
+            } else {
+                classify_file(path)
+            },
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            parent_path: parent,
+            is_complete: true,
+            is_symlink,
+            symlink_target: is_symlink.then(|| fs::read_link(path).ok()).flatten(),
+            file_count: u64::from(!is_symlink),
+            dropped: None,
+        };
+        self.nodes.insert(path.to_path_buf(), node);
+    }
+
+    fn visit_dir(&mut self, path: &Path, parent: Option<PathBuf>, metadata: &Metadata) {
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let previous = self
+            .previous
+            .get(path)
+            .filter(|p| p.is_directory && p.is_complete);
+        let listing = match (previous, &self.journal) {
+            (None, _) => Listing::Relist,
+            (Some(_), Some(journal)) if journal.changed(path) => Listing::Relist,
+            (Some(_), Some(journal)) if journal.dirty(path) => Listing::ReuseFiles,
+            (Some(_), Some(_)) => Listing::ReuseSubtree,
+            (Some(p), None) if self.trust_mtimes && p.modified == modified => Listing::ReuseFiles,
+            (Some(_), None) => Listing::Relist,
+        };
+
+        self.nodes.insert(
+            path.to_path_buf(),
+            DiscoveredNode {
+                id: self.id_for(path),
+                path: path.to_path_buf(),
+                name: file_name(path),
+                size: 0,
+                logical_size: 0,
+                is_directory: true,
+                file_type: FileType::Other,
+                modified,
+                parent_path: parent,
+                is_complete: true,
+                is_symlink: false,
+                symlink_target: None,
+                file_count: 0,
+                dropped: None,
+            },
+        );
+        self.counters.directories += 1;
+
+        match listing {
+            Listing::Relist => {
+                self.rewalked += 1;
+                let Ok(entries) = fs::read_dir(path) else {
+                    self.counters.errors += 1;
+                    return;
+                };
+                for entry in entries.flatten() {
+                    self.visit(&entry.path(), Some(path.to_path_buf()));
+                }
+            }
+            Listing::ReuseFiles => {
+                // Same entries as last time: reuse the files, but still check every
+                // subdirectory, since changes deeper down don't touch this directory
+                self.reused += 1;
+                self.reuse_dropped(path);
+                let children = self.children.get(path).cloned().unwrap_or_default();
+                for child in children {
+                    if child.is_directory {
+                        self.visit(&child.path, Some(path.to_path_buf()));
+                    } else {
+                        self.reuse(child);
+                    }
+                }
+            }
+            Listing::ReuseSubtree => {
+                self.reused += 1;
+                self.reuse_dropped(path);
+                self.reuse_children(path);
+            }
+        }
+    }
+
+    /// Carry over the totals of files the previous scan kept no nodes for
+    fn reuse_dropped(&mut self, path: &Path) {
+        let dropped = self.previous.get(path).and_then(|p| p.dropped.clone());
+        if let Some(node) = self.nodes.get_mut(path) {
+            node.dropped = dropped;
+        }
+    }
+
+    fn reuse(&mut self, node: &DiscoveredNode) {
+        self.counters.symlinks += u64::from(node.is_symlink);
+        self.nodes.insert(node.path.clone(), node.clone());
+    }
+
+    /// Copy everything below `path` from the previous scan without touching the disk
+    fn reuse_children(&mut self, path: &Path) {
+        let children = self.children.get(path).cloned().unwrap_or_default();
+        for child in children {
+            self.reuse(child);
+            if child.is_directory {
+                self.counters.directories += 1;
+                self.reused += 1;
+                self.reuse_children(&child.path);
+            }
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Rescan `root` against `previous`
+///
+/// With a journal's list of changed directories only those are listed again, and untouched
+/// subtrees are copied over whole. Without one, directories whose mtime is unchanged reuse
+/// their files when `trust_mtimes` is set.
+fn rescan(
+    root: &Path,
+    previous: &HashMap<PathBuf, DiscoveredNode>,
+    trust_mtimes: bool,
+    changed: Option<HashSet<PathBuf>>,
+) -> RescanResult {
+    let mut rescan = Rescan::new(previous, trust_mtimes, changed.map(JournalChanges::new));
+    rescan.visit(root, None);
+    RescanResult {
+        nodes: rescan.nodes,
+        counters: rescan.counters,
+        reused: rescan.reused,
+        rewalked: rescan.rewalked,
+    }
+}
+
+/// A scan to rescan against, retained in memory or read from the on-disk cache
+pub(crate) struct PreviousScan {
+    pub scan_id: u64,
+    pub root: PathBuf,
+    pub nodes: HashMap<PathBuf, DiscoveredNode>,
+    pub journal: Option<JournalCursor>,
+    pub from_cache: bool,
+}
+
+/// Rescan `previous.root`, re-listing only directories that changed since `previous`
+///
+/// When the previous scan recorded a change journal position that is still valid, the journal
+/// says exactly what changed. Otherwise `trust_directory_mtimes` (overriding the setting of the
+/// same name) decides whether unchanged directory mtimes are trusted; it is ignored on file
+/// systems known not to update them, where every directory is re-listed.
+pub(crate) async fn rescan_previous(
+    previous: PreviousScan,
+    trust_directory_mtimes: Option<bool>,
+    window: Window,
+) -> Result<FileNode, String> {
+    let started = Instant::now();
+    let PreviousScan {
+        scan_id,
+        root,
+        nodes: previous,
+        journal: cursor,
+        from_cache,
+    } = previous;
+
+    let requested = match trust_directory_mtimes {
+        Some(trust) => trust,
+        None => settings::load_settings()?.trust_directory_mtimes,
+    };
+    let mtimes_trusted = requested && mtimes_reliable(&root);
+
+    let scan_root = root.clone();
+    let (result, journal, used_change_journal) = tokio::task::spawn_blocking(move || {
+        let changes = cursor.and_then(|c| change_journal::changes_since(&scan_root, &c).ok());
+        let used_change_journal = changes.is_some();
+        // Take the new position before walking, so changes made during the walk are replayed
+        let journal: Option<JournalCursor> = match &changes {
+            Some(changes) => Some(changes.cursor.clone()),
+            None => change_journal::current_cursor(&scan_root),
+        };
+        let result = rescan(
+            &scan_root,
+            &previous,
+            mtimes_trusted,
+            changes.map(|c| c.directories),
+        );
+        (result, journal, used_change_journal)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let RescanResult {
+        nodes,
+        counters,
+        reused,
+        rewalked,
+    } = result;
+    if !nodes.contains_key(&root) {
+        return Err(format!("Cannot access {}", root.display()));
+    }
+
+    let mut summary = build_summary(
+        registry::next_scan_id(),
+        &nodes,
+        &root,
+        &counters,
+        started.elapsed(),
+        ScanStrategy::Incremental,
+    );
+    summary.inodes = get_inode_usage(&root);
+    summary.quota = get_user_quota(&root);
+    summary.cluster_size = get_cluster_size(&root);
+    summary.journal = journal;
+    summary.incremental = Some(IncrementalStats {
+        previous_scan_id: scan_id,
+        from_cache,
+        used_change_journal,
+        mtimes_trusted,
+        directories_reused: reused,
+        directories_rewalked: rewalked,
+    });
+
+    let tree = build_tree_from_registry_with_depth(&nodes, &root, 2, TreeShape::default())
+        .ok_or_else(|| "Failed to build final tree".to_string())?;
+    registry::retain_scan(Arc::new(Mutex::new(nodes)), summary.clone()).await;
+
+    let _ = window.emit(
+        "streaming-scan-event",
+        &StreamingScanEvent::Complete {
+            files_scanned: summary.files,
+            total_size: summary.total_size,
+            schema_version: SCHEMA_VERSION,
+            summary: Box::new(summary),
+        },
+    );
+
+    Ok(tree)
+}
+
+// Tauri commands
+
+/// Rescan a retained scan's root, re-listing only directories that changed
+///
+/// See [`rescan_previous`] for how changes are found.
+#[tauri::command]
+pub async fn incremental_scan_command(
+    scan_id: u64,
+    trust_directory_mtimes: Option<bool>,
+    window: Window,
+) -> Result<FileNode, String> {
+    let previous = registry::with_scan(scan_id, |nodes, summary| PreviousScan {
+        scan_id,
+        root: PathBuf::from(&summary.root),
+        nodes: nodes.clone(),
+        journal: summary.journal.clone(),
+        from_cache: false,
+    })
+    .await?;
+    access::ensure_scan_root_allowed(&previous.root)?;
+    rescan_previous(previous, trust_directory_mtimes, window).await
+}
+
+/// Scan the home folder as quickly as possible, for a keyboard shortcut or tray action
+///
+/// Rescans the home folder incrementally against its newest retained or cached scan, so a
+/// warm rescan only lists what changed; without one it falls back to a normal scan. Uses the
+/// saved settings and the default tree shape rather than taking options.
+#[tauri::command]
+pub async fn quick_scan_command(window: Window) -> Result<FileNode, String> {
+    let home = dirs::home_dir().ok_or("Cannot find the current user's profile folder")?;
+    access::ensure_scan_root_allowed(&home)?;
+    cache::rescan_directory_command(home.to_string_lossy().to_string(), None, window).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rescan_reuses_unchanged_directories() {
+        let root = std::env::temp_dir().join("test_incremental");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("photos/2023")).unwrap();
+        fs::create_dir_all(root.join("music")).unwrap();
+        fs::write(root.join("photos/2023/a.jpg"), [1u8; 100]).unwrap();
+        fs::write(root.join("music/song.mp3"), [1u8; 100]).unwrap();
+
+        let first = rescan(&root, &HashMap::new(), true, None);
+        assert_eq!((first.reused, first.rewalked), (0, 4));
+
+        fs::write(root.join("photos/2023/b.jpg"), [1u8; 100]).unwrap();
+        let second = rescan(&root, &first.nodes, true, None);
+        let untrusted = rescan(&root, &first.nodes, false, None);
+        let journal = rescan(
+            &root,
+            &first.nodes,
+            false,
+            Some(HashSet::from([root.join("photos/2023")])),
+        );
+        fs::remove_dir_all(&root).unwrap();
+
+        // Only the directory that gained a file is listed again
+        assert_eq!((second.reused, second.rewalked), (3, 1));
+        assert_eq!(second.counters.directories, 4);
+        assert!(second.nodes.contains_key(&root.join("photos/2023/b.jpg")));
+        let song = root.join("music/song.mp3");
+        assert_eq!(second.nodes[&song].id, first.nodes[&song].id);
+        assert_eq!(untrusted.rewalked, 4);
+        assert_eq!(untrusted.nodes.len(), second.nodes.len());
+        // The journal also lets the untouched music folder be copied without a look
+        assert_eq!((journal.reused, journal.rewalked), (3, 1));
+        assert_eq!(journal.nodes.len(), second.nodes.len());
+    }
+}
@@ -1,18 +1,180 @@
+mod access;
+mod api;
+mod archives;
+mod archiving;
+mod breadcrumbs;
+mod broken_links;
+mod budgeted;
+mod cache;
+mod change_journal;
 mod classifier;
+mod composition;
+mod confidence;
+mod config_bundle;
+mod csv_export;
+mod devices;
+mod diff;
+mod disk_images;
+mod duplicates;
+mod eject;
+mod encryption;
+mod entry_counts;
+mod environment;
+mod estimate;
+mod events;
+mod exclusions;
+mod explain;
+mod families;
+mod fleet;
+mod history;
+mod i18n;
+mod incremental;
+mod listing;
+mod maintenance;
+mod memory;
+mod moves;
+mod never_suggest;
+mod onboarding;
+mod ownership;
+mod persistence;
+mod pins;
+mod policies;
+mod privacy;
+mod profiling;
+mod recommendations;
+mod reconcile;
+mod registry;
+mod reports;
 mod safety;
+mod sampling;
+mod sandbox;
 mod scanner;
+mod schema;
+mod script_export;
+mod server;
+mod settings;
+mod sizes;
+mod snapshots;
+mod sniffing;
+mod space_guard;
+mod staging;
 mod storage;
+mod summary;
+mod surface;
+mod system_stores;
+mod tags;
+mod telemetry;
+mod tools;
+mod transfer;
 mod types;
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+mod uring;
+mod vm_disks;
+mod volume_roots;
+mod watchdog;
+mod webhooks;
 
+pub use access::{AccessMode, ManagedPolicy, POLICY_DENIED};
+pub use api::{api_version, ApiVersion, DeprecationWarning, API_VERSION};
+pub use archives::{inspect_archive, ArchiveEntry, ArchiveFormat, ArchiveListing};
+pub use archiving::{
+    ArchiveManifest, ArchiveOperation, ArchiveOptions, ArchiveResult, ManifestEntry,
+    RestoreFailure, RestoreResult,
+};
+pub use breadcrumbs::{Ancestor, DirKind};
+pub use broken_links::{BrokenLink, BrokenLinkKind};
+pub use change_journal::JournalCursor;
 pub use classifier::{classify_file, get_category_stats, CategoryStats};
+pub use composition::{
+    analyze_large_file, ArchiveLayer, FileComposition, LargeFileAnalysis, VmDiskFormat,
+};
+pub use confidence::Confidence;
+pub use config_bundle::ConfigBundle;
+pub use csv_export::{CsvExportProgress, CsvExportResult};
+pub use devices::is_portable_device_path;
+pub use diff::{CategoryChange, DiffWaterfall, SizeChange, WaterfallBucket, WaterfallStep};
+pub use disk_images::{inspect_disk_image, DiskImageEntry, DiskImageFormat, DiskImageInfo};
+pub use duplicates::{
+    DuplicateDirectory, DuplicateDirectoryGroup, DuplicateFile, DuplicateGroup, DuplicateProgress,
+    DuplicateReport, HashPhase, UnreadableFile, VolumeCopies,
+};
+pub use eject::EjectResult;
+pub use entry_counts::EntryCount;
+pub use environment::{
+    environment_report, EnvironmentReport, FeatureAvailability, GrantedPermissions, Integration,
+    MountedFileSystem,
+};
+pub use estimate::{estimate_tree, EstimatedNode};
+pub use events::EventSink;
+pub use explain::{
+    AgeBucket, AgeRange, CategoryShare, Contributor, DuplicatesInside, KnownKind, NodeExplanation,
+};
+pub use families::{FamilyMember, VersionFamily};
+pub use fleet::{FleetMachine, FleetOverview, FleetScan, FleetVolume};
+pub use history::{HistoryPruneResult, HistoryRetention, HistoryStorageUsage, VolumeHistory};
+pub use i18n::LocalizedMessage;
+pub use maintenance::{AppDataUsage, BucketUsage, DataBucket, PruneOptions, PruneResult};
+pub use memory::{CompactResult, MemoryUsage};
+pub use moves::{EntryKind, MoveEntry, MoveJob, MoveMode, MoveStatus};
+pub use never_suggest::{is_never_suggested, load_never_suggest, NeverSuggestEntry};
+pub use onboarding::{load_first_run_choice, FirstRunChoice, FirstRunScope};
+pub use ownership::{FailedRepair, RepairResult};
+pub use persistence::{
+    export_scan, is_app_data_path, list_saved_scans, load_scan, save_scan, SavedScan, SavedScanInfo,
+};
+pub use pins::{PinnedPath, PinnedPathStatus, SizeSample};
+pub use policies::{CleanupPolicy, PolicyMatch, PolicyReport, PolicyRule};
+pub use privacy::{anonymize_tree, Anonymizer};
+pub use recommendations::{
+    CategorySavings, Recommendation, RecommendationCategory, Recommendations,
+};
+pub use reconcile::{HiddenSpace, HiddenSpaceSource, SpaceReconciliation};
+pub use reports::{
+    ClusterOverhead, CrowdedDirectories, CrowdedDirectory, DirectorySlack, LargestFile,
+    LargestFiles, OtherDirectory, OtherFilesReport, OtherGroup, PathExtremes, PathLength,
+    TinyFileDirectory, TinyFilesReport,
+};
 pub use safety::{
     check_deletion_safety, check_multiple_deletions, delete_items, DeletionResult, SafetyCheck,
 };
-pub use scanner::{cancel_scan, check_path_permissions, scan_directory_async, validate_path};
-pub use storage::{get_quick_access_folders, get_storage_locations, LocationType, StorageLocation};
+pub use sampling::{sampled_scan, SampledDirectory, SampledScan};
+pub use sandbox::{is_sandboxed, GrantedFolder, OUTSIDE_GRANTED_SCOPE};
+pub use scanner::{
+    cancel_scan, check_path_permissions, scan_directory_async, validate_path, TreeShape,
+};
+pub use schema::api_schema;
+pub use script_export::{CleanupPlan, PlanItem, ScriptFormat};
+pub use settings::{load_settings, AppSettings};
+pub use sizes::{SizeFormatter, UnitSystem};
+pub use snapshots::{
+    delete_local_snapshot, list_local_snapshots, thin_local_snapshots, LocalSnapshot,
+};
+pub use sniffing::{MisnamedFile, MisnamedReport};
+pub use space_guard::{InsufficientSpace, INSUFFICIENT_SPACE};
+pub use storage::{
+    get_cluster_size, get_filesystem_type, get_inode_usage, get_quick_access_folders,
+    get_shadow_storage, get_storage_locations, get_user_quota, list_user_profiles,
+    resolve_scan_scope, InodeUsage, LocationType, QuotaUsage, ScanScope, ScopeRoot, ShadowStorage,
+    StorageLocation, UserProfile,
+};
+pub use summary::{
+    BudgetStats, ExcludedPath, ExclusionReason, IncrementalStats, PathDepth, PathSize,
+    ScanStrategy, ScanSummary, UnexploredDir,
+};
+pub use surface::{SurfaceCheckMode, SurfaceCheckResult, SurfaceProgress};
+pub use system_stores::{get_system_stores, CleanupSuggestion, SystemStore, SystemStoreKind};
+pub use tags::{NodeTag, TagEntry, TaggedNode};
+pub use telemetry::{IoStats, ThroughputSample};
+pub use tools::{tool_capabilities, ToolCapability, ToolState, TOOL_UNAVAILABLE};
+pub use transfer::{prepare_tree_transfer, TreeHandle};
 pub use types::{
-    FileNode, FileType, NodeStats, PartialScanResult, ScanProgress, StreamingScanEvent,
+    ChildrenPage, FileNode, FileType, NodeStats, PartialScanResult, ScanProgress,
+    StreamingScanEvent, SyntheticNode, SCHEMA_VERSION,
 };
+pub use vm_disks::{inspect_vm_disk, CompactionPlan, VmDisk};
+pub use volume_roots::CachedVolumeRoot;
+pub use watchdog::OperationStalled;
+pub use webhooks::{Notification, Webhook, WebhookEvent, WebhookFormat, WebhookSettings};
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -27,12 +189,55 @@ fn validate_path_command(path: String) -> Result<bool, String> {
 }
 
 /// Tauri command to scan a directory with streaming updates
+///
+/// Each directory in the returned tree keeps its `child_limit` largest children (100 by
+/// default); the rest are summed into one "more items" node. With `aggregate_below`, files
+/// smaller than that many bytes are grouped into one "small files" node per directory.
+/// With `count_entries`, a quick pre-pass counts the entries first when no earlier count or
+/// scan of `path` left one, so progress events carry a percentage. With `drop_files_below`,
+/// files smaller than that many bytes aren't kept in the retained scan at all, only in their
+/// directory's totals, which keeps huge caches of tiny files cheap to hold in memory.
 #[tauri::command]
 async fn scan_directory_streaming_command(
     path: String,
+    child_limit: Option<usize>,
+    aggregate_below: Option<u64>,
+    count_entries: Option<bool>,
+    exclude_patterns: Option<Vec<String>>,
+    drop_files_below: Option<u64>,
     window: tauri::Window,
 ) -> Result<FileNode, String> {
-    scanner::scan_directory_async(path, window).await
+    let shape = scanner::TreeShape::new(child_limit, aggregate_below);
+    if count_entries.unwrap_or(false) {
+        entry_counts::ensure_counted(std::path::Path::new(&path)).await?;
+    }
+    scanner::scan_directory_async(
+        path,
+        shape,
+        exclude_patterns.unwrap_or_default(),
+        drop_files_below,
+        window,
+    )
+    .await
+}
+
+/// Tauri command to page through a directory's children in a retained scan
+///
+/// Without `scan_id`, the newest retained scan that has `path` is used, and a folder none
+/// of them has is listed from disk (see [`scanner::find_children`]).
+#[tauri::command]
+async fn get_children_command(
+    scan_id: Option<u64>,
+    path: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<ChildrenPage, String> {
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(scanner::DEFAULT_CHILD_LIMIT);
+    match scan_id {
+        Some(scan_id) => scanner::get_children(scan_id, path.into(), offset, limit).await,
+        None => scanner::find_children(path.into(), offset, limit).await,
+    }
 }
 
 /// Tauri command to check if the app has necessary permissions for a path
@@ -47,9 +252,16 @@ async fn cancel_scan_command() -> Result<(), String> {
     scanner::cancel_scan().await
 }
 
+/// Old name of `open_full_disk_access_settings_command`, kept until API version 3
+#[tauri::command]
+fn open_full_disk_access_settings(window: tauri::Window) -> Result<(), String> {
+    api::warn_deprecated(&window, "open_full_disk_access_settings");
+    open_full_disk_access_settings_command()
+}
+
 /// Tauri command to open System Settings to Full Disk Access (macOS only)
 #[tauri::command]
-fn open_full_disk_access_settings() -> Result<(), String> {
+fn open_full_disk_access_settings_command() -> Result<(), String> {
     #[cfg(target_os = "macos")]
     {
         use std::process::Command;
@@ -73,21 +285,168 @@ fn open_full_disk_access_settings() -> Result<(), String> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    access::init_from_args();
+    webhooks::start_low_space_monitor();
+    staging::remove_stale_stages();
+    // `--serve` runs the engine headless instead of opening a window
+    match server::config_from_args() {
+        Ok(Some(config)) => {
+            if let Err(e) = server::serve(config) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+    let handler = tauri::generate_handler![
+        greet,
+        validate_path_command,
+        scan_directory_streaming_command,
+        estimate::scan_directory_dual_pass_command,
+        sampling::sampled_scan_command,
+        cache::rescan_directory_command,
+        incremental::incremental_scan_command,
+        incremental::quick_scan_command,
+        entry_counts::count_entries_command,
+        entry_counts::get_entry_counts_command,
+        budgeted::scan_with_budget_command,
+        check_path_permissions_command,
+        cancel_scan_command,
+        get_children_command,
+        breadcrumbs::get_ancestors_command,
+        watchdog::skip_stalled_path_command,
+        profiling::set_scan_profiling_command,
+        profiling::export_scan_profile_command,
+        open_full_disk_access_settings,
+        open_full_disk_access_settings_command,
+        api::get_api_version_command,
+        safety::check_deletion_safety_command,
+        safety::delete_items_command,
+        storage::get_storage_locations_command,
+        storage::get_quick_access_folders_command,
+        storage::resolve_scan_scope_command,
+        onboarding::get_first_run_scopes_command,
+        onboarding::get_first_run_choice_command,
+        onboarding::save_first_run_choice_command,
+        volume_roots::get_volume_roots_command,
+        persistence::save_scan_command,
+        persistence::export_scan_command,
+        persistence::load_scan_command,
+        persistence::list_saved_scans_command,
+        fleet::import_fleet_scan_command,
+        fleet::list_fleet_scans_command,
+        fleet::load_fleet_scan_command,
+        fleet::remove_fleet_scan_command,
+        fleet::get_fleet_overview_command,
+        diff::get_diff_waterfall_command,
+        diff::get_category_diff_command,
+        transfer::scan_directory_binary_command,
+        transfer::read_tree_chunk_command,
+        transfer::release_tree_handle_command,
+        summary::get_scan_summary_command,
+        reports::get_path_extremes_command,
+        reports::get_most_crowded_directories_command,
+        reports::get_tiny_files_report_command,
+        reports::get_cluster_overhead_command,
+        reports::get_other_files_report_command,
+        reports::get_largest_files_command,
+        reconcile::get_space_reconciliation_command,
+        recommendations::get_recommendations_command,
+        explain::explain_node_command,
+        system_stores::get_system_stores_command,
+        snapshots::list_local_snapshots_command,
+        snapshots::thin_local_snapshots_command,
+        snapshots::delete_local_snapshot_command,
+        tags::tag_node_command,
+        tags::get_scan_tags_command,
+        tags::list_tags_command,
+        pins::pin_path_command,
+        pins::unpin_path_command,
+        pins::get_pinned_paths_command,
+        never_suggest::add_never_suggest_command,
+        never_suggest::remove_never_suggest_command,
+        never_suggest::list_never_suggest_command,
+        policies::list_policies_command,
+        policies::save_policy_command,
+        policies::delete_policy_command,
+        policies::dry_run_policy_command,
+        policies::apply_policy_report_command,
+        webhooks::get_webhook_settings_command,
+        webhooks::save_webhook_command,
+        webhooks::delete_webhook_command,
+        webhooks::set_low_space_threshold_command,
+        webhooks::test_webhook_command,
+        i18n::get_message_languages_command,
+        moves::start_move_command,
+        moves::resume_move_command,
+        moves::pause_move_command,
+        moves::list_move_jobs_command,
+        moves::discard_move_job_command,
+        eject::flush_and_eject_command,
+        script_export::export_cleanup_script_command,
+        duplicates::find_duplicates_command,
+        duplicates::pause_duplicate_search_command,
+        surface::surface_check_command,
+        surface::cancel_surface_check_command,
+        families::find_version_families_command,
+        sniffing::find_misnamed_files_command,
+        broken_links::find_broken_links_command,
+        broken_links::remove_broken_links_command,
+        ownership::take_ownership_command,
+        ownership::fix_permissions_command,
+        archives::inspect_archive_command,
+        archiving::archive_items_command,
+        archiving::get_archive_operations_command,
+        archiving::restore_from_archive_command,
+        disk_images::inspect_disk_image_command,
+        composition::analyze_large_file_command,
+        composition::analyze_large_files_command,
+        vm_disks::get_vm_disks_command,
+        vm_disks::compact_vm_disk_command,
+        maintenance::get_app_data_usage_command,
+        maintenance::prune_app_data_command,
+        memory::get_memory_usage_command,
+        memory::compact_scan_command,
+        history::get_history_storage_usage_command,
+        history::prune_history_command,
+        settings::get_settings_command,
+        settings::save_settings_command,
+        config_bundle::export_config_command,
+        config_bundle::import_config_command,
+        csv_export::export_scan_csv_command,
+        csv_export::cancel_csv_export_command,
+        access::get_access_mode_command,
+        access::get_managed_policy_command,
+        sandbox::grant_folder_command,
+        sandbox::list_granted_folders_command,
+        sandbox::revoke_folder_command,
+        environment::get_environment_report_command,
+        tools::get_tool_capabilities_command,
+        sizes::format_size_command
+    ];
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![
-            greet,
-            validate_path_command,
-            scan_directory_streaming_command,
-            check_path_permissions_command,
-            cancel_scan_command,
-            open_full_disk_access_settings,
-            safety::check_deletion_safety_command,
-            safety::delete_items_command,
-            storage::get_storage_locations_command,
-            storage::get_quick_access_folders_command
-        ])
+        .setup(|app| {
+            sandbox::restore_granted_folders();
+            policies::start_scheduler(app.handle().clone());
+            Ok(())
+        })
+        .invoke_handler(move |invoke| {
+            // The one place invoked commands are checked against the managed policy
+            match access::ensure_command_allowed(invoke.message.command()) {
+                Ok(()) => handler(invoke),
+                Err(e) => {
+                    invoke.resolver.reject(e);
+                    true
+                }
+            }
+        })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
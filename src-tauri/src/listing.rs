@@ -0,0 +1,664 @@
+//! Directory listings that come with each entry's type, size and mtime
+//!
+//! Listing a directory and then calling `symlink_metadata` on every entry costs one path
+//! lookup and system call per file. Each platform has a way to get most of that in bulk:
+//! `getattrlistbulk` on macOS returns names and attributes for many entries per call,
+//! `getdents64` on Linux returns names with their types so only non-directories need a
+//! `statx` (relative to the open directory, without resolving the path again), and on
+//! Windows `GetFileInformationByHandleEx` with `FileIdBothDirectoryInfo` (the documented
+//! wrapper over `NtQueryDirectoryFile`) fills a large buffer with sizes and times. Other
+//! platforms fall back to `read_dir` and `symlink_metadata`.
+
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// What a listed entry is; junctions count as symlinks, as `std` treats them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ListedEntry {
+    pub name: OsString,
+    pub kind: EntryKind,
+    /// Bytes on disk as the scanner counts them (allocated blocks on Unix, length on
+    /// Windows); 0 for directories, which the scanner reads when it visits them
+    pub size: u64,
+    /// Length of a file, which on Unix can be well below `size`; 0 for directories
+    pub logical_size: u64,
+    /// `UNIX_EPOCH` for directories, for the same reason
+    pub modified: SystemTime,
+    /// For spotting hard links; only set on Unix
+    pub inode: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Listing {
+    pub entries: Vec<ListedEntry>,
+    /// Entries whose metadata couldn't be read; they are left out
+    pub errors: u64,
+}
+
+/// List `path` with the metadata of every entry, in as few system calls as the platform allows
+///
+/// `io_uring` submits the Linux `statx` calls as one io_uring batch where the kernel allows
+/// it; it is ignored on other platforms.
+pub(crate) fn list_dir(path: &Path, io_uring: bool) -> io::Result<Listing> {
+    imp::list_dir(path, io_uring)
+}
+
+#[cfg(unix)]
+struct Fd(libc::c_int);
+
+#[cfg(unix)]
+impl Drop for Fd {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn open_dir(path: &Path) -> io::Result<Fd> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    let fd = unsafe {
+        libc::open(
+            path.as_ptr(),
+            libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Fd(fd))
+}
+
+#[cfg(unix)]
+fn unix_time(seconds: i64, nanos: u32) -> SystemTime {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    u64::try_from(seconds)
+        .map(|s| UNIX_EPOCH + Duration::new(s, nanos))
+        .unwrap_or(UNIX_EPOCH)
+}
+
+/// Buffer handed to the bulk listing calls; big enough for a few hundred entries per call
+#[cfg(any(target_os = "linux", target_os = "macos", windows))]
+const BUFFER_SIZE: usize = 64 * 1024;
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+mod imp {
+    use super::{open_dir, unix_time, EntryKind, Fd, ListedEntry, Listing, BUFFER_SIZE};
+    use crate::uring;
+    use std::ffi::{CStr, CString, OsStr};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::time::UNIX_EPOCH;
+
+    /// Offsets into a `linux_dirent64` record
+    const RECLEN_OFFSET: usize = 16;
+    const TYPE_OFFSET: usize = 18;
+    const NAME_OFFSET: usize = 19;
+
+    const STAT_FLAGS: libc::c_int = libc::AT_SYMLINK_NOFOLLOW | libc::AT_STATX_DONT_SYNC;
+    const STAT_MASK: libc::c_uint = libc::STATX_TYPE
+        | libc::STATX_INO
+        | libc::STATX_SIZE
+        | libc::STATX_BLOCKS
+        | libc::STATX_MTIME;
+
+    /// What the listing needs from a `stat`
+    struct Stat {
+        format: u32,
+        length: u64,
+        blocks: u64,
+        inode: u64,
+        seconds: i64,
+        nanos: u32,
+    }
+
+    impl From<&libc::statx> for Stat {
+        fn from(stx: &libc::statx) -> Self {
+            Self {
+                format: u32::from(stx.stx_mode) & libc::S_IFMT,
+                length: stx.stx_size,
+                blocks: stx.stx_blocks,
+                inode: stx.stx_ino,
+                seconds: stx.stx_mtime.tv_sec,
+                nanos: stx.stx_mtime.tv_nsec,
+            }
+        }
+    }
+
+    /// Type, blocks, inode and mtime of `name` inside `dir`, without following links
+    fn stat_at(dir: &Fd, name: &CStr) -> io::Result<Stat> {
+        let mut stx: libc::statx = unsafe { std::mem::zeroed() };
+        let result = unsafe { libc::statx(dir.0, name.as_ptr(), STAT_FLAGS, STAT_MASK, &mut stx) };
+        if result == 0 {
+            return Ok(Stat::from(&stx));
+        }
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(libc::ENOSYS) {
+            return Err(error);
+        }
+
+        // Kernels before 4.11 have no statx
+        let mut st: libc::stat64 = unsafe { std::mem::zeroed() };
+        if unsafe { libc::fstatat64(dir.0, name.as_ptr(), &mut st, libc::AT_SYMLINK_NOFOLLOW) } != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Stat {
+            format: st.st_mode & libc::S_IFMT,
+            length: st.st_size as u64,
+            blocks: st.st_blocks as u64,
+            inode: st.st_ino,
+            seconds: st.st_mtime,
+            nanos: st.st_mtime_nsec as u32,
+        })
+    }
+
+    fn entry(name: &CStr, stat: Stat) -> ListedEntry {
+        let kind = match stat.format {
+            libc::S_IFDIR => EntryKind::Dir,
+            libc::S_IFLNK => EntryKind::Symlink,
+            _ => EntryKind::File,
+        };
+        ListedEntry {
+            name: OsStr::from_bytes(name.to_bytes()).to_os_string(),
+            kind,
+            size: if kind == EntryKind::File {
+                stat.blocks * 512
+            } else {
+                0
+            },
+            logical_size: if kind == EntryKind::File {
+                stat.length
+            } else {
+                0
+            },
+            modified: if kind == EntryKind::Dir {
+                UNIX_EPOCH
+            } else {
+                unix_time(stat.seconds, stat.nanos)
+            },
+            inode: Some(stat.inode),
+        }
+    }
+
+    /// `stat` the entries the listing didn't say were directories, in one io_uring batch
+    /// when asked to and the kernel allows it
+    fn stat_all(dir: &Fd, names: &[CString], io_uring: bool, listing: &mut Listing) {
+        let refs: Vec<&CStr> = names.iter().map(CString::as_c_str).collect();
+        let batch = (io_uring && refs.len() > 1)
+            .then(|| uring::statx_batch(dir.0, &refs, STAT_FLAGS, STAT_MASK))
+            .flatten();
+        let stats: Vec<io::Result<Stat>> = match batch {
+            Some(batch) => batch
+                .into_iter()
+                .map(|r| r.map(|stx| Stat::from(&stx)))
+                .collect(),
+            None => refs.iter().map(|name| stat_at(dir, name)).collect(),
+        };
+        for (name, stat) in refs.iter().zip(stats) {
+            match stat {
+                Ok(stat) => listing.entries.push(entry(name, stat)),
+                Err(_) => listing.errors += 1,
+            }
+        }
+    }
+
+    pub(super) fn list_dir(path: &Path, io_uring: bool) -> io::Result<Listing> {
+        let dir = open_dir(path)?;
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let mut listing = Listing::default();
+        let mut unstated = Vec::new();
+
+        loop {
+            let read = unsafe {
+                libc::syscall(
+                    libc::SYS_getdents64,
+                    dir.0,
+                    buffer.as_mut_ptr(),
+                    buffer.len(),
+                )
+            };
+            if read < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if read == 0 {
+                break;
+            }
+
+            let mut offset = 0;
+            while offset < read as usize {
+                let record = &buffer[offset..read as usize];
+                let reclen =
+                    u16::from_ne_bytes([record[RECLEN_OFFSET], record[RECLEN_OFFSET + 1]]) as usize;
+                let d_ino = u64::from_ne_bytes(record[..8].try_into().unwrap());
+                let d_type = record[TYPE_OFFSET];
+                offset += reclen;
+
+                let Ok(name) = CStr::from_bytes_until_nul(&record[NAME_OFFSET..reclen]) else {
+                    listing.errors += 1;
+                    continue;
+                };
+                if matches!(name.to_bytes(), b"." | b"..") {
+                    continue;
+                }
+                // Directories are read when visited, so their type from the listing is enough
+                if d_type == libc::DT_DIR {
+                    listing.entries.push(ListedEntry {
+                        name: OsStr::from_bytes(name.to_bytes()).to_os_string(),
+                        kind: EntryKind::Dir,
+                        size: 0,
+                        logical_size: 0,
+                        modified: UNIX_EPOCH,
+                        inode: Some(d_ino),
+                    });
+                } else {
+                    unstated.push(name.to_owned());
+                }
+            }
+        }
+
+        stat_all(&dir, &unstated, io_uring, &mut listing);
+        Ok(listing)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use super::{open_dir, unix_time, EntryKind, ListedEntry, Listing, BUFFER_SIZE};
+    use std::ffi::{CStr, OsStr};
+    use std::io;
+    use std::mem::size_of;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::time::UNIX_EPOCH;
+
+    /// Not exported by `libc`; values from `<sys/attr.h>` and `<sys/vnode.h>`
+    const ATTR_CMN_ERROR: u32 = 0x2000_0000;
+    const VDIR: u32 = 2;
+    const VLNK: u32 = 5;
+
+    fn read<T: Copy>(record: &[u8], at: usize) -> Option<T> {
+        record
+            .get(at..at + size_of::<T>())
+            .map(|bytes| unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const T) })
+    }
+
+    /// Parse one record; attributes come in a fixed order, with the returned set first and
+    /// the error (if any) right after it
+    fn entry(record: &[u8]) -> Option<Result<ListedEntry, ()>> {
+        let mut cursor = size_of::<u32>();
+        let returned: libc::attribute_set_t = read(record, cursor)?;
+        cursor += size_of::<libc::attribute_set_t>();
+        if returned.commonattr & ATTR_CMN_ERROR != 0 {
+            let error: u32 = read(record, cursor)?;
+            cursor += size_of::<u32>();
+            if error != 0 {
+                return Some(Err(()));
+            }
+        }
+
+        let name_ref: libc::attrreference_t = read(record, cursor)?;
+        let name_start = cursor + name_ref.attr_dataoffset as usize;
+        let name_bytes = record.get(name_start..name_start + name_ref.attr_length as usize)?;
+        let name = CStr::from_bytes_until_nul(name_bytes).ok()?;
+        cursor += size_of::<libc::attrreference_t>();
+
+        let object_type: u32 = read(record, cursor)?;
+        cursor += size_of::<u32>();
+        let modified: libc::timespec = read(record, cursor)?;
+        cursor += size_of::<libc::timespec>();
+        let file_id: u64 = read(record, cursor)?;
+        cursor += size_of::<u64>();
+        // Both sizes are packed for directories too, as zero, because of FSOPT_PACK_INVAL_ATTRS
+        let allocated: libc::off_t = read(record, cursor)?;
+        cursor += size_of::<libc::off_t>();
+        let length: libc::off_t = read(record, cursor)?;
+
+        let kind = match object_type {
+            VDIR => EntryKind::Dir,
+            VLNK => EntryKind::Symlink,
+            _ => EntryKind::File,
+        };
+        Some(Ok(ListedEntry {
+            name: OsStr::from_bytes(name.to_bytes()).to_os_string(),
+            kind,
+            size: if kind == EntryKind::File {
+                allocated.max(0) as u64
+            } else {
+                0
+            },
+            logical_size: if kind == EntryKind::File {
+                length.max(0) as u64
+            } else {
+                0
+            },
+            modified: if kind == EntryKind::Dir {
+                UNIX_EPOCH
+            } else {
+                unix_time(modified.tv_sec, modified.tv_nsec as u32)
+            },
+            inode: Some(file_id),
+        }))
+    }
+
+    pub(super) fn list_dir(path: &Path, _io_uring: bool) -> io::Result<Listing> {
+        let dir = open_dir(path)?;
+        let mut attributes = libc::attrlist {
+            bitmapcount: libc::ATTR_BIT_MAP_COUNT,
+            reserved: 0,
+            commonattr: libc::ATTR_CMN_RETURNED_ATTRS
+                | libc::ATTR_CMN_NAME
+                | ATTR_CMN_ERROR
+                | libc::ATTR_CMN_OBJTYPE
+                | libc::ATTR_CMN_MODTIME
+                | libc::ATTR_CMN_FILEID,
+            volattr: 0,
+            dirattr: 0,
+            fileattr: libc::ATTR_FILE_ALLOCSIZE | libc::ATTR_FILE_DATALENGTH,
+            forkattr: 0,
+        };
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let mut listing = Listing::default();
+
+        loop {
+            let count = unsafe {
+                libc::getattrlistbulk(
+                    dir.0,
+                    &mut attributes as *mut libc::attrlist as *mut libc::c_void,
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                    u64::from(libc::FSOPT_PACK_INVAL_ATTRS),
+                )
+            };
+            if count < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if count == 0 {
+                break;
+            }
+
+            let mut offset = 0;
+            for _ in 0..count {
+                let Some(length) = read::<u32>(&buffer, offset) else {
+                    break;
+                };
+                let record = &buffer[offset..(offset + length as usize).min(buffer.len())];
+                offset += length as usize;
+                match entry(record) {
+                    Some(Ok(entry)) => listing.entries.push(entry),
+                    Some(Err(())) | None => listing.errors += 1,
+                }
+            }
+        }
+        Ok(listing)
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::{EntryKind, ListedEntry, Listing, BUFFER_SIZE};
+    use std::ffi::OsString;
+    use std::io;
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    use std::path::Path;
+    use std::ptr::{addr_of, null_mut};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use winapi::shared::minwindef::{DWORD, LPVOID};
+    use winapi::um::fileapi::{CreateFileW, FILE_ID_BOTH_DIR_INFO, OPEN_EXISTING};
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::minwinbase::FileIdBothDirectoryInfo;
+    use winapi::um::winbase::{GetFileInformationByHandleEx, FILE_FLAG_BACKUP_SEMANTICS};
+    use winapi::um::winnt::{
+        FILE_ATTRIBUTE_DIRECTORY, FILE_ATTRIBUTE_REPARSE_POINT, FILE_LIST_DIRECTORY,
+        FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, HANDLE, IO_REPARSE_TAG_MOUNT_POINT,
+        IO_REPARSE_TAG_SYMLINK,
+    };
+
+    const ERROR_NO_MORE_FILES: i32 = 18;
+    /// 100 ns intervals between 1601-01-01 and the Unix epoch
+    const FILETIME_UNIX_OFFSET: u64 = 116_444_736_000_000_000;
+
+    struct Handle(HANDLE);
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    fn filetime(ticks: i64) -> SystemTime {
+        (ticks as u64)
+            .checked_sub(FILETIME_UNIX_OFFSET)
+            .map(|t| UNIX_EPOCH + Duration::from_nanos(t.saturating_mul(100)))
+            .unwrap_or(UNIX_EPOCH)
+    }
+
+    pub(super) fn list_dir(path: &Path, _io_uring: bool) -> io::Result<Listing> {
+        let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let handle = unsafe {
+            CreateFileW(
+                wide.as_ptr(),
+                FILE_LIST_DIRECTORY,
+                FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+                null_mut(),
+                OPEN_EXISTING,
+                FILE_FLAG_BACKUP_SEMANTICS,
+                null_mut(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        let handle = Handle(handle);
+
+        // u64s keep the records' LARGE_INTEGER fields aligned
+        let mut buffer = vec![0u64; BUFFER_SIZE / 8];
+        let mut listing = Listing::default();
+        loop {
+            let ok = unsafe {
+                GetFileInformationByHandleEx(
+                    handle.0,
+                    FileIdBothDirectoryInfo,
+                    buffer.as_mut_ptr() as LPVOID,
+                    BUFFER_SIZE as DWORD,
+                )
+            };
+            if ok == 0 {
+                let error = io::Error::last_os_error();
+                if error.raw_os_error() == Some(ERROR_NO_MORE_FILES) {
+                    break;
+                }
+                return Err(error);
+            }
+
+            let mut record = buffer.as_ptr() as *const u8;
+            loop {
+                let info = record as *const FILE_ID_BOTH_DIR_INFO;
+                let (next, attributes, reparse_tag, name_length, end_of_file, last_write) = unsafe {
+                    (
+                        (*info).NextEntryOffset,
+                        (*info).FileAttributes,
+                        (*info).EaSize,
+                        (*info).FileNameLength as usize / 2,
+                        *(*info).EndOfFile.QuadPart(),
+                        *(*info).LastWriteTime.QuadPart(),
+                    )
+                };
+                let name = unsafe {
+                    std::slice::from_raw_parts(
+                        addr_of!((*info).FileName) as *const u16,
+                        name_length,
+                    )
+                };
+
+                if name != [b'.' as u16] && name != [b'.' as u16, b'.' as u16] {
+                    // For reparse points the EA size field holds the reparse tag instead;
+                    // only links and junctions are treated as links, not e.g. cloud placeholders
+                    let is_link = attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+                        && matches!(
+                            reparse_tag,
+                            IO_REPARSE_TAG_SYMLINK | IO_REPARSE_TAG_MOUNT_POINT
+                        );
+                    let kind = if is_link {
+                        EntryKind::Symlink
+                    } else if attributes & FILE_ATTRIBUTE_DIRECTORY != 0 {
+                        EntryKind::Dir
+                    } else {
+                        EntryKind::File
+                    };
+                    listing.entries.push(ListedEntry {
+                        name: OsString::from_wide(name),
+                        kind,
+                        size: if kind == EntryKind::File {
+                            end_of_file.max(0) as u64
+                        } else {
+                            0
+                        },
+                        logical_size: if kind == EntryKind::File {
+                            end_of_file.max(0) as u64
+                        } else {
+                            0
+                        },
+                        modified: if kind == EntryKind::Dir {
+                            UNIX_EPOCH
+                        } else {
+                            filetime(last_write)
+                        },
+                        inode: None,
+                    });
+                }
+
+                if next == 0 {
+                    break;
+                }
+                record = unsafe { record.add(next as usize) };
+            }
+        }
+        Ok(listing)
+    }
+}
+
+#[cfg(not(any(
+    all(target_os = "linux", target_env = "gnu"),
+    target_os = "macos",
+    windows
+)))]
+mod imp {
+    use super::{EntryKind, ListedEntry, Listing};
+    use std::io;
+    use std::path::Path;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    pub(super) fn list_dir(path: &Path, _io_uring: bool) -> io::Result<Listing> {
+        let mut listing = Listing::default();
+        for entry in std::fs::read_dir(path)? {
+            let Ok(entry) = entry else {
+                listing.errors += 1;
+                continue;
+            };
+            let Ok(metadata) = std::fs::symlink_metadata(entry.path()) else {
+                listing.errors += 1;
+                continue;
+            };
+            let kind = if metadata.is_symlink() {
+                EntryKind::Symlink
+            } else if metadata.is_dir() {
+                EntryKind::Dir
+            } else {
+                EntryKind::File
+            };
+            listing.entries.push(ListedEntry {
+                name: entry.file_name(),
+                kind,
+                size: if kind == EntryKind::File {
+                    crate::estimate::disk_size(&metadata)
+                } else {
+                    0
+                },
+                logical_size: if kind == EntryKind::File {
+                    metadata.len()
+                } else {
+                    0
+                },
+                modified: if kind == EntryKind::Dir {
+                    UNIX_EPOCH
+                } else {
+                    metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)
+                },
+                inode: inode(&metadata),
+            });
+        }
+        Ok(listing)
+    }
+
+    #[cfg(unix)]
+    fn inode(metadata: &std::fs::Metadata) -> Option<u64> {
+        use std::os::unix::fs::MetadataExt;
+        Some(metadata.ino())
+    }
+
+    #[cfg(not(unix))]
+    fn inode(_metadata: &std::fs::Metadata) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_listing_matches_symlink_metadata() {
+        let root = std::env::temp_dir().join("test_listing");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("data.bin"), vec![1u8; 10_000]).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root.join("data.bin"), root.join("link")).unwrap();
+
+        let mut listing = list_dir(&root, false).unwrap();
+        listing.entries.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut batched = list_dir(&root, true).unwrap();
+        batched.entries.sort_by(|a, b| a.name.cmp(&b.name));
+        let metadata = fs::symlink_metadata(root.join("data.bin")).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(listing.errors, 0);
+        let kinds: Vec<(&str, EntryKind)> = listing
+            .entries
+            .iter()
+            .map(|e| (e.name.to_str().unwrap(), e.kind))
+            .collect();
+        #[cfg(unix)]
+        assert_eq!(
+            kinds,
+            [
+                ("data.bin", EntryKind::File),
+                ("link", EntryKind::Symlink),
+                ("sub", EntryKind::Dir)
+            ]
+        );
+        let data = &listing.entries[0];
+        assert_eq!(data.size, crate::estimate::disk_size(&metadata));
+        assert_eq!(data.modified, metadata.modified().unwrap());
+        let sizes = |l: &Listing| l.entries.iter().map(|e| e.size).collect::<Vec<_>>();
+        assert_eq!(sizes(&batched), sizes(&listing));
+    }
+}
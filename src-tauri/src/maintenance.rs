@@ -0,0 +1,325 @@
+use crate::cache::SCAN_CACHE_DIR;
+use crate::duplicates::{self, HASH_CACHE_FILE};
+use crate::persistence::{app_cache_dir, app_data_dir, relocated_dirs};
+use crate::safety::calculate_path_size;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A category of data the app accumulates on its own
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DataBucket {
+    SavedScans,
+    HashCache,
+    Scripts,
+    Logs,
+    /// Webview and other disposable caches
+    Caches,
+    /// Completed scans kept for rescans after a restart
+    ScanCache,
+    /// Tags, policies, the never-suggest list and other settings
+    Settings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BucketUsage {
+    pub bucket: DataBucket,
+    pub path: PathBuf,
+    pub size: u64,
+    pub file_count: u64,
+}
+
+/// How much disk the app itself is using, per bucket
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AppDataUsage {
+    pub total_size: u64,
+    pub buckets: Vec<BucketUsage>,
+}
+
+/// What to prune; everything is off by default
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PruneOptions {
+    /// Keep only this many of the newest saved scans
+    pub keep_saved_scans: Option<usize>,
+    /// Delete exported cleanup scripts older than this
+    pub script_max_age_days: Option<u64>,
+    pub clear_logs: bool,
+    /// Also clears the scan cache
+    pub clear_caches: bool,
+    /// Drop hash cache entries for files that were deleted or changed
+    pub compact_hash_cache: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PruneResult {
+    pub freed_bytes: u64,
+    pub files_removed: u64,
+    pub hash_entries_dropped: usize,
+    /// Files that could not be removed, with the reason
+    pub errors: Vec<String>,
+}
+
+/// Where each bucket lives; settings are the loose files at the top of the data directory
+///
+/// Saved scans and the hash cache may also be in `relocated` directories, where they were
+/// moved off a nearly full volume.
+fn bucket_paths(
+    data_dir: &Path,
+    cache_dir: &Path,
+    relocated: &[PathBuf],
+) -> Vec<(DataBucket, PathBuf)> {
+    let mut buckets = vec![
+        (DataBucket::SavedScans, data_dir.join("scans")),
+        (DataBucket::HashCache, data_dir.join(HASH_CACHE_FILE)),
+        (DataBucket::Scripts, data_dir.join("scripts")),
+        (DataBucket::Logs, data_dir.join("logs")),
+        (DataBucket::Caches, cache_dir.to_path_buf()),
+        (DataBucket::ScanCache, data_dir.join(SCAN_CACHE_DIR)),
+    ];
+    for dir in relocated {
+        let moved = [
+            (DataBucket::SavedScans, dir.join("scans")),
+            (DataBucket::HashCache, dir.join(HASH_CACHE_FILE)),
+        ];
+        buckets.extend(moved.into_iter().filter(|(_, path)| path.exists()));
+    }
+    buckets
+}
+
+fn count_files(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .count() as u64
+}
+
+fn measure(data_dir: &Path, cache_dir: &Path, relocated: &[PathBuf]) -> AppDataUsage {
+    let mut buckets: Vec<BucketUsage> = bucket_paths(data_dir, cache_dir, relocated)
+        .into_iter()
+        .map(|(bucket, path)| BucketUsage {
+            bucket,
+            size: calculate_path_size(&path).unwrap_or(0),
+            file_count: count_files(&path),
+            path,
+        })
+        .collect();
+
+    let settings: Vec<fs::Metadata> = fs::read_dir(data_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.file_name() != HASH_CACHE_FILE)
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .collect();
+    buckets.push(BucketUsage {
+        bucket: DataBucket::Settings,
+        path: data_dir.to_path_buf(),
+        size: settings.iter().map(|m| m.len()).sum(),
+        file_count: settings.len() as u64,
+    });
+
+    AppDataUsage {
+        total_size: buckets.iter().map(|b| b.size).sum(),
+        buckets,
+    }
+}
+
+/// Files directly inside `dir`, newest first
+fn files_newest_first(dir: &Path) -> Vec<(PathBuf, fs::Metadata)> {
+    let mut files: Vec<(PathBuf, fs::Metadata)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| Some((e.path(), e.metadata().ok()?)))
+        .filter(|(_, m)| m.is_file())
+        .collect();
+    files.sort_by_key(|(_, m)| std::cmp::Reverse(m.modified().ok()));
+    files
+}
+
+fn remove(path: &Path, result: &mut PruneResult) {
+    let size = calculate_path_size(path).unwrap_or(0);
+    let files = count_files(path);
+    let removed = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    match removed {
+        Ok(()) => {
+            result.freed_bytes += size;
+            result.files_removed += files;
+        }
+        Err(e) => result.errors.push(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Delete what the options select; the hash cache is compacted separately
+fn prune(
+    data_dir: &Path,
+    cache_dir: &Path,
+    relocated: &[PathBuf],
+    options: &PruneOptions,
+    now: SystemTime,
+) -> PruneResult {
+    let mut result = PruneResult::default();
+
+    if let Some(keep) = options.keep_saved_scans {
+        // The newest are kept wherever they were saved
+        let mut saved: Vec<(PathBuf, fs::Metadata)> = std::iter::once(data_dir)
+            .chain(relocated.iter().map(PathBuf::as_path))
+            .flat_map(|dir| files_newest_first(&dir.join("scans")))
+            .filter(|(f, _)| f.extension().is_some_and(|e| e == "json"))
+            .collect();
+        saved.sort_by_key(|(_, m)| std::cmp::Reverse(m.modified().ok()));
+        for (file, _) in saved.into_iter().skip(keep) {
+            remove(&file, &mut result);
+        }
+    }
+
+    if let Some(days) = options.script_max_age_days {
+        let cutoff = now - Duration::from_secs(days * SECONDS_PER_DAY);
+        for (file, metadata) in files_newest_first(&data_dir.join("scripts")) {
+            if metadata.modified().is_ok_and(|m| m < cutoff) {
+                remove(&file, &mut result);
+            }
+        }
+    }
+
+    let mut clear = |dir: PathBuf| {
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            remove(&entry.path(), &mut result);
+        }
+    };
+    if options.clear_logs {
+        clear(data_dir.join("logs"));
+    }
+    if options.clear_caches {
+        clear(cache_dir.to_path_buf());
+        clear(data_dir.join(SCAN_CACHE_DIR));
+        // Copies of the hash cache made while its volume was nearly full; the usual one is
+        // only ever compacted
+        for dir in relocated {
+            let hash_cache = dir.join(HASH_CACHE_FILE);
+            if hash_cache.exists() {
+                remove(&hash_cache, &mut result);
+            }
+        }
+    }
+
+    result
+}
+
+// Tauri commands
+
+/// Disk used by the app's own data, per bucket
+#[tauri::command]
+pub async fn get_app_data_usage_command() -> Result<AppDataUsage, String> {
+    let (data_dir, cache_dir) = (app_data_dir()?, app_cache_dir()?);
+    let relocated = relocated_dirs();
+    tokio::task::spawn_blocking(move || measure(&data_dir, &cache_dir, &relocated))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Prune the app's own data as selected and report what was freed
+#[tauri::command]
+pub async fn prune_app_data_command(options: PruneOptions) -> Result<PruneResult, String> {
+    let (data_dir, cache_dir) = (app_data_dir()?, app_cache_dir()?);
+
+    let hash_cache = data_dir.join(HASH_CACHE_FILE);
+    let cache_size_before = fs::metadata(&hash_cache).map(|m| m.len()).unwrap_or(0);
+    let hash_entries_dropped = if options.compact_hash_cache {
+        duplicates::compact_hash_cache().await?
+    } else {
+        0
+    };
+    let cache_size_after = fs::metadata(&hash_cache).map(|m| m.len()).unwrap_or(0);
+
+    let relocated = relocated_dirs();
+    let mut result = tokio::task::spawn_blocking(move || {
+        prune(
+            &data_dir,
+            &cache_dir,
+            &relocated,
+            &options,
+            SystemTime::now(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    result.hash_entries_dropped = hash_entries_dropped;
+    result.freed_bytes += cache_size_before.saturating_sub(cache_size_after);
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_and_prune() {
+        let root = std::env::temp_dir().join("test_maintenance");
+        let _ = fs::remove_dir_all(&root);
+        let (data_dir, cache_dir) = (root.join("data"), root.join("cache"));
+        let relocated = vec![root.join("other-volume/.disk-analyser-cache")];
+        for dir in ["scans", "scripts", "logs"] {
+            fs::create_dir_all(data_dir.join(dir)).unwrap();
+        }
+        fs::create_dir_all(relocated[0].join("scans")).unwrap();
+        fs::create_dir_all(cache_dir.join("WebKit")).unwrap();
+
+        let now = SystemTime::now();
+        let scans = [
+            data_dir.join("scans/scan-1.json"),
+            relocated[0].join("scans/scan-2.json"),
+            data_dir.join("scans/scan-3.json"),
+        ];
+        for (i, file) in scans.iter().enumerate() {
+            fs::write(file, [0u8; 100]).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(file)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(60 * (3 - i as u64)))
+                .unwrap();
+        }
+        fs::write(data_dir.join("scripts/cleanup-1.sh"), [0u8; 10]).unwrap();
+        fs::write(data_dir.join("logs/app.log"), [0u8; 30]).unwrap();
+        fs::write(cache_dir.join("WebKit/blob"), [0u8; 50]).unwrap();
+        fs::write(data_dir.join("tags.json"), [0u8; 5]).unwrap();
+        fs::write(relocated[0].join(HASH_CACHE_FILE), [0u8; 20]).unwrap();
+
+        let usage = measure(&data_dir, &cache_dir, &relocated);
+        assert_eq!(usage.total_size, 300 + 10 + 30 + 50 + 5 + 20);
+
+        let options = PruneOptions {
+            keep_saved_scans: Some(1),
+            script_max_age_days: Some(30),
+            clear_caches: true,
+            ..Default::default()
+        };
+        let result = prune(&data_dir, &cache_dir, &relocated, &options, now);
+        let scans_left = fs::read_dir(data_dir.join("scans")).unwrap().count()
+            + fs::read_dir(relocated[0].join("scans")).unwrap().count();
+        let newest_kept = data_dir.join("scans/scan-3.json").exists();
+        let relocated_cache_left = relocated[0].join(HASH_CACHE_FILE).exists();
+        let cache_left = fs::read_dir(&cache_dir).unwrap().count();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(result.freed_bytes, 200 + 50 + 20);
+        assert_eq!(result.files_removed, 4);
+        assert_eq!(scans_left, 1);
+        assert!(newest_kept);
+        assert!(!relocated_cache_left);
+        assert_eq!(cache_left, 0);
+    }
+}
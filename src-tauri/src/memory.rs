@@ -0,0 +1,158 @@
+use crate::registry::{self, DiscoveredNode, DroppedFiles};
+use crate::types::FileType;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::path::PathBuf;
+
+/// How much memory a retained scan holds
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MemoryUsage {
+    pub scan_id: u64,
+    /// Entries with a node of their own
+    pub nodes: u64,
+    /// Files counted only in their directory's totals
+    pub dropped_files: u64,
+    /// Estimate of the bytes held by the nodes, their paths and names, and the map indexing
+    /// them; allocator overhead isn't counted
+    pub estimated_bytes: u64,
+}
+
+/// What compacting a retained scan freed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CompactResult {
+    pub scan_id: u64,
+    pub files_dropped: u64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Heap memory a node owns beyond its own struct
+fn heap_bytes(node: &DiscoveredNode) -> usize {
+    let dropped = node.dropped.as_ref().map_or(0, |d| {
+        size_of::<DroppedFiles>() + d.categories.capacity() * size_of::<(FileType, u64, u64)>()
+    });
+    node.path.capacity()
+        + node.name.capacity()
+        + node.parent_path.as_ref().map_or(0, |p| p.capacity())
+        + node.symlink_target.as_ref().map_or(0, |p| p.capacity())
+        + dropped
+}
+
+fn estimate(nodes: &HashMap<PathBuf, DiscoveredNode>) -> u64 {
+    // The map stores entries inline, plus a control byte for each slot
+    let table = nodes.capacity() * (size_of::<(PathBuf, DiscoveredNode)>() + 1);
+    let heap: usize = nodes
+        .iter()
+        .map(|(key, node)| key.capacity() + heap_bytes(node))
+        .sum();
+    (table + heap) as u64
+}
+
+fn usage(scan_id: u64, nodes: &HashMap<PathBuf, DiscoveredNode>) -> MemoryUsage {
+    MemoryUsage {
+        scan_id,
+        nodes: nodes.len() as u64,
+        dropped_files: nodes
+            .values()
+            .filter_map(|n| n.dropped.as_ref())
+            .map(|d| d.files)
+            .sum(),
+        estimated_bytes: estimate(nodes),
+    }
+}
+
+/// Fold files smaller than `below` bytes into their directory's totals, returning how many
+///
+/// Directory sizes, file counts and category totals stay the same; the files just can't be
+/// listed, searched or acted on any more.
+fn compact(nodes: &mut HashMap<PathBuf, DiscoveredNode>, below: u64) -> u64 {
+    let small: Vec<PathBuf> = nodes
+        .values()
+        .filter(|n| !n.is_directory && !n.is_symlink && n.size < below)
+        .filter(|n| {
+            n.parent_path
+                .as_ref()
+                .is_some_and(|p| nodes.contains_key(p))
+        })
+        .map(|n| n.path.clone())
+        .collect();
+    for path in &small {
+        if let Some(file) = nodes.remove(path) {
+            if let Some(parent) = file.parent_path.as_ref().and_then(|p| nodes.get_mut(p)) {
+                parent.drop_file(file.file_type, file.size);
+            }
+        }
+    }
+    nodes.shrink_to_fit();
+    small.len() as u64
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn get_memory_usage_command(scan_id: u64) -> Result<MemoryUsage, String> {
+    registry::with_scan(scan_id, |nodes, _| usage(scan_id, nodes)).await
+}
+
+/// Free memory held by a retained scan by keeping files under `below_bytes` only as totals
+#[tauri::command]
+pub async fn compact_scan_command(scan_id: u64, below_bytes: u64) -> Result<CompactResult, String> {
+    registry::with_scan_mut(scan_id, |nodes| {
+        let bytes_before = estimate(nodes);
+        let files_dropped = compact(nodes, below_bytes);
+        CompactResult {
+            scan_id,
+            files_dropped,
+            bytes_before,
+            bytes_after: estimate(nodes),
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::summary::{build_summary, ScanCounters, ScanStrategy};
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_compact_keeps_totals() {
+        let mut nodes: HashMap<PathBuf, DiscoveredNode> = [
+            registry::test_node("/scan", 0, true),
+            registry::test_node("/scan/cache", 0, true),
+            registry::test_node("/scan/cache/a", 10, false),
+            registry::test_node("/scan/cache/b", 20, false),
+            registry::test_node("/scan/cache/big.bin", 5_000, false),
+            registry::test_node("/scan/c", 30, false),
+        ]
+        .into_iter()
+        .map(|n| (n.path.clone(), n))
+        .collect();
+        let summarize = |nodes: &HashMap<PathBuf, DiscoveredNode>| {
+            let summary = build_summary(
+                1,
+                nodes,
+                Path::new("/scan"),
+                &ScanCounters::default(),
+                Duration::ZERO,
+                ScanStrategy::Progressive,
+            );
+            (summary.files, summary.total_size, summary.bytes_by_category)
+        };
+        let before = summarize(&nodes);
+        let bytes_before = estimate(&nodes);
+
+        assert_eq!(compact(&mut nodes, 100), 3);
+
+        assert_eq!(summarize(&nodes), before);
+        assert!(estimate(&nodes) < bytes_before);
+        assert!(nodes.contains_key(Path::new("/scan/cache/big.bin")));
+        let dropped = nodes[Path::new("/scan/cache")].dropped.as_ref().unwrap();
+        assert_eq!((dropped.files, dropped.bytes), (2, 30));
+        assert_eq!(usage(1, &nodes).dropped_files, 3);
+    }
+}
@@ -0,0 +1,737 @@
+use crate::access;
+use crate::events::EventSink;
+use crate::persistence::{app_data_dir, read_sealed_json_or_default, write_sealed_json};
+use crate::registry;
+use crate::safety::{self, check_deletion_safety, SafetyCheck};
+use crate::space_guard::ensure_space;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tauri::Window;
+use tokio_util::sync::CancellationToken;
+
+/// Files are copied and checksummed in chunks of this size
+const CHUNK_SIZE: usize = 8 << 20;
+
+/// Chunks copied between saves of the job state; a resume redoes at most this many
+const CHUNKS_PER_SAVE: usize = 16;
+
+/// Job state files live here, inside the app data directory
+const MOVES_DIR: &str = "moves";
+
+/// The job being run, with the token that pauses it
+struct RunningJob {
+    id: u64,
+    token: CancellationToken,
+    /// Sources and destination, which must stay mounted until the job stops
+    paths: Vec<PathBuf>,
+}
+
+static RUNNING: once_cell::sync::Lazy<Mutex<Option<RunningJob>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveMode {
+    /// Leave the sources in place
+    Copy,
+    /// Delete the sources once every copy has been verified
+    Move,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+/// One file, directory or link of a job, with how far its copy got
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MoveEntry {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub kind: EntryKind,
+    pub size: u64,
+    /// The source's modification time when the job was planned, in nanoseconds since the
+    /// Unix epoch; a move leaves the source in place if it changed since
+    #[serde(default)]
+    pub modified_ns: i64,
+    /// Bytes written and synced to the target's partial file
+    pub copied: u64,
+    /// BLAKE3 of each chunk copied so far, checked against the target once it is complete
+    pub chunk_hashes: Vec<String>,
+    pub done: bool,
+}
+
+/// A copy or move, saved after every file so it can resume after a crash or a drive
+/// being unplugged
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MoveJob {
+    pub id: u64,
+    pub mode: MoveMode,
+    pub sources: Vec<PathBuf>,
+    pub destination: PathBuf,
+    pub entries: Vec<MoveEntry>,
+    pub created_at: i64,
+    /// Every copy verified and, for a move, the sources deleted
+    pub finished: bool,
+    /// Sources a finished move didn't delete: changed since they were copied, not empty,
+    /// or refused by the safety checks
+    #[serde(default)]
+    pub left_behind: Vec<PathBuf>,
+}
+
+/// A job without its per-file detail; also the payload of `move-progress` events
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MoveStatus {
+    pub id: u64,
+    pub mode: MoveMode,
+    pub sources: Vec<PathBuf>,
+    pub destination: PathBuf,
+    pub files_total: u64,
+    pub files_done: u64,
+    pub bytes_total: u64,
+    pub bytes_done: u64,
+    pub created_at: i64,
+    pub finished: bool,
+    pub left_behind: Vec<PathBuf>,
+    /// Set while the job runs
+    pub current: Option<PathBuf>,
+}
+
+impl MoveJob {
+    fn status(&self, current: Option<&Path>) -> MoveStatus {
+        let files = self.entries.iter().filter(|e| e.kind == EntryKind::File);
+        MoveStatus {
+            id: self.id,
+            mode: self.mode,
+            sources: self.sources.clone(),
+            destination: self.destination.clone(),
+            files_total: files.clone().count() as u64,
+            files_done: files.clone().filter(|e| e.done).count() as u64,
+            bytes_total: files.clone().map(|e| e.size).sum(),
+            bytes_done: files.map(|e| e.copied).sum(),
+            created_at: self.created_at,
+            finished: self.finished,
+            left_behind: self.left_behind.clone(),
+            current: current.map(Path::to_path_buf),
+        }
+    }
+}
+
+fn moves_dir() -> Result<PathBuf, String> {
+    let dir = app_data_dir()?.join(MOVES_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+fn job_file(id: u64) -> Result<PathBuf, String> {
+    Ok(moves_dir()?.join(format!("move-{}.json", id)))
+}
+
+fn load_job(id: u64) -> Result<MoveJob, String> {
+    let file = job_file(id)?;
+    let job: Option<MoveJob> = read_sealed_json_or_default(&file)?;
+    job.ok_or_else(|| format!("Unknown move job: {}", id))
+}
+
+/// Where a file is written until its copy is verified
+fn partial_path(target: &Path) -> PathBuf {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    target.with_file_name(format!(".{}.partial", name))
+}
+
+fn modified_ns(metadata: &fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as i64)
+}
+
+/// Refuse sources that couldn't be deleted anyway, before anything is copied
+fn ensure_sources_deletable(sources: &[PathBuf]) -> Result<(), String> {
+    for source in sources {
+        if let SafetyCheck::Protected { message } | SafetyCheck::InUse { message } =
+            check_deletion_safety(source)
+        {
+            return Err(message.text);
+        }
+    }
+    Ok(())
+}
+
+/// List everything under `sources` with where it goes below `destination`
+fn plan_job(
+    id: u64,
+    sources: Vec<PathBuf>,
+    destination: PathBuf,
+    mode: MoveMode,
+) -> Result<MoveJob, String> {
+    let mut entries = Vec::new();
+    for source in &sources {
+        let name = source
+            .file_name()
+            .ok_or_else(|| format!("Cannot copy {}", source.display()))?;
+        let base = destination.join(name);
+        if destination.starts_with(source) {
+            return Err(format!("Cannot copy {} into itself", source.display()));
+        }
+        if base.symlink_metadata().is_ok() {
+            return Err(format!("{} already exists", base.display()));
+        }
+        if mode == MoveMode::Move {
+            // Refuse now rather than after copying everything
+            ensure_sources_deletable(std::slice::from_ref(source))?;
+        }
+        for entry in walkdir::WalkDir::new(source).follow_links(false) {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let relative = entry.path().strip_prefix(source).unwrap_or(Path::new(""));
+            let file_type = entry.file_type();
+            let metadata = entry.metadata().map_err(|e| e.to_string())?;
+            let (kind, size) = if file_type.is_symlink() {
+                (EntryKind::Symlink, 0)
+            } else if file_type.is_dir() {
+                (EntryKind::Directory, 0)
+            } else {
+                (EntryKind::File, metadata.len())
+            };
+            entries.push(MoveEntry {
+                source: entry.path().to_path_buf(),
+                target: base.join(relative),
+                kind,
+                size,
+                modified_ns: modified_ns(&metadata),
+                copied: 0,
+                chunk_hashes: Vec::new(),
+                done: false,
+            });
+        }
+    }
+    Ok(MoveJob {
+        id,
+        mode,
+        sources,
+        destination,
+        entries,
+        created_at: chrono::Utc::now().timestamp(),
+        finished: false,
+        left_behind: Vec::new(),
+    })
+}
+
+/// Fill `buf` unless the file ends first; chunks stay aligned across resumes this way
+fn read_chunk(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn chunk_hash(bytes: &[u8]) -> String {
+    blake3::hash(bytes).to_hex().to_string()
+}
+
+/// Whether the last recorded chunk is really in the partial file; if not (the drive was
+/// pulled before the data reached it), the file starts over
+fn resume_point(partial: &mut File, entry: &mut MoveEntry) -> io::Result<()> {
+    let intact = match entry.chunk_hashes.last() {
+        None => true,
+        Some(last) => {
+            let start = (entry.chunk_hashes.len() - 1) as u64 * CHUNK_SIZE as u64;
+            let mut buf = vec![0; (entry.copied - start) as usize];
+            partial.metadata()?.len() >= entry.copied
+                && partial.seek(SeekFrom::Start(start)).is_ok()
+                && partial.read_exact(&mut buf).is_ok()
+                && chunk_hash(&buf) == *last
+        }
+    };
+    if !intact {
+        entry.copied = 0;
+        entry.chunk_hashes.clear();
+    }
+    partial.set_len(entry.copied)?;
+    partial.seek(SeekFrom::Start(entry.copied))?;
+    Ok(())
+}
+
+/// Re-read the finished copy and compare it chunk by chunk
+fn verify(partial: &Path, hashes: &[String]) -> io::Result<bool> {
+    let mut file = File::open(partial)?;
+    let mut buf = vec![0; CHUNK_SIZE];
+    for expected in hashes {
+        let n = read_chunk(&mut file, &mut buf)?;
+        if chunk_hash(&buf[..n]) != *expected {
+            return Ok(false);
+        }
+    }
+    Ok(read_chunk(&mut file, &mut buf)? == 0)
+}
+
+/// Copy one file from where it stopped; `false` means paused before it finished
+fn copy_file(
+    entry: &mut MoveEntry,
+    token: &CancellationToken,
+    mut checkpoint: impl FnMut(&MoveEntry) -> Result<(), String>,
+) -> Result<bool, String> {
+    let partial = partial_path(&entry.target);
+    let source_path = entry.source.clone();
+    let io_error = |e: io::Error| format!("Copying {} failed: {}", source_path.display(), e);
+
+    let mut out = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&partial)
+        .map_err(io_error)?;
+    resume_point(&mut out, entry).map_err(io_error)?;
+    let mut source = File::open(&source_path).map_err(io_error)?;
+    source
+        .seek(SeekFrom::Start(entry.copied))
+        .map_err(io_error)?;
+
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut unsaved = 0;
+    loop {
+        if token.is_cancelled() {
+            out.sync_data().map_err(io_error)?;
+            checkpoint(entry)?;
+            return Ok(false);
+        }
+        let n = read_chunk(&mut source, &mut buf).map_err(io_error)?;
+        if n == 0 {
+            break;
+        }
+        out.write_all(&buf[..n]).map_err(io_error)?;
+        entry.chunk_hashes.push(chunk_hash(&buf[..n]));
+        entry.copied += n as u64;
+        unsaved += 1;
+        if unsaved == CHUNKS_PER_SAVE {
+            // Only record offsets the drive has actually stored
+            out.sync_data().map_err(io_error)?;
+            checkpoint(entry)?;
+            unsaved = 0;
+        }
+    }
+    out.sync_all().map_err(io_error)?;
+    drop(out);
+
+    if !verify(&partial, &entry.chunk_hashes).map_err(io_error)? {
+        entry.copied = 0;
+        entry.chunk_hashes.clear();
+        checkpoint(entry)?;
+        return Err(format!(
+            "The copy of {} doesn't match the original; it will be copied again on resume",
+            entry.source.display()
+        ));
+    }
+    fs::rename(&partial, &entry.target).map_err(io_error)?;
+    if let Ok(modified) = fs::metadata(&entry.source).and_then(|m| m.modified()) {
+        let _ = File::options()
+            .write(true)
+            .open(&entry.target)
+            .and_then(|f| f.set_modified(modified));
+    }
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, target)
+}
+
+#[cfg(windows)]
+fn copy_symlink(source: &Path, target: &Path) -> io::Result<()> {
+    let link = fs::read_link(source)?;
+    if source.is_dir() {
+        std::os::windows::fs::symlink_dir(link, target)
+    } else {
+        std::os::windows::fs::symlink_file(link, target)
+    }
+}
+
+fn copy_entry(
+    entry: &mut MoveEntry,
+    token: &CancellationToken,
+    checkpoint: impl FnMut(&MoveEntry) -> Result<(), String>,
+) -> Result<bool, String> {
+    let io_error = |e: io::Error| format!("Copying {} failed: {}", entry.source.display(), e);
+    match entry.kind {
+        EntryKind::Directory => fs::create_dir_all(&entry.target).map_err(io_error)?,
+        EntryKind::Symlink => {
+            if entry.target.symlink_metadata().is_err() {
+                copy_symlink(&entry.source, &entry.target).map_err(io_error)?;
+            }
+        }
+        EntryKind::File => return copy_file(entry, token, checkpoint),
+    }
+    Ok(true)
+}
+
+/// Copy whatever is left of `job`, saving its state to `state_file` as it goes
+///
+/// Returns whether every entry has been copied and verified, rather than paused.
+fn run_job<E: EventSink>(
+    job: &mut MoveJob,
+    state_file: &Path,
+    token: &CancellationToken,
+    events: &E,
+) -> Result<bool, String> {
+    // A file may have grown or shrunk since the job was planned
+    let remaining: u64 = job
+        .entries
+        .iter()
+        .filter(|e| !e.done)
+        .map(|e| e.size.saturating_sub(e.copied))
+        .sum();
+    ensure_space(&job.destination, remaining)?;
+
+    for index in 0..job.entries.len() {
+        if job.entries[index].done {
+            continue;
+        }
+        if token.is_cancelled() {
+            write_sealed_json(state_file, job)?;
+            return Ok(false);
+        }
+        let mut entry = job.entries[index].clone();
+        events.send("move-progress", &job.status(Some(&entry.source)));
+        let completed = copy_entry(&mut entry, token, |progress| {
+            job.entries[index] = progress.clone();
+            write_sealed_json(state_file, job)
+        });
+        entry.done = completed.as_ref().is_ok_and(|c| *c);
+        job.entries[index] = entry;
+        write_sealed_json(state_file, job)?;
+        match completed {
+            Ok(true) => {}
+            Ok(false) => return Ok(false),
+            Err(e) => return Err(format!("{}; resume the job to continue", e)),
+        }
+    }
+    Ok(true)
+}
+
+/// Whether a source is as it was when the job was planned, so its verified copy stands in
+/// for it
+fn unchanged_since_plan(entry: &MoveEntry) -> bool {
+    let Ok(metadata) = fs::symlink_metadata(&entry.source) else {
+        return false;
+    };
+    match entry.kind {
+        EntryKind::Symlink => metadata.file_type().is_symlink(),
+        EntryKind::File => {
+            metadata.is_file()
+                && metadata.len() == entry.size
+                && entry.copied == entry.size
+                && modified_ns(&metadata) == entry.modified_ns
+        }
+        EntryKind::Directory => metadata.is_dir(),
+    }
+}
+
+/// Delete the sources of a fully copied move
+///
+/// Only files and links whose verified copy still matches them go, through the usual
+/// safety checks, and the retained scan is patched. Directories are removed once empty,
+/// deepest first; anything created or changed since the job was planned stays behind.
+async fn remove_sources<E: EventSink>(job: &mut MoveJob, events: &E) -> Result<(), String> {
+    let (deletable, mut left_behind) = deletable_sources(job);
+    if !deletable.is_empty() {
+        let deletion = safety::delete_and_patch_scan(deletable, false, events).await?;
+        left_behind.extend(deletion.failed.iter().map(|f| PathBuf::from(&f.path)));
+    }
+
+    let removed = remove_emptied_dirs(job, &mut left_behind);
+    for event in registry::remove_deleted_paths(&removed).await {
+        events.send("streaming-scan-event", &event);
+    }
+    job.left_behind = left_behind;
+    Ok(())
+}
+
+/// Source files and links split into those that may be deleted and those that changed
+/// since the job was planned
+fn deletable_sources(job: &MoveJob) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut left_behind = Vec::new();
+    let mut deletable = Vec::new();
+    for entry in job
+        .entries
+        .iter()
+        .filter(|e| e.kind != EntryKind::Directory)
+    {
+        if unchanged_since_plan(entry) {
+            deletable.push(entry.source.clone());
+        } else if entry.source.symlink_metadata().is_ok() {
+            left_behind.push(entry.source.clone());
+        }
+    }
+    (deletable, left_behind)
+}
+
+/// Remove source directories that are now empty, deepest first, adding the rest to
+/// `left_behind`; returns the removed ones
+fn remove_emptied_dirs(job: &MoveJob, left_behind: &mut Vec<PathBuf>) -> Vec<String> {
+    let mut removed = Vec::new();
+    for entry in job.entries.iter().rev() {
+        if entry.kind != EntryKind::Directory {
+            continue;
+        }
+        if fs::remove_dir(&entry.source).is_ok() {
+            removed.push(entry.source.to_string_lossy().to_string());
+        } else if entry.source.symlink_metadata().is_ok() {
+            left_behind.push(entry.source.clone());
+        }
+    }
+    removed
+}
+
+/// Mark a fully copied job finished, deleting the sources first for a move
+async fn finish_job<E: EventSink>(
+    job: &mut MoveJob,
+    state_file: &Path,
+    events: &E,
+) -> Result<(), String> {
+    if job.mode == MoveMode::Move {
+        remove_sources(job, events).await?;
+    }
+    job.finished = true;
+    write_sealed_json(state_file, job)?;
+    events.send("move-progress", &job.status(None));
+    Ok(())
+}
+
+/// Run a job in the background until it finishes, fails or is paused
+async fn run_in_background(mut job: MoveJob, window: Window) -> Result<MoveStatus, String> {
+    access::ensure_writable("Copying files")?;
+    let token = CancellationToken::new();
+    {
+        let mut running = RUNNING.lock().unwrap();
+        if running.is_some() {
+            return Err("A copy or move is already running".to_string());
+        }
+        let mut paths = job.sources.clone();
+        paths.push(job.destination.clone());
+        *running = Some(RunningJob {
+            id: job.id,
+            token: token.clone(),
+            paths,
+        });
+    }
+    let result = async {
+        let state_file = job_file(job.id)?;
+        let (events, saved_to) = (window.clone(), state_file.clone());
+        let (mut job, copied) = tokio::task::spawn_blocking(move || {
+            let copied = run_job(&mut job, &saved_to, &token, &events)?;
+            Ok::<_, String>((job, copied))
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+        if copied {
+            finish_job(&mut job, &state_file, &window).await?;
+        }
+        Ok(job.status(None))
+    }
+    .await;
+    *RUNNING.lock().unwrap() = None;
+    result
+}
+
+/// Paths the running job reads or writes, if one is running
+pub(crate) fn running_paths() -> Vec<PathBuf> {
+    RUNNING
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|running| running.paths.clone())
+        .unwrap_or_default()
+}
+
+// Tauri commands
+
+/// Copy or move files and folders into `destination`, emitting `move-progress` events
+///
+/// The job survives errors and restarts: call `resume_move_command` to continue it.
+#[tauri::command]
+pub async fn start_move_command(
+    sources: Vec<String>,
+    destination: String,
+    mode: MoveMode,
+    window: Window,
+) -> Result<MoveStatus, String> {
+    let sources: Vec<PathBuf> = sources.iter().map(PathBuf::from).collect();
+    let destination = PathBuf::from(destination);
+    let id = chrono::Utc::now().timestamp_millis() as u64;
+    let job = tokio::task::spawn_blocking(move || plan_job(id, sources, destination, mode))
+        .await
+        .map_err(|e| e.to_string())??;
+    write_sealed_json(&job_file(job.id)?, &job)?;
+    run_in_background(job, window).await
+}
+
+#[tauri::command]
+pub async fn resume_move_command(job_id: u64, window: Window) -> Result<MoveStatus, String> {
+    let job = load_job(job_id)?;
+    if job.finished {
+        return Ok(job.status(None));
+    }
+    if job.mode == MoveMode::Move {
+        // The sources may have become protected or busy while the job was paused
+        ensure_sources_deletable(&job.sources)?;
+    }
+    run_in_background(job, window).await
+}
+
+/// Stop the running job after the current chunk; returns false when none is running
+#[tauri::command]
+pub async fn pause_move_command() -> Result<bool, String> {
+    match RUNNING.lock().unwrap().as_ref() {
+        Some(running) => {
+            running.token.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Saved jobs, newest first, including unfinished ones that can be resumed
+#[tauri::command]
+pub async fn list_move_jobs_command() -> Result<Vec<MoveStatus>, String> {
+    let entries = fs::read_dir(moves_dir()?).map_err(|e| e.to_string())?;
+    let mut jobs: Vec<MoveStatus> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let job: Option<MoveJob> = read_sealed_json_or_default(&entry.path()).ok()?;
+            job.map(|j| j.status(None))
+        })
+        .collect();
+    jobs.sort_by_key(|j| std::cmp::Reverse(j.id));
+    Ok(jobs)
+}
+
+/// Forget a job, deleting the partial files of an unfinished one; copied files stay
+#[tauri::command]
+pub async fn discard_move_job_command(job_id: u64) -> Result<(), String> {
+    if RUNNING
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|running| running.id == job_id)
+    {
+        return Err("Pause the job before discarding it".to_string());
+    }
+    let job = load_job(job_id)?;
+    for entry in job.entries.iter().filter(|e| !e.done) {
+        let _ = fs::remove_file(partial_path(&entry.target));
+    }
+    fs::remove_file(job_file(job_id)?).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Quiet;
+
+    impl EventSink for Quiet {
+        fn send<S: Serialize + Clone>(&self, _event: &str, _payload: &S) {}
+    }
+
+    #[test]
+    fn test_copy_resumes_and_verifies() {
+        let root = std::env::temp_dir().join(format!("moves-test-{}", std::process::id()));
+        let source = root.join("photos");
+        let destination = root.join("usb");
+        fs::create_dir_all(source.join("2024")).unwrap();
+        fs::create_dir_all(&destination).unwrap();
+        fs::write(source.join("a.txt"), b"hello").unwrap();
+        let big: Vec<u8> = (0..CHUNK_SIZE + 100).map(|i| (i % 251) as u8).collect();
+        fs::write(source.join("2024").join("b.bin"), &big).unwrap();
+
+        let mut job =
+            plan_job(1, vec![source.clone()], destination.clone(), MoveMode::Copy).unwrap();
+        assert_eq!(job.status(None).files_total, 2);
+        assert_eq!(job.status(None).bytes_total, 5 + big.len() as u64);
+
+        // An interrupted copy whose last recorded chunk never reached the drive
+        let target = destination.join("photos").join("2024").join("b.bin");
+        let entry = job.entries.iter_mut().find(|e| e.target == target).unwrap();
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        fs::write(partial_path(&target), vec![0u8; CHUNK_SIZE]).unwrap();
+        entry.copied = CHUNK_SIZE as u64;
+        entry.chunk_hashes = vec![chunk_hash(&big[..CHUNK_SIZE])];
+
+        let state_file = root.join("state.json");
+        assert!(run_job(&mut job, &state_file, &CancellationToken::new(), &Quiet).unwrap());
+        assert_eq!(fs::read(&target).unwrap(), big);
+        assert!(!partial_path(&target).exists());
+        assert_eq!(
+            fs::read(destination.join("photos").join("a.txt")).unwrap(),
+            b"hello"
+        );
+        // A copy leaves the sources alone, and won't overwrite what it made
+        assert!(source.join("a.txt").exists());
+        assert!(plan_job(2, vec![source.clone()], destination.clone(), MoveMode::Copy).is_err());
+        assert!(plan_job(3, vec![root.clone()], destination, MoveMode::Copy).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_move_leaves_changed_sources() {
+        let root = std::env::temp_dir().join(format!("moves-move-test-{}", std::process::id()));
+        let source = root.join("docs");
+        let destination = root.join("usb");
+        fs::create_dir_all(source.join("old")).unwrap();
+        fs::create_dir_all(&destination).unwrap();
+        fs::write(source.join("old").join("a.txt"), b"hello").unwrap();
+        fs::write(source.join("b.txt"), b"draft").unwrap();
+
+        let mut job =
+            plan_job(1, vec![source.clone()], destination.clone(), MoveMode::Move).unwrap();
+        let state_file = root.join("state.json");
+        assert!(run_job(&mut job, &state_file, &CancellationToken::new(), &Quiet).unwrap());
+
+        // Edited and created after the copy, so neither may be deleted
+        fs::write(source.join("b.txt"), b"draft, longer").unwrap();
+        fs::write(source.join("new.txt"), b"new").unwrap();
+        let (deletable, mut left_behind) = deletable_sources(&job);
+        assert_eq!(deletable, vec![source.join("old").join("a.txt")]);
+        assert_eq!(left_behind, vec![source.join("b.txt")]);
+
+        // Stands in for the deletion pipeline, which goes through the user's settings
+        for path in &deletable {
+            fs::remove_file(path).unwrap();
+        }
+        let removed = remove_emptied_dirs(&job, &mut left_behind);
+
+        assert_eq!(
+            removed,
+            vec![source.join("old").to_string_lossy().to_string()]
+        );
+        assert!(!source.join("old").exists());
+        assert_eq!(fs::read(source.join("b.txt")).unwrap(), b"draft, longer");
+        assert!(source.join("new.txt").exists());
+        assert_eq!(left_behind, vec![source.join("b.txt"), source.clone()]);
+        assert_eq!(
+            fs::read(destination.join("docs").join("old").join("a.txt")).unwrap(),
+            b"hello"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+use crate::persistence::{data_file, read_sealed_json_or_default, write_sealed_json};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const NEVER_SUGGEST_FILE: &str = "never_suggest.json";
+
+/// A path (and everything beneath it) the user never wants offered for deletion
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NeverSuggestEntry {
+    pub path: PathBuf,
+    /// Why the user excluded it, shown back to them in the list
+    pub note: Option<String>,
+    /// Unix timestamp (seconds) when the entry was added
+    pub added_at: i64,
+}
+
+/// Load the user's never-suggest list
+pub fn load_never_suggest() -> Result<Vec<NeverSuggestEntry>, String> {
+    read_sealed_json_or_default(&data_file(NEVER_SUGGEST_FILE)?)
+}
+
+pub(crate) fn save_never_suggest(entries: &[NeverSuggestEntry]) -> Result<(), String> {
+    write_sealed_json(&data_file(NEVER_SUGGEST_FILE)?, &entries)
+}
+
+/// Whether `path` is on the list or inside a listed folder
+///
+/// Suggestion sources (cleanup rules, duplicate finders) filter their candidates with this.
+pub fn is_never_suggested(entries: &[NeverSuggestEntry], path: &Path) -> bool {
+    entries.iter().any(|entry| path.starts_with(&entry.path))
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn add_never_suggest_command(
+    path: String,
+    note: Option<String>,
+) -> Result<Vec<NeverSuggestEntry>, String> {
+    let mut entries = load_never_suggest()?;
+    let path = PathBuf::from(path);
+
+    if !entries.iter().any(|entry| entry.path == path) {
+        entries.push(NeverSuggestEntry {
+            path,
+            note,
+            added_at: chrono::Utc::now().timestamp(),
+        });
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        save_never_suggest(&entries)?;
+    }
+
+    Ok(entries)
+}
+
+#[tauri::command]
+pub async fn remove_never_suggest_command(path: String) -> Result<Vec<NeverSuggestEntry>, String> {
+    let mut entries = load_never_suggest()?;
+    let path = PathBuf::from(path);
+
+    entries.retain(|entry| entry.path != path);
+    save_never_suggest(&entries)?;
+    Ok(entries)
+}
+
+#[tauri::command]
+pub async fn list_never_suggest_command() -> Result<Vec<NeverSuggestEntry>, String> {
+    load_never_suggest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_never_suggested_covers_subfolders() {
+        let entries = vec![NeverSuggestEntry {
+            path: PathBuf::from("/home/user/Photos/Archive"),
+            note: Some("curated".to_string()),
+            added_at: 0,
+        }];
+
+        assert!(is_never_suggested(
+            &entries,
+            Path::new("/home/user/Photos/Archive")
+        ));
+        assert!(is_never_suggested(
+            &entries,
+            Path::new("/home/user/Photos/Archive/2019/img.jpg")
+        ));
+        assert!(!is_never_suggested(
+            &entries,
+            Path::new("/home/user/Photos/Archive2")
+        ));
+        assert!(!is_never_suggested(
+            &entries,
+            Path::new("/home/user/Photos")
+        ));
+    }
+}
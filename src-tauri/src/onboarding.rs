@@ -0,0 +1,153 @@
+use crate::environment::GrantedPermissions;
+use crate::persistence::{data_file, read_json_or_default, write_json};
+use crate::sampling::{sampled_scan, SampledScan};
+use crate::storage::{resolve_scan_scope, ScanScope};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const FIRST_RUN_FILE: &str = "first_run.json";
+
+/// Fewer walks than a sampled scan, so estimating every scope stays under a few seconds
+const ESTIMATE_WALKS: usize = 8;
+
+/// Rough rate of a full scan on a local SSD; slower disks take longer
+const FILES_PER_SECOND: u64 = 20_000;
+
+/// Scopes offered on first run, most focused first
+const DEFAULT_SCOPES: &[ScanScope] = &[ScanScope::CurrentUser, ScanScope::WholeVolume];
+
+/// A scope offered on first run, with what scanning it would take
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FirstRunScope {
+    pub scope: ScanScope,
+    pub root: PathBuf,
+    /// Sampled, so only good enough to compare scopes
+    pub estimated_size: u64,
+    pub estimated_files: u64,
+    pub estimated_duration_ms: u64,
+    /// Whether a full scan would skip folders without Full Disk Access or elevation
+    pub requires_elevation: bool,
+}
+
+/// What the user picked on first run; later launches scan these roots automatically
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct FirstRunChoice {
+    /// Unix timestamp (seconds) the flow was finished; `None` until then
+    pub completed_at: Option<i64>,
+    pub scopes: Vec<ScanScope>,
+    /// Roots resolved from `scopes` when they were chosen
+    pub roots: Vec<PathBuf>,
+    /// Permissions the app had once the user went through the grant steps
+    pub permissions: Option<GrantedPermissions>,
+    /// Scan the roots at launch without asking
+    pub auto_scan: bool,
+}
+
+fn estimated_duration_ms(files: u64) -> u64 {
+    files.saturating_mul(1000) / FILES_PER_SECOND
+}
+
+fn first_run_scope(
+    scope: ScanScope,
+    requires_elevation: bool,
+    sample: &SampledScan,
+) -> FirstRunScope {
+    let estimated_files: u64 = sample.directories.iter().map(|d| d.estimated_files).sum();
+    FirstRunScope {
+        scope,
+        root: sample.root.clone(),
+        estimated_size: sample.estimated_total,
+        estimated_files,
+        estimated_duration_ms: estimated_duration_ms(estimated_files),
+        requires_elevation,
+    }
+}
+
+/// Load the saved first-run choice, or an unfinished default
+pub fn load_first_run_choice() -> Result<FirstRunChoice, String> {
+    read_json_or_default(&data_file(FIRST_RUN_FILE)?)
+}
+
+// Tauri commands
+
+/// Default scopes for the first-run flow, each estimated by a quick sampled walk
+#[tauri::command]
+pub async fn get_first_run_scopes_command() -> Result<Vec<FirstRunScope>, String> {
+    let seed = chrono::Utc::now().timestamp_millis() as u64;
+    tokio::task::spawn_blocking(move || {
+        DEFAULT_SCOPES
+            .iter()
+            .map(|&scope| {
+                let resolved = resolve_scan_scope(scope)?;
+                let sample = sampled_scan(&resolved.root, ESTIMATE_WALKS, seed);
+                Ok(first_run_scope(scope, resolved.requires_elevation, &sample))
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_first_run_choice_command() -> Result<FirstRunChoice, String> {
+    load_first_run_choice()
+}
+
+/// Finish the first-run flow, recording the chosen scopes and the permissions granted so far
+#[tauri::command]
+pub async fn save_first_run_choice_command(
+    scopes: Vec<ScanScope>,
+    auto_scan: bool,
+) -> Result<FirstRunChoice, String> {
+    let mut roots = Vec::new();
+    for &scope in &scopes {
+        let root = resolve_scan_scope(scope)?.root;
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+    let choice = FirstRunChoice {
+        completed_at: Some(chrono::Utc::now().timestamp()),
+        scopes,
+        roots,
+        permissions: Some(crate::environment::environment_report().permissions),
+        auto_scan,
+    };
+    write_json(&data_file(FIRST_RUN_FILE)?, &choice)?;
+    Ok(choice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::SampledDirectory;
+
+    #[test]
+    fn test_first_run_scope_estimates_from_sample() {
+        let directory = |name: &str, files: u64| SampledDirectory {
+            path: PathBuf::from("/home/u").join(name),
+            estimated_size: files * 1000,
+            size_low: 0,
+            size_high: 0,
+            estimated_files: files,
+        };
+        let sample = SampledScan {
+            root: PathBuf::from("/home/u"),
+            strategy: crate::summary::ScanStrategy::Sampled,
+            walks_per_directory: ESTIMATE_WALKS,
+            confidence: 0.95,
+            root_files_size: 500,
+            estimated_total: 60_500,
+            directories: vec![directory("Documents", 40_000), directory("Music", 20_000)],
+            duration_ms: 12,
+        };
+
+        let scope = first_run_scope(ScanScope::CurrentUser, false, &sample);
+        assert_eq!(scope.root, PathBuf::from("/home/u"));
+        assert_eq!(scope.estimated_size, 60_500);
+        assert_eq!(scope.estimated_files, 60_000);
+        assert_eq!(scope.estimated_duration_ms, 3_000);
+        assert!(FirstRunChoice::default().completed_at.is_none());
+    }
+}
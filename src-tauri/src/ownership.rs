@@ -0,0 +1,272 @@
+use crate::access;
+use crate::persistence::is_app_data_path;
+use crate::safety::is_protected_path;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// An entry whose ownership or permissions could not be changed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FailedRepair {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RepairResult {
+    /// Entries that were changed; entries that were already fine aren't counted
+    pub changed: u64,
+    pub failed: Vec<FailedRepair>,
+}
+
+/// Refuse anything but the user's own data
+///
+/// Repairs recurse into whole subtrees, so they are limited to the home directory and never
+/// touch system locations, the app's data, or the home directory itself.
+fn check_repairable(path: &Path, home: &Path) -> Result<(), String> {
+    let metadata = path
+        .symlink_metadata()
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    if metadata.file_type().is_symlink() {
+        return Err(format!(
+            "{} is a link; repair its target instead",
+            path.display()
+        ));
+    }
+    let path = path
+        .canonicalize()
+        .map_err(|e| format!("Cannot resolve {}: {}", path.display(), e))?;
+    if is_protected_path(&path) || is_app_data_path(&path) {
+        return Err(format!("{} is a protected location", path.display()));
+    }
+    if path == home || !path.starts_with(home) {
+        return Err(format!(
+            "Only files inside your home folder can be repaired, not {}",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+fn check_root(path: &Path) -> Result<(), String> {
+    access::ensure_writable("Changing ownership and permissions")?;
+    let home = dirs::home_dir()
+        .and_then(|h| h.canonicalize().ok())
+        .ok_or_else(|| "Could not determine your home folder".to_string())?;
+    check_repairable(path, &home)
+}
+
+#[cfg(unix)]
+fn for_each_entry(
+    root: &Path,
+    mut repair: impl FnMut(&Path) -> std::io::Result<bool>,
+) -> RepairResult {
+    let mut result = RepairResult::default();
+    let fail = |result: &mut RepairResult, path: &Path, error: std::io::Error| {
+        result.failed.push(FailedRepair {
+            path: path.to_string_lossy().to_string(),
+            error: error.to_string(),
+        })
+    };
+    // Walked by hand rather than with walkdir, which reads a directory before yielding it:
+    // here a directory is repaired first, so an unreadable one can still be descended
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        let repaired = match repair(&path) {
+            Ok(changed) => {
+                result.changed += changed as u64;
+                true
+            }
+            Err(e) => {
+                fail(&mut result, &path, e);
+                false
+            }
+        };
+        if !path.symlink_metadata().is_ok_and(|m| m.is_dir()) {
+            continue;
+        }
+        match std::fs::read_dir(&path) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok(entry) => pending.push(entry.path()),
+                        Err(e) => fail(&mut result, &path, e),
+                    }
+                }
+            }
+            // Already reported when the repair itself failed
+            Err(e) if repaired => fail(&mut result, &path, e),
+            Err(_) => {}
+        }
+    }
+    result
+}
+
+/// Give the current user read and write access (and search access to directories)
+#[cfg(unix)]
+fn repair_permissions(root: &Path) -> RepairResult {
+    use std::os::unix::fs::PermissionsExt;
+
+    for_each_entry(root, |path| {
+        let metadata = path.symlink_metadata()?;
+        if metadata.file_type().is_symlink() {
+            return Ok(false);
+        }
+        let needed = if metadata.is_dir() { 0o700 } else { 0o600 };
+        let mode = metadata.permissions().mode();
+        if mode & needed == needed {
+            return Ok(false);
+        }
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode | needed))?;
+        Ok(true)
+    })
+}
+
+/// Make the current user the owner; only succeeds for files the OS lets this user claim
+#[cfg(unix)]
+fn repair_ownership(root: &Path) -> RepairResult {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    for_each_entry(root, |path| {
+        if path.symlink_metadata()?.uid() == uid {
+            return Ok(false);
+        }
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        if unsafe { libc::lchown(c_path.as_ptr(), uid, gid) } != 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::PermissionDenied {
+                return Err(std::io::Error::new(
+                    error.kind(),
+                    "Taking ownership of another user's files needs administrator rights",
+                ));
+            }
+            return Err(error);
+        }
+        Ok(true)
+    })
+}
+
+/// Run one of the Windows ACL tools over the whole subtree
+#[cfg(target_os = "windows")]
+fn run_acl_tool(program: &str, path: &str, args: &[&str]) -> RepairResult {
+    use std::process::Command;
+
+    let mut result = RepairResult::default();
+    match Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => {
+            result.changed = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|l| l.starts_with("SUCCESS") || l.starts_with("processed file"))
+                .count() as u64;
+        }
+        Ok(output) => result.failed.push(FailedRepair {
+            path: path.to_string(),
+            error: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }),
+        Err(e) => result.failed.push(FailedRepair {
+            path: path.to_string(),
+            error: format!("Failed to run {}: {}", program, e),
+        }),
+    }
+    result
+}
+
+/// Reset ACLs to what the parent folder grants, which is the usual fix after a migration
+#[cfg(target_os = "windows")]
+fn repair_permissions(root: &Path) -> RepairResult {
+    let path = root.to_string_lossy();
+    let path: &str = &path;
+    run_acl_tool("icacls", path, &[path, "/reset", "/T", "/C"])
+}
+
+#[cfg(target_os = "windows")]
+fn repair_ownership(root: &Path) -> RepairResult {
+    let path = root.to_string_lossy();
+    let path: &str = &path;
+    let mut result = run_acl_tool("takeown", path, &["/F", path, "/R", "/D", "Y"]);
+    if result.failed.is_empty() {
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        let grant = format!("{}:(OI)(CI)F", user);
+        let granted = run_acl_tool(
+            "icacls",
+            path,
+            &[path, "/grant", grant.as_str(), "/T", "/C"],
+        );
+        result.failed.extend(granted.failed);
+    }
+    result
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn repair_permissions(_root: &Path) -> RepairResult {
+    RepairResult::default()
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn repair_ownership(_root: &Path) -> RepairResult {
+    RepairResult::default()
+}
+
+// Tauri commands
+
+/// Make the current user the owner of a folder or file in their home folder, recursively
+#[tauri::command]
+pub async fn take_ownership_command(path: String) -> Result<RepairResult, String> {
+    let root = PathBuf::from(&path);
+    check_root(&root)?;
+    tokio::task::spawn_blocking(move || repair_ownership(&root))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Restore the current user's read and write access below `path`, recursively
+#[tauri::command]
+pub async fn fix_permissions_command(path: String) -> Result<RepairResult, String> {
+    let root = PathBuf::from(&path);
+    check_root(&root)?;
+    tokio::task::spawn_blocking(move || repair_permissions(&root))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_repair_permissions() {
+        let home = std::env::temp_dir().join("test_ownership");
+        let _ = fs::remove_dir_all(&home);
+        fs::create_dir_all(home.join("migrated/nested")).unwrap();
+        fs::write(home.join("migrated/nested/notes.txt"), b"notes").unwrap();
+        let home = home.canonicalize().unwrap();
+
+        assert!(check_repairable(&home.join("migrated"), &home).is_ok());
+        assert!(check_repairable(&home, &home).is_err());
+        assert!(check_repairable(&home.join("missing"), &home).is_err());
+        assert!(check_repairable(&std::env::temp_dir(), &home).is_err());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            // Both a file and the directory holding it were left unreadable by a migration
+            let file = home.join("migrated/nested/notes.txt");
+            let dir = home.join("migrated/nested");
+            fs::set_permissions(&file, fs::Permissions::from_mode(0o000)).unwrap();
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o000)).unwrap();
+
+            let result = repair_permissions(&home.join("migrated"));
+            assert!(result.failed.is_empty());
+            assert_eq!(result.changed, 2);
+            let mode = fs::metadata(&dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o700, 0o700);
+            let mode = fs::metadata(&file).unwrap().permissions().mode();
+            assert_eq!(mode & 0o600, 0o600);
+        }
+        fs::remove_dir_all(&home).unwrap();
+    }
+}
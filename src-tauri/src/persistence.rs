@@ -0,0 +1,737 @@
+use crate::encryption;
+use crate::history;
+use crate::privacy::{anonymize_tree, Anonymizer};
+use crate::registry;
+use crate::settings::load_settings;
+use crate::sizes::{SizeFormatter, UnitSystem};
+use crate::space_guard::write_guarded;
+use crate::storage::is_network_filesystem;
+use crate::types::{FileNode, SCHEMA_VERSION};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Bundle identifier, used to namespace the app's data directory
+pub(crate) const APP_IDENTIFIER: &str = "com.dikrana.disk-analyser";
+
+/// A volume with less free space than this share is too full for the app's caches
+const NEARLY_FULL_PERCENT: u64 = 10;
+
+/// Created at the root of another local, fixed volume when caches have to move off a full one
+const FALLBACK_CACHE_DIR: &str = ".disk-analyser-cache";
+
+/// Every directory caches were ever moved to, so they are still known as the app's own
+/// after a restart
+const RELOCATED_DIRS_FILE: &str = "relocated_dirs.json";
+
+/// Kept in each scans directory so listing doesn't have to read every scan; not `.json`,
+/// so it is never mistaken for one
+const SCAN_INDEX_FILE: &str = "index.bin";
+
+static RELOCATED_DIRS: once_cell::sync::Lazy<RwLock<Vec<PathBuf>>> =
+    once_cell::sync::Lazy::new(|| {
+        let dirs = data_file(RELOCATED_DIRS_FILE).and_then(|file| read_json_or_default(&file));
+        RwLock::new(dirs.unwrap_or_default())
+    });
+
+/// A scan written to disk, wrapped with the schema version it was written with
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SavedScan {
+    pub schema_version: u32,
+    /// Unix timestamp (seconds) when the scan was saved
+    pub saved_at: i64,
+    /// Size convention the user had chosen when saving, so exported figures can be read back
+    #[serde(default)]
+    pub unit_system: UnitSystem,
+    /// Host name of the machine that scanned; left out of anonymized exports
+    #[serde(default)]
+    pub machine: Option<String>,
+    pub tree: FileNode,
+}
+
+/// Summary of a saved scan file, used for listing without loading whole trees
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SavedScanInfo {
+    pub file: PathBuf,
+    pub root: PathBuf,
+    pub saved_at: i64,
+    pub total_size: u64,
+}
+
+/// Directory where the app keeps its own data
+pub fn app_data_dir() -> Result<PathBuf, String> {
+    // Tests get a directory of their own so they never read or write the user's data
+    if cfg!(test) {
+        return Ok(std::env::temp_dir().join(format!(
+            "{}-test-{}",
+            APP_IDENTIFIER,
+            std::process::id()
+        )));
+    }
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| "Could not determine the app data directory".to_string())
+}
+
+/// This machine's host name, recorded in saved scans so imported ones can be told apart
+pub(crate) fn machine_name() -> Option<String> {
+    sysinfo::System::host_name()
+}
+
+/// Directory for disposable caches (webview data and the like)
+pub fn app_cache_dir() -> Result<PathBuf, String> {
+    dirs::cache_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| "Could not determine the app cache directory".to_string())
+}
+
+/// Free and total space of a mounted volume
+#[derive(Debug, Clone)]
+struct VolumeSpace {
+    mount_point: PathBuf,
+    available: u64,
+    total: u64,
+    /// Neither removable nor a network share, so caches written to it stay reachable
+    local: bool,
+}
+
+fn mounted_volumes() -> Vec<VolumeSpace> {
+    sysinfo::Disks::new_with_refreshed_list()
+        .list()
+        .iter()
+        .map(|disk| VolumeSpace {
+            mount_point: disk.mount_point().to_path_buf(),
+            available: disk.available_space(),
+            total: disk.total_space(),
+            local: !disk.is_removable()
+                && !is_network_filesystem(&disk.file_system().to_string_lossy().to_lowercase()),
+        })
+        .collect()
+}
+
+fn volume_of<'a>(path: &Path, volumes: &'a [VolumeSpace]) -> Option<&'a VolumeSpace> {
+    volumes
+        .iter()
+        .filter(|v| path.starts_with(&v.mount_point))
+        .max_by_key(|v| v.mount_point.as_os_str().len())
+}
+
+fn nearly_full(volume: &VolumeSpace) -> bool {
+    volume.total > 0 && volume.available * 100 < volume.total * NEARLY_FULL_PERCENT
+}
+
+/// `preferred`, unless it is on a nearly full volume that is being scanned; then the
+/// roomiest local volume that is neither scanned nor nearly full
+fn choose_cache_dir(
+    preferred: &Path,
+    scanned: &[&Path],
+    volumes: &[VolumeSpace],
+) -> Result<PathBuf, String> {
+    let scanned_volumes: Vec<&Path> = scanned
+        .iter()
+        .filter_map(|path| volume_of(path, volumes))
+        .map(|v| v.mount_point.as_path())
+        .collect();
+    let Some(home) = volume_of(preferred, volumes)
+        .filter(|v| nearly_full(v) && scanned_volumes.contains(&v.mount_point.as_path()))
+    else {
+        return Ok(preferred.to_path_buf());
+    };
+    volumes
+        .iter()
+        .filter(|v| {
+            v.local && !nearly_full(v) && !scanned_volumes.contains(&v.mount_point.as_path())
+        })
+        .max_by_key(|v| v.available)
+        .map(|v| v.mount_point.join(FALLBACK_CACHE_DIR))
+        .ok_or_else(|| {
+            format!(
+                "{} is nearly full and no other local volume has room for the cache; choose a cache location in settings",
+                home.mount_point.display()
+            )
+        })
+}
+
+/// Directory for cache or temporary data written while `scanned` is analysed
+///
+/// Stays at `preferred` unless that is on a nearly full volume being scanned, where a few
+/// more gigabytes of cache could fill it up; the cache location from settings is used then,
+/// or else another local, fixed volume with room. Wherever it goes is remembered, so it is
+/// treated as app data from then on.
+pub(crate) fn cache_dir_avoiding(preferred: &Path, scanned: &[&Path]) -> Result<PathBuf, String> {
+    let dir = match choose_cache_dir(preferred, scanned, &mounted_volumes()) {
+        Ok(dir) if dir == preferred => dir,
+        chosen => match load_settings()?.cache_location {
+            Some(location) => location,
+            None => chosen?,
+        },
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    if dir != preferred {
+        remember_relocated_dir(&dir)?;
+    }
+    Ok(dir)
+}
+
+fn remember_relocated_dir(dir: &Path) -> Result<(), String> {
+    let mut dirs = RELOCATED_DIRS.write().unwrap();
+    if !dirs.iter().any(|known| known == dir) {
+        dirs.push(dir.to_path_buf());
+        write_json(&data_file(RELOCATED_DIRS_FILE)?, &*dirs)?;
+    }
+    Ok(())
+}
+
+/// Directories outside the app data directory that caches and saved scans were moved to
+/// because the data volume was nearly full
+pub(crate) fn relocated_dirs() -> Vec<PathBuf> {
+    RELOCATED_DIRS.read().unwrap().clone()
+}
+
+/// Whether a path is the app's own data: the app data directory, a directory its caches
+/// were moved to, or anything inside them
+///
+/// Scanners skip it and cleanup suggestions never offer it: the hash cache and saved
+/// scans are written while a scan runs, and deleting them would only hurt the app.
+pub fn is_app_data_path(path: &Path) -> bool {
+    app_data_dir().is_ok_and(|dir| path.starts_with(dir))
+        || RELOCATED_DIRS
+            .read()
+            .unwrap()
+            .iter()
+            .any(|dir| path.starts_with(dir))
+}
+
+/// Path of a file directly inside the app data directory, creating the directory if needed
+pub(crate) fn data_file(name: &str) -> Result<PathBuf, String> {
+    let dir = app_data_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    Ok(dir.join(name))
+}
+
+/// Read a JSON file, treating a missing file as the default value
+pub(crate) fn read_json_or_default<T: DeserializeOwned + Default>(
+    file: &Path,
+) -> Result<T, String> {
+    match fs::read(file) {
+        Ok(bytes) => {
+            serde_json::from_slice(&bytes).map_err(|e| format!("Invalid {}: {}", file.display(), e))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(format!("Cannot read {}: {}", file.display(), e)),
+    }
+}
+
+/// Write a JSON file whole or not at all, so a crash never leaves it truncated
+pub(crate) fn write_json<T: Serialize>(file: &Path, value: &T) -> Result<(), String> {
+    let json = serde_json::to_vec(value).map_err(|e| format!("Cannot serialize: {}", e))?;
+    write_guarded(file, &json)
+}
+
+/// [`read_json_or_default`] for files that name the user's files, which may be encrypted
+pub(crate) fn read_sealed_json_or_default<T: DeserializeOwned + Default>(
+    file: &Path,
+) -> Result<T, String> {
+    match fs::read(file) {
+        Ok(bytes) => serde_json::from_slice(&encryption::open(bytes)?)
+            .map_err(|e| format!("Invalid {}: {}", file.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(format!("Cannot read {}: {}", file.display(), e)),
+    }
+}
+
+/// [`write_json`] for files that name the user's files, encrypted like saved scans when
+/// the user turned that on
+pub(crate) fn write_sealed_json<T: Serialize>(file: &Path, value: &T) -> Result<(), String> {
+    let json = serde_json::to_vec(value).map_err(|e| format!("Cannot serialize: {}", e))?;
+    write_guarded(file, &encryption::seal(json)?)
+}
+
+/// Directory holding saved scans
+fn scans_dir() -> Result<PathBuf, String> {
+    let dir = app_data_dir()?.join("scans");
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// Directories saved scans can be in: the usual one, and any the app itself moved them
+/// to because the data volume was nearly full
+///
+/// Only directories the app created count; a scans folder that merely turns up on some
+/// mounted volume is never trusted.
+fn saved_scan_dirs() -> Result<Vec<PathBuf>, String> {
+    let mut dirs = vec![scans_dir()?];
+    let relocated = relocated_dirs()
+        .into_iter()
+        .map(|dir| dir.join("scans"))
+        .filter(|dir| dir.is_dir() && !dirs.contains(dir))
+        .collect::<Vec<_>>();
+    dirs.extend(relocated);
+    Ok(dirs)
+}
+
+/// Save a scan tree with the current schema version
+///
+/// Saved next to the other app data, unless that would mean writing to the nearly full
+/// volume the tree came from.
+pub fn save_scan(tree: &FileNode) -> Result<PathBuf, String> {
+    let now = chrono::Utc::now();
+    let dir = cache_dir_avoiding(&app_data_dir()?, &[&tree.path])?.join("scans");
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let file = claim_scan_file(&dir, now.timestamp_millis())?;
+
+    let saved = SavedScan {
+        schema_version: SCHEMA_VERSION,
+        saved_at: now.timestamp(),
+        unit_system: SizeFormatter::current().unit_system,
+        machine: machine_name(),
+        tree: tree.clone(),
+    };
+    let written = serde_json::to_vec(&saved)
+        .map_err(|e| format!("Cannot serialize scan: {}", e))
+        .and_then(encryption::seal)
+        .and_then(|sealed| write_guarded(&file, &sealed));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&file);
+        return Err(e);
+    }
+    index_saved_scan(&dir, &file, &saved);
+
+    Ok(file)
+}
+
+/// Create an empty file for a scan saved at `saved_at_ms`, under a name no other save
+/// has taken, even one made in the same millisecond
+fn claim_scan_file(dir: &Path, saved_at_ms: i64) -> Result<PathBuf, String> {
+    for attempt in 0..100 {
+        let name = match attempt {
+            0 => format!("scan-{}.json", saved_at_ms),
+            n => format!("scan-{}-{}.json", saved_at_ms, n),
+        };
+        let file = dir.join(name);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&file)
+        {
+            Ok(_) => return Ok(file),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Cannot create {}: {}", file.display(), e)),
+        }
+    }
+    Err(format!("Cannot find a free file name in {}", dir.display()))
+}
+
+/// Write a scan to a file the user chose, for sharing; never encrypted
+///
+/// With `anonymize`, names and paths are replaced with pseudonyms so the file shows where
+/// the space goes without exposing personal file names.
+pub fn export_scan(tree: &FileNode, destination: &Path, anonymize: bool) -> Result<(), String> {
+    let (tree, machine) = if anonymize {
+        (
+            anonymize_tree(tree, &Anonymizer::new(fastrand::u64(..))),
+            None,
+        )
+    } else {
+        (tree.clone(), machine_name())
+    };
+    let exported = SavedScan {
+        schema_version: SCHEMA_VERSION,
+        saved_at: chrono::Utc::now().timestamp(),
+        unit_system: SizeFormatter::current().unit_system,
+        machine,
+        tree,
+    };
+    let json = serde_json::to_vec_pretty(&exported)
+        .map_err(|e| format!("Cannot serialize scan: {}", e))?;
+    write_guarded(destination, &json)
+}
+
+/// Load a saved scan, decrypting it and upgrading it from older schema versions if needed
+pub fn load_scan(file: &Path) -> Result<SavedScan, String> {
+    let bytes = fs::read(file).map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+    let bytes = encryption::open(bytes)?;
+    let value: Value =
+        serde_json::from_slice(&bytes).map_err(|e| format!("Invalid scan file: {}", e))?;
+
+    let migrated = migrate(value, saved_at_fallback(file))?;
+    serde_json::from_value(migrated).map_err(|e| format!("Invalid scan file: {}", e))
+}
+
+/// What the index of a scans directory records about one saved scan file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IndexedScan {
+    /// Length and mtime of the file when indexed; if either changed, the file is read again
+    len: u64,
+    modified_ns: i64,
+    root: PathBuf,
+    saved_at: i64,
+    total_size: u64,
+}
+
+/// Index entries by file name
+type ScanIndex = HashMap<String, IndexedScan>;
+
+fn modified_ns(metadata: &fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as i64)
+}
+
+/// The index of a scans directory; a missing or unreadable one is just empty, since it
+/// can always be rebuilt from the scans themselves
+fn read_scan_index(dir: &Path) -> ScanIndex {
+    fs::read(dir.join(SCAN_INDEX_FILE))
+        .ok()
+        .and_then(|bytes| encryption::open(bytes).ok())
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// Sealed like the scans, since it names the scanned roots
+fn write_scan_index(dir: &Path, index: &ScanIndex) -> Result<(), String> {
+    let json = serde_json::to_vec(index).map_err(|e| format!("Cannot serialize: {}", e))?;
+    write_guarded(&dir.join(SCAN_INDEX_FILE), &encryption::seal(json)?)
+}
+
+fn indexed(metadata: &fs::Metadata, saved: &SavedScan) -> IndexedScan {
+    IndexedScan {
+        len: metadata.len(),
+        modified_ns: modified_ns(metadata),
+        root: saved.tree.path.clone(),
+        saved_at: saved.saved_at,
+        total_size: saved.tree.size,
+    }
+}
+
+/// Add a scan just saved to its directory's index, so listing never has to read it
+///
+/// Failing here only costs one extra read when the scans are next listed.
+fn index_saved_scan(dir: &Path, file: &Path, saved: &SavedScan) {
+    let (Some(name), Ok(metadata)) = (file.file_name(), fs::metadata(file)) else {
+        return;
+    };
+    let mut index = read_scan_index(dir);
+    index.insert(
+        name.to_string_lossy().to_string(),
+        indexed(&metadata, saved),
+    );
+    let _ = write_scan_index(dir, &index);
+}
+
+/// Saved scans in `dir`, taken from its index where the file is unchanged since it was
+/// indexed; only new or changed files are read, and the index is brought up to date
+fn list_scans_in(dir: &Path) -> Result<Vec<SavedScanInfo>, String> {
+    let index = read_scan_index(dir);
+    let mut updated = ScanIndex::new();
+    let mut scans = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())?.flatten() {
+        let file = entry.path();
+        if file.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let (Some(name), Ok(metadata)) = (file.file_name(), entry.metadata()) else {
+            continue;
+        };
+        let name = name.to_string_lossy().to_string();
+        let known = index.get(&name).filter(|known| {
+            known.len == metadata.len() && known.modified_ns == modified_ns(&metadata)
+        });
+        let info = match known {
+            Some(known) => known.clone(),
+            None => match load_scan(&file) {
+                Ok(saved) => indexed(&metadata, &saved),
+                Err(_) => continue,
+            },
+        };
+        scans.push(SavedScanInfo {
+            file,
+            root: info.root.clone(),
+            saved_at: info.saved_at,
+            total_size: info.total_size,
+        });
+        updated.insert(name, info);
+    }
+    if updated != index {
+        // Only costs a re-read next time
+        let _ = write_scan_index(dir, &updated);
+    }
+    Ok(scans)
+}
+
+/// List saved scans, newest first
+pub fn list_saved_scans() -> Result<Vec<SavedScanInfo>, String> {
+    let mut scans = Vec::new();
+    for dir in saved_scan_dirs()? {
+        scans.extend(list_scans_in(&dir)?);
+    }
+    scans.sort_by_key(|s| std::cmp::Reverse(s.saved_at));
+    Ok(scans)
+}
+
+/// Upgrade a serialized scan to the current schema version, one version at a time
+fn migrate(mut value: Value, saved_at: i64) -> Result<Value, String> {
+    let mut version = schema_version_of(&value);
+
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "Scan was saved by a newer version of the app (schema {}, supported {})",
+            version, SCHEMA_VERSION
+        ));
+    }
+
+    while version < SCHEMA_VERSION {
+        value = match version {
+            0 => migrate_v0_to_v1(value, saved_at),
+            1 => migrate_v1_to_v2(value),
+            _ => return Err(format!("No migration from schema version {}", version)),
+        };
+        version += 1;
+    }
+
+    Ok(value)
+}
+
+/// Version 0 files are a bare `FileNode` with no envelope
+fn schema_version_of(value: &Value) -> u32 {
+    value
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .map(|v| v as u32)
+        .unwrap_or(0)
+}
+
+/// v0 -> v1: wrap the bare tree in a versioned envelope
+fn migrate_v0_to_v1(tree: Value, saved_at: i64) -> Value {
+    serde_json::json!({
+        "schema_version": 1,
+        "saved_at": saved_at,
+        "tree": tree,
+    })
+}
+
+/// v1 -> v2: nodes gained stable ids; number the saved tree in pre-order, from the same
+/// source as live nodes so the ids never clash with theirs
+fn migrate_v1_to_v2(mut value: Value) -> Value {
+    fn assign_ids(node: &mut Value) {
+        let Some(node) = node.as_object_mut() else {
+            return;
+        };
+        node.insert("id".to_string(), Value::from(registry::next_node_id()));
+        if let Some(children) = node.get_mut("children").and_then(Value::as_array_mut) {
+            for child in children {
+                assign_ids(child);
+            }
+        }
+    }
+
+    if let Some(envelope) = value.as_object_mut() {
+        if let Some(tree) = envelope.get_mut("tree") {
+            assign_ids(tree);
+        }
+        envelope.insert("schema_version".to_string(), Value::from(2));
+    }
+    value
+}
+
+/// Unversioned files carry no timestamp, so fall back to the file's mtime
+fn saved_at_fallback(file: &Path) -> i64 {
+    fs::metadata(file)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn save_scan_command(tree: FileNode) -> Result<PathBuf, String> {
+    tokio::task::spawn_blocking(move || {
+        let saved = save_scan(&tree)?;
+        history::auto_compact();
+        Ok(saved)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Export a scan for sharing; `anonymize` defaults to the privacy setting
+#[tauri::command]
+pub async fn export_scan_command(
+    tree: FileNode,
+    destination: String,
+    anonymize: Option<bool>,
+) -> Result<(), String> {
+    let anonymize = match anonymize {
+        Some(anonymize) => anonymize,
+        None => crate::settings::load_settings()?.anonymize_exports,
+    };
+    export_scan(&tree, Path::new(&destination), anonymize)
+}
+
+#[tauri::command]
+pub async fn load_scan_command(file: String) -> Result<SavedScan, String> {
+    load_scan(Path::new(&file))
+}
+
+#[tauri::command]
+pub async fn list_saved_scans_command() -> Result<Vec<SavedScanInfo>, String> {
+    list_saved_scans()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FileType;
+    use std::time::SystemTime;
+
+    fn sample_tree() -> FileNode {
+        FileNode {
+            id: 0,
+            name: "root".to_string(),
+            path: PathBuf::from("/root"),
+            size: 42,
+            is_directory: true,
+            children: vec![],
+            file_type: FileType::Other,
+            modified: SystemTime::UNIX_EPOCH,
+            is_symlink: false,
+            symlink_target: None,
+            synthetic: None,
+            pct_of_parent: None,
+            pct_of_volume: None,
+            is_partial: false,
+        }
+    }
+
+    #[test]
+    fn test_cache_moves_off_a_full_scanned_volume() {
+        let volume = |mount: &str, available: u64| VolumeSpace {
+            mount_point: PathBuf::from(mount),
+            available,
+            total: 1000,
+            local: true,
+        };
+        // Roomiest of all, but a USB stick that may be gone next time
+        let usb = VolumeSpace {
+            local: false,
+            ..volume("/media/usb", 990)
+        };
+        let volumes = [
+            volume("/", 20),
+            volume("/data", 900),
+            volume("/backup", 500),
+            usb,
+        ];
+        let preferred = Path::new("/home/alice/.cache");
+
+        // Full, but not being scanned: left alone
+        assert_eq!(
+            choose_cache_dir(preferred, &[Path::new("/data/photos")], &volumes).unwrap(),
+            preferred
+        );
+        assert_eq!(
+            choose_cache_dir(preferred, &[Path::new("/home")], &volumes).unwrap(),
+            Path::new("/data").join(FALLBACK_CACHE_DIR)
+        );
+        assert_eq!(
+            choose_cache_dir(preferred, &[Path::new("/"), Path::new("/data")], &volumes).unwrap(),
+            Path::new("/backup").join(FALLBACK_CACHE_DIR)
+        );
+        let all = [Path::new("/"), Path::new("/data"), Path::new("/backup")];
+        assert!(choose_cache_dir(preferred, &all, &volumes).is_err());
+    }
+
+    #[test]
+    fn test_migrate_unversioned_tree() {
+        let v0 = serde_json::to_value(sample_tree()).unwrap();
+        let migrated = migrate(v0.clone(), 1234).unwrap();
+
+        let saved: SavedScan = serde_json::from_value(migrated).unwrap();
+        assert_eq!(saved.schema_version, SCHEMA_VERSION);
+        assert_eq!(saved.saved_at, 1234);
+        assert_eq!(saved.tree.size, 42);
+        assert_ne!(saved.tree.id, 0);
+
+        // Ids come from the live source, so a second migration never repeats them
+        let again: SavedScan = serde_json::from_value(migrate(v0, 1234).unwrap()).unwrap();
+        assert_ne!(again.tree.id, saved.tree.id);
+    }
+
+    #[test]
+    fn test_current_version_unchanged() {
+        let saved = SavedScan {
+            schema_version: SCHEMA_VERSION,
+            saved_at: 99,
+            unit_system: UnitSystem::Binary,
+            machine: Some("laptop".to_string()),
+            tree: sample_tree(),
+        };
+        let value = serde_json::to_value(&saved).unwrap();
+        assert_eq!(migrate(value.clone(), 0).unwrap(), value);
+    }
+
+    #[test]
+    fn test_json_file_roundtrip() {
+        let file = std::env::temp_dir().join("test_persistence_roundtrip.json");
+        let missing: Vec<u64> = read_json_or_default(&file.with_extension("missing")).unwrap();
+        assert!(missing.is_empty());
+
+        write_json(&file, &vec![1u64, 2, 3]).unwrap();
+        let loaded: Vec<u64> = read_json_or_default(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(loaded, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_saves_in_the_same_second_are_all_kept_and_indexed() {
+        let first = save_scan(&sample_tree()).unwrap();
+        let second = save_scan(&sample_tree()).unwrap();
+        assert_ne!(first, second);
+        let dir = first.parent().unwrap();
+        assert!(dir.join(SCAN_INDEX_FILE).exists());
+
+        // A scan the index doesn't know yet is read once and then indexed
+        let copied = dir.join("scan-copied.json");
+        fs::copy(&first, &copied).unwrap();
+        let listed = list_scans_in(dir).unwrap();
+        let files: Vec<&Path> = listed.iter().map(|s| s.file.as_path()).collect();
+        for file in [&first, &second, &copied] {
+            assert!(files.contains(&file.as_path()));
+        }
+        assert!(read_scan_index(dir).contains_key("scan-copied.json"));
+        assert!(listed
+            .iter()
+            .all(|s| s.root == Path::new("/root") && s.total_size == 42));
+
+        // Removed files drop out of the index
+        fs::remove_file(&copied).unwrap();
+        list_scans_in(dir).unwrap();
+        assert!(!read_scan_index(dir).contains_key("scan-copied.json"));
+        fs::remove_file(&first).unwrap();
+        fs::remove_file(&second).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_newer_schema() {
+        let value = serde_json::json!({ "schema_version": SCHEMA_VERSION + 1 });
+        assert!(migrate(value, 0).is_err());
+    }
+
+    #[test]
+    fn test_app_data_path() {
+        let dir = app_data_dir().unwrap();
+        assert!(is_app_data_path(&dir.join("hash_cache.msgpack")));
+        assert!(!is_app_data_path(dir.parent().unwrap()));
+    }
+}
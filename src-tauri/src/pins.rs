@@ -0,0 +1,197 @@
+use crate::persistence::{data_file, read_sealed_json_or_default, write_sealed_json};
+use crate::registry::DiscoveredNode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const PINS_FILE: &str = "pins.json";
+
+/// Sizes kept per pinned path; older ones are dropped
+const MAX_SAMPLES: usize = 30;
+
+/// A pinned path's size as one scan saw it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SizeSample {
+    /// Unix timestamp (seconds) when the scan was retained
+    pub at: i64,
+    pub size: u64,
+}
+
+/// A path the user keeps an eye on, with the sizes scans have seen for it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PinnedPath {
+    pub path: PathBuf,
+    /// Unix timestamp (seconds) when the path was pinned
+    pub pinned_at: i64,
+    /// Oldest first
+    pub samples: Vec<SizeSample>,
+}
+
+/// A pinned path's latest size and how it moved since the scan before
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PinnedPathStatus {
+    pub path: PathBuf,
+    pub pinned_at: i64,
+    /// `None` until a scan has covered the path
+    pub size: Option<u64>,
+    pub last_scanned_at: Option<i64>,
+    /// Bytes gained (negative when shrunk) since the previous sample
+    pub trend: Option<i64>,
+    /// Oldest first, for a sparkline
+    pub samples: Vec<SizeSample>,
+}
+
+fn load_pins() -> Result<Vec<PinnedPath>, String> {
+    read_sealed_json_or_default(&data_file(PINS_FILE)?)
+}
+
+fn save_pins(pins: &[PinnedPath]) -> Result<(), String> {
+    write_sealed_json(&data_file(PINS_FILE)?, &pins)
+}
+
+/// Paths currently pinned
+pub(crate) fn pinned_paths() -> Result<Vec<PathBuf>, String> {
+    Ok(load_pins()?.into_iter().map(|pin| pin.path).collect())
+}
+
+/// Pin exactly `paths`, keeping the samples of paths that were already pinned
+pub(crate) fn replace_pins(paths: Vec<PathBuf>) -> Result<(), String> {
+    let mut existing = load_pins()?;
+    let now = chrono::Utc::now().timestamp();
+    let mut pins: Vec<PinnedPath> = paths
+        .into_iter()
+        .map(
+            |path| match existing.iter().position(|pin| pin.path == path) {
+                Some(i) => existing.swap_remove(i),
+                None => PinnedPath {
+                    path,
+                    pinned_at: now,
+                    samples: Vec::new(),
+                },
+            },
+        )
+        .collect();
+    pins.sort_by(|a, b| a.path.cmp(&b.path));
+    pins.dedup_by(|a, b| a.path == b.path);
+    save_pins(&pins)
+}
+
+fn status(pin: &PinnedPath) -> PinnedPathStatus {
+    let last = pin.samples.last();
+    let previous = pin.samples.len().checked_sub(2).map(|i| pin.samples[i]);
+    PinnedPathStatus {
+        path: pin.path.clone(),
+        pinned_at: pin.pinned_at,
+        size: last.map(|s| s.size),
+        last_scanned_at: last.map(|s| s.at),
+        trend: last
+            .zip(previous)
+            .map(|(last, previous)| last.size as i64 - previous.size as i64),
+        samples: pin.samples.clone(),
+    }
+}
+
+/// Add a sample to every pin the scan covered; returns whether any pin changed
+fn record(pins: &mut [PinnedPath], nodes: &HashMap<PathBuf, DiscoveredNode>, at: i64) -> bool {
+    let mut changed = false;
+    for pin in pins.iter_mut() {
+        let Some(node) = nodes.get(&pin.path) else {
+            continue;
+        };
+        pin.samples.push(SizeSample {
+            at,
+            size: node.size,
+        });
+        if pin.samples.len() > MAX_SAMPLES {
+            pin.samples.remove(0);
+        }
+        changed = true;
+    }
+    changed
+}
+
+/// Record the sizes of pinned paths a newly retained scan covered
+///
+/// Called once directory totals are aggregated; failures only cost a sample.
+pub(crate) fn record_scan(nodes: &HashMap<PathBuf, DiscoveredNode>) {
+    let Ok(mut pins) = load_pins() else {
+        return;
+    };
+    if record(&mut pins, nodes, chrono::Utc::now().timestamp()) {
+        let _ = save_pins(&pins);
+    }
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn pin_path_command(path: String) -> Result<Vec<PinnedPathStatus>, String> {
+    let mut pins = load_pins()?;
+    let path = PathBuf::from(path);
+
+    if !pins.iter().any(|pin| pin.path == path) {
+        pins.push(PinnedPath {
+            path,
+            pinned_at: chrono::Utc::now().timestamp(),
+            samples: Vec::new(),
+        });
+        pins.sort_by(|a, b| a.path.cmp(&b.path));
+        save_pins(&pins)?;
+    }
+
+    Ok(pins.iter().map(status).collect())
+}
+
+#[tauri::command]
+pub async fn unpin_path_command(path: String) -> Result<Vec<PinnedPathStatus>, String> {
+    let mut pins = load_pins()?;
+    let path = PathBuf::from(path);
+
+    pins.retain(|pin| pin.path != path);
+    save_pins(&pins)?;
+    Ok(pins.iter().map(status).collect())
+}
+
+/// Pinned paths with the size the latest scan covering each saw, and the change since the
+/// one before
+#[tauri::command]
+pub async fn get_pinned_paths_command() -> Result<Vec<PinnedPathStatus>, String> {
+    Ok(load_pins()?.iter().map(status).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::test_node;
+
+    #[test]
+    fn test_scans_add_samples_to_covered_pins() {
+        let mut pins = vec![
+            PinnedPath {
+                path: PathBuf::from("/home/u/.cache"),
+                pinned_at: 0,
+                samples: vec![SizeSample { at: 10, size: 500 }],
+            },
+            PinnedPath {
+                path: PathBuf::from("/mnt/other"),
+                pinned_at: 0,
+                samples: Vec::new(),
+            },
+        ];
+        let nodes: HashMap<PathBuf, DiscoveredNode> = [test_node("/home/u/.cache", 800, true)]
+            .into_iter()
+            .map(|n| (n.path.clone(), n))
+            .collect();
+
+        assert!(record(&mut pins, &nodes, 20));
+        let cache = status(&pins[0]);
+        assert_eq!(cache.size, Some(800));
+        assert_eq!(cache.last_scanned_at, Some(20));
+        assert_eq!(cache.trend, Some(300));
+
+        let other = status(&pins[1]);
+        assert_eq!((other.size, other.trend), (None, None));
+        assert!(!record(&mut pins[1..], &nodes, 30));
+    }
+}
@@ -0,0 +1,471 @@
+use crate::confidence::{path_confidence, Confidence};
+use crate::never_suggest::{self, is_never_suggested, NeverSuggestEntry};
+use crate::persistence::{
+    data_file, is_app_data_path, read_sealed_json_or_default, write_sealed_json,
+};
+use crate::registry;
+use crate::safety::{self, DeletionResult, FailedDeletion};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Window};
+use tokio::sync::Mutex;
+
+const POLICIES_FILE: &str = "policies.json";
+
+/// Dry-run reports older than this must be regenerated before they can be applied
+const REPORT_TTL_SECS: i64 = 60 * 60;
+
+/// How often the scheduler looks for policies that are due
+const SCHEDULER_TICK: Duration = Duration::from_secs(15 * 60);
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// What a policy matches
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PolicyRule {
+    /// Files under the root last modified at least `min_age_days` ago and at least `min_size` bytes
+    OlderThan { min_age_days: u64, min_size: u64 },
+    /// Files or folders whose name ends with `suffix`, keeping only the newest `keep`
+    KeepLatest { suffix: String, keep: usize },
+}
+
+/// A user-defined cleanup rule scoped to one folder
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CleanupPolicy {
+    /// Assigned when the policy is first saved (send 0 for a new policy)
+    pub id: u64,
+    pub name: String,
+    pub root: PathBuf,
+    pub rule: PolicyRule,
+    /// Produce a dry-run report every this many hours; `None` means on demand only
+    pub schedule_hours: Option<u64>,
+    pub enabled: bool,
+    pub last_evaluated_at: Option<i64>,
+    /// Leave out matches less safe than this, so unattended runs only touch e.g. caches
+    #[serde(default)]
+    pub min_confidence: Option<Confidence>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+struct PolicyStore {
+    next_id: u64,
+    policies: Vec<CleanupPolicy>,
+}
+
+/// An entry a policy would delete
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PolicyMatch {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Unix timestamp (seconds) of the last modification
+    pub modified: i64,
+    /// How safe the match is to delete, judged by where it lives
+    pub confidence: Confidence,
+}
+
+/// Dry-run result; applying a policy always goes through one of these
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PolicyReport {
+    pub report_id: u64,
+    pub policy_id: u64,
+    pub generated_at: i64,
+    pub matches: Vec<PolicyMatch>,
+    pub total_bytes: u64,
+    /// Matches left out because they are on the never-suggest list
+    pub skipped_never_suggest: u64,
+    /// Matches left out because they are below the policy's `min_confidence`
+    pub skipped_low_confidence: u64,
+}
+
+/// Dry-run reports waiting for an apply, keyed by report id
+static REPORTS: once_cell::sync::Lazy<Arc<Mutex<HashMap<u64, PolicyReport>>>> =
+    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+static NEXT_REPORT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Held while the store is read, changed and written back, so concurrent changes (a
+/// scheduled dry run and an edit, say) never undo each other
+static STORE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+fn load_store() -> Result<PolicyStore, String> {
+    read_sealed_json_or_default(&data_file(POLICIES_FILE)?)
+}
+
+fn save_store(store: &PolicyStore) -> Result<(), String> {
+    write_sealed_json(&data_file(POLICIES_FILE)?, store)
+}
+
+/// Apply `change` to the stored policies and save them, under the store lock
+fn update_store<T>(
+    change: impl FnOnce(&mut PolicyStore) -> Result<T, String>,
+) -> Result<T, String> {
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut store = load_store()?;
+    let changed = change(&mut store)?;
+    save_store(&store)?;
+    Ok(changed)
+}
+
+/// Saved cleanup policies
+pub(crate) fn load_policies() -> Result<Vec<CleanupPolicy>, String> {
+    Ok(load_store()?.policies)
+}
+
+/// Replace every saved policy, numbering them afresh and forgetting when they last ran
+pub(crate) fn replace_policies(policies: Vec<CleanupPolicy>) -> Result<(), String> {
+    update_store(|store| {
+        *store = PolicyStore::default();
+        for mut policy in policies {
+            store.next_id += 1;
+            policy.id = store.next_id;
+            policy.last_evaluated_at = None;
+            store.policies.push(policy);
+        }
+        Ok(())
+    })
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn to_match(path: &Path, metadata: &std::fs::Metadata) -> PolicyMatch {
+    let size = if metadata.is_dir() {
+        safety::calculate_path_size(path).unwrap_or(0)
+    } else {
+        metadata.len()
+    };
+    PolicyMatch {
+        path: path.to_path_buf(),
+        size,
+        modified: metadata.modified().map(unix_seconds).unwrap_or(0),
+        confidence: path_confidence(path),
+    }
+}
+
+/// Walk a policy's root and collect what its rule matches, minus never-suggest paths
+fn evaluate(
+    policy: &CleanupPolicy,
+    now: SystemTime,
+    never_suggest: &[NeverSuggestEntry],
+) -> (Vec<PolicyMatch>, u64) {
+    let mut matches = Vec::new();
+
+    match &policy.rule {
+        PolicyRule::OlderThan {
+            min_age_days,
+            min_size,
+        } => {
+            // An age too large to express can't have been reached by anything
+            let Some(cutoff) = min_age_days
+                .checked_mul(SECONDS_PER_DAY)
+                .and_then(|secs| now.checked_sub(Duration::from_secs(secs)))
+            else {
+                return (matches, 0);
+            };
+            for entry in walkdir::WalkDir::new(&policy.root)
+                .into_iter()
+                .filter_entry(|e| !is_app_data_path(e.path()))
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+            {
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                let old_enough = metadata.modified().is_ok_and(|m| m <= cutoff);
+                if old_enough && metadata.len() >= *min_size {
+                    matches.push(to_match(entry.path(), &metadata));
+                }
+            }
+        }
+        PolicyRule::KeepLatest { suffix, keep } => {
+            let mut candidates = Vec::new();
+            let mut walker = walkdir::WalkDir::new(&policy.root)
+                .min_depth(1)
+                .into_iter()
+                .filter_entry(|e| !is_app_data_path(e.path()));
+            while let Some(Ok(entry)) = walker.next() {
+                if !entry
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(suffix.as_str())
+                {
+                    continue;
+                }
+                // Bundles like .xcarchive are folders; match them whole
+                if entry.file_type().is_dir() {
+                    walker.skip_current_dir();
+                }
+                if let Ok(metadata) = entry.metadata() {
+                    candidates.push(to_match(entry.path(), &metadata));
+                }
+            }
+            candidates.sort_by_key(|m| std::cmp::Reverse(m.modified));
+            matches.extend(candidates.into_iter().skip(*keep));
+        }
+    }
+
+    let before = matches.len();
+    matches.retain(|m| !is_never_suggested(never_suggest, &m.path));
+    let skipped = (before - matches.len()) as u64;
+
+    matches.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    (matches, skipped)
+}
+
+/// Evaluate a policy without deleting anything and park the report for a later apply
+async fn dry_run(policy_id: u64) -> Result<PolicyReport, String> {
+    let policy = load_store()?
+        .policies
+        .into_iter()
+        .find(|p| p.id == policy_id)
+        .ok_or_else(|| format!("Unknown policy: {}", policy_id))?;
+
+    let never_suggest = never_suggest::load_never_suggest()?;
+    let evaluated = policy.clone();
+    let (mut matches, skipped_never_suggest) = tokio::task::spawn_blocking(move || {
+        evaluate(&evaluated, SystemTime::now(), &never_suggest)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let before = matches.len();
+    if let Some(min_confidence) = policy.min_confidence {
+        matches.retain(|m| m.confidence >= min_confidence);
+    }
+    let skipped_low_confidence = (before - matches.len()) as u64;
+
+    // The walk can take a while; record the run on the store as it is now, not as it was
+    // read before, so edits made meanwhile stay
+    let generated_at = chrono::Utc::now().timestamp();
+    update_store(|store| {
+        if let Some(policy) = store.policies.iter_mut().find(|p| p.id == policy_id) {
+            policy.last_evaluated_at = Some(generated_at);
+        }
+        Ok(())
+    })?;
+
+    let report = PolicyReport {
+        report_id: NEXT_REPORT_ID.fetch_add(1, Ordering::Relaxed),
+        policy_id,
+        generated_at,
+        total_bytes: matches.iter().map(|m| m.size).sum(),
+        matches,
+        skipped_never_suggest,
+        skipped_low_confidence,
+    };
+
+    let mut reports = REPORTS.lock().await;
+    reports.retain(|_, r| generated_at - r.generated_at <= REPORT_TTL_SECS);
+    reports.insert(report.report_id, report.clone());
+    Ok(report)
+}
+
+/// Whether an entry still looks the way it did in the dry run
+fn unchanged_since_report(entry: &PolicyMatch) -> bool {
+    std::fs::symlink_metadata(&entry.path)
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .is_some_and(|m| unix_seconds(m) == entry.modified)
+}
+
+/// Periodically produce dry-run reports for scheduled policies (never deletes anything)
+pub fn start_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SCHEDULER_TICK).await;
+
+            let Ok(store) = load_store() else {
+                continue;
+            };
+            let now = chrono::Utc::now().timestamp();
+            let due = store.policies.iter().filter(|p| {
+                p.enabled
+                    && p.schedule_hours.is_some_and(|hours| {
+                        p.last_evaluated_at
+                            .is_none_or(|last| now - last >= hours as i64 * 3600)
+                    })
+            });
+
+            for policy in due {
+                if let Ok(report) = dry_run(policy.id).await {
+                    let _ = app.emit("policy-report", &report);
+                }
+            }
+        }
+    });
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn list_policies_command() -> Result<Vec<CleanupPolicy>, String> {
+    Ok(load_store()?.policies)
+}
+
+/// Create (id 0) or update a policy
+#[tauri::command]
+pub async fn save_policy_command(mut policy: CleanupPolicy) -> Result<CleanupPolicy, String> {
+    update_store(|store| {
+        if policy.id == 0 {
+            store.next_id += 1;
+            policy.id = store.next_id;
+            store.policies.push(policy.clone());
+        } else {
+            let existing = store
+                .policies
+                .iter_mut()
+                .find(|p| p.id == policy.id)
+                .ok_or_else(|| format!("Unknown policy: {}", policy.id))?;
+            *existing = policy.clone();
+        }
+        Ok(policy)
+    })
+}
+
+#[tauri::command]
+pub async fn delete_policy_command(id: u64) -> Result<(), String> {
+    update_store(|store| {
+        store.policies.retain(|p| p.id != id);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn dry_run_policy_command(id: u64) -> Result<PolicyReport, String> {
+    dry_run(id).await
+}
+
+/// Delete what a dry-run report listed, through the usual safety checks
+///
+/// Entries modified since the dry run are skipped rather than deleted.
+#[tauri::command]
+pub async fn apply_policy_report_command(
+    report_id: u64,
+    window: Window,
+) -> Result<DeletionResult, String> {
+    let report = REPORTS
+        .lock()
+        .await
+        .remove(&report_id)
+        .ok_or_else(|| format!("Unknown report {}; run a dry run first", report_id))?;
+
+    if chrono::Utc::now().timestamp() - report.generated_at > REPORT_TTL_SECS {
+        return Err("The dry-run report is out of date; run it again".to_string());
+    }
+
+    let (unchanged, changed): (Vec<PolicyMatch>, Vec<PolicyMatch>) =
+        report.matches.into_iter().partition(unchanged_since_report);
+
+    let mut result =
+        safety::delete_items(unchanged.into_iter().map(|m| m.path).collect(), false).await?;
+    result
+        .failed
+        .extend(changed.into_iter().map(|m| FailedDeletion {
+            path: m.path.to_string_lossy().to_string(),
+            error: "Changed since the dry run".to_string(),
+        }));
+
+    for event in registry::remove_deleted_paths(&result.deleted).await {
+        let _ = window.emit("streaming-scan-event", &event);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write_aged(path: &Path, bytes: usize, age_days: u64) {
+        fs::write(path, vec![0u8; bytes]).unwrap();
+        let modified = SystemTime::now() - Duration::from_secs(age_days * SECONDS_PER_DAY);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    fn policy(root: &Path, rule: PolicyRule) -> CleanupPolicy {
+        CleanupPolicy {
+            id: 1,
+            name: "test".to_string(),
+            root: root.to_path_buf(),
+            rule,
+            schedule_hours: None,
+            enabled: true,
+            last_evaluated_at: None,
+            min_confidence: None,
+        }
+    }
+
+    #[test]
+    fn test_older_than_rule() {
+        let root = std::env::temp_dir().join("test_policies_older");
+        fs::create_dir_all(root.join("keep")).unwrap();
+        write_aged(&root.join("old-big.iso"), 2000, 120);
+        write_aged(&root.join("old-small.txt"), 10, 120);
+        write_aged(&root.join("new-big.iso"), 2000, 5);
+        write_aged(&root.join("keep/old-big.iso"), 2000, 120);
+
+        let never_suggest = vec![NeverSuggestEntry {
+            path: root.join("keep"),
+            note: None,
+            added_at: 0,
+        }];
+        let rule = PolicyRule::OlderThan {
+            min_age_days: 90,
+            min_size: 1000,
+        };
+        let (matches, skipped) = evaluate(&policy(&root, rule), SystemTime::now(), &never_suggest);
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, root.join("old-big.iso"));
+        assert_eq!(skipped, 1);
+
+        let rule = PolicyRule::OlderThan {
+            min_age_days: u64::MAX / 2,
+            min_size: 0,
+        };
+        let (matches, _) = evaluate(&policy(&root, rule), SystemTime::now(), &never_suggest);
+        fs::remove_dir_all(&root).unwrap();
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_keep_latest_rule() {
+        let root = std::env::temp_dir().join("test_policies_latest");
+        for (name, age) in [("a", 30), ("b", 20), ("c", 10)] {
+            let archive = root
+                .join(format!("2024-0{}", age / 10))
+                .join(format!("{}.xcarchive", name));
+            fs::create_dir_all(&archive).unwrap();
+            write_aged(&archive.join("Info.plist"), 100, age);
+            fs::File::open(&archive)
+                .unwrap()
+                .set_modified(SystemTime::now() - Duration::from_secs(age * SECONDS_PER_DAY))
+                .unwrap();
+        }
+
+        let rule = PolicyRule::KeepLatest {
+            suffix: ".xcarchive".to_string(),
+            keep: 2,
+        };
+        let (matches, _) = evaluate(&policy(&root, rule), SystemTime::now(), &[]);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(matches.len(), 1);
+        assert!(matches[0].path.ends_with("a.xcarchive"));
+        assert_eq!(matches[0].size, 100);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::types::FileNode;
+use std::path::{Component, Path, PathBuf};
+
+/// Replaces file and directory names with pseudonyms, keeping extensions
+///
+/// Pseudonyms are keyed with a per-export salt: the same name maps to the same pseudonym
+/// within one export, so repeated names stay visible, but common names can't be looked up.
+pub struct Anonymizer {
+    salt: [u8; 16],
+}
+
+impl Anonymizer {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let mut salt = [0u8; 16];
+        rng.fill(&mut salt);
+        Self { salt }
+    }
+
+    /// Pseudonym for one name: "dir-…" or "file-…", followed by the original extension
+    pub fn name(&self, name: &str, is_directory: bool) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&self.salt);
+        hasher.update(name.as_bytes());
+        let hash = hasher.finalize().to_hex();
+        let prefix = if is_directory { "dir" } else { "file" };
+        match Path::new(name).extension() {
+            Some(ext) => format!("{}-{}.{}", prefix, &hash[..8], ext.to_string_lossy()),
+            None => format!("{}-{}", prefix, &hash[..8]),
+        }
+    }
+
+    /// Anonymize every named component of a path, keeping the root and drive prefix
+    pub fn path(&self, path: &Path, is_directory: bool) -> PathBuf {
+        let components: Vec<Component> = path.components().collect();
+        let last = components.len().saturating_sub(1);
+        components
+            .iter()
+            .enumerate()
+            .map(|(i, component)| match component {
+                Component::Normal(name) => {
+                    PathBuf::from(self.name(&name.to_string_lossy(), i < last || is_directory))
+                }
+                other => PathBuf::from(other.as_os_str()),
+            })
+            .collect()
+    }
+}
+
+/// A copy of the tree with every name and path anonymized; sizes, types and shape are kept
+pub fn anonymize_tree(tree: &FileNode, anonymizer: &Anonymizer) -> FileNode {
+    FileNode {
+        id: tree.id,
+        name: anonymizer.name(&tree.name, tree.is_directory),
+        path: anonymizer.path(&tree.path, tree.is_directory),
+        children: tree
+            .children
+            .iter()
+            .map(|child| anonymize_tree(child, anonymizer))
+            .collect(),
+        size: tree.size,
+        is_directory: tree.is_directory,
+        file_type: tree.file_type.clone(),
+        modified: tree.modified,
+        is_symlink: tree.is_symlink,
+        symlink_target: tree
+            .symlink_target
+            .as_deref()
+            .map(|target| anonymizer.path(target, false)),
+        synthetic: tree.synthetic.clone(),
+        pct_of_parent: tree.pct_of_parent,
+        pct_of_volume: tree.pct_of_volume,
+        is_partial: tree.is_partial,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FileType;
+    use std::time::SystemTime;
+
+    fn node(path: &str, size: u64, children: Vec<FileNode>) -> FileNode {
+        let path = PathBuf::from(path);
+        FileNode {
+            id: 0,
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            is_directory: !children.is_empty(),
+            path,
+            size,
+            children,
+            file_type: FileType::Other,
+            modified: SystemTime::UNIX_EPOCH,
+            is_symlink: false,
+            symlink_target: None,
+            synthetic: None,
+            pct_of_parent: None,
+            pct_of_volume: None,
+            is_partial: false,
+        }
+    }
+
+    #[test]
+    fn test_anonymize_tree() {
+        let tree = node(
+            "/Users/alice",
+            300,
+            vec![
+                node("/Users/alice/Divorce papers.pdf", 100, vec![]),
+                node(
+                    "/Users/alice/Photos",
+                    200,
+                    vec![node("/Users/alice/Photos/Divorce papers.pdf", 200, vec![])],
+                ),
+            ],
+        );
+
+        let anonymized = anonymize_tree(&tree, &Anonymizer::new(1));
+        let text = serde_json::to_string(&anonymized).unwrap();
+
+        assert!(!text.contains("alice") && !text.contains("Divorce") && !text.contains("Photos"));
+        let (file, photos) = (&anonymized.children[0], &anonymized.children[1]);
+        assert!(file.name.starts_with("file-") && file.name.ends_with(".pdf"));
+        assert!(photos.name.starts_with("dir-"));
+        assert_eq!(file.size, 100);
+        assert_eq!(photos.children[0].name, file.name);
+        assert_eq!(
+            photos.children[0].path.parent(),
+            Some(photos.path.as_path())
+        );
+        assert!(anonymized.path.starts_with("/"));
+        assert_ne!(Anonymizer::new(2).name("Photos", true), photos.name);
+    }
+}
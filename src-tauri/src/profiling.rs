@@ -0,0 +1,115 @@
+use crate::space_guard::write_guarded;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Where scan time goes; each phase is one frame under `scan` in the exported stacks
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Phase {
+    Metadata,
+    ReadDir,
+    Emit,
+    TreeBuild,
+}
+
+const PHASES: [Phase; 4] = [
+    Phase::Metadata,
+    Phase::ReadDir,
+    Phase::Emit,
+    Phase::TreeBuild,
+];
+
+impl Phase {
+    /// Call stack the phase is reported under, outermost frame first
+    fn stack(self) -> &'static str {
+        match self {
+            Phase::Metadata => "scan;walk;metadata",
+            Phase::ReadDir => "scan;walk;read_dir",
+            Phase::Emit => "scan;emit_events",
+            Phase::TreeBuild => "scan;build_tree",
+        }
+    }
+}
+
+/// Off unless turned on from a debug build; spans cost nothing while it is off
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Microseconds spent per phase, indexed like `PHASES`, summed over concurrent tasks
+static MICROS: [AtomicU64; 4] = [const { AtomicU64::new(0) }; 4];
+
+/// Adds the time until it is dropped to its phase
+pub(crate) struct Span {
+    phase: Phase,
+    started: Option<Instant>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(started) = self.started {
+            MICROS[self.phase as usize]
+                .fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Time `phase` until the returned span is dropped
+pub(crate) fn span(phase: Phase) -> Span {
+    Span {
+        phase,
+        started: ENABLED.load(Ordering::Relaxed).then(Instant::now),
+    }
+}
+
+/// Forget the previous scan's profile
+pub(crate) fn reset() {
+    for micros in &MICROS {
+        micros.store(0, Ordering::Relaxed);
+    }
+}
+
+/// The profile in folded-stack format (`frame;frame microseconds` per line), which
+/// flamegraph.pl, inferno and speedscope all read
+fn folded(micros: &[u64]) -> String {
+    PHASES
+        .iter()
+        .zip(micros)
+        .filter(|(_, &micros)| micros > 0)
+        .map(|(phase, micros)| format!("{} {}\n", phase.stack(), micros))
+        .collect()
+}
+
+// Tauri commands
+
+/// Turn scan profiling on or off; only debug builds can profile
+#[tauri::command]
+pub async fn set_scan_profiling_command(enabled: bool) -> Result<(), String> {
+    if enabled && !cfg!(debug_assertions) {
+        return Err("Scan profiling is only available in debug builds".to_string());
+    }
+    ENABLED.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Write the last profiled scan as a folded-stack file for a flamegraph viewer
+#[tauri::command]
+pub async fn export_scan_profile_command(destination: String) -> Result<(), String> {
+    let micros: Vec<u64> = MICROS.iter().map(|m| m.load(Ordering::Relaxed)).collect();
+    if micros.iter().all(|&m| m == 0) {
+        return Err("No profiled scan yet; turn on profiling and run a scan first".to_string());
+    }
+    write_guarded(&PathBuf::from(destination), folded(&micros).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folded_stacks() {
+        let text = folded(&[1500, 0, 20, 300]);
+        assert_eq!(
+            text,
+            "scan;walk;metadata 1500\nscan;emit_events 20\nscan;build_tree 300\n"
+        );
+    }
+}
@@ -0,0 +1,684 @@
+use crate::composition::{analyze_large_file, FileComposition};
+use crate::confidence::{path_confidence, Confidence};
+use crate::duplicates::{self, DuplicateFile, DuplicateReport};
+use crate::i18n::{localize, LocalizedMessage};
+use crate::never_suggest::{self, is_never_suggested, NeverSuggestEntry};
+use crate::reconcile::{self, volume_for, HiddenSpaceSource};
+use crate::registry::{self, DiscoveredNode};
+use crate::snapshots;
+use crate::storage::{self, StorageLocation};
+use crate::system_stores::{self, measure_link_aware};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Cache folders smaller than this aren't worth a recommendation
+const CACHE_MIN_SIZE: u64 = 50 << 20;
+
+/// Downloads untouched for this long are offered for review
+const DOWNLOAD_MIN_AGE_DAYS: u64 = 90;
+
+/// Files at least this large and untouched for `UNUSED_MIN_AGE_DAYS` are offered for review
+const LARGE_FILE_MIN_SIZE: u64 = 512 << 20;
+const UNUSED_MIN_AGE_DAYS: u64 = 365;
+
+/// SQLite databases smaller than this aren't worth vacuuming
+const DATABASE_MIN_SIZE: u64 = 64 << 20;
+/// Vacuuming is suggested once free pages make up this share of a database
+const DATABASE_MIN_FREE_PERCENT: u64 = 10;
+
+/// Extensions SQLite databases are usually saved with
+const DATABASE_EXTENSIONS: &[&str] = &["db", "db3", "sqlite", "sqlite3", "sqlitedb"];
+
+/// SQLite databases browsers and mail apps save without an extension
+const DATABASE_NAMES: &[&str] = &[
+    "Cookies",
+    "Envelope Index",
+    "Favicons",
+    "History",
+    "Login Data",
+    "Top Sites",
+    "Web Data",
+];
+
+/// Which suggestion engine a recommendation came from
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RecommendationCategory {
+    Trash,
+    Caches,
+    Downloads,
+    Duplicates,
+    LargeUnusedFiles,
+    Snapshots,
+    /// Databases to vacuum; nothing is deleted
+    Databases,
+}
+
+/// One thing the user could clean up, with what it would free
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Recommendation {
+    pub category: RecommendationCategory,
+    pub title: LocalizedMessage,
+    pub description: LocalizedMessage,
+    /// What the cleanup would remove; empty for snapshots, which are deleted by date label
+    pub paths: Vec<PathBuf>,
+    /// Date label of the snapshot, for `delete_local_snapshot_command`
+    pub snapshot: Option<String>,
+    pub bytes: u64,
+    /// How safe the cleanup is without reviewing it
+    pub confidence: Confidence,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CategorySavings {
+    pub category: RecommendationCategory,
+    pub count: u64,
+    pub bytes: u64,
+}
+
+/// Every suggestion for a volume, largest saving first
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Recommendations {
+    pub volume: PathBuf,
+    pub items: Vec<Recommendation>,
+    /// One entry per category with at least one recommendation
+    pub categories: Vec<CategorySavings>,
+    pub total_bytes: u64,
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn days(count: u64) -> Duration {
+    Duration::from_secs(count * SECONDS_PER_DAY)
+}
+
+/// What a category's engine knows about its suggestions: caches are rebuilt and the trash
+/// was already discarded; old downloads, duplicate copies (one copy stays) and snapshots
+/// are probably unneeded; a large unused file could be anything. Vacuuming a database
+/// loses nothing.
+fn category_confidence(category: RecommendationCategory) -> Confidence {
+    match category {
+        RecommendationCategory::Trash
+        | RecommendationCategory::Caches
+        | RecommendationCategory::Databases => Confidence::High,
+        RecommendationCategory::Downloads
+        | RecommendationCategory::Duplicates
+        | RecommendationCategory::Snapshots => Confidence::Medium,
+        RecommendationCategory::LargeUnusedFiles => Confidence::Low,
+    }
+}
+
+/// The category's confidence, raised when every path lives somewhere known to be safer,
+/// e.g. a large unused file inside a cache folder
+fn confidence_for(category: RecommendationCategory, paths: &[PathBuf]) -> Confidence {
+    let by_location = paths.iter().map(|p| path_confidence(p)).min();
+    category_confidence(category).max(by_location.unwrap_or_default())
+}
+
+fn recommendation(
+    category: RecommendationCategory,
+    code: &str,
+    params: &[(&str, serde_json::Value)],
+    paths: Vec<PathBuf>,
+    bytes: u64,
+) -> Recommendation {
+    Recommendation {
+        category,
+        title: localize(&format!("{}-title", code), params),
+        description: localize(&format!("{}-description", code), params),
+        confidence: confidence_for(category, &paths),
+        paths,
+        snapshot: None,
+        bytes,
+    }
+}
+
+/// Trash folders the current user has on `volume`
+fn trash_dirs(volume: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    #[cfg(target_os = "macos")]
+    {
+        if let Some(home) = dirs::home_dir() {
+            dirs.push(home.join(".Trash"));
+        }
+        dirs.push(
+            volume
+                .join(".Trashes")
+                .join(unsafe { libc::getuid() }.to_string()),
+        );
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        if let Some(data) = dirs::data_local_dir() {
+            dirs.push(data.join("Trash"));
+        }
+        dirs.push(volume.join(format!(".Trash-{}", unsafe { libc::getuid() })));
+    }
+    #[cfg(windows)]
+    dirs.push(volume.join("$Recycle.Bin"));
+    dirs
+}
+
+fn trash(on_volume: &impl Fn(&Path) -> bool, volume: &Path) -> Vec<Recommendation> {
+    trash_dirs(volume)
+        .into_iter()
+        .filter(|dir| dir.is_dir() && on_volume(dir))
+        .filter_map(|dir| {
+            let bytes = measure_link_aware(&dir).actual;
+            (bytes > 0).then(|| {
+                let path = dir.to_string_lossy().to_string();
+                recommendation(
+                    RecommendationCategory::Trash,
+                    "recommendation-trash",
+                    &[("path", json!(path))],
+                    vec![dir],
+                    bytes,
+                )
+            })
+        })
+        .collect()
+}
+
+/// The user's cache folders, one per app, plus Windows system stores
+fn caches(on_volume: &impl Fn(&Path) -> bool) -> Vec<Recommendation> {
+    let mut found = Vec::new();
+    if let Some(entries) = dirs::cache_dir().and_then(|dir| fs::read_dir(dir).ok()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !entry.file_type().is_ok_and(|t| t.is_dir()) || !on_volume(&path) {
+                continue;
+            }
+            let bytes = measure_link_aware(&path).actual;
+            if bytes >= CACHE_MIN_SIZE {
+                let name = entry.file_name().to_string_lossy().to_string();
+                found.push(recommendation(
+                    RecommendationCategory::Caches,
+                    "recommendation-cache",
+                    &[("name", json!(name))],
+                    vec![path],
+                    bytes,
+                ));
+            }
+        }
+    }
+    for store in system_stores::get_system_stores() {
+        if on_volume(&store.path) && store.exclusive_size > 0 {
+            found.push(Recommendation {
+                category: RecommendationCategory::Caches,
+                title: store.suggestion.title,
+                description: store.suggestion.description,
+                paths: vec![store.path],
+                snapshot: None,
+                bytes: store.exclusive_size,
+                confidence: store.suggestion.confidence,
+            });
+        }
+    }
+    found
+}
+
+/// Top-level entries of Downloads that haven't been touched for a while
+fn old_downloads(on_volume: &impl Fn(&Path) -> bool, now: SystemTime) -> Vec<Recommendation> {
+    let Some(downloads) = dirs::download_dir().filter(|dir| on_volume(dir)) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&downloads) else {
+        return Vec::new();
+    };
+    let cutoff = now - days(DOWNLOAD_MIN_AGE_DAYS);
+    let mut paths = Vec::new();
+    let mut bytes = 0;
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.modified().is_ok_and(|m| m <= cutoff) {
+            continue;
+        }
+        bytes += if metadata.is_dir() {
+            measure_link_aware(&entry.path()).actual
+        } else {
+            metadata.len()
+        };
+        paths.push(entry.path());
+    }
+    if paths.is_empty() {
+        return Vec::new();
+    }
+    vec![recommendation(
+        RecommendationCategory::Downloads,
+        "recommendation-downloads",
+        &[
+            ("count", json!(paths.len())),
+            ("days", json!(DOWNLOAD_MIN_AGE_DAYS)),
+        ],
+        paths,
+        bytes,
+    )]
+}
+
+/// Copies to remove so one of each is left on the volume: a never-suggest copy if
+/// there is one, otherwise the newest
+fn extra_copies(files: &[DuplicateFile]) -> Vec<PathBuf> {
+    let keep = files.iter().position(|f| f.never_suggest).or_else(|| {
+        files
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, f)| f.modified)
+            .map(|(i, _)| i)
+    });
+    files
+        .iter()
+        .enumerate()
+        .filter(|(i, f)| Some(*i) != keep && !f.never_suggest)
+        .map(|(_, f)| f.path.clone())
+        .collect()
+}
+
+/// Duplicates within the volume from the last completed duplicate search; copies on other
+/// volumes (backups) never count as a reason to delete one here
+fn duplicate_copies(report: &DuplicateReport, volume: &Path) -> Vec<Recommendation> {
+    let mut paths = Vec::new();
+    let mut bytes = 0;
+    for group in &report.groups {
+        for copies in group
+            .volumes
+            .iter()
+            .filter(|v| Path::new(&v.volume) == volume)
+        {
+            let extra = extra_copies(&copies.files);
+            bytes += group.size * extra.len() as u64;
+            paths.extend(extra);
+        }
+    }
+    for group in &report.directory_groups {
+        let on_volume: Vec<DuplicateFile> = group
+            .directories
+            .iter()
+            .filter(|d| Path::new(&d.volume) == volume)
+            .map(|d| DuplicateFile {
+                path: d.path.clone(),
+                modified: 0,
+                never_suggest: d.never_suggest,
+            })
+            .collect();
+        let extra = extra_copies(&on_volume);
+        bytes += group.size * extra.len() as u64;
+        paths.extend(extra);
+    }
+    if paths.is_empty() {
+        return Vec::new();
+    }
+    vec![recommendation(
+        RecommendationCategory::Duplicates,
+        "recommendation-duplicates",
+        &[("count", json!(paths.len()))],
+        paths,
+        bytes,
+    )]
+}
+
+/// Files in retained scans of the volume that `keep` accepts
+///
+/// Overlapping scans list the same files, so they are keyed by path.
+async fn retained_files(
+    on_volume: &impl Fn(&Path) -> bool,
+    keep: impl Fn(&DiscoveredNode) -> bool,
+) -> BTreeMap<PathBuf, (u64, SystemTime)> {
+    let mut files = BTreeMap::new();
+    for summary in registry::retained_summaries().await {
+        if !on_volume(Path::new(&summary.root)) {
+            continue;
+        }
+        let found = registry::with_scan(summary.scan_id, |nodes, _| {
+            nodes
+                .values()
+                .filter(|n| !n.is_directory && !n.is_symlink && keep(n))
+                .map(|n| (n.path.clone(), (n.size, n.modified)))
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+        files.extend(found);
+    }
+    files
+}
+
+/// Large files in retained scans of the volume that haven't been modified for a long time
+async fn large_unused_files(
+    on_volume: &impl Fn(&Path) -> bool,
+    now: SystemTime,
+) -> Vec<Recommendation> {
+    let cutoff = now - days(UNUSED_MIN_AGE_DAYS);
+    retained_files(on_volume, |n| {
+        n.size >= LARGE_FILE_MIN_SIZE && n.modified <= cutoff
+    })
+    .await
+    .into_iter()
+    .filter(|(path, _)| path.exists())
+    .map(|(path, (size, modified))| {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let unused_days = now
+            .duration_since(modified)
+            .map(|d| d.as_secs() / SECONDS_PER_DAY)
+            .unwrap_or(0);
+        recommendation(
+            RecommendationCategory::LargeUnusedFiles,
+            "recommendation-large-unused",
+            &[
+                ("name", json!(name)),
+                ("days", json!(unused_days)),
+                ("modified", json!(unix_seconds(modified))),
+            ],
+            vec![path],
+            size,
+        )
+    })
+    .collect()
+}
+
+/// Whether a file is named like an SQLite database; the header confirms it
+fn is_database_candidate(path: &Path) -> bool {
+    let by_extension = path.extension().is_some_and(|ext| {
+        DATABASE_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+    });
+    let by_name = path
+        .file_name()
+        .is_some_and(|name| DATABASE_NAMES.contains(&name.to_string_lossy().as_ref()));
+    by_extension || by_name
+}
+
+/// Vacuum suggestion for a database whose free pages are worth giving back
+fn vacuum(path: PathBuf, composition: &FileComposition) -> Option<Recommendation> {
+    let FileComposition::Sqlite {
+        page_size,
+        page_count,
+        free_pages,
+    } = *composition
+    else {
+        return None;
+    };
+    if page_count == 0 || free_pages * 100 / page_count < DATABASE_MIN_FREE_PERCENT {
+        return None;
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Some(recommendation(
+        RecommendationCategory::Databases,
+        "recommendation-vacuum",
+        &[
+            ("name", json!(name)),
+            ("percent", json!(free_pages * 100 / page_count)),
+        ],
+        vec![path],
+        free_pages * page_size,
+    ))
+}
+
+/// SQLite databases in retained scans of the volume with many free pages
+///
+/// The free page count is read from the database header, so the file is never opened for
+/// writing and no lock is taken.
+async fn sparse_databases(on_volume: &impl Fn(&Path) -> bool) -> Vec<Recommendation> {
+    let candidates = retained_files(on_volume, |n| {
+        n.size >= DATABASE_MIN_SIZE && is_database_candidate(&n.path)
+    })
+    .await;
+    tokio::task::spawn_blocking(move || {
+        candidates
+            .into_keys()
+            .filter_map(|path| {
+                let composition = analyze_large_file(&path).ok()?.composition?;
+                vacuum(path, &composition)
+            })
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Local snapshots, sized from the reconciliation of a retained scan of the volume
+async fn local_snapshots(volume: &Path, on_volume: &impl Fn(&Path) -> bool) -> Vec<Recommendation> {
+    let Ok(mut found) = snapshots::list_local_snapshots(volume) else {
+        return Vec::new();
+    };
+    if found.is_empty() {
+        return Vec::new();
+    }
+    let scan = registry::retained_summaries()
+        .await
+        .into_iter()
+        .rev()
+        .find(|s| on_volume(Path::new(&s.root)));
+    if let Some(scan) = scan {
+        if let Ok(report) = reconcile::reconcile_scan(scan.scan_id).await {
+            if let Some(held) = report
+                .hidden
+                .iter()
+                .find(|h| h.source == HiddenSpaceSource::LocalSnapshots)
+            {
+                snapshots::apportion(&mut found, held.bytes);
+            }
+        }
+    }
+    found
+        .into_iter()
+        .map(|snapshot| {
+            let mut item = recommendation(
+                RecommendationCategory::Snapshots,
+                "recommendation-snapshot",
+                &[("label", json!(snapshot.date_label))],
+                Vec::new(),
+                snapshot.approximate_size.unwrap_or(0),
+            );
+            item.snapshot = Some(snapshot.date_label);
+            item
+        })
+        .collect()
+}
+
+/// Drop never-suggest paths, then recommendations with nothing left to clean
+///
+/// Sizes of trimmed multi-path recommendations are scaled down by the share of paths
+/// removed, since per-path sizes aren't kept.
+fn without_never_suggest(
+    items: Vec<Recommendation>,
+    never_suggest: &[NeverSuggestEntry],
+) -> Vec<Recommendation> {
+    items
+        .into_iter()
+        .filter_map(|mut item| {
+            if item.paths.is_empty() {
+                return Some(item);
+            }
+            let before = item.paths.len() as u64;
+            item.paths.retain(|p| !is_never_suggested(never_suggest, p));
+            let after = item.paths.len() as u64;
+            item.bytes = item.bytes * after / before;
+            (after > 0).then_some(item)
+        })
+        .collect()
+}
+
+/// Rank recommendations largest first and total them per category
+fn rank(volume: &Path, mut items: Vec<Recommendation>) -> Recommendations {
+    items.retain(|item| item.bytes > 0);
+    items.sort_by_key(|item| std::cmp::Reverse(item.bytes));
+
+    let mut categories: BTreeMap<RecommendationCategory, CategorySavings> = BTreeMap::new();
+    for item in &items {
+        let savings = categories.entry(item.category).or_insert(CategorySavings {
+            category: item.category,
+            count: 0,
+            bytes: 0,
+        });
+        savings.count += 1;
+        savings.bytes += item.bytes;
+    }
+
+    Recommendations {
+        volume: volume.to_path_buf(),
+        total_bytes: items.iter().map(|item| item.bytes).sum(),
+        categories: categories.into_values().collect(),
+        items,
+    }
+}
+
+/// Collect every engine's suggestions for `volume`
+pub async fn recommendations_for(volume: PathBuf) -> Result<Recommendations, String> {
+    let locations: Vec<StorageLocation> = storage::get_storage_locations().unwrap_or_default();
+    let volume_path = volume.clone();
+    let on_volume = move |path: &Path| {
+        volume_for(path, &locations).is_some_and(|location| location.path == volume_path)
+    };
+    let now = SystemTime::now();
+
+    let mut items = Vec::new();
+    items.extend(large_unused_files(&on_volume, now).await);
+    items.extend(sparse_databases(&on_volume).await);
+    items.extend(local_snapshots(&volume, &on_volume).await);
+    if let Some(report) = duplicates::last_report().await {
+        items.extend(duplicate_copies(&report, &volume));
+    }
+
+    let walk_volume = volume.clone();
+    let found = tokio::task::spawn_blocking(move || {
+        let mut found = trash(&on_volume, &walk_volume);
+        found.extend(caches(&on_volume));
+        found.extend(old_downloads(&on_volume, now));
+        found
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    items.extend(found);
+
+    let never_suggest = never_suggest::load_never_suggest()?;
+    Ok(rank(&volume, without_never_suggest(items, &never_suggest)))
+}
+
+// Tauri commands
+
+/// Trash, caches, old downloads, duplicates, large unused files, snapshots and databases
+/// worth vacuuming on a volume, ranked in one list for the Recommended tab
+///
+/// Duplicates come from the last completed `find_duplicates_command` run and large unused
+/// files from retained scans of the volume; nothing is hashed or scanned here.
+#[tauri::command]
+pub async fn get_recommendations_command(volume: String) -> Result<Recommendations, String> {
+    recommendations_for(PathBuf::from(volume)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duplicates::{DuplicateGroup, VolumeCopies};
+
+    fn copy(path: &str, modified: i64, never_suggest: bool) -> DuplicateFile {
+        DuplicateFile {
+            path: PathBuf::from(path),
+            modified,
+            never_suggest,
+        }
+    }
+
+    #[test]
+    fn test_duplicates_keep_one_copy_per_volume_and_rank() {
+        let report = DuplicateReport {
+            scan_ids: vec![1],
+            paused: false,
+            groups: vec![DuplicateGroup {
+                hash: "h".to_string(),
+                size: 100,
+                volumes: vec![
+                    VolumeCopies {
+                        volume: "/".to_string(),
+                        files: vec![copy("/a/x", 1, false), copy("/b/x", 3, false)],
+                    },
+                    VolumeCopies {
+                        volume: "/backup".to_string(),
+                        files: vec![copy("/backup/x", 2, false)],
+                    },
+                ],
+                cross_volume: true,
+                reclaimable: 200,
+            }],
+            directory_groups: Vec::new(),
+            total_reclaimable: 200,
+            files_hashed: 3,
+            files_from_cache: 0,
+            possibly_corrupt: Vec::new(),
+        };
+        // The newest copy on the volume is kept, whatever the backup holds
+        let found = duplicate_copies(&report, Path::new("/"));
+        assert_eq!(found[0].paths, [PathBuf::from("/a/x")]);
+        assert_eq!(found[0].bytes, 100);
+        assert_eq!(found[0].confidence, Confidence::Medium);
+        assert_eq!(
+            extra_copies(&[copy("/a", 1, true), copy("/b", 3, false)]),
+            [PathBuf::from("/b")]
+        );
+
+        let cache = recommendation(
+            RecommendationCategory::Caches,
+            "recommendation-cache",
+            &[("name", json!("thumbnails"))],
+            vec![PathBuf::from("/home/u/.cache/thumbnails")],
+            300,
+        );
+        let mut items = found;
+        items.push(cache);
+        let never_suggest = [NeverSuggestEntry {
+            path: PathBuf::from("/home/u/.cache"),
+            note: None,
+            added_at: 0,
+        }];
+        let ranked = rank(
+            Path::new("/"),
+            without_never_suggest(items.clone(), &never_suggest),
+        );
+        assert_eq!(ranked.items.len(), 1);
+
+        let ranked = rank(Path::new("/"), items);
+        assert_eq!(ranked.total_bytes, 400);
+        assert_eq!(ranked.items[0].category, RecommendationCategory::Caches);
+        assert_eq!(ranked.categories.len(), 2);
+    }
+
+    #[test]
+    fn test_vacuum_needs_enough_free_pages() {
+        let sqlite = |free_pages| FileComposition::Sqlite {
+            page_size: 4096,
+            page_count: 100_000,
+            free_pages,
+        };
+        let path = PathBuf::from("/home/u/.mozilla/places.sqlite");
+        assert!(is_database_candidate(&path));
+        assert!(is_database_candidate(Path::new(
+            "/home/u/Chrome/Default/History"
+        )));
+        assert!(!is_database_candidate(Path::new("/home/u/notes.txt")));
+
+        assert!(vacuum(path.clone(), &sqlite(5_000)).is_none());
+        let item = vacuum(path, &sqlite(30_000)).unwrap();
+        assert_eq!(item.bytes, 30_000 * 4096);
+        assert_eq!(item.confidence, Confidence::High);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::registry;
+use crate::snapshots;
+use crate::storage::{self, StorageLocation};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Consumers of disk space that a file walk can't see
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HiddenSpaceSource {
+    /// Volume Shadow Copy storage (System Restore points, Windows backups)
+    ShadowCopies,
+    /// Time Machine local APFS snapshots (purgeable on demand)
+    LocalSnapshots,
+}
+
+/// Space held by one hidden consumer
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HiddenSpace {
+    pub source: HiddenSpaceSource,
+    pub bytes: u64,
+    pub description: String,
+    /// Whether `bytes` is an estimate rather than a reported figure
+    pub estimated: bool,
+    /// App command that reclaims this space, if there is one
+    pub reclaim_command: Option<String>,
+}
+
+/// Explains the gap between a volume's used space and the bytes a scan found
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SpaceReconciliation {
+    pub scan_id: u64,
+    /// Mount point or drive the scan root lives on
+    pub volume: String,
+    pub total_space: u64,
+    pub used_space: u64,
+    pub scanned_bytes: u64,
+    /// False when the scan started below the volume root, so the gap includes unscanned folders
+    pub covers_volume: bool,
+    pub hidden: Vec<HiddenSpace>,
+    /// Used space not explained by the scan or any hidden consumer
+    pub unaccounted_bytes: u64,
+}
+
+/// The storage location containing `root` (longest matching mount point)
+pub(crate) fn volume_for<'a>(
+    root: &Path,
+    locations: &'a [StorageLocation],
+) -> Option<&'a StorageLocation> {
+    locations
+        .iter()
+        .filter(|l| l.total_space.is_some() && root.starts_with(&l.path))
+        .max_by_key(|l| l.path.as_os_str().len())
+}
+
+/// Hidden consumers reported for a volume
+fn hidden_space(location: &StorageLocation) -> Vec<HiddenSpace> {
+    let mut hidden = Vec::new();
+
+    if let Some(shadow) = &location.shadow_storage {
+        hidden.push(HiddenSpace {
+            source: HiddenSpaceSource::ShadowCopies,
+            bytes: shadow.allocated_bytes,
+            description: format!("Shadow copy storage on {}", shadow.volume),
+            estimated: false,
+            reclaim_command: None,
+        });
+    }
+
+    hidden
+}
+
+/// Reconcile a scan against the volume it was taken on
+///
+/// APFS doesn't report how much snapshots hold, so when the scan covers the whole volume
+/// the remaining gap is attributed to local snapshots (if there are any).
+fn reconcile(
+    scan_id: u64,
+    root: &Path,
+    scanned_bytes: u64,
+    location: &StorageLocation,
+    snapshot_count: usize,
+) -> SpaceReconciliation {
+    let total_space = location.total_space.unwrap_or(0);
+    let used_space = total_space.saturating_sub(location.available_space.unwrap_or(0));
+    let covers_volume = root == location.path;
+    let mut hidden = hidden_space(location);
+    let hidden_bytes: u64 = hidden.iter().map(|h| h.bytes).sum();
+    let mut unaccounted_bytes = used_space
+        .saturating_sub(scanned_bytes)
+        .saturating_sub(hidden_bytes);
+
+    if snapshot_count > 0 {
+        hidden.push(HiddenSpace {
+            source: HiddenSpaceSource::LocalSnapshots,
+            bytes: if covers_volume { unaccounted_bytes } else { 0 },
+            description: format!("{} local Time Machine snapshot(s)", snapshot_count),
+            estimated: true,
+            reclaim_command: Some("thin_local_snapshots_command".to_string()),
+        });
+        if covers_volume {
+            unaccounted_bytes = 0;
+        }
+    }
+
+    SpaceReconciliation {
+        scan_id,
+        volume: location.path.to_string_lossy().to_string(),
+        total_space,
+        used_space,
+        scanned_bytes,
+        covers_volume,
+        hidden,
+        unaccounted_bytes,
+    }
+}
+
+/// Reconcile a retained scan against the volume holding its root
+pub(crate) async fn reconcile_scan(scan_id: u64) -> Result<SpaceReconciliation, String> {
+    let (root, scanned_bytes) = registry::with_scan(scan_id, |_, summary| {
+        (summary.root.clone(), summary.total_size)
+    })
+    .await?;
+
+    let locations = storage::get_storage_locations()?;
+    let location = volume_for(Path::new(&root), &locations)
+        .ok_or_else(|| format!("No mounted volume found for {}", root))?;
+    let snapshot_count = snapshots::list_local_snapshots(&location.path)
+        .map(|s| s.len())
+        .unwrap_or(0);
+
+    Ok(reconcile(
+        scan_id,
+        Path::new(&root),
+        scanned_bytes,
+        location,
+        snapshot_count,
+    ))
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn get_space_reconciliation_command(scan_id: u64) -> Result<SpaceReconciliation, String> {
+    reconcile_scan(scan_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{LocationType, ShadowStorage};
+    use std::path::PathBuf;
+
+    fn location(path: &str, total: u64, available: u64) -> StorageLocation {
+        StorageLocation {
+            name: path.to_string(),
+            path: PathBuf::from(path),
+            location_type: LocationType::Storage,
+            total_space: Some(total),
+            available_space: Some(available),
+            inodes: None,
+            quota: None,
+            shadow_storage: None,
+        }
+    }
+
+    #[test]
+    fn test_reconcile_with_shadow_storage() {
+        let mut volume = location("/", 1000, 200);
+        volume.shadow_storage = Some(ShadowStorage {
+            volume: "C:".to_string(),
+            used_bytes: 80,
+            allocated_bytes: 100,
+            max_bytes: None,
+        });
+        let locations = vec![volume, location("/mnt/data", 5000, 5000)];
+
+        let found = volume_for(Path::new("/home/user"), &locations).unwrap();
+        assert_eq!(found.path, PathBuf::from("/"));
+        assert_eq!(
+            volume_for(Path::new("/mnt/data/x"), &locations)
+                .unwrap()
+                .path,
+            PathBuf::from("/mnt/data")
+        );
+
+        let report = reconcile(3, Path::new("/home/user"), 600, found, 0);
+
+        assert_eq!(report.used_space, 800);
+        assert!(!report.covers_volume);
+        assert_eq!(report.hidden.len(), 1);
+        assert_eq!(report.hidden[0].source, HiddenSpaceSource::ShadowCopies);
+        assert_eq!(report.unaccounted_bytes, 100);
+    }
+
+    #[test]
+    fn test_reconcile_attributes_gap_to_snapshots() {
+        let volume = location("/", 1000, 200);
+
+        let report = reconcile(4, Path::new("/"), 500, &volume, 2);
+
+        assert!(report.covers_volume);
+        assert_eq!(report.hidden[0].source, HiddenSpaceSource::LocalSnapshots);
+        assert_eq!(report.hidden[0].bytes, 300);
+        assert!(report.hidden[0].estimated);
+        assert_eq!(report.unaccounted_bytes, 0);
+
+        let partial = reconcile(4, Path::new("/Users"), 500, &volume, 2);
+        assert_eq!(partial.hidden[0].bytes, 0);
+        assert_eq!(partial.unaccounted_bytes, 300);
+    }
+}
@@ -0,0 +1,377 @@
+use crate::summary::ScanSummary;
+use crate::types::{AncestorSize, FileType, StreamingScanEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+
+/// Represents a discovered node during progressive scanning
+#[derive(Clone, Debug)]
+pub(crate) struct DiscoveredNode {
+    /// Stable id, independent of the node's path
+    pub id: u64,
+    pub path: PathBuf,
+    pub name: String,
+    /// Size in bytes (for directories: aggregate size, filled in once the scan is retained)
+    pub size: u64,
+    /// Length of a file as `st_size` reports it; 0 for directories and links. Differs from
+    /// `size` where that counts allocated blocks (Unix) or for sparse and compressed files
+    pub logical_size: u64,
+    pub is_directory: bool,
+    pub file_type: FileType,
+    pub modified: SystemTime,
+    pub parent_path: Option<PathBuf>,
+    pub is_complete: bool, // true if directory fully scanned
+    pub is_symlink: bool,
+    pub symlink_target: Option<PathBuf>,
+    /// Number of files (for directories: all descendant files, filled in once retained)
+    pub file_count: u64,
+    /// Files of this directory kept only as totals, to save memory; counted in `size` and
+    /// `file_count` like the files that have nodes
+    pub dropped: Option<Box<DroppedFiles>>,
+}
+
+impl DiscoveredNode {
+    /// Count a file of this directory without keeping a node for it
+    pub(crate) fn drop_file(&mut self, file_type: FileType, size: u64) {
+        let dropped = self.dropped.get_or_insert_with(Default::default);
+        dropped.files += 1;
+        dropped.bytes += size;
+        match dropped
+            .categories
+            .iter_mut()
+            .find(|(t, _, _)| *t == file_type)
+        {
+            Some((_, bytes, files)) => {
+                *bytes += size;
+                *files += 1;
+            }
+            None => dropped.categories.push((file_type, size, 1)),
+        }
+    }
+}
+
+/// Totals of a directory's files that have no nodes of their own
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct DroppedFiles {
+    pub files: u64,
+    pub bytes: u64,
+    /// (category, bytes, files)
+    pub categories: Vec<(FileType, u64, u64)>,
+}
+
+/// Shared registry of discovered nodes
+pub(crate) type NodeRegistry = Arc<Mutex<HashMap<PathBuf, DiscoveredNode>>>;
+
+/// Source of node ids; never reused within a session, so ids stay unique across rescans
+static NEXT_NODE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Allocate a new stable node id
+pub(crate) fn next_node_id() -> u64 {
+    NEXT_NODE_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A completed scan kept in memory so later commands can query and patch it
+pub(crate) struct RetainedScan {
+    pub nodes: NodeRegistry,
+    pub summary: ScanSummary,
+}
+
+/// How many completed scans to keep in memory before evicting the oldest
+const MAX_RETAINED_SCANS: usize = 4;
+
+/// Completed scans keyed by scan id (ids increase, so the last entry is the latest scan)
+static RETAINED_SCANS: once_cell::sync::Lazy<Arc<Mutex<BTreeMap<u64, RetainedScan>>>> =
+    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(BTreeMap::new())));
+
+static NEXT_SCAN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Allocate an id for a new scan
+pub(crate) fn next_scan_id() -> u64 {
+    NEXT_SCAN_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Retain a completed scan's registry under `summary.scan_id`
+pub(crate) async fn retain_scan(nodes: NodeRegistry, summary: ScanSummary) {
+    {
+        let mut locked = nodes.lock().await;
+        aggregate_directory_totals(&mut locked);
+        crate::pins::record_scan(&locked);
+    }
+
+    crate::cache::store_in_background(nodes.clone(), summary.clone());
+
+    let mut scans = RETAINED_SCANS.lock().await;
+    scans.insert(summary.scan_id, RetainedScan { nodes, summary });
+    while scans.len() > MAX_RETAINED_SCANS {
+        scans.pop_first();
+    }
+}
+
+/// Summary of the most recently retained scan, if any
+pub(crate) async fn active_summary() -> Option<ScanSummary> {
+    RETAINED_SCANS
+        .lock()
+        .await
+        .last_key_value()
+        .map(|(_, scan)| scan.summary.clone())
+}
+
+/// Summaries of every retained scan, oldest first
+pub(crate) async fn retained_summaries() -> Vec<ScanSummary> {
+    RETAINED_SCANS
+        .lock()
+        .await
+        .values()
+        .map(|scan| scan.summary.clone())
+        .collect()
+}
+
+/// The newest retained scan that has `path` as a directory
+pub(crate) async fn newest_scan_with_dir(path: &Path) -> Option<u64> {
+    let scans = RETAINED_SCANS.lock().await;
+    for (scan_id, scan) in scans.iter().rev() {
+        if path.starts_with(&scan.summary.root)
+            && scan
+                .nodes
+                .lock()
+                .await
+                .get(path)
+                .is_some_and(|node| node.is_directory)
+        {
+            return Some(*scan_id);
+        }
+    }
+    None
+}
+
+/// Run a read-only query against a retained scan's nodes
+pub(crate) async fn with_scan<R>(
+    scan_id: u64,
+    query: impl FnOnce(&HashMap<PathBuf, DiscoveredNode>, &ScanSummary) -> R,
+) -> Result<R, String> {
+    let scans = RETAINED_SCANS.lock().await;
+    let scan = scans.get(&scan_id).ok_or_else(|| {
+        format!(
+            "Scan {} is not available (it may have been evicted)",
+            scan_id
+        )
+    })?;
+
+    let nodes = scan.nodes.lock().await;
+    Ok(query(&nodes, &scan.summary))
+}
+
+/// A retained scan's nodes, for work too long to do while holding every scan's lock
+pub(crate) async fn scan_nodes(scan_id: u64) -> Result<NodeRegistry, String> {
+    RETAINED_SCANS
+        .lock()
+        .await
+        .get(&scan_id)
+        .map(|scan| scan.nodes.clone())
+        .ok_or_else(|| {
+            format!(
+                "Scan {} is not available (it may have been evicted)",
+                scan_id
+            )
+        })
+}
+
+/// Change a retained scan's nodes in place
+pub(crate) async fn with_scan_mut<R>(
+    scan_id: u64,
+    update: impl FnOnce(&mut HashMap<PathBuf, DiscoveredNode>) -> R,
+) -> Result<R, String> {
+    let scans = RETAINED_SCANS.lock().await;
+    let scan = scans.get(&scan_id).ok_or_else(|| {
+        format!(
+            "Scan {} is not available (it may have been evicted)",
+            scan_id
+        )
+    })?;
+
+    let mut nodes = scan.nodes.lock().await;
+    Ok(update(&mut nodes))
+}
+
+/// Remove deleted paths from every retained scan and adjust their ancestors' sizes
+///
+/// Returns a `NodeRemoved` event for every (scan, path) pair that was affected.
+pub(crate) async fn remove_deleted_paths(paths: &[String]) -> Vec<StreamingScanEvent> {
+    let scans = RETAINED_SCANS.lock().await;
+    let mut events = Vec::new();
+
+    for (scan_id, scan) in scans.iter() {
+        let mut nodes = scan.nodes.lock().await;
+        events.extend(
+            paths
+                .iter()
+                .filter_map(|path| remove_subtree(&mut nodes, *scan_id, Path::new(path))),
+        );
+    }
+
+    events
+}
+
+/// Fill in directory sizes and file counts as the totals of all files beneath them
+fn aggregate_directory_totals(nodes: &mut HashMap<PathBuf, DiscoveredNode>) {
+    let files: Vec<(Option<PathBuf>, u64, u64)> = nodes
+        .values()
+        .filter(|n| !n.is_directory)
+        .map(|n| (n.parent_path.clone(), n.size, n.file_count))
+        .chain(nodes.values().filter_map(|n| {
+            let dropped = n.dropped.as_ref()?;
+            Some((Some(n.path.clone()), dropped.bytes, dropped.files))
+        }))
+        .collect();
+
+    for node in nodes.values_mut().filter(|n| n.is_directory) {
+        node.size = 0;
+        node.file_count = 0;
+    }
+
+    for (parent, size, file_count) in files {
+        let mut current = parent;
+        while let Some(dir) = current.and_then(|p| nodes.get_mut(&p)) {
+            dir.size += size;
+            dir.file_count += file_count;
+            current = dir.parent_path.clone();
+        }
+    }
+}
+
+/// Remove a node and all of its descendants, subtracting its size from every ancestor
+fn remove_subtree(
+    nodes: &mut HashMap<PathBuf, DiscoveredNode>,
+    scan_id: u64,
+    path: &Path,
+) -> Option<StreamingScanEvent> {
+    let removed = nodes.remove(path)?;
+
+    if removed.is_directory {
+        nodes.retain(|p, _| !p.starts_with(path));
+    }
+
+    let mut ancestors = Vec::new();
+    let mut current = removed.parent_path.clone();
+    while let Some(dir) = current.as_ref().and_then(|p| nodes.get_mut(p)) {
+        dir.size = dir.size.saturating_sub(removed.size);
+        dir.file_count = dir.file_count.saturating_sub(removed.file_count);
+        ancestors.push(AncestorSize {
+            id: dir.id,
+            path: dir.path.to_string_lossy().to_string(),
+            size: dir.size,
+        });
+        current = dir.parent_path.clone();
+    }
+
+    Some(StreamingScanEvent::NodeRemoved {
+        scan_id,
+        id: removed.id,
+        path: path.to_string_lossy().to_string(),
+        parent_path: removed.parent_path.map(|p| p.to_string_lossy().to_string()),
+        size_removed: removed.size,
+        ancestors,
+    })
+}
+
+/// Build a node for tests, with its parent derived from the path
+#[cfg(test)]
+pub(crate) fn test_node(path: &str, size: u64, is_directory: bool) -> DiscoveredNode {
+    let path = PathBuf::from(path);
+    DiscoveredNode {
+        id: next_node_id(),
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        parent_path: path.parent().map(Path::to_path_buf),
+        path,
+        size,
+        logical_size: if is_directory { 0 } else { size },
+        is_directory,
+        file_type: FileType::Other,
+        modified: SystemTime::UNIX_EPOCH,
+        is_complete: true,
+        is_symlink: false,
+        symlink_target: None,
+        file_count: u64::from(!is_directory),
+        dropped: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(path: &str, size: u64, is_directory: bool) -> DiscoveredNode {
+        test_node(path, size, is_directory)
+    }
+
+    fn sample_registry() -> HashMap<PathBuf, DiscoveredNode> {
+        let mut root = node("/scan", 0, true);
+        root.parent_path = None;
+
+        [
+            root,
+            node("/scan/a", 0, true),
+            node("/scan/a/one.bin", 100, false),
+            node("/scan/a/b", 0, true),
+            node("/scan/a/b/two.bin", 50, false),
+            node("/scan/three.bin", 25, false),
+        ]
+        .into_iter()
+        .map(|n| (n.path.clone(), n))
+        .collect()
+    }
+
+    #[test]
+    fn test_aggregate_directory_totals() {
+        let mut nodes = sample_registry();
+        aggregate_directory_totals(&mut nodes);
+
+        assert_eq!(nodes[Path::new("/scan")].size, 175);
+        assert_eq!(nodes[Path::new("/scan/a")].size, 150);
+        assert_eq!(nodes[Path::new("/scan/a/b")].size, 50);
+        assert_eq!(nodes[Path::new("/scan")].file_count, 3);
+        assert_eq!(nodes[Path::new("/scan/a")].file_count, 2);
+    }
+
+    #[test]
+    fn test_remove_subtree_updates_ancestors() {
+        let mut nodes = sample_registry();
+        aggregate_directory_totals(&mut nodes);
+
+        let event = remove_subtree(&mut nodes, 1, Path::new("/scan/a/b")).unwrap();
+
+        assert!(!nodes.contains_key(Path::new("/scan/a/b")));
+        assert!(!nodes.contains_key(Path::new("/scan/a/b/two.bin")));
+        assert_eq!(nodes[Path::new("/scan/a")].size, 100);
+        assert_eq!(nodes[Path::new("/scan")].size, 125);
+        assert_eq!(nodes[Path::new("/scan")].file_count, 2);
+
+        match event {
+            StreamingScanEvent::NodeRemoved {
+                size_removed,
+                ancestors,
+                ..
+            } => {
+                assert_eq!(size_removed, 50);
+                assert_eq!(ancestors.len(), 2);
+                assert_eq!(ancestors[0].path, "/scan/a");
+                assert_eq!(ancestors[1].size, 125);
+            }
+            _ => panic!("Expected NodeRemoved event"),
+        }
+    }
+
+    #[test]
+    fn test_remove_unknown_path() {
+        let mut nodes = sample_registry();
+        assert!(remove_subtree(&mut nodes, 1, Path::new("/elsewhere")).is_none());
+        assert_eq!(nodes.len(), 6);
+    }
+}
@@ -0,0 +1,800 @@
+use crate::registry::{self, DiscoveredNode};
+use crate::staging;
+use crate::storage::InodeUsage;
+use crate::summary::PathDepth;
+use crate::types::FileType;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// How many entries each ranked report returns by default
+const REPORT_LIMIT: usize = 20;
+
+/// Allocation unit assumed when the scanned volume's cluster size is unknown
+const DEFAULT_CLUSTER_SIZE: u64 = 4096;
+
+/// Direct tiny files a directory needs before it is listed in the tiny-file report
+const TINY_FILE_DIRECTORY_MIN: u64 = 1000;
+
+/// Directories listed under each extension in the Other-files report
+const OTHER_GROUP_DIRECTORIES: usize = 3;
+
+/// Windows' legacy MAX_PATH, in UTF-16 code units
+const WINDOWS_MAX_PATH: usize = 260;
+
+/// A path paired with its length in UTF-16 code units (the unit MAX_PATH is measured in)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PathLength {
+    pub path: String,
+    pub length: usize,
+}
+
+/// Deepest nesting and longest paths found in a scan
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PathExtremes {
+    pub scan_id: u64,
+    pub max_depth: usize,
+    pub deepest_directories: Vec<PathDepth>,
+    pub longest_paths: Vec<PathLength>,
+    /// Number of paths at or beyond Windows' MAX_PATH limit
+    pub paths_over_max_path: u64,
+}
+
+/// Depth of a path below the scan root
+fn depth_below(path: &Path, root: &Path) -> usize {
+    path.strip_prefix(root)
+        .map(|p| p.components().count())
+        .unwrap_or(0)
+}
+
+/// Compute the path extremes report from a scan's nodes
+fn path_extremes(
+    scan_id: u64,
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+    root: &Path,
+) -> PathExtremes {
+    let mut deepest: Vec<PathDepth> = nodes
+        .values()
+        .filter(|n| n.is_directory)
+        .map(|n| PathDepth {
+            path: n.path.to_string_lossy().to_string(),
+            depth: depth_below(&n.path, root),
+        })
+        .collect();
+    deepest.sort_by(|a, b| b.depth.cmp(&a.depth).then_with(|| a.path.cmp(&b.path)));
+    deepest.truncate(REPORT_LIMIT);
+
+    // Every node is a candidate here, so rank lengths first and build strings for the top only
+    let all: Vec<&DiscoveredNode> = nodes.values().collect();
+    let lengths: Vec<usize> = all
+        .iter()
+        .map(|n| n.path.to_string_lossy().encode_utf16().count())
+        .collect();
+    let paths_over_max_path = lengths.iter().filter(|l| **l >= WINDOWS_MAX_PATH).count() as u64;
+    let mut longest: Vec<PathLength> = staging::largest(
+        scan_id,
+        root,
+        lengths.iter().map(|l| *l as u64),
+        REPORT_LIMIT,
+    )
+    .into_iter()
+    .map(|i| PathLength {
+        path: all[i].path.to_string_lossy().to_string(),
+        length: lengths[i],
+    })
+    .collect();
+    longest.sort_by(|a, b| b.length.cmp(&a.length).then_with(|| a.path.cmp(&b.path)));
+    longest.truncate(REPORT_LIMIT);
+
+    PathExtremes {
+        scan_id,
+        max_depth: deepest.first().map(|d| d.depth).unwrap_or(0),
+        deepest_directories: deepest,
+        longest_paths: longest,
+        paths_over_max_path,
+    }
+}
+
+/// A directory ranked by how many files it holds
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CrowdedDirectory {
+    pub path: String,
+    /// Files anywhere beneath this directory
+    pub file_count: u64,
+    /// Files directly inside this directory
+    pub direct_file_count: u64,
+    pub size: u64,
+    pub average_file_size: u64,
+    /// Share of the volume's used inodes taken by this directory's files (Unix only)
+    pub share_of_used_inodes: Option<f64>,
+}
+
+/// Directories ranked by file count, with inode context for the scanned volume
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CrowdedDirectories {
+    pub scan_id: u64,
+    pub directories: Vec<CrowdedDirectory>,
+    pub inodes: Option<InodeUsage>,
+}
+
+/// Rank directories by descendant file count
+fn most_crowded_directories(
+    scan_id: u64,
+    root: &Path,
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+    limit: usize,
+    inodes: Option<&InodeUsage>,
+) -> Vec<CrowdedDirectory> {
+    let mut direct_counts: HashMap<&Path, u64> = HashMap::new();
+    for node in nodes.values().filter(|n| !n.is_directory) {
+        if let Some(parent) = &node.parent_path {
+            *direct_counts.entry(parent.as_path()).or_insert(0) += node.file_count;
+        }
+    }
+
+    let all: Vec<&DiscoveredNode> = nodes.values().filter(|n| n.is_directory).collect();
+    let mut directories: Vec<&DiscoveredNode> =
+        staging::largest(scan_id, root, all.iter().map(|d| d.file_count), limit)
+            .into_iter()
+            .map(|i| all[i])
+            .collect();
+    directories.sort_by(|a, b| {
+        b.file_count
+            .cmp(&a.file_count)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+
+    directories
+        .into_iter()
+        .take(limit)
+        .map(|dir| CrowdedDirectory {
+            path: dir.path.to_string_lossy().to_string(),
+            file_count: dir.file_count,
+            direct_file_count: direct_counts.get(dir.path.as_path()).copied().unwrap_or(0),
+            size: dir.size,
+            average_file_size: dir.size.checked_div(dir.file_count).unwrap_or(0),
+            share_of_used_inodes: inodes
+                .filter(|i| i.used > 0)
+                .map(|i| dir.file_count as f64 / i.used as f64),
+        })
+        .collect()
+}
+
+/// Space a file of `size` bytes takes on disk: whole clusters, none for an empty file
+pub(crate) fn allocated_size(size: u64, cluster_size: u64) -> u64 {
+    size.div_ceil(cluster_size) * cluster_size
+}
+
+/// A directory holding many files smaller than one cluster
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TinyFileDirectory {
+    pub path: String,
+    /// Tiny files directly inside this directory
+    pub tiny_files: u64,
+    pub logical_size: u64,
+    pub allocated_size: u64,
+}
+
+/// Zero-byte files and the allocation overhead of files smaller than one cluster
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TinyFilesReport {
+    pub scan_id: u64,
+    /// Cluster size the allocated sizes were computed with
+    pub cluster_size: u64,
+    pub zero_byte_files: u64,
+    /// First zero-byte files by path
+    pub zero_byte_paths: Vec<String>,
+    /// Non-empty files smaller than one cluster
+    pub tiny_files: u64,
+    pub tiny_logical_size: u64,
+    pub tiny_allocated_size: u64,
+    /// Directories with at least the minimum number of tiny files, most first
+    pub directories: Vec<TinyFileDirectory>,
+}
+
+/// Collect zero-byte files and tiny files, grouping the tiny ones by parent directory
+///
+/// Works from each file's logical length: on Unix a node's `size` is already whole blocks.
+fn tiny_files_report(
+    scan_id: u64,
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+    cluster_size: u64,
+    min_files: u64,
+) -> TinyFilesReport {
+    let mut zero_byte_paths: Vec<String> = Vec::new();
+    let mut by_directory: HashMap<&Path, TinyFileDirectory> = HashMap::new();
+
+    for node in nodes.values().filter(|n| !n.is_directory && !n.is_symlink) {
+        if node.logical_size == 0 {
+            zero_byte_paths.push(node.path.to_string_lossy().to_string());
+        } else if node.logical_size < cluster_size {
+            let Some(parent) = node.parent_path.as_deref() else {
+                continue;
+            };
+            let dir = by_directory
+                .entry(parent)
+                .or_insert_with(|| TinyFileDirectory {
+                    path: parent.to_string_lossy().to_string(),
+                    tiny_files: 0,
+                    logical_size: 0,
+                    allocated_size: 0,
+                });
+            dir.tiny_files += 1;
+            dir.logical_size += node.logical_size;
+            dir.allocated_size += allocated_size(node.logical_size, cluster_size);
+        }
+    }
+
+    let zero_byte_files = zero_byte_paths.len() as u64;
+    zero_byte_paths.sort();
+    zero_byte_paths.truncate(REPORT_LIMIT);
+
+    let tiny_files = by_directory.values().map(|d| d.tiny_files).sum();
+    let tiny_logical_size = by_directory.values().map(|d| d.logical_size).sum();
+    let tiny_allocated_size = by_directory.values().map(|d| d.allocated_size).sum();
+
+    let mut directories: Vec<TinyFileDirectory> = by_directory
+        .into_values()
+        .filter(|d| d.tiny_files >= min_files)
+        .collect();
+    directories.sort_by(|a, b| {
+        b.tiny_files
+            .cmp(&a.tiny_files)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    directories.truncate(REPORT_LIMIT);
+
+    TinyFilesReport {
+        scan_id,
+        cluster_size,
+        zero_byte_files,
+        zero_byte_paths,
+        tiny_files,
+        tiny_logical_size,
+        tiny_allocated_size,
+        directories,
+    }
+}
+
+/// Space lost to partially filled clusters beneath one directory
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DirectorySlack {
+    pub path: String,
+    pub logical_size: u64,
+    pub allocated_size: u64,
+    /// Allocated minus logical size
+    pub slack: u64,
+}
+
+/// Cluster overhead for a whole scan, with the directories wasting the most
+///
+/// Matters most on exFAT external drives, whose clusters can be 128 KiB or larger.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ClusterOverhead {
+    pub scan_id: u64,
+    pub cluster_size: u64,
+    pub logical_size: u64,
+    pub allocated_size: u64,
+    pub wasted: u64,
+    pub directories: Vec<DirectorySlack>,
+}
+
+/// Roll per-file slack up into every ancestor directory and rank directories by it
+///
+/// Slack is measured from logical lengths, since on Unix a node's `size` is already rounded.
+fn cluster_overhead(
+    scan_id: u64,
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+    cluster_size: u64,
+    limit: usize,
+) -> ClusterOverhead {
+    // (logical, allocated) of the files directly inside each directory
+    let mut totals: HashMap<&Path, (u64, u64)> = HashMap::new();
+    for node in nodes.values().filter(|n| !n.is_directory && !n.is_symlink) {
+        if let Some(parent) = &node.parent_path {
+            let entry = totals.entry(parent.as_path()).or_insert((0, 0));
+            entry.0 += node.logical_size;
+            entry.1 += allocated_size(node.logical_size, cluster_size);
+        }
+    }
+
+    // Children before parents, so each directory's totals are complete when passed up
+    let mut directories: Vec<&DiscoveredNode> = nodes.values().filter(|n| n.is_directory).collect();
+    directories.sort_by_key(|d| std::cmp::Reverse(d.path.components().count()));
+    for dir in &directories {
+        let own = totals.get(dir.path.as_path()).copied().unwrap_or((0, 0));
+        if let Some(parent) = dir
+            .parent_path
+            .as_deref()
+            .filter(|p| nodes.contains_key(*p))
+        {
+            let entry = totals.entry(parent).or_insert((0, 0));
+            entry.0 += own.0;
+            entry.1 += own.1;
+        }
+    }
+
+    let (total_logical, total_allocated) = nodes
+        .values()
+        .filter(|n| !n.is_directory && !n.is_symlink)
+        .fold((0, 0), |(logical, allocated), n| {
+            (
+                logical + n.logical_size,
+                allocated + allocated_size(n.logical_size, cluster_size),
+            )
+        });
+
+    let mut ranked: Vec<DirectorySlack> = directories
+        .iter()
+        .map(|dir| {
+            let (logical, allocated) = totals.get(dir.path.as_path()).copied().unwrap_or((0, 0));
+            DirectorySlack {
+                path: dir.path.to_string_lossy().to_string(),
+                logical_size: logical,
+                allocated_size: allocated,
+                slack: allocated - logical,
+            }
+        })
+        .filter(|d| d.slack > 0)
+        .collect();
+    ranked.sort_by(|a, b| b.slack.cmp(&a.slack).then_with(|| a.path.cmp(&b.path)));
+    ranked.truncate(limit);
+
+    ClusterOverhead {
+        scan_id,
+        cluster_size,
+        logical_size: total_logical,
+        allocated_size: total_allocated,
+        wasted: total_allocated - total_logical,
+        directories: ranked,
+    }
+}
+
+/// Where one extension's Other files sit
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OtherDirectory {
+    pub path: String,
+    pub size: u64,
+    pub file_count: u64,
+}
+
+/// Other files sharing an extension, with the directories holding most of them
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OtherGroup {
+    /// Lowercased, without the dot; `None` for files with no extension
+    pub extension: Option<String>,
+    pub size: u64,
+    pub file_count: u64,
+    pub directories: Vec<OtherDirectory>,
+}
+
+/// One of the largest files in a scan
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LargestFile {
+    pub path: String,
+    pub size: u64,
+    pub file_type: FileType,
+    /// Unix timestamp (seconds) of the last modification
+    pub modified: i64,
+}
+
+/// The largest files beneath a directory, however deep and whatever else is beside them
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LargestFiles {
+    pub scan_id: u64,
+    pub path: String,
+    pub files: Vec<LargestFile>,
+}
+
+/// Rank the files beneath `dir` by size, ignoring the per-directory limit of returned trees
+fn largest_files(
+    scan_id: u64,
+    root: &Path,
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+    dir: &Path,
+    limit: usize,
+) -> Vec<LargestFile> {
+    let all: Vec<&DiscoveredNode> = nodes
+        .values()
+        .filter(|n| !n.is_directory && !n.is_symlink && n.path.starts_with(dir))
+        .collect();
+    let mut files: Vec<&DiscoveredNode> =
+        staging::largest(scan_id, root, all.iter().map(|f| f.size), limit)
+            .into_iter()
+            .map(|i| all[i])
+            .collect();
+    files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+
+    files
+        .into_iter()
+        .take(limit)
+        .map(|file| LargestFile {
+            path: file.path.to_string_lossy().to_string(),
+            size: file.size,
+            file_type: file.file_type.clone(),
+            modified: file
+                .modified
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0),
+        })
+        .collect()
+}
+
+/// Files the classifier couldn't place, grouped to show which categories are missing
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OtherFilesReport {
+    pub scan_id: u64,
+    pub other_size: u64,
+    pub other_files: u64,
+    /// Largest groups first
+    pub groups: Vec<OtherGroup>,
+}
+
+/// Group Other files by extension, then by parent directory within each extension
+fn other_files_report(
+    scan_id: u64,
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+    limit: usize,
+) -> OtherFilesReport {
+    // extension -> parent directory -> (bytes, files)
+    let mut by_extension: HashMap<Option<String>, HashMap<&Path, (u64, u64)>> = HashMap::new();
+    for node in nodes
+        .values()
+        .filter(|n| !n.is_directory && !n.is_symlink && n.file_type == FileType::Other)
+    {
+        let extension = node
+            .path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        let parent = node.parent_path.as_deref().unwrap_or(Path::new(""));
+        let entry = by_extension
+            .entry(extension)
+            .or_default()
+            .entry(parent)
+            .or_insert((0, 0));
+        entry.0 += node.size;
+        entry.1 += 1;
+    }
+
+    let mut groups: Vec<OtherGroup> = by_extension
+        .into_iter()
+        .map(|(extension, parents)| {
+            let mut directories: Vec<OtherDirectory> = parents
+                .into_iter()
+                .map(|(path, (size, file_count))| OtherDirectory {
+                    path: path.to_string_lossy().to_string(),
+                    size,
+                    file_count,
+                })
+                .collect();
+            directories.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+            let size = directories.iter().map(|d| d.size).sum();
+            let file_count = directories.iter().map(|d| d.file_count).sum();
+            directories.truncate(OTHER_GROUP_DIRECTORIES);
+            OtherGroup {
+                extension,
+                size,
+                file_count,
+                directories,
+            }
+        })
+        .collect();
+    let other_size = groups.iter().map(|g| g.size).sum();
+    let other_files = groups.iter().map(|g| g.file_count).sum();
+    groups.sort_by(|a, b| {
+        b.size
+            .cmp(&a.size)
+            .then_with(|| a.extension.cmp(&b.extension))
+    });
+    groups.truncate(limit);
+
+    OtherFilesReport {
+        scan_id,
+        other_size,
+        other_files,
+        groups,
+    }
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn get_path_extremes_command(scan_id: u64) -> Result<PathExtremes, String> {
+    registry::with_scan(scan_id, |nodes, summary| {
+        path_extremes(scan_id, nodes, Path::new(&summary.root))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_most_crowded_directories_command(
+    scan_id: u64,
+    n: Option<usize>,
+) -> Result<CrowdedDirectories, String> {
+    registry::with_scan(scan_id, |nodes, summary| {
+        let inodes = summary.inodes.clone();
+        CrowdedDirectories {
+            scan_id,
+            directories: most_crowded_directories(
+                scan_id,
+                Path::new(&summary.root),
+                nodes,
+                n.unwrap_or(REPORT_LIMIT),
+                inodes.as_ref(),
+            ),
+            inodes,
+        }
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_tiny_files_report_command(
+    scan_id: u64,
+    min_files: Option<u64>,
+) -> Result<TinyFilesReport, String> {
+    registry::with_scan(scan_id, |nodes, summary| {
+        tiny_files_report(
+            scan_id,
+            nodes,
+            summary.cluster_size.unwrap_or(DEFAULT_CLUSTER_SIZE),
+            min_files.unwrap_or(TINY_FILE_DIRECTORY_MIN),
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_cluster_overhead_command(
+    scan_id: u64,
+    n: Option<usize>,
+) -> Result<ClusterOverhead, String> {
+    registry::with_scan(scan_id, |nodes, summary| {
+        cluster_overhead(
+            scan_id,
+            nodes,
+            summary.cluster_size.unwrap_or(DEFAULT_CLUSTER_SIZE),
+            n.unwrap_or(REPORT_LIMIT),
+        )
+    })
+    .await
+}
+
+/// The `limit` largest files beneath `path`, from the newest retained scan that has it
+#[tauri::command]
+pub async fn get_largest_files_command(
+    path: String,
+    limit: Option<usize>,
+) -> Result<LargestFiles, String> {
+    let dir = PathBuf::from(&path);
+    let scan_id = registry::newest_scan_with_dir(&dir)
+        .await
+        .ok_or_else(|| format!("No retained scan covers {}", path))?;
+    registry::with_scan(scan_id, |nodes, summary| LargestFiles {
+        scan_id,
+        files: largest_files(
+            scan_id,
+            Path::new(&summary.root),
+            nodes,
+            &dir,
+            limit.unwrap_or(REPORT_LIMIT),
+        ),
+        path,
+    })
+    .await
+}
+
+/// What the Other category is made of, to show which new categories would explain most of it
+#[tauri::command]
+pub async fn get_other_files_report_command(
+    scan_id: u64,
+    n: Option<usize>,
+) -> Result<OtherFilesReport, String> {
+    registry::with_scan(scan_id, |nodes, _| {
+        other_files_report(scan_id, nodes, n.unwrap_or(REPORT_LIMIT))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(path: &str, is_directory: bool) -> (PathBuf, DiscoveredNode) {
+        let node = registry::test_node(path, 1, is_directory);
+        (node.path.clone(), node)
+    }
+
+    fn dir_with_count(path: &str, file_count: u64) -> (PathBuf, DiscoveredNode) {
+        let (path, mut node) = node(path, true);
+        node.file_count = file_count;
+        node.size = file_count * 10;
+        (path, node)
+    }
+
+    #[test]
+    fn test_path_extremes() {
+        let long_name = format!("/scan/{}", "x".repeat(300));
+        let nodes: HashMap<PathBuf, DiscoveredNode> = [
+            node("/scan", true),
+            node("/scan/a", true),
+            node("/scan/a/b", true),
+            node("/scan/a/b/c", true),
+            node("/scan/a/b/c/file.txt", false),
+            node(&long_name, false),
+        ]
+        .into_iter()
+        .collect();
+
+        let report = path_extremes(7, &nodes, Path::new("/scan"));
+
+        assert_eq!(report.scan_id, 7);
+        assert_eq!(report.max_depth, 3);
+        assert_eq!(report.deepest_directories[0].path, "/scan/a/b/c");
+        assert_eq!(report.longest_paths[0].path, long_name);
+        assert_eq!(report.paths_over_max_path, 1);
+    }
+
+    #[test]
+    fn test_most_crowded_directories() {
+        let nodes: HashMap<PathBuf, DiscoveredNode> = [
+            dir_with_count("/scan", 4),
+            dir_with_count("/scan/cache", 3),
+            node("/scan/cache/a", false),
+            node("/scan/cache/b", false),
+            node("/scan/cache/c", false),
+            node("/scan/top.txt", false),
+        ]
+        .into_iter()
+        .collect();
+        let inodes = InodeUsage {
+            total: 100,
+            free: 92,
+            used: 8,
+        };
+
+        let ranked = most_crowded_directories(1, Path::new("/scan"), &nodes, 5, Some(&inodes));
+
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].path, "/scan");
+        assert_eq!(ranked[0].direct_file_count, 1);
+        assert_eq!(ranked[1].path, "/scan/cache");
+        assert_eq!(ranked[1].direct_file_count, 3);
+        assert_eq!(ranked[1].average_file_size, 10);
+        assert_eq!(ranked[1].share_of_used_inodes, Some(3.0 / 8.0));
+    }
+
+    #[test]
+    fn test_largest_files() {
+        let nodes: HashMap<PathBuf, DiscoveredNode> = [
+            registry::test_node("/scan", 0, true),
+            registry::test_node("/scan/a", 0, true),
+            registry::test_node("/scan/a/b/deep.iso", 900, false),
+            registry::test_node("/scan/a/small", 5, false),
+            registry::test_node("/scan/a/tie-b", 50, false),
+            registry::test_node("/scan/a/tie-a", 50, false),
+            registry::test_node("/scan/outside.bin", 1000, false),
+        ]
+        .into_iter()
+        .map(|n| (n.path.clone(), n))
+        .collect();
+
+        let files = largest_files(1, Path::new("/scan"), &nodes, Path::new("/scan/a"), 3);
+
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["/scan/a/b/deep.iso", "/scan/a/tie-a", "/scan/a/tie-b"]
+        );
+        assert_eq!(files[0].size, 900);
+    }
+
+    #[test]
+    fn test_tiny_files_report() {
+        let mut nodes: HashMap<PathBuf, DiscoveredNode> = [
+            dir_with_count("/scan", 5),
+            dir_with_count("/scan/node_modules", 3),
+            node("/scan/empty.lock", false),
+            node("/scan/big.bin", false),
+        ]
+        .into_iter()
+        .collect();
+        // Sized the way Unix reports them: allocated blocks, with the length kept apart
+        for (path, length) in [("/scan/empty.lock", 0), ("/scan/big.bin", 10_000)] {
+            let file = nodes.get_mut(Path::new(path)).unwrap();
+            file.size = allocated_size(length, 4096);
+            file.logical_size = length;
+        }
+        for (name, length) in [("a.js", 100), ("b.js", 5000), ("c.js", 4096)] {
+            let (path, mut file) = node(&format!("/scan/node_modules/{}", name), false);
+            file.size = allocated_size(length, 4096);
+            file.logical_size = length;
+            nodes.insert(path, file);
+        }
+
+        let report = tiny_files_report(1, &nodes, 4096, 1);
+
+        assert_eq!(report.zero_byte_files, 1);
+        assert_eq!(report.zero_byte_paths, vec!["/scan/empty.lock"]);
+        assert_eq!(report.tiny_files, 1);
+        assert_eq!(report.tiny_logical_size, 100);
+        assert_eq!(report.tiny_allocated_size, 4096);
+        assert_eq!(report.directories[0].path, "/scan/node_modules");
+        assert_eq!(allocated_size(5000, 4096), 8192);
+        assert_eq!(allocated_size(0, 4096), 0);
+    }
+
+    #[test]
+    fn test_other_files_report() {
+        let mut nodes: HashMap<PathBuf, DiscoveredNode> = [
+            dir_with_count("/scan", 5),
+            node("/scan/cache/a.PAK", false),
+            node("/scan/cache/b.pak", false),
+            node("/scan/game/c.pak", false),
+            node("/scan/bin/tool", false),
+            node("/scan/notes.txt", false),
+        ]
+        .into_iter()
+        .collect();
+        for (path, size) in [
+            ("/scan/cache/a.PAK", 300),
+            ("/scan/cache/b.pak", 200),
+            ("/scan/game/c.pak", 100),
+            ("/scan/bin/tool", 50),
+        ] {
+            nodes.get_mut(Path::new(path)).unwrap().size = size;
+        }
+        nodes
+            .get_mut(Path::new("/scan/notes.txt"))
+            .unwrap()
+            .file_type = FileType::Document;
+
+        let report = other_files_report(1, &nodes, 10);
+
+        assert_eq!(report.other_size, 650);
+        assert_eq!(report.other_files, 4);
+        let pak = &report.groups[0];
+        assert_eq!(pak.extension.as_deref(), Some("pak"));
+        assert_eq!((pak.size, pak.file_count), (600, 3));
+        assert_eq!(pak.directories[0].path, "/scan/cache");
+        assert_eq!(pak.directories[0].size, 500);
+        assert_eq!(report.groups[1].extension, None);
+    }
+
+    #[test]
+    fn test_cluster_overhead() {
+        let mut nodes: HashMap<PathBuf, DiscoveredNode> = [
+            dir_with_count("/usb", 3),
+            dir_with_count("/usb/photos", 2),
+            dir_with_count("/usb/photos/2024", 1),
+            node("/usb/movie.mkv", false),
+            node("/usb/photos/a.jpg", false),
+            node("/usb/photos/2024/b.jpg", false),
+        ]
+        .into_iter()
+        .collect();
+        let cluster = 128 * 1024;
+        for (path, length) in [
+            ("/usb/movie.mkv", 2 * cluster),
+            ("/usb/photos/a.jpg", 1000),
+            ("/usb/photos/2024/b.jpg", cluster + 24),
+        ] {
+            let file = nodes.get_mut(Path::new(path)).unwrap();
+            file.size = allocated_size(length, cluster);
+            file.logical_size = length;
+        }
+
+        let report = cluster_overhead(1, &nodes, cluster, 10);
+
+        let photos_slack = (cluster - 1000) + (cluster - 24);
+        assert_eq!(report.wasted, photos_slack);
+        assert_eq!(report.directories.len(), 3);
+        assert_eq!(report.directories[0].path, "/usb");
+        assert_eq!(report.directories[1].path, "/usb/photos");
+        assert_eq!(report.directories[1].slack, photos_slack);
+        assert_eq!(report.directories[2].slack, cluster - 24);
+    }
+}
@@ -1,24 +1,44 @@
+use crate::access;
+use crate::devices::is_portable_device_path;
+use crate::events::EventSink;
+use crate::i18n::{localize, LocalizedMessage};
+use crate::persistence::is_app_data_path;
+use crate::registry;
+use crate::sizes::SizeFormatter;
+use crate::system_stores;
+use crate::webhooks::{self, Notification};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::path::{Path, PathBuf};
 use sysinfo::{ProcessRefreshKind, RefreshKind, System};
+use tauri::Window;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum SafetyCheck {
     Safe,
-    Protected { message: String },
-    InUse { message: String },
-    RequiresConfirmation { message: String },
+    Protected { message: LocalizedMessage },
+    InUse { message: LocalizedMessage },
+    RequiresConfirmation { message: LocalizedMessage },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DeletionResult {
+    /// Every item that is gone from where it was, trashed or not
     pub deleted: Vec<String>,
+    /// Those of `deleted` moved to the trash; the rest were removed for good
+    #[serde(default)]
+    pub trashed: Vec<String>,
     pub failed: Vec<FailedDeletion>,
+    /// Bytes removed for good
     pub space_freed: u64,
+    /// Bytes moved to the trash, which still take space until it is emptied
+    #[serde(default)]
+    pub space_trashed: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FailedDeletion {
     pub path: String,
     pub error: String,
@@ -51,7 +71,7 @@ const PROTECTED_PATHS: &[&str] = &[
 const LARGE_DELETION_THRESHOLD: u64 = 10 * 1024 * 1024 * 1024;
 
 /// Check if a path is within a protected system directory
-fn is_protected_path(path: &Path) -> bool {
+pub(crate) fn is_protected_path(path: &Path) -> bool {
     let path_str = path.to_string_lossy();
 
     for protected in PROTECTED_PATHS {
@@ -87,7 +107,7 @@ fn is_file_in_use(path: &Path) -> bool {
     let path_str = path.to_string_lossy();
 
     // Check if any process has this file open
-    for (_pid, process) in system.processes() {
+    for process in system.processes().values() {
         // Check the process executable path
         if let Some(exe_path) = process.exe() {
             if exe_path == path {
@@ -106,8 +126,15 @@ fn is_file_in_use(path: &Path) -> bool {
 }
 
 /// Calculate the total size of a path (file or directory)
-fn calculate_path_size(path: &Path) -> std::io::Result<u64> {
-    if path.is_file() {
+pub(crate) fn calculate_path_size(path: &Path) -> std::io::Result<u64> {
+    // A link is deleted or trashed on its own, never what it points to
+    let link = path
+        .symlink_metadata()
+        .ok()
+        .filter(|m| m.file_type().is_symlink());
+    if let Some(link) = link {
+        Ok(link.len())
+    } else if path.is_file() {
         Ok(path.metadata()?.len())
     } else if path.is_dir() {
         let mut total_size = 0u64;
@@ -127,37 +154,71 @@ fn calculate_path_size(path: &Path) -> std::io::Result<u64> {
     }
 }
 
+fn display(path: &Path) -> Value {
+    json!(path.display().to_string())
+}
+
 /// Check the safety of deleting a single path
 pub fn check_deletion_safety(path: &Path) -> SafetyCheck {
-    // Check if path exists
-    if !path.exists() {
+    // Check if path exists (without following symlinks, so dangling links can be removed)
+    if path.symlink_metadata().is_err() {
         return SafetyCheck::Protected {
-            message: format!("Path does not exist: {}", path.display()),
+            message: localize("safety-not-found", &[("path", display(path))]),
+        };
+    }
+
+    // Windows-managed caches have a supported cleanup; point at it instead of a bare refusal
+    if let Some((_, suggestion)) = system_stores::store_containing(path) {
+        return SafetyCheck::Protected {
+            message: localize(
+                "safety-system-store",
+                &[
+                    ("path", display(path)),
+                    ("title", json!(suggestion.title.text)),
+                    ("command", json!(suggestion.command)),
+                ],
+            ),
+        };
+    }
+
+    // Saved scans and caches are managed by the app itself
+    if is_app_data_path(path) {
+        return SafetyCheck::Protected {
+            message: localize("safety-app-data", &[("path", display(path))]),
+        };
+    }
+
+    // Phones and cameras are only ever scanned
+    if is_portable_device_path(path) {
+        return SafetyCheck::Protected {
+            message: localize("safety-portable-device", &[("path", display(path))]),
         };
     }
 
     // Check if it's a protected system path
     if is_protected_path(path) {
         return SafetyCheck::Protected {
-            message: format!("Cannot delete protected system path: {}", path.display()),
+            message: localize("safety-protected", &[("path", display(path))]),
         };
     }
 
     // Check if file is in use
     if is_file_in_use(path) {
         return SafetyCheck::InUse {
-            message: format!("File or directory is currently in use: {}", path.display()),
+            message: localize("safety-in-use", &[("path", display(path))]),
         };
     }
 
     // Check size threshold
     if let Ok(size) = calculate_path_size(path) {
         if size > LARGE_DELETION_THRESHOLD {
-            let size_gb = size as f64 / (1024.0 * 1024.0 * 1024.0);
             return SafetyCheck::RequiresConfirmation {
-                message: format!(
-                    "Large deletion ({:.2} GB). Please confirm this action.",
-                    size_gb
+                message: localize(
+                    "safety-large-deletion",
+                    &[
+                        ("size", json!(SizeFormatter::current().format(size))),
+                        ("bytes", json!(size)),
+                    ],
                 ),
             };
         }
@@ -172,10 +233,17 @@ pub fn check_multiple_deletions(paths: &[PathBuf]) -> Vec<SafetyCheck> {
 }
 
 /// Delete items after safety checks have been performed
-pub async fn delete_items(paths: Vec<PathBuf>) -> Result<DeletionResult, String> {
+///
+/// With `use_trash`, items go to the Recycle Bin, the macOS Trash or the freedesktop trash
+/// instead; one that can't be trashed is reported as failed rather than removed for good.
+pub async fn delete_items(paths: Vec<PathBuf>, use_trash: bool) -> Result<DeletionResult, String> {
+    access::ensure_writable("Deleting files")?;
+
     let mut deleted = Vec::new();
+    let mut trashed = Vec::new();
     let mut failed = Vec::new();
     let mut space_freed = 0u64;
+    let mut space_trashed = 0u64;
 
     for path in paths {
         // Perform safety check before deletion
@@ -184,21 +252,28 @@ pub async fn delete_items(paths: Vec<PathBuf>) -> Result<DeletionResult, String>
                 // Calculate size before deletion
                 if let Ok(size) = calculate_path_size(&path) {
                     // Attempt deletion
-                    let result = if path.is_dir() {
-                        std::fs::remove_dir_all(&path)
+                    let result = if use_trash {
+                        trash::delete(&path).map_err(|e| e.to_string())
+                    } else if path.is_dir() {
+                        std::fs::remove_dir_all(&path).map_err(|e| e.to_string())
                     } else {
-                        std::fs::remove_file(&path)
+                        std::fs::remove_file(&path).map_err(|e| e.to_string())
                     };
 
                     match result {
+                        Ok(_) if use_trash => {
+                            space_trashed += size;
+                            trashed.push(path.to_string_lossy().to_string());
+                            deleted.push(path.to_string_lossy().to_string());
+                        }
                         Ok(_) => {
                             space_freed += size;
                             deleted.push(path.to_string_lossy().to_string());
                         }
-                        Err(e) => {
+                        Err(error) => {
                             failed.push(FailedDeletion {
                                 path: path.to_string_lossy().to_string(),
-                                error: e.to_string(),
+                                error,
                             });
                         }
                     }
@@ -212,25 +287,50 @@ pub async fn delete_items(paths: Vec<PathBuf>) -> Result<DeletionResult, String>
             SafetyCheck::Protected { message } => {
                 failed.push(FailedDeletion {
                     path: path.to_string_lossy().to_string(),
-                    error: message,
+                    error: message.text,
                 });
             }
             SafetyCheck::InUse { message } => {
                 failed.push(FailedDeletion {
                     path: path.to_string_lossy().to_string(),
-                    error: message,
+                    error: message.text,
                 });
             }
         }
     }
 
+    if !deleted.is_empty() {
+        webhooks::notify(Notification::CleanupExecuted {
+            deleted: deleted.len() as u64,
+            failed: failed.len() as u64,
+            space_freed,
+            space_trashed,
+        });
+    }
+
     Ok(DeletionResult {
         deleted,
+        trashed,
         failed,
         space_freed,
+        space_trashed,
     })
 }
 
+/// Delete items, then patch the retained scan so the frontend doesn't have to edit its
+/// own copy of the tree
+pub(crate) async fn delete_and_patch_scan<E: EventSink>(
+    paths: Vec<PathBuf>,
+    use_trash: bool,
+    events: &E,
+) -> Result<DeletionResult, String> {
+    let result = delete_items(paths, use_trash).await?;
+    for event in registry::remove_deleted_paths(&result.deleted).await {
+        events.send("streaming-scan-event", &event);
+    }
+    Ok(result)
+}
+
 // Tauri commands
 
 #[tauri::command]
@@ -239,10 +339,15 @@ pub async fn check_deletion_safety_command(paths: Vec<String>) -> Result<Vec<Saf
     Ok(check_multiple_deletions(&path_bufs))
 }
 
+/// Delete `paths`, moving them to the trash with `use_trash` (off by default)
 #[tauri::command]
-pub async fn delete_items_command(paths: Vec<String>) -> Result<DeletionResult, String> {
+pub async fn delete_items_command(
+    paths: Vec<String>,
+    use_trash: Option<bool>,
+    window: Window,
+) -> Result<DeletionResult, String> {
     let path_bufs: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
-    delete_items(path_bufs).await
+    delete_and_patch_scan(path_bufs, use_trash.unwrap_or(false), &window).await
 }
 
 #[cfg(test)]
@@ -290,6 +395,42 @@ mod tests {
         fs::remove_dir_all(&temp_dir).unwrap();
     }
 
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_trash_mode_reports_what_was_trashed() {
+        let dir = std::env::temp_dir().join(format!("test_safety_trash_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        // A freedesktop trash of the test's own, on the same volume, instead of the user's
+        std::env::set_var("XDG_DATA_HOME", dir.join("data"));
+        let file = dir.join("report.txt");
+        fs::write(&file, vec![0u8; 5000]).unwrap();
+        let link = dir.join("latest-report");
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+        let link_size = calculate_path_size(&link).unwrap();
+        assert!(link_size < 5000);
+
+        let result = delete_items(vec![link.clone(), file.clone()], true)
+            .await
+            .unwrap();
+        let trash = dir.join("data/Trash/files");
+        let trashed_report = trash.join("report.txt").exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result.failed.is_empty());
+        let names = |paths: &[PathBuf]| -> Vec<String> {
+            paths
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect()
+        };
+        assert_eq!(result.trashed, names(&[link.clone(), file.clone()]));
+        assert_eq!(result.deleted, result.trashed);
+        assert_eq!(result.space_trashed, 5000 + link_size);
+        assert_eq!(result.space_freed, 0);
+        assert!(trashed_report);
+    }
+
     #[test]
     fn test_safety_check_nonexistent() {
         let result = check_deletion_safety(Path::new("/nonexistent/path"));
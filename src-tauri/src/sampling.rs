@@ -0,0 +1,200 @@
+use crate::estimate::disk_size;
+use crate::persistence::is_app_data_path;
+use crate::summary::ScanStrategy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Random walks taken per top-level directory unless the caller asks for more
+const DEFAULT_WALKS: usize = 30;
+/// Walks stop descending here, in case of a directory cycle the walk can't detect
+const MAX_WALK_DEPTH: usize = 64;
+/// Files whose size is read per visited directory; the rest are scaled from them
+const SAMPLED_FILES: usize = 64;
+/// Two-sided 95% interval under the normal approximation
+const Z_95: f64 = 1.96;
+
+/// Estimated size of one top-level directory, with a 95% confidence interval
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SampledDirectory {
+    pub path: PathBuf,
+    pub estimated_size: u64,
+    pub size_low: u64,
+    pub size_high: u64,
+    pub estimated_files: u64,
+}
+
+/// Result of a sampled scan; every figure in it is an estimate
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SampledScan {
+    pub root: PathBuf,
+    /// Always `Sampled`, so the UI can label the result as an estimate
+    pub strategy: ScanStrategy,
+    pub walks_per_directory: usize,
+    pub confidence: f64,
+    /// Files directly inside the root, measured exactly
+    pub root_files_size: u64,
+    pub estimated_total: u64,
+    /// Largest estimate first
+    pub directories: Vec<SampledDirectory>,
+    pub duration_ms: u64,
+}
+
+/// Entries of one directory, split into files and subdirectories
+fn list(path: &Path) -> (Vec<Metadata>, Vec<PathBuf>) {
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(path).into_iter().flatten().flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if !is_app_data_path(&entry.path()) {
+                dirs.push(entry.path());
+            }
+        } else if metadata.is_file() {
+            files.push(metadata);
+        }
+    }
+    (files, dirs)
+}
+
+/// Bytes of the files directly in a directory, read from a random sample when there are many
+fn files_size(files: &[Metadata], rng: &mut fastrand::Rng) -> f64 {
+    if files.len() <= SAMPLED_FILES {
+        return files.iter().map(disk_size).sum::<u64>() as f64;
+    }
+    let sampled: u64 = (0..SAMPLED_FILES)
+        .map(|_| disk_size(&files[rng.usize(..files.len())]))
+        .sum();
+    sampled as f64 * files.len() as f64 / SAMPLED_FILES as f64
+}
+
+/// One random root-to-leaf walk, returning (bytes, files) estimates for the whole subtree
+///
+/// This is Knuth's tree-size estimator: each directory on the path stands in for all of its
+/// siblings, so its contents are weighted by the product of the branching factors above it.
+fn random_walk(start: &Path, rng: &mut fastrand::Rng) -> (f64, f64) {
+    let mut node = start.to_path_buf();
+    let mut weight = 1.0;
+    let (mut bytes, mut file_count) = (0.0, 0.0);
+
+    for _ in 0..MAX_WALK_DEPTH {
+        let (files, dirs) = list(&node);
+        bytes += weight * files_size(&files, rng);
+        file_count += weight * files.len() as f64;
+
+        if dirs.is_empty() {
+            break;
+        }
+        weight *= dirs.len() as f64;
+        node = dirs[rng.usize(..dirs.len())].clone();
+    }
+
+    (bytes, file_count)
+}
+
+/// Mean and half-width of the 95% interval
+fn mean_and_margin(samples: &[f64]) -> (f64, f64) {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    if samples.len() < 2 {
+        return (mean, 0.0);
+    }
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, Z_95 * (variance / n).sqrt())
+}
+
+fn sample_directory(path: &Path, walks: usize, rng: &mut fastrand::Rng) -> SampledDirectory {
+    let (sizes, counts): (Vec<f64>, Vec<f64>) = (0..walks).map(|_| random_walk(path, rng)).unzip();
+    let (size, margin) = mean_and_margin(&sizes);
+    let (files, _) = mean_and_margin(&counts);
+
+    SampledDirectory {
+        path: path.to_path_buf(),
+        estimated_size: size.round() as u64,
+        size_low: (size - margin).max(0.0).round() as u64,
+        size_high: (size + margin).round() as u64,
+        estimated_files: files.round() as u64,
+    }
+}
+
+/// Estimate per-directory sizes below `root` from random walks instead of a full scan
+///
+/// Meant for filers with hundreds of millions of files. Each top-level directory gets its
+/// own walks, so the cost grows with the number of walks, not with the number of files.
+pub fn sampled_scan(root: &Path, walks: usize, seed: u64) -> SampledScan {
+    let started = Instant::now();
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let walks = walks.max(1);
+
+    let (root_files, dirs) = list(root);
+    let root_files_size = root_files.iter().map(disk_size).sum();
+
+    let mut directories: Vec<SampledDirectory> = dirs
+        .iter()
+        .map(|dir| sample_directory(dir, walks, &mut rng))
+        .collect();
+    directories.sort_by_key(|d| std::cmp::Reverse(d.estimated_size));
+
+    SampledScan {
+        root: root.to_path_buf(),
+        strategy: ScanStrategy::Sampled,
+        walks_per_directory: walks,
+        confidence: 0.95,
+        root_files_size,
+        estimated_total: root_files_size
+            + directories.iter().map(|d| d.estimated_size).sum::<u64>(),
+        directories,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+// Tauri commands
+
+/// Estimate a huge volume's size distribution by sampling instead of walking every file
+#[tauri::command]
+pub async fn sampled_scan_command(
+    path: String,
+    walks: Option<usize>,
+) -> Result<SampledScan, String> {
+    crate::scanner::validate_path(&path)?;
+    let seed = chrono::Utc::now().timestamp_millis() as u64;
+    tokio::task::spawn_blocking(move || {
+        sampled_scan(Path::new(&path), walks.unwrap_or(DEFAULT_WALKS), seed)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_tree_is_estimated_exactly() {
+        let root = std::env::temp_dir().join("test_sampling");
+        let _ = fs::remove_dir_all(&root);
+        for branch in ["a", "b", "c"] {
+            for leaf in ["x", "y"] {
+                let dir = root.join("projects").join(branch).join(leaf);
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(dir.join("1.bin"), vec![1u8; 4096]).unwrap();
+                fs::write(dir.join("2.bin"), vec![1u8; 4096]).unwrap();
+            }
+        }
+        let file_size = disk_size(&fs::metadata(root.join("projects/a/x/1.bin")).unwrap());
+
+        let scan = sampled_scan(&root, 10, 7);
+        fs::remove_dir_all(&root).unwrap();
+
+        // Every walk sees the same branching, so the estimate has no variance
+        let projects = &scan.directories[0];
+        assert_eq!(scan.strategy, ScanStrategy::Sampled);
+        assert_eq!(projects.estimated_files, 12);
+        assert_eq!(projects.estimated_size, 12 * file_size);
+        assert_eq!(projects.size_low, projects.size_high);
+    }
+}
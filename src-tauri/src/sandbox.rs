@@ -0,0 +1,349 @@
+use crate::persistence::{data_file, read_sealed_json_or_default, write_sealed_json};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Prefix of errors for paths a sandboxed build has not been granted, so the frontend can
+/// ask the user to pick the folder instead of showing the raw error
+pub const OUTSIDE_GRANTED_SCOPE: &str = "OutsideGrantedScope";
+
+const BOOKMARKS_FILE: &str = "bookmarks.json";
+
+/// A folder the user granted through the open panel, kept as a security-scoped bookmark
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GrantedFolder {
+    pub path: PathBuf,
+    /// Opaque bookmark data from the OS
+    pub bookmark: Vec<u8>,
+}
+
+/// Folders whose security scope is currently open, with the handle to close it
+static ACTIVE_SCOPES: once_cell::sync::Lazy<Mutex<Vec<(PathBuf, bookmarks::Scope)>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Whether this is a sandboxed (Mac App Store) build; macOS sets this for sandboxed apps
+pub fn is_sandboxed() -> bool {
+    cfg!(target_os = "macos") && std::env::var_os("APP_SANDBOX_CONTAINER_ID").is_some()
+}
+
+fn load_bookmarks() -> Result<Vec<GrantedFolder>, String> {
+    read_sealed_json_or_default(&data_file(BOOKMARKS_FILE)?)
+}
+
+fn save_bookmarks(folders: &[GrantedFolder]) -> Result<(), String> {
+    write_sealed_json(&data_file(BOOKMARKS_FILE)?, &folders)
+}
+
+fn in_scope(path: &Path, scopes: &[PathBuf]) -> bool {
+    scopes.iter().any(|scope| path.starts_with(scope))
+}
+
+/// Refuse a path outside every granted folder when sandboxed; unsandboxed builds allow all
+pub(crate) fn ensure_in_granted_scope(path: &Path) -> Result<(), String> {
+    if !is_sandboxed() {
+        return Ok(());
+    }
+    let scopes: Vec<PathBuf> = ACTIVE_SCOPES
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(p, _)| p.clone())
+        .collect();
+    if !in_scope(path, &scopes) {
+        return Err(format!(
+            "{}: {} has not been granted; choose it in the folder picker first",
+            OUTSIDE_GRANTED_SCOPE,
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Resolve saved bookmarks and open their scopes; called once at startup
+///
+/// Bookmarks the OS reports as stale are recreated, and ones that no longer resolve
+/// (the folder was deleted) are dropped.
+pub fn restore_granted_folders() {
+    if !is_sandboxed() {
+        return;
+    }
+    let Ok(saved) = load_bookmarks() else {
+        return;
+    };
+    let mut kept = Vec::new();
+    let mut active = ACTIVE_SCOPES.lock().unwrap();
+    for folder in saved {
+        let Ok(resolved) = bookmarks::resolve(&folder.bookmark) else {
+            continue;
+        };
+        let bookmark = match resolved.refreshed {
+            Some(bookmark) => bookmark,
+            None => folder.bookmark,
+        };
+        kept.push(GrantedFolder {
+            path: resolved.path.clone(),
+            bookmark,
+        });
+        active.push((resolved.path, resolved.scope));
+    }
+    let _ = save_bookmarks(&kept);
+}
+
+// Tauri commands
+
+/// Remember a folder the user just picked, so it stays accessible after a relaunch
+#[tauri::command]
+pub async fn grant_folder_command(path: String) -> Result<GrantedFolder, String> {
+    let path = PathBuf::from(path);
+    let bookmark = bookmarks::create(&path)?;
+    let resolved = bookmarks::resolve(&bookmark)?;
+
+    let mut folders = load_bookmarks()?;
+    folders.retain(|f| f.path != path);
+    let folder = GrantedFolder {
+        path: path.clone(),
+        bookmark,
+    };
+    folders.push(folder.clone());
+    save_bookmarks(&folders)?;
+
+    let mut active = ACTIVE_SCOPES.lock().unwrap();
+    active.retain(|(p, _)| *p != path);
+    active.push((path, resolved.scope));
+    Ok(folder)
+}
+
+#[tauri::command]
+pub async fn list_granted_folders_command() -> Result<Vec<GrantedFolder>, String> {
+    load_bookmarks()
+}
+
+/// Forget a granted folder and give up access to it
+#[tauri::command]
+pub async fn revoke_folder_command(path: String) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    let mut folders = load_bookmarks()?;
+    folders.retain(|f| f.path != path);
+    save_bookmarks(&folders)?;
+    // Dropping a scope closes it
+    ACTIVE_SCOPES.lock().unwrap().retain(|(p, _)| *p != path);
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+mod bookmarks {
+    use std::ffi::OsStr;
+    use std::os::raw::c_void;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+
+    type CFTypeRef = *const c_void;
+    type CFIndex = isize;
+    type CFOptionFlags = usize;
+    type Boolean = u8;
+
+    const BOOKMARK_CREATION_WITH_SECURITY_SCOPE: CFOptionFlags = 1 << 11;
+    const BOOKMARK_RESOLUTION_WITH_SECURITY_SCOPE: CFOptionFlags = 1 << 10;
+    const PATH_MAX: usize = 1024;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFURLCreateFromFileSystemRepresentation(
+            allocator: CFTypeRef,
+            buffer: *const u8,
+            length: CFIndex,
+            is_directory: Boolean,
+        ) -> CFTypeRef;
+        fn CFURLGetFileSystemRepresentation(
+            url: CFTypeRef,
+            resolve_against_base: Boolean,
+            buffer: *mut u8,
+            max_length: CFIndex,
+        ) -> Boolean;
+        fn CFURLCreateBookmarkData(
+            allocator: CFTypeRef,
+            url: CFTypeRef,
+            options: CFOptionFlags,
+            resource_properties: CFTypeRef,
+            relative_to: CFTypeRef,
+            error: *mut CFTypeRef,
+        ) -> CFTypeRef;
+        fn CFURLCreateByResolvingBookmarkData(
+            allocator: CFTypeRef,
+            bookmark: CFTypeRef,
+            options: CFOptionFlags,
+            relative_to: CFTypeRef,
+            resource_properties: CFTypeRef,
+            is_stale: *mut Boolean,
+            error: *mut CFTypeRef,
+        ) -> CFTypeRef;
+        fn CFURLStartAccessingSecurityScopedResource(url: CFTypeRef) -> Boolean;
+        fn CFURLStopAccessingSecurityScopedResource(url: CFTypeRef);
+        fn CFDataCreate(allocator: CFTypeRef, bytes: *const u8, length: CFIndex) -> CFTypeRef;
+        fn CFDataGetLength(data: CFTypeRef) -> CFIndex;
+        fn CFDataGetBytePtr(data: CFTypeRef) -> *const u8;
+        fn CFRelease(object: CFTypeRef);
+    }
+
+    /// An open security scope; closed and released when dropped
+    #[derive(Debug)]
+    pub(super) struct Scope(CFTypeRef);
+
+    // The URL is immutable and only touched again to close the scope
+    unsafe impl Send for Scope {}
+
+    impl Drop for Scope {
+        fn drop(&mut self) {
+            unsafe {
+                CFURLStopAccessingSecurityScopedResource(self.0);
+                CFRelease(self.0);
+            }
+        }
+    }
+
+    pub(super) struct Resolved {
+        pub path: PathBuf,
+        pub scope: Scope,
+        /// New bookmark data when the OS reported the saved one as stale
+        pub refreshed: Option<Vec<u8>>,
+    }
+
+    fn data_bytes(data: CFTypeRef) -> Vec<u8> {
+        unsafe {
+            std::slice::from_raw_parts(CFDataGetBytePtr(data), CFDataGetLength(data) as usize)
+                .to_vec()
+        }
+    }
+
+    fn bookmark_for_url(url: CFTypeRef) -> Option<Vec<u8>> {
+        let data = unsafe {
+            CFURLCreateBookmarkData(
+                std::ptr::null(),
+                url,
+                BOOKMARK_CREATION_WITH_SECURITY_SCOPE,
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null_mut(),
+            )
+        };
+        if data.is_null() {
+            return None;
+        }
+        let bytes = data_bytes(data);
+        unsafe { CFRelease(data) };
+        Some(bytes)
+    }
+
+    /// Bookmark a folder the user granted in this session
+    pub(super) fn create(path: &Path) -> Result<Vec<u8>, String> {
+        let bytes = path.as_os_str().as_bytes();
+        let url = unsafe {
+            CFURLCreateFromFileSystemRepresentation(
+                std::ptr::null(),
+                bytes.as_ptr(),
+                bytes.len() as CFIndex,
+                1,
+            )
+        };
+        if url.is_null() {
+            return Err(format!("Invalid path {}", path.display()));
+        }
+        let bookmark = bookmark_for_url(url);
+        unsafe { CFRelease(url) };
+        bookmark.ok_or_else(|| {
+            format!(
+                "Cannot keep access to {}; choose it in the folder picker first",
+                path.display()
+            )
+        })
+    }
+
+    /// Resolve a bookmark and open its security scope
+    pub(super) fn resolve(bookmark: &[u8]) -> Result<Resolved, String> {
+        let data = unsafe {
+            CFDataCreate(
+                std::ptr::null(),
+                bookmark.as_ptr(),
+                bookmark.len() as CFIndex,
+            )
+        };
+        let mut is_stale: Boolean = 0;
+        let url = unsafe {
+            CFURLCreateByResolvingBookmarkData(
+                std::ptr::null(),
+                data,
+                BOOKMARK_RESOLUTION_WITH_SECURITY_SCOPE,
+                std::ptr::null(),
+                std::ptr::null(),
+                &mut is_stale,
+                std::ptr::null_mut(),
+            )
+        };
+        unsafe { CFRelease(data) };
+        if url.is_null() {
+            return Err("The granted folder no longer exists".to_string());
+        }
+
+        let mut buffer = [0u8; PATH_MAX];
+        let has_path = unsafe {
+            CFURLGetFileSystemRepresentation(url, 1, buffer.as_mut_ptr(), PATH_MAX as CFIndex)
+        };
+        let started = unsafe { CFURLStartAccessingSecurityScopedResource(url) };
+        if has_path == 0 || started == 0 {
+            unsafe { CFRelease(url) };
+            return Err("The OS refused access to the granted folder".to_string());
+        }
+        let length = buffer.iter().position(|&b| b == 0).unwrap_or(PATH_MAX);
+        Ok(Resolved {
+            path: PathBuf::from(OsStr::from_bytes(&buffer[..length])),
+            refreshed: if is_stale != 0 {
+                bookmark_for_url(url)
+            } else {
+                None
+            },
+            scope: Scope(url),
+        })
+    }
+}
+
+/// Security-scoped bookmarks are a macOS sandbox feature
+#[cfg(not(target_os = "macos"))]
+mod bookmarks {
+    use std::path::{Path, PathBuf};
+
+    #[derive(Debug)]
+    pub(super) struct Scope;
+
+    pub(super) struct Resolved {
+        pub path: PathBuf,
+        pub scope: Scope,
+        pub refreshed: Option<Vec<u8>>,
+    }
+
+    pub(super) fn create(_path: &Path) -> Result<Vec<u8>, String> {
+        Err("Granting folders is only needed in the sandboxed macOS build".to_string())
+    }
+
+    pub(super) fn resolve(_bookmark: &[u8]) -> Result<Resolved, String> {
+        Err("Granting folders is only needed in the sandboxed macOS build".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_check() {
+        let scopes = vec![PathBuf::from("/Users/alice/Documents")];
+        assert!(in_scope(Path::new("/Users/alice/Documents/taxes"), &scopes));
+        assert!(in_scope(Path::new("/Users/alice/Documents"), &scopes));
+        assert!(!in_scope(Path::new("/Users/alice/Documents2"), &scopes));
+        assert!(!in_scope(Path::new("/Users/alice"), &scopes));
+        // Outside a sandbox every path is allowed
+        if !is_sandboxed() {
+            assert!(ensure_in_granted_scope(Path::new("/")).is_ok());
+        }
+    }
+}
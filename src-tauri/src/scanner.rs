@@ -1,10 +1,32 @@
-use crate::classifier::classify_file;
-use crate::types::{FileNode, FileType, StreamingScanEvent};
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use crate::access;
+use crate::change_journal;
+use crate::classifier::{classify_file, CategoryStats};
+use crate::entry_counts;
+use crate::events::EventSink;
+use crate::exclusions::ExcludePatterns;
+use crate::listing::{self, EntryKind};
+use crate::persistence::is_app_data_path;
+use crate::profiling::{self, Phase};
+use crate::registry::{self, DiscoveredNode, NodeRegistry};
+use crate::settings;
+use crate::sizes::percent_of;
+use crate::storage::{
+    get_cluster_size, get_inode_usage, get_user_quota, network_mount_points, volume_capacity,
+};
+use crate::summary::{
+    build_summary, ExcludedPath, ExclusionReason, ScanCounters, ScanStrategy, ScanSummary,
+};
+use crate::telemetry;
+use crate::types::{
+    ChildrenPage, FileNode, FileType, HotPath, StreamingScanEvent, SyntheticNode, SCHEMA_VERSION,
+};
+use crate::watchdog::{self, InFlightGuard, OperationStalled, StallDetector};
+use crate::webhooks::{self, Notification};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
-use tauri::{Emitter, Window};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::fs;
 use tokio::sync::{mpsc, Mutex, Semaphore};
 use tokio_util::sync::CancellationToken;
@@ -14,6 +36,35 @@ use std::os::unix::fs::MetadataExt;
 
 const MAX_CONCURRENT_DIRS: usize = 100; // Limit concurrent directory scans
 
+/// Children kept per directory in a returned tree unless the request asks for another limit;
+/// the rest are summed into a "more items" node
+pub(crate) const DEFAULT_CHILD_LIMIT: usize = 100;
+
+/// How a returned tree is trimmed for display; the retained scan keeps every entry, so
+/// trimmed ones can still be paged through with `get_children_command`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeShape {
+    /// Largest children kept per directory; the rest are summed into a "more items" node
+    pub child_limit: usize,
+    /// Files smaller than this are grouped into one "small files" node per directory
+    pub aggregate_below: Option<u64>,
+}
+
+impl TreeShape {
+    pub fn new(child_limit: Option<usize>, aggregate_below: Option<u64>) -> Self {
+        Self {
+            child_limit: child_limit.unwrap_or(DEFAULT_CHILD_LIMIT),
+            aggregate_below,
+        }
+    }
+}
+
+impl Default for TreeShape {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
 /// Global cancellation token for the current scan
 static SCAN_CANCELLATION: once_cell::sync::Lazy<Arc<Mutex<Option<CancellationToken>>>> =
     once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(None)));
@@ -25,6 +76,7 @@ pub fn validate_path(path: &str) -> Result<bool, String> {
     if !path_buf.exists() {
         return Err(format!("Path does not exist: {}", path));
     }
+    access::ensure_scan_root_allowed(&path_buf)?;
 
     // Try to read metadata to check accessibility
     match std::fs::metadata(&path_buf) {
@@ -76,39 +128,140 @@ pub async fn cancel_scan() -> Result<(), String> {
 
 const BATCH_INTERVAL_MS: u64 = 500; // Progress update interval in milliseconds
 
-/// Represents a discovered node during progressive scanning
-#[derive(Clone, Debug)]
-struct DiscoveredNode {
-    path: PathBuf,
-    name: String,
-    size: u64,
-    is_directory: bool,
-    file_type: FileType,
-    modified: SystemTime,
-    parent_path: Option<PathBuf>,
-    is_complete: bool, // true if directory fully scanned
+/// Smallest completed directory reported in the hot-path ticker
+const HOT_PATH_MIN_SIZE: u64 = 100 * 1024 * 1024;
+/// Completed large directories kept for the ticker
+const HOT_PATH_WINDOW: usize = 8;
+
+/// How long one read on a network file system may take before its mount is given up on
+const NETWORK_READ_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Network mounts the scan can reach, and the ones that stopped answering
+#[derive(Debug, Default)]
+struct NetworkMounts {
+    mounts: Vec<PathBuf>,
+    unreachable: std::sync::Mutex<HashSet<PathBuf>>,
 }
 
-/// Shared registry of discovered nodes
-type NodeRegistry = Arc<Mutex<HashMap<PathBuf, DiscoveredNode>>>;
+impl NetworkMounts {
+    /// The network mount holding `path`, if any
+    fn containing(&self, path: &Path) -> Option<&PathBuf> {
+        self.mounts
+            .iter()
+            .filter(|mount| path.starts_with(mount))
+            .max_by_key(|mount| mount.as_os_str().len())
+    }
+
+    fn is_unreachable(&self, mount: &Path) -> bool {
+        self.unreachable.lock().unwrap().contains(mount)
+    }
+}
+
+/// What every directory task of one scan shares besides the registry and progress
+#[derive(Debug)]
+struct ScanContext {
+    root: PathBuf,
+    network: NetworkMounts,
+    exclude: ExcludePatterns,
+    /// Files smaller than this many bytes are only counted in their directory's totals
+    drop_files_below: Option<u64>,
+    /// Batch metadata reads through io_uring where the kernel allows it
+    io_uring: bool,
+    /// Directory sizes from the newest retained scan covering the root; subdirectories are
+    /// walked largest first by these, so on a rescan the top consumers settle early
+    previous_sizes: HashMap<PathBuf, u64>,
+    /// Node ids from that scan, kept for the same paths so the frontend can match nodes up
+    /// across rescans
+    previous_ids: HashMap<PathBuf, u64>,
+    /// Where `NodeUpdate` events go
+    updates: mpsc::UnboundedSender<StreamingScanEvent>,
+}
+
+/// Levels below the root announced with `NodeUpdate` as they are found: the ones the
+/// returned tree shows, so deep trees don't flood the frontend with events
+const NODE_UPDATE_DEPTH: usize = 2;
+
+impl ScanContext {
+    /// The id `path` had in the previous scan of the root, or a new one
+    fn id_for(&self, path: &Path) -> u64 {
+        self.previous_ids
+            .get(path)
+            .copied()
+            .unwrap_or_else(registry::next_node_id)
+    }
+
+    /// Send a `NodeUpdate` for a node just added to `registry`, if it is shallow enough
+    fn announce(&self, registry: &HashMap<PathBuf, DiscoveredNode>, node: &DiscoveredNode) {
+        let depth = node
+            .path
+            .strip_prefix(&self.root)
+            .map_or(usize::MAX, |relative| relative.components().count());
+        if depth > NODE_UPDATE_DEPTH {
+            return;
+        }
+        let parent_id = node
+            .parent_path
+            .as_ref()
+            .and_then(|parent| registry.get(parent))
+            .map(|parent| parent.id);
+        // Nobody listening any more just means the scan is ending
+        let _ = self.updates.send(StreamingScanEvent::NodeUpdate {
+            id: node.id,
+            parent_id,
+            path: node.path.to_string_lossy().to_string(),
+            parent_path: node
+                .parent_path
+                .as_ref()
+                .map(|p| p.to_string_lossy().to_string()),
+            name: node.name.clone(),
+            size: node.size,
+            is_directory: node.is_directory,
+            file_type: node.file_type.clone(),
+        });
+    }
+}
 
 /// Progress stats for tracking scan progress
 #[derive(Debug)]
 struct ProgressStats {
     files_scanned: u64,
+    /// Directories whose children have all finished, out of `counters.directories` found
+    dirs_completed: u64,
+    counters: ScanCounters,
     total_size: u64,
     current_path: String,
+    /// Most recently completed large directories, newest first
+    hot_paths: VecDeque<HotPath>,
+    /// Large directories completed so far, so the ticker is only sent when it changes
+    hot_paths_found: u64,
+    /// (bytes, files) counted so far per category
+    categories: HashMap<FileType, (u64, u64)>,
     #[cfg(unix)]
     seen_inodes: HashSet<u64>, // Track inodes to avoid counting hard links multiple times
 }
 
-pub async fn scan_directory_async(path: String, window: Window) -> Result<FileNode, String> {
+/// Scan `path`, streaming events to `events`; the returned tree is trimmed to `shape`
+///
+/// Entries matching `exclude_patterns` (see [`ExcludePatterns`]) are never read; they are
+/// reported in the summary's exclusions. Files smaller than `drop_files_below` bytes get no
+/// node of their own: they still count towards their directory's size and the category
+/// totals, but can't be listed or acted on individually.
+pub async fn scan_directory_async<E: EventSink>(
+    path: String,
+    shape: TreeShape,
+    exclude_patterns: Vec<String>,
+    drop_files_below: Option<u64>,
+    events: E,
+) -> Result<FileNode, String> {
     let root_path = PathBuf::from(&path);
 
     // Validate path
     if !root_path.exists() {
         return Err(format!("Path does not exist: {}", path));
     }
+    access::ensure_scan_root_allowed(&root_path)?;
+    let exclude = ExcludePatterns::new(&exclude_patterns)?;
+    let expected_entries = entry_counts::expected_entries(&root_path);
 
     // Create new cancellation token for this scan
     let cancel_token = CancellationToken::new();
@@ -117,13 +270,20 @@ pub async fn scan_directory_async(path: String, window: Window) -> Result<FileNo
         *cancellation = Some(cancel_token.clone());
     }
 
-    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_DIRS));
+    telemetry::reset();
+    profiling::reset();
+    let scan_started = Instant::now();
 
     // Create progress tracker
     let progress = Arc::new(Mutex::new(ProgressStats {
         files_scanned: 0,
+        dirs_completed: 0,
+        counters: ScanCounters::default(),
         total_size: 0,
         current_path: path.clone(),
+        hot_paths: VecDeque::new(),
+        hot_paths_found: 0,
+        categories: HashMap::new(),
         #[cfg(unix)]
         seen_inodes: HashSet::new(),
     }));
@@ -133,46 +293,110 @@ pub async fn scan_directory_async(path: String, window: Window) -> Result<FileNo
     let (tx, mut rx) = mpsc::unbounded_channel::<StreamingScanEvent>();
 
     // Spawn progress emitter task - emits progress updates periodically
-    let window_clone = window.clone();
+    let events_clone = events.clone();
     let progress_clone = progress.clone();
     let progress_task = tokio::spawn(async move {
         let mut interval = tokio::time::interval(Duration::from_millis(BATCH_INTERVAL_MS));
+        let mut stalls = StallDetector::new();
+        let mut hot_paths_sent = 0;
+        let mut categories_sent_at = 0;
         loop {
             interval.tick().await;
 
-            let (files_scanned, total_size, current_path) = {
+            let (files_scanned, dirs_found, dirs_completed, total_size, current_path, visited) = {
                 let stats = progress_clone.lock().await;
                 (
                     stats.files_scanned,
+                    stats.counters.directories,
+                    stats.dirs_completed,
                     stats.total_size,
                     stats.current_path.clone(),
+                    stats.files_scanned + stats.counters.directories + stats.counters.symlinks,
                 )
             };
-            let _ = window_clone.emit(
-                "streaming-scan-event",
-                &StreamingScanEvent::Progress {
+            // The ticker and the category breakdown are only sent when they changed
+            let (hot_paths, categories) = {
+                let stats = progress_clone.lock().await;
+                let hot_paths = (stats.hot_paths_found != hot_paths_sent).then(|| {
+                    hot_paths_sent = stats.hot_paths_found;
+                    stats.hot_paths.iter().cloned().collect::<Vec<_>>()
+                });
+                let categories = (files_scanned != categories_sent_at).then(|| {
+                    categories_sent_at = files_scanned;
+                    category_breakdown(&stats.categories)
+                });
+                (hot_paths, categories)
+            };
+
+            telemetry::sample(scan_started.elapsed(), total_size);
+            let last_path = current_path.clone();
+
+            // Until the first bytes are counted we're still enumerating directories,
+            // so report discovery progress instead of an all-zero progress event
+            let event = if total_size == 0 {
+                StreamingScanEvent::Discovering {
+                    dirs_found,
+                    current_path,
+                }
+            } else {
+                StreamingScanEvent::Progress {
                     files_scanned,
                     total_size,
                     current_path,
-                },
-            );
+                    dirs_found,
+                    dirs_completed,
+                    // The count may be from an earlier scan, so hold back 100 until the end
+                    percent: expected_entries.map(|total| percent_of(visited, total).min(99.9)),
+                }
+            };
+            let span = profiling::span(Phase::Emit);
+            events_clone.send("streaming-scan-event", &event);
+            if let Some(categories) = categories {
+                events_clone.send(
+                    "streaming-scan-event",
+                    &StreamingScanEvent::CategoryProgress { categories },
+                );
+            }
+            if let Some(directories) = hot_paths {
+                events_clone.send(
+                    "streaming-scan-event",
+                    &StreamingScanEvent::HotPaths { directories },
+                );
+            }
+            drop(span);
+
+            let marker = files_scanned + dirs_found + dirs_completed;
+            if let Some(stuck) = stalls.observe(marker, Instant::now()) {
+                // Prefer the read that is hanging over the last path that made progress
+                let in_flight = watchdog::oldest_in_flight();
+                let stalled = OperationStalled {
+                    operation: "scan".to_string(),
+                    can_skip: in_flight.is_some(),
+                    path: in_flight
+                        .map(|(path, _)| path.to_string_lossy().to_string())
+                        .unwrap_or(last_path),
+                    stalled_for_secs: stuck.as_secs(),
+                };
+                events_clone.send("operation-stalled", &stalled);
+            }
         }
     });
 
     // Spawn completion event handler
-    let window_clone2 = window.clone();
     let event_task = tokio::spawn(async move {
         while let Some(evt) = rx.recv().await {
-            let _ = window_clone2.emit("streaming-scan-event", &evt);
+            events.send("streaming-scan-event", &evt);
         }
     });
 
     // Scan the directory tree with progressive updates for root level
     let result = scan_root_with_updates(
         root_path.clone(),
-        semaphore,
+        shape,
+        exclude,
+        drop_files_below,
+        tx.clone(),
         progress.clone(),
-        window.clone(),
         cancel_token.clone(),
     )
     .await;
@@ -186,7 +410,12 @@ pub async fn scan_directory_async(path: String, window: Window) -> Result<FileNo
         *cancellation = None;
     }
 
-    let result = result?;
+    let (result, summary) = result?;
+    // Whatever the pre-pass said, this scan's count is the better guess for the next one
+    let _ = entry_counts::record_entries(
+        &root_path,
+        summary.files + summary.directories + summary.symlinks,
+    );
 
     let total_files = count_files(&result);
     let total_size = result.size;
@@ -195,15 +424,37 @@ pub async fn scan_directory_async(path: String, window: Window) -> Result<FileNo
     let _ = tx.send(StreamingScanEvent::Complete {
         files_scanned: total_files,
         total_size,
+        schema_version: SCHEMA_VERSION,
+        summary: Box::new(summary),
     });
 
     // Close channel and wait for event task to finish
     drop(tx);
     let _ = event_task.await;
 
+    webhooks::notify(Notification::ScanCompleted {
+        root: path,
+        files_scanned: total_files,
+        total_size,
+    });
+
     Ok(result)
 }
 
+/// Per-category totals, largest first
+fn category_breakdown(categories: &HashMap<FileType, (u64, u64)>) -> Vec<CategoryStats> {
+    let mut breakdown: Vec<CategoryStats> = categories
+        .iter()
+        .map(|(category, &(total_size, file_count))| CategoryStats {
+            category: category.clone(),
+            total_size,
+            file_count,
+        })
+        .collect();
+    breakdown.sort_by_key(|c| std::cmp::Reverse(c.total_size));
+    breakdown
+}
+
 /// Top-down progressive scanner that populates the registry
 fn scan_progressive(
     path: PathBuf,
@@ -211,8 +462,9 @@ fn scan_progressive(
     registry: NodeRegistry,
     semaphore: Arc<Semaphore>,
     progress: Arc<Mutex<ProgressStats>>,
+    context: Arc<ScanContext>,
     cancel_token: CancellationToken,
-) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), String>> + Send>> {
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<u64, String>> + Send>> {
     Box::pin(async move {
         scan_progressive_impl(
             path,
@@ -220,6 +472,7 @@ fn scan_progressive(
             registry,
             semaphore,
             progress,
+            context,
             cancel_token,
         )
         .await
@@ -232,18 +485,54 @@ async fn scan_progressive_impl(
     registry: NodeRegistry,
     semaphore: Arc<Semaphore>,
     progress: Arc<Mutex<ProgressStats>>,
+    context: Arc<ScanContext>,
     cancel_token: CancellationToken,
-) -> Result<(), String> {
+) -> Result<u64, String> {
     // Check if scan was cancelled
     if cancel_token.is_cancelled() {
         return Err("Scan cancelled".to_string());
     }
 
-    let _permit = semaphore.acquire().await.expect("semaphore closed");
+    // Leave out our own data directory (unless it is what's being scanned)
+    if parent_path.is_some() && is_app_data_path(&path) {
+        progress.lock().await.counters.excluded.push(ExcludedPath {
+            path: path.to_string_lossy().to_string(),
+            reason: ExclusionReason::AppData,
+        });
+        return Ok(0);
+    }
 
-    let metadata = fs::symlink_metadata(&path)
-        .await
-        .map_err(|e| format!("Cannot access {}: {}", path.display(), e))?;
+    // Once a network mount stops answering, nothing more is read from it
+    let network = &context.network;
+    let mount = network.containing(&path);
+    if mount.is_some_and(|m| network.is_unreachable(m)) {
+        leave_out(
+            &path,
+            ExclusionReason::Unreachable,
+            mount,
+            &registry,
+            &progress,
+            network,
+        )
+        .await;
+        return Ok(0);
+    }
+    let timeout = mount.map(|_| NETWORK_READ_TIMEOUT);
+
+    let _permit = semaphore.acquire().await.expect("semaphore closed");
+    let in_flight = watchdog::begin(&path);
+
+    let span = profiling::span(Phase::Metadata);
+    let metadata = guarded(&in_flight, timeout, fs::symlink_metadata(&path)).await;
+    drop(span);
+    let metadata = match metadata {
+        Ok(metadata) => metadata,
+        Err(reason) => {
+            leave_out(&path, reason, mount, &registry, &progress, network).await;
+            return Ok(0);
+        }
+    };
+    let metadata = metadata.map_err(|e| format!("Cannot access {}: {}", path.display(), e))?;
 
     let name = path
         .file_name()
@@ -253,107 +542,148 @@ async fn scan_progressive_impl(
 
     let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
 
-    // Skip symlinks entirely to avoid double-counting and confusion
+    // Report symlinks instead of following them
     if metadata.is_symlink() {
-        return Ok(());
+        record_symlink(path, parent_path, modified, &registry, &progress, &context).await;
+        return Ok(0);
     }
 
     if !metadata.is_dir() {
-        // Regular file - add to registry and update progress
         // On Unix, use blocks * 512 to get actual disk usage (handles sparse files correctly)
         #[cfg(unix)]
-        let size = metadata.blocks() * 512;
+        let (size, inode) = (metadata.blocks() * 512, Some(metadata.ino()));
 
         #[cfg(not(unix))]
-        let size = metadata.len();
-
-        let file_type = classify_file(&path);
+        let (size, inode) = (metadata.len(), None);
 
-        // Check if this file was already scanned (shouldn't happen, but be safe)
-        let is_new = {
-            let mut reg = registry.lock().await;
-            let was_present = reg.contains_key(&path);
-            reg.insert(
-                path.clone(),
-                DiscoveredNode {
-                    path: path.clone(),
-                    name: name.clone(),
-                    size,
-                    is_directory: false,
-                    file_type: file_type.clone(),
-                    modified,
-                    parent_path: parent_path.clone(),
-                    is_complete: true,
-                },
-            );
-            !was_present
+        let file = FileEntry {
+            path,
+            parent_path,
+            size,
+            logical_size: metadata.len(),
+            modified,
+            inode,
         };
-
-        // Only update progress stats if this is a new file
-        if is_new {
-            let mut stats = progress.lock().await;
-
-            // On Unix, check if we've seen this inode before (hard link detection)
-            #[cfg(unix)]
-            let is_new_inode = {
-                let inode = metadata.ino();
-                stats.seen_inodes.insert(inode)
-            };
-
-            #[cfg(not(unix))]
-            let is_new_inode = true;
-
-            // Only count size if this is a new inode (not a hard link)
-            if is_new_inode {
-                stats.files_scanned += 1;
-                stats.total_size += size;
-            }
-            stats.current_path = path.to_string_lossy().to_string();
-        }
-
-        return Ok(());
+        return Ok(record_file(file, &registry, &progress, &context).await);
     }
 
     // Directory - add to registry
     let file_type = FileType::Other;
 
-    registry.lock().await.insert(
-        path.clone(),
-        DiscoveredNode {
-            path: path.clone(),
-            name: name.clone(),
-            size: 0,
-            is_directory: true,
-            file_type: file_type.clone(),
-            modified,
-            parent_path: parent_path.clone(),
-            is_complete: false,
-        },
-    );
+    let node = DiscoveredNode {
+        id: context.id_for(&path),
+        path: path.clone(),
+        name: name.clone(),
+        size: 0,
+        logical_size: 0,
+        is_directory: true,
+        file_type: file_type.clone(),
+        modified,
+        parent_path: parent_path.clone(),
+        is_complete: false,
+        is_symlink: false,
+        symlink_target: None,
+        file_count: 0,
+        dropped: None,
+    };
+    {
+        let mut reg = registry.lock().await;
+        context.announce(&reg, &node);
+        reg.insert(path.clone(), node);
+    }
 
     // Update progress with current directory
     {
         let mut stats = progress.lock().await;
+        stats.counters.directories += 1;
         stats.current_path = path.to_string_lossy().to_string();
     }
 
-    // Read directory entries
-    let mut entries = fs::read_dir(&path)
-        .await
-        .map_err(|e| format!("Cannot read directory {}: {}", path.display(), e))?;
+    // List the directory with every entry's metadata in one batch; files and links are
+    // recorded right away and only subdirectories get a task of their own
+    let span = profiling::span(Phase::ReadDir);
+    let list_path = path.clone();
+    let io_uring = context.io_uring;
+    let listing = guarded(
+        &in_flight,
+        timeout,
+        tokio::task::spawn_blocking(move || listing::list_dir(&list_path, io_uring)),
+    )
+    .await;
+    drop(span);
+    let listing = match listing {
+        Ok(listing) => listing.map_err(|e| e.to_string())?,
+        Err(reason) => {
+            leave_out(&path, reason, mount, &registry, &progress, network).await;
+            return Ok(0);
+        }
+    };
+    let listing =
+        listing.map_err(|e| format!("Cannot read directory {}: {}", path.display(), e))?;
+    progress.lock().await.counters.errors += listing.errors;
+
+    let mut subtree_size = 0;
+    let mut entry_paths = Vec::new();
+    for entry in listing.entries {
+        let entry_path = path.join(&entry.name);
+        if is_app_data_path(&entry_path) {
+            progress.lock().await.counters.excluded.push(ExcludedPath {
+                path: entry_path.to_string_lossy().to_string(),
+                reason: ExclusionReason::AppData,
+            });
+            continue;
+        }
+        let is_dir = entry.kind == EntryKind::Dir;
+        if !context.exclude.is_empty() {
+            let relative = entry_path
+                .strip_prefix(&context.root)
+                .unwrap_or(&entry_path);
+            if context.exclude.is_excluded(relative, is_dir) {
+                progress.lock().await.counters.excluded.push(ExcludedPath {
+                    path: entry_path.to_string_lossy().to_string(),
+                    reason: ExclusionReason::Pattern,
+                });
+                continue;
+            }
+        }
+        match entry.kind {
+            EntryKind::Dir => entry_paths.push(entry_path),
+            EntryKind::Symlink => {
+                let parent = Some(path.clone());
+                record_symlink(
+                    entry_path,
+                    parent,
+                    entry.modified,
+                    &registry,
+                    &progress,
+                    &context,
+                )
+                .await;
+            }
+            EntryKind::File => {
+                let file = FileEntry {
+                    path: entry_path,
+                    parent_path: Some(path.clone()),
+                    size: entry.size,
+                    logical_size: entry.logical_size,
+                    modified: entry.modified,
+                    inode: entry.inode,
+                };
+                subtree_size += record_file(file, &registry, &progress, &context).await;
+            }
+        }
+    }
 
+    // Permits are handed out in the order tasks ask for them, so spawning the previously
+    // largest first walks them first
+    prioritize(&mut entry_paths, &context.previous_sizes);
     let mut child_handles = Vec::new();
-
-    while let Some(entry) = entries
-        .next_entry()
-        .await
-        .map_err(|e| format!("Error reading entry: {}", e))?
-    {
-        let entry_path = entry.path();
+    for entry_path in entry_paths {
         let registry_clone = registry.clone();
         let sem = semaphore.clone();
         let progress_clone = progress.clone();
         let parent = Some(path.clone());
+        let context_clone = context.clone();
         let cancel_clone = cancel_token.clone();
 
         let handle = tokio::task::spawn(async move {
@@ -363,6 +693,7 @@ async fn scan_progressive_impl(
                 registry_clone,
                 sem,
                 progress_clone,
+                context_clone,
                 cancel_clone,
             )
             .await
@@ -372,29 +703,282 @@ async fn scan_progressive_impl(
     }
 
     // Release permit before waiting
+    drop(in_flight);
     drop(_permit);
 
-    // Wait for all children
+    // Wait for all children, counting the ones that failed
     for handle in child_handles {
-        let _ = handle.await;
+        match handle.await {
+            Ok(Ok(size)) => subtree_size += size,
+            Ok(Err(_)) => progress.lock().await.counters.errors += 1,
+            Err(_) => {}
+        }
     }
 
     // Mark directory as complete
     if let Some(node) = registry.lock().await.get_mut(&path) {
         node.is_complete = true;
     }
+    let mut stats = progress.lock().await;
+    stats.dirs_completed += 1;
+    if subtree_size >= HOT_PATH_MIN_SIZE {
+        stats.hot_paths.push_front(HotPath {
+            path: path.to_string_lossy().to_string(),
+            size: subtree_size,
+        });
+        stats.hot_paths.truncate(HOT_PATH_WINDOW);
+        stats.hot_paths_found += 1;
+    }
+
+    Ok(subtree_size)
+}
+
+/// A regular file found by the walk
+struct FileEntry {
+    path: PathBuf,
+    parent_path: Option<PathBuf>,
+    size: u64,
+    logical_size: u64,
+    modified: SystemTime,
+    /// For hard link detection; `None` where the platform has no inodes
+    inode: Option<u64>,
+}
+
+/// Add a file to the registry and the progress stats, returning the bytes it adds
+///
+/// A file under the scan's `drop_files_below` is folded into its directory's node instead.
+async fn record_file(
+    file: FileEntry,
+    registry: &NodeRegistry,
+    progress: &Arc<Mutex<ProgressStats>>,
+    context: &ScanContext,
+) -> u64 {
+    let FileEntry {
+        path,
+        parent_path,
+        size,
+        logical_size,
+        modified,
+        inode,
+    } = file;
+    let file_type = classify_file(&path);
+
+    // Check if this file was already scanned (shouldn't happen, but be safe)
+    let is_new = {
+        let mut reg = registry.lock().await;
+        let dropped_into = parent_path
+            .as_ref()
+            .filter(|_| context.drop_files_below.is_some_and(|below| size < below))
+            .and_then(|parent| reg.get_mut(parent));
+        if let Some(parent) = dropped_into {
+            parent.drop_file(file_type.clone(), size);
+            true
+        } else {
+            let was_present = reg.contains_key(&path);
+            let node = DiscoveredNode {
+                id: context.id_for(&path),
+                path: path.clone(),
+                name: path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("")
+                    .to_string(),
+                size,
+                logical_size,
+                is_directory: false,
+                file_type: file_type.clone(),
+                modified,
+                parent_path,
+                is_complete: true,
+                is_symlink: false,
+                symlink_target: None,
+                file_count: 1,
+                dropped: None,
+            };
+            context.announce(&reg, &node);
+            reg.insert(path.clone(), node);
+            !was_present
+        }
+    };
+
+    // Only update progress stats if this is a new file
+    if is_new {
+        let mut stats = progress.lock().await;
+
+        // On Unix, check if we've seen this inode before (hard link detection)
+        #[cfg(unix)]
+        let is_new_inode = inode.is_none_or(|inode| stats.seen_inodes.insert(inode));
+
+        #[cfg(not(unix))]
+        let is_new_inode = inode.is_none();
+
+        // Only count size if this is a new inode (not a hard link)
+        if is_new_inode {
+            stats.files_scanned += 1;
+            stats.total_size += size;
+            let category = stats.categories.entry(file_type).or_insert((0, 0));
+            category.0 += size;
+            category.1 += 1;
+        } else {
+            stats.counters.hard_link_bytes += size;
+        }
+        stats.current_path = path.to_string_lossy().to_string();
+    }
+
+    size
+}
+
+/// Report a symlink (or Windows junction, which std treats as a symlink) as a zero-size node
+/// instead of following it, to avoid double-counting while still showing it
+async fn record_symlink(
+    path: PathBuf,
+    parent_path: Option<PathBuf>,
+    modified: SystemTime,
+    registry: &NodeRegistry,
+    progress: &Arc<Mutex<ProgressStats>>,
+    context: &ScanContext,
+) {
+    let symlink_target = fs::read_link(&path).await.ok();
+    let node = DiscoveredNode {
+        id: context.id_for(&path),
+        name: path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string(),
+        path,
+        size: 0,
+        logical_size: 0,
+        is_directory: false,
+        file_type: FileType::Other,
+        modified,
+        parent_path,
+        is_complete: true,
+        is_symlink: true,
+        symlink_target,
+        file_count: 0,
+        dropped: None,
+    };
+    {
+        let mut reg = registry.lock().await;
+        context.announce(&reg, &node);
+        reg.insert(node.path.clone(), node);
+    }
+    progress.lock().await.counters.symlinks += 1;
+}
+
+/// Order entries by their size in the previous scan, largest first; entries it didn't have
+/// (files among them) keep their listing order after the ones it did
+fn prioritize(paths: &mut [PathBuf], previous_sizes: &HashMap<PathBuf, u64>) {
+    if !previous_sizes.is_empty() {
+        paths.sort_by_key(|p| std::cmp::Reverse(previous_sizes.get(p).copied()));
+    }
+}
+
+/// The newest retained scan covering `root`
+async fn newest_scan_covering(root: &Path) -> Option<u64> {
+    registry::retained_summaries()
+        .await
+        .into_iter()
+        .filter(|summary| root.starts_with(&summary.root))
+        .map(|summary| summary.scan_id)
+        .max()
+}
+
+/// Directory sizes below `root` from the newest retained scan covering it
+pub(crate) async fn previous_directory_sizes(root: &Path) -> HashMap<PathBuf, u64> {
+    let Some(scan_id) = newest_scan_covering(root).await else {
+        return HashMap::new();
+    };
+    registry::with_scan(scan_id, |nodes, _| {
+        nodes
+            .values()
+            .filter(|n| n.is_directory && n.path.starts_with(root))
+            .map(|n| (n.path.clone(), n.size))
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Ids of every node below `root` in the newest retained scan covering it
+async fn previous_node_ids(root: &Path) -> HashMap<PathBuf, u64> {
+    let Some(scan_id) = newest_scan_covering(root).await else {
+        return HashMap::new();
+    };
+    registry::with_scan(scan_id, |nodes, _| {
+        nodes
+            .values()
+            .filter(|n| n.path.starts_with(root))
+            .map(|n| (n.path.clone(), n.id))
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Await a file system call unless the user skips the path or the timeout runs out
+async fn guarded<T>(
+    in_flight: &InFlightGuard,
+    timeout: Option<Duration>,
+    call: impl Future<Output = T>,
+) -> Result<T, ExclusionReason> {
+    let timed_out = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    let timed = async {
+        let started = Instant::now();
+        let result = call.await;
+        telemetry::record(started.elapsed());
+        result
+    };
+    tokio::select! {
+        result = timed => Ok(result),
+        _ = in_flight.skip.cancelled() => Err(ExclusionReason::Skipped),
+        _ = timed_out => Err(ExclusionReason::Unreachable),
+    }
+}
 
-    Ok(())
+/// Leave a path out of the scan, keeping whatever was found below it
+async fn leave_out(
+    path: &Path,
+    reason: ExclusionReason,
+    mount: Option<&PathBuf>,
+    registry: &NodeRegistry,
+    progress: &Arc<Mutex<ProgressStats>>,
+    network: &NetworkMounts,
+) {
+    if reason == ExclusionReason::Unreachable {
+        if let Some(mount) = mount {
+            network.unreachable.lock().unwrap().insert(mount.clone());
+        }
+    }
+    if let Some(node) = registry.lock().await.get_mut(path) {
+        node.is_complete = true;
+    }
+    progress.lock().await.counters.excluded.push(ExcludedPath {
+        path: path.to_string_lossy().to_string(),
+        reason,
+    });
 }
 
 /// Special root-level scan that sends time-based partial tree snapshots
 async fn scan_root_with_updates(
     path: PathBuf,
-    semaphore: Arc<Semaphore>,
+    shape: TreeShape,
+    exclude: ExcludePatterns,
+    drop_files_below: Option<u64>,
+    updates: mpsc::UnboundedSender<StreamingScanEvent>,
     progress: Arc<Mutex<ProgressStats>>,
-    _window: Window,
     cancel_token: CancellationToken,
-) -> Result<FileNode, String> {
+) -> Result<(FileNode, ScanSummary), String> {
+    let started = Instant::now();
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_DIRS));
+    let journal = change_journal::current_cursor(&path);
+
     // Create shared registry for discovered nodes
     let registry: NodeRegistry = Arc::new(Mutex::new(HashMap::new()));
 
@@ -404,6 +988,19 @@ async fn scan_root_with_updates(
     let progress_clone = progress.clone();
     let root_path_clone = path.clone();
     let cancel_clone = cancel_token.clone();
+    let context = Arc::new(ScanContext {
+        root: path.clone(),
+        network: NetworkMounts {
+            mounts: network_mount_points(&path),
+            ..Default::default()
+        },
+        exclude,
+        drop_files_below,
+        io_uring: settings::load_settings().is_ok_and(|s| s.use_io_uring),
+        previous_sizes: previous_directory_sizes(&path).await,
+        previous_ids: previous_node_ids(&path).await,
+        updates,
+    });
 
     scan_progressive(
         root_path_clone,
@@ -411,6 +1008,7 @@ async fn scan_root_with_updates(
         registry_clone,
         sem_clone,
         progress_clone,
+        context,
         cancel_clone,
     )
     .await?;
@@ -418,25 +1016,51 @@ async fn scan_root_with_updates(
     // Build a shallow tree for initial display (depth 2)
     // This prevents freezing when dealing with millions of files
     // Deeper levels can be loaded on-demand by the frontend
-    let reg = registry.lock().await;
-    let final_tree = build_tree_from_registry_with_depth(&reg, &path, 2)
-        .ok_or_else(|| "Failed to build final tree".to_string())?;
+    let (final_tree, summary) = {
+        let _span = profiling::span(Phase::TreeBuild);
+        let reg = registry.lock().await;
+        let tree = build_tree_from_registry_with_depth(&reg, &path, 2, shape)
+            .ok_or_else(|| "Failed to build final tree".to_string())?;
+        let counters = progress.lock().await.counters.clone();
+        let mut summary = build_summary(
+            registry::next_scan_id(),
+            &reg,
+            &path,
+            &counters,
+            started.elapsed(),
+            ScanStrategy::Progressive,
+        );
+        summary.inodes = get_inode_usage(&path);
+        summary.quota = get_user_quota(&path);
+        summary.cluster_size = get_cluster_size(&path);
+        summary.journal = journal;
+        summary.io = Some(telemetry::snapshot());
+        (tree, summary)
+    };
 
-    Ok(final_tree)
+    // Keep the registry around so deletions can be applied to it later
+    registry::retain_scan(registry, summary.clone()).await;
+
+    Ok((final_tree, summary))
 }
 
 fn count_files(node: &FileNode) -> u64 {
+    if node.is_symlink {
+        return 0;
+    }
+
     if !node.is_directory {
         return 1;
     }
 
-    node.children.iter().map(|c| count_files(c)).sum()
+    node.children.iter().map(count_files).sum()
 }
 
-fn build_tree_from_registry_with_depth(
+pub(crate) fn build_tree_from_registry_with_depth(
     registry: &HashMap<PathBuf, DiscoveredNode>,
     path: &PathBuf,
     max_depth: usize,
+    shape: TreeShape,
 ) -> Option<FileNode> {
     // Build parent->children index for O(1) lookups
     let mut parent_to_children: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
@@ -444,7 +1068,7 @@ fn build_tree_from_registry_with_depth(
         if let Some(parent) = &child_node.parent_path {
             parent_to_children
                 .entry(parent.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(child_path.clone());
         }
     }
@@ -453,14 +1077,32 @@ fn build_tree_from_registry_with_depth(
     // This way we only calculate sizes for nodes we actually include in the tree
     let mut size_cache: HashMap<PathBuf, u64> = HashMap::new();
 
-    build_tree_recursive_lazy(
+    let mut tree = build_tree_recursive_lazy(
         registry,
         &parent_to_children,
         &mut size_cache,
         path,
         0,
         max_depth,
-    )
+        shape,
+    )?;
+    let parent_size = tree
+        .path
+        .parent()
+        .and_then(|parent| registry.get(parent))
+        .map(|parent| parent.size);
+    fill_percentages(&mut tree, parent_size, volume_capacity(path));
+    Some(tree)
+}
+
+/// Set the percentage fields of `node` and everything below it, so every view shows the
+/// same rounding
+fn fill_percentages(node: &mut FileNode, parent_size: Option<u64>, volume_total: Option<u64>) {
+    node.pct_of_parent = parent_size.map(|parent| percent_of(node.size, parent));
+    node.pct_of_volume = volume_total.map(|total| percent_of(node.size, total));
+    for child in &mut node.children {
+        fill_percentages(child, Some(node.size), volume_total);
+    }
 }
 
 /// Build tree recursively with lazy size calculation (only for nodes we include)
@@ -471,20 +1113,13 @@ fn build_tree_recursive_lazy(
     path: &PathBuf,
     current_depth: usize,
     max_depth: usize,
+    shape: TreeShape,
 ) -> Option<FileNode> {
     let node = registry.get(path)?;
 
     if !node.is_directory {
         // File - return immediately with its size
-        return Some(FileNode {
-            name: node.name.clone(),
-            path: node.path.clone(),
-            size: node.size,
-            is_directory: false,
-            file_type: node.file_type.clone(),
-            children: vec![],
-            modified: node.modified,
-        });
+        return Some(leaf_node(node));
     }
 
     // Directory - build children if within depth limit
@@ -500,16 +1135,24 @@ fn build_tree_recursive_lazy(
                     child_path,
                     current_depth + 1,
                     max_depth,
+                    shape,
                 ) {
                     children.push(child_tree);
                 }
             }
         }
 
-        // Sort by size and limit to top 100
-        children.sort_by(|a, b| b.size.cmp(&a.size));
-        if children.len() > 100 {
-            children.truncate(100);
+        children.extend(dropped_files(node));
+        if let Some(threshold) = shape.aggregate_below {
+            aggregate_small_files(node, &mut children, threshold);
+        }
+
+        // Sort by size and keep the largest, summing the rest into one node
+        // Ties are broken by name so ordering is deterministic across runs
+        sort_children(&mut children);
+        if children.len() > shape.child_limit {
+            let rest = children.split_off(shape.child_limit);
+            children.push(more_items(node, &rest));
         }
     }
 
@@ -517,6 +1160,7 @@ fn build_tree_recursive_lazy(
     let dir_size = calculate_dir_size_lazy(registry, parent_to_children, size_cache, path);
 
     Some(FileNode {
+        id: node.id,
         name: node.name.clone(),
         path: node.path.clone(),
         size: dir_size,
@@ -524,9 +1168,229 @@ fn build_tree_recursive_lazy(
         file_type: FileType::Other,
         children,
         modified: node.modified,
+        is_symlink: false,
+        symlink_target: None,
+        synthetic: None,
+        pct_of_parent: None,
+        pct_of_volume: None,
+        is_partial: !node.is_complete,
     })
 }
 
+/// A node without its children
+fn leaf_node(node: &DiscoveredNode) -> FileNode {
+    FileNode {
+        id: node.id,
+        name: node.name.clone(),
+        path: node.path.clone(),
+        size: node.size,
+        is_directory: node.is_directory,
+        file_type: node.file_type.clone(),
+        children: vec![],
+        modified: node.modified,
+        is_symlink: node.is_symlink,
+        symlink_target: node.symlink_target.clone(),
+        synthetic: None,
+        pct_of_parent: None,
+        pct_of_volume: None,
+        is_partial: node.is_directory && !node.is_complete,
+    }
+}
+
+/// One node standing for `entries` of `dir`, with their total size
+fn synthetic_node(
+    dir: &DiscoveredNode,
+    name: String,
+    entries: &[FileNode],
+    synthetic: SyntheticNode,
+) -> FileNode {
+    FileNode {
+        id: 0,
+        name,
+        path: dir.path.clone(),
+        size: entries.iter().map(|c| c.size).sum(),
+        is_directory: false,
+        file_type: FileType::Other,
+        children: vec![],
+        modified: dir.modified,
+        is_symlink: false,
+        symlink_target: None,
+        synthetic: Some(synthetic),
+        pct_of_parent: None,
+        pct_of_volume: None,
+        is_partial: false,
+    }
+}
+
+/// One node standing for the files of `dir` that have no nodes of their own
+fn dropped_files(dir: &DiscoveredNode) -> Option<FileNode> {
+    let dropped = dir.dropped.as_ref()?;
+    let count = dropped.files;
+    Some(FileNode {
+        size: dropped.bytes,
+        ..synthetic_node(
+            dir,
+            format!("{} small files", count),
+            &[],
+            SyntheticNode::Dropped { count },
+        )
+    })
+}
+
+/// One node carrying the count and total size of the children left out of `dir`
+fn more_items(dir: &DiscoveredNode, rest: &[FileNode]) -> FileNode {
+    let count = rest.len() as u64;
+    let name = format!("{} more items", count);
+    synthetic_node(dir, name, rest, SyntheticNode::MoreItems { count })
+}
+
+/// Replace the files of `dir` smaller than `threshold` with one node summing them, when
+/// there is more than one
+fn aggregate_small_files(dir: &DiscoveredNode, children: &mut Vec<FileNode>, threshold: u64) {
+    let (small, kept): (Vec<FileNode>, Vec<FileNode>) = std::mem::take(children)
+        .into_iter()
+        .partition(|c| !c.is_directory && c.synthetic.is_none() && c.size < threshold);
+    *children = kept;
+    if small.len() < 2 {
+        children.extend(small);
+        return;
+    }
+    let count = small.len() as u64;
+    let name = format!("{} small files", count);
+    children.push(synthetic_node(
+        dir,
+        name,
+        &small,
+        SyntheticNode::SmallFiles { count, threshold },
+    ));
+}
+
+/// Children of `path` in a retained scan, largest first, from `offset` on
+pub async fn get_children(
+    scan_id: u64,
+    path: PathBuf,
+    offset: usize,
+    limit: usize,
+) -> Result<ChildrenPage, String> {
+    let volume_total = volume_capacity(&path);
+    registry::with_scan(scan_id, |nodes, _| {
+        // Directory sizes were aggregated when the scan was retained
+        let mut children: Vec<FileNode> = nodes
+            .values()
+            .filter(|n| n.parent_path.as_ref() == Some(&path))
+            .map(leaf_node)
+            .chain(nodes.get(&path).and_then(dropped_files))
+            .collect();
+        sort_children(&mut children);
+        let total = children.len();
+        let parent_size = nodes.get(&path).map(|parent| parent.size);
+        let children = children
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|mut child| {
+                fill_percentages(&mut child, parent_size, volume_total);
+                child
+            })
+            .collect();
+        ChildrenPage {
+            scan_id,
+            path,
+            offset,
+            total,
+            children,
+        }
+    })
+    .await
+}
+
+/// Children of `path` from the newest retained scan that has it, or read from disk
+///
+/// Lets a folder below the depth of the returned tree be expanded without knowing which
+/// scan it came from. A folder in no retained scan is listed one level deep and not
+/// retained: the page then has scan id 0, and its subdirectories are partial, with no size.
+pub async fn find_children(
+    path: PathBuf,
+    offset: usize,
+    limit: usize,
+) -> Result<ChildrenPage, String> {
+    match registry::newest_scan_with_dir(&path).await {
+        Some(scan_id) => get_children(scan_id, path, offset, limit).await,
+        None => list_children(path, offset, limit).await,
+    }
+}
+
+/// Children of `path` straight from disk, for a folder no retained scan has
+async fn list_children(path: PathBuf, offset: usize, limit: usize) -> Result<ChildrenPage, String> {
+    access::ensure_scan_root_allowed(&path)?;
+    let volume_total = volume_capacity(&path);
+    let io_uring = settings::load_settings().is_ok_and(|s| s.use_io_uring);
+    let dir = path.clone();
+    let mut children = tokio::task::spawn_blocking(move || {
+        let listing = listing::list_dir(&dir, io_uring)
+            .map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?;
+        Ok::<_, String>(
+            listing
+                .entries
+                .into_iter()
+                .map(|entry| listed_node(&dir, entry))
+                .collect::<Vec<_>>(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    sort_children(&mut children);
+    let total = children.len();
+    let children = children
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|mut child| {
+            fill_percentages(&mut child, None, volume_total);
+            child
+        })
+        .collect();
+    Ok(ChildrenPage {
+        scan_id: 0,
+        path,
+        offset,
+        total,
+        children,
+    })
+}
+
+/// A directory entry as listed, sized the way the scanner would record it
+fn listed_node(dir: &Path, entry: listing::ListedEntry) -> FileNode {
+    let path = dir.join(&entry.name);
+    let is_directory = entry.kind == EntryKind::Dir;
+    let is_symlink = entry.kind == EntryKind::Symlink;
+    FileNode {
+        id: registry::next_node_id(),
+        name: entry.name.to_string_lossy().to_string(),
+        size: if is_symlink { 0 } else { entry.size },
+        is_directory,
+        file_type: if entry.kind == EntryKind::File {
+            classify_file(&path)
+        } else {
+            FileType::Other
+        },
+        children: vec![],
+        modified: entry.modified,
+        is_symlink,
+        symlink_target: is_symlink.then(|| std::fs::read_link(&path).ok()).flatten(),
+        synthetic: None,
+        pct_of_parent: None,
+        pct_of_volume: None,
+        is_partial: is_directory,
+        path,
+    }
+}
+
+/// Order children largest first, breaking ties by name
+fn sort_children(children: &mut [FileNode]) {
+    children.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+}
+
 /// Calculate directory size recursively with memoization
 fn calculate_dir_size_lazy(
     registry: &HashMap<PathBuf, DiscoveredNode>,
@@ -548,7 +1412,7 @@ fn calculate_dir_size_lazy(
         node.size
     } else {
         // Sum all children
-        let mut total = 0u64;
+        let mut total = node.dropped.as_ref().map_or(0, |d| d.bytes);
         if let Some(child_paths) = parent_to_children.get(path) {
             for child_path in child_paths {
                 total += calculate_dir_size_lazy(registry, parent_to_children, cache, child_path);
@@ -560,3 +1424,185 @@ fn calculate_dir_size_lazy(
     cache.insert(path.clone(), size);
     size
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+
+    #[tokio::test]
+    async fn test_network_reads_time_out() {
+        let network = NetworkMounts {
+            mounts: vec![
+                PathBuf::from("/mnt/share"),
+                PathBuf::from("/mnt/share/nested"),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            network.containing(Path::new("/mnt/share/nested/a")),
+            Some(&PathBuf::from("/mnt/share/nested"))
+        );
+        assert_eq!(network.containing(Path::new("/home")), None);
+
+        let path = Path::new("/mnt/share/test_network_reads_time_out");
+        let in_flight = watchdog::begin(path);
+        let hung = guarded(
+            &in_flight,
+            Some(Duration::from_millis(10)),
+            std::future::pending::<()>(),
+        );
+        assert_eq!(hung.await, Err(ExclusionReason::Unreachable));
+        assert_eq!(guarded(&in_flight, None, async { 7 }).await, Ok(7));
+    }
+
+    #[test]
+    fn test_children_past_the_limit_are_summed() {
+        let nodes: HashMap<PathBuf, DiscoveredNode> = [
+            registry::test_node("/scan", 0, true),
+            registry::test_node("/scan/a", 40, false),
+            registry::test_node("/scan/b", 30, false),
+            registry::test_node("/scan/c", 20, false),
+            registry::test_node("/scan/d", 10, false),
+        ]
+        .into_iter()
+        .map(|n| (n.path.clone(), n))
+        .collect();
+
+        let root = PathBuf::from("/scan");
+        let tree =
+            build_tree_from_registry_with_depth(&nodes, &root, 2, TreeShape::new(Some(2), None))
+                .unwrap();
+        let names: Vec<&str> = tree.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "2 more items"]);
+        let rest = &tree.children[2];
+        assert_eq!(rest.size, 30);
+        assert_eq!(rest.path, Path::new("/scan"));
+        assert_eq!(rest.synthetic, Some(SyntheticNode::MoreItems { count: 2 }));
+        assert_eq!(tree.children.iter().map(|c| c.size).sum::<u64>(), tree.size);
+        assert_eq!(tree.pct_of_parent, None);
+        assert_eq!(tree.children[0].pct_of_parent, Some(40.0));
+        assert_eq!(rest.pct_of_parent, Some(30.0));
+
+        let tree =
+            build_tree_from_registry_with_depth(&nodes, &root, 2, TreeShape::new(None, Some(25)))
+                .unwrap();
+        let names: Vec<&str> = tree.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["a", "2 small files", "b"]);
+        assert_eq!(
+            tree.children[1].synthetic,
+            Some(SyntheticNode::SmallFiles {
+                count: 2,
+                threshold: 25
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unscanned_directory_is_listed_from_disk() {
+        let root = std::env::temp_dir().join(format!("test_find_children_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("nested")).unwrap();
+        std::fs::write(root.join("notes.txt"), "hello").unwrap();
+
+        let page = find_children(root.clone(), 0, 10).await.unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(page.scan_id, 0);
+        assert_eq!(page.total, 2);
+        let nested = page.children.iter().find(|c| c.name == "nested").unwrap();
+        assert!(nested.is_directory && nested.is_partial);
+        let notes = page
+            .children
+            .iter()
+            .find(|c| c.name == "notes.txt")
+            .unwrap();
+        assert_eq!(notes.path, root.join("notes.txt"));
+        assert!(!notes.is_partial);
+    }
+
+    #[test]
+    fn test_previously_largest_directories_go_first() {
+        let mut paths: Vec<PathBuf> = ["/scan/a.txt", "/scan/small", "/scan/new", "/scan/big"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        prioritize(&mut paths, &HashMap::new());
+        assert_eq!(paths[0], Path::new("/scan/a.txt"));
+
+        let previous = HashMap::from([
+            (PathBuf::from("/scan/small"), 10),
+            (PathBuf::from("/scan/big"), 900),
+        ]);
+        prioritize(&mut paths, &previous);
+        let order: Vec<&str> = paths.iter().map(|p| p.to_str().unwrap()).collect();
+        assert_eq!(
+            order,
+            ["/scan/big", "/scan/small", "/scan/a.txt", "/scan/new"]
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct NodeUpdates(Arc<std::sync::Mutex<Vec<serde_json::Value>>>);
+
+    impl EventSink for NodeUpdates {
+        fn send<S: Serialize + Clone>(&self, event: &str, payload: &S) {
+            let payload = serde_json::to_value(payload).unwrap();
+            if event == "streaming-scan-event" && payload["type"] == "node_update" {
+                self.0.lock().unwrap().push(payload);
+            }
+        }
+    }
+
+    impl NodeUpdates {
+        fn ids(&self) -> HashMap<String, (u64, Option<u64>)> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|u| {
+                    let id = u["id"].as_u64().unwrap();
+                    let path = u["path"].as_str().unwrap().to_string();
+                    (path, (id, u["parent_id"].as_u64()))
+                })
+                .collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_node_updates_keep_ids_across_rescans() {
+        let root = std::env::temp_dir().join(format!("test_node_updates_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("docs/deep/deeper")).unwrap();
+        std::fs::write(root.join("a.txt"), "hello").unwrap();
+        std::fs::write(root.join("docs/b.txt"), "world").unwrap();
+        std::fs::write(root.join("docs/deep/deeper/c.txt"), "!").unwrap();
+        let path = root.to_string_lossy().to_string();
+
+        let first = NodeUpdates::default();
+        scan_directory_async(
+            path.clone(),
+            TreeShape::default(),
+            vec![],
+            None,
+            first.clone(),
+        )
+        .await
+        .unwrap();
+        let second = NodeUpdates::default();
+        scan_directory_async(path, TreeShape::default(), vec![], None, second.clone())
+            .await
+            .unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        let first = first.ids();
+        let key = |p: &str| root.join(p).to_string_lossy().to_string();
+        let (docs, _) = first[&key("docs")];
+        assert_eq!(first[&key("docs/b.txt")].1, Some(docs));
+        assert!(first.contains_key(&key("a.txt")));
+        assert!(first.contains_key(&key("docs/deep")));
+        // Below the top two levels nothing is announced
+        assert!(!first.contains_key(&key("docs/deep/deeper")));
+        assert_eq!(second.ids(), first);
+    }
+}
@@ -0,0 +1,151 @@
+use schemars::generate::SchemaSettings;
+use schemars::SchemaGenerator;
+use serde_json::{json, Map, Value};
+
+/// Every type that crosses the IPC boundary, as command arguments, results or event
+/// payloads, plus whatever they contain
+macro_rules! api_types {
+    ($($module:ident::{$($name:ident),* $(,)?}),* $(,)?) => {
+        fn collect(generator: &mut SchemaGenerator) {
+            $($(generator.subschema_for::<crate::$module::$name>();)*)*
+        }
+    };
+}
+
+api_types! {
+    access::{AccessMode, ManagedPolicy},
+    api::{ApiVersion, DeprecationWarning},
+    archives::{ArchiveEntry, ArchiveFormat, ArchiveListing},
+    archiving::{
+        ArchiveManifest, ArchiveOperation, ArchiveOptions, ArchiveResult, ManifestEntry,
+        RestoreFailure, RestoreResult,
+    },
+    breadcrumbs::{Ancestor, DirKind},
+    broken_links::{BrokenLink, BrokenLinkKind},
+    change_journal::{JournalCursor},
+    classifier::{CategoryStats},
+    composition::{ArchiveLayer, FileComposition, LargeFileAnalysis, VmDiskFormat},
+    confidence::{Confidence},
+    config_bundle::{ConfigBundle},
+    csv_export::{CsvExportProgress, CsvExportResult},
+    diff::{CategoryChange, DiffWaterfall, SizeChange, WaterfallBucket, WaterfallStep},
+    disk_images::{DiskImageEntry, DiskImageFormat, DiskImageInfo},
+    duplicates::{
+        DuplicateDirectory, DuplicateDirectoryGroup, DuplicateFile, DuplicateGroup,
+        DuplicateProgress, DuplicateReport, HashPhase, UnreadableFile, VolumeCopies,
+    },
+    eject::{EjectResult},
+    entry_counts::{EntryCount},
+    environment::{
+        EnvironmentReport, FeatureAvailability, GrantedPermissions, Integration, MountedFileSystem,
+    },
+    estimate::{EstimatedNode},
+    explain::{
+        AgeBucket, AgeRange, CategoryShare, Contributor, DuplicatesInside, KnownKind,
+        NodeExplanation,
+    },
+    families::{FamilyMember, VersionFamily},
+    fleet::{FleetMachine, FleetOverview, FleetScan, FleetVolume},
+    history::{HistoryPruneResult, HistoryRetention, HistoryStorageUsage, VolumeHistory},
+    i18n::{LocalizedMessage},
+    maintenance::{AppDataUsage, BucketUsage, DataBucket, PruneOptions, PruneResult},
+    memory::{CompactResult, MemoryUsage},
+    moves::{EntryKind, MoveEntry, MoveJob, MoveMode, MoveStatus},
+    never_suggest::{NeverSuggestEntry},
+    onboarding::{FirstRunChoice, FirstRunScope},
+    ownership::{FailedRepair, RepairResult},
+    persistence::{SavedScan, SavedScanInfo},
+    pins::{PinnedPath, PinnedPathStatus, SizeSample},
+    policies::{CleanupPolicy, PolicyMatch, PolicyReport, PolicyRule},
+    recommendations::{CategorySavings, Recommendation, RecommendationCategory, Recommendations},
+    reconcile::{HiddenSpace, HiddenSpaceSource, SpaceReconciliation},
+    reports::{
+        ClusterOverhead, CrowdedDirectories, CrowdedDirectory, DirectorySlack, LargestFile,
+        LargestFiles, OtherDirectory, OtherFilesReport, OtherGroup, PathExtremes, PathLength,
+        TinyFileDirectory, TinyFilesReport,
+    },
+    safety::{DeletionResult, SafetyCheck},
+    sampling::{SampledDirectory, SampledScan},
+    sandbox::{GrantedFolder},
+    script_export::{CleanupPlan, PlanItem, ScriptFormat},
+    settings::{AppSettings},
+    sizes::{UnitSystem},
+    snapshots::{LocalSnapshot},
+    sniffing::{MisnamedFile, MisnamedReport},
+    space_guard::{InsufficientSpace},
+    storage::{
+        InodeUsage, LocationType, QuotaUsage, ScanScope, ScopeRoot, ShadowStorage, StorageLocation,
+        UserProfile,
+    },
+    summary::{
+        BudgetStats, ExcludedPath, ExclusionReason, IncrementalStats, PathDepth, PathSize,
+        ScanStrategy, ScanSummary, UnexploredDir,
+    },
+    surface::{SurfaceCheckMode, SurfaceCheckResult, SurfaceProgress},
+    system_stores::{CleanupSuggestion, SystemStore, SystemStoreKind},
+    tags::{NodeTag, TagEntry, TaggedNode},
+    telemetry::{IoStats, ThroughputSample},
+    tools::{ToolCapability, ToolState},
+    transfer::{TreeHandle},
+    types::{
+        ChildrenPage, FileNode, FileType, NodeStats, PartialScanResult, ScanProgress,
+        StreamingScanEvent, SyntheticNode,
+    },
+    vm_disks::{CompactionPlan, VmDisk},
+    volume_roots::{CachedVolumeRoot},
+    watchdog::{OperationStalled},
+    webhooks::{Notification, Webhook, WebhookEvent, WebhookFormat, WebhookSettings},
+}
+
+/// JSON Schema (draft 2020-12) with one definition per API type under `$defs`
+pub fn api_schema() -> Value {
+    let mut generator = SchemaSettings::draft2020_12().into_generator();
+    collect(&mut generator);
+    let definitions: Map<String, Value> = generator.take_definitions(true);
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Disk Analyser API",
+        "schema_version": crate::types::SCHEMA_VERSION,
+        "$defs": definitions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Schema file the frontend and third-party clients generate their types from
+    const SCHEMA_FILE: &str = "schema/api.schema.json";
+
+    /// Fails when a type changed without regenerating the schema; run the tests with
+    /// `UPDATE_SCHEMA=1` to write the new one
+    #[test]
+    fn test_schema_file_is_current() {
+        let file = Path::new(env!("CARGO_MANIFEST_DIR")).join(SCHEMA_FILE);
+        let schema = serde_json::to_string_pretty(&api_schema()).unwrap() + "\n";
+        for name in [
+            "StreamingScanEvent",
+            "FileNode",
+            "SafetyCheck",
+            "ScanSummary",
+        ] {
+            assert!(
+                schema.contains(&format!("\"{}\"", name)),
+                "{} missing",
+                name
+            );
+        }
+
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(&file, &schema).unwrap();
+        }
+        let saved = std::fs::read_to_string(&file).unwrap_or_default();
+        assert!(
+            saved == schema,
+            "{} is out of date; run `UPDATE_SCHEMA=1 cargo test schema`",
+            SCHEMA_FILE
+        );
+    }
+}
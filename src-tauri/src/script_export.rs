@@ -0,0 +1,311 @@
+use crate::never_suggest::{self, is_never_suggested, NeverSuggestEntry};
+use crate::persistence::{app_data_dir, is_app_data_path};
+use crate::safety;
+use crate::sizes::SizeFormatter;
+use crate::space_guard::write_guarded;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One entry of a reviewed cleanup plan
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PlanItem {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Why the item was suggested, written as a comment above its command
+    pub reason: Option<String>,
+}
+
+/// Items the user reviewed and wants to remove outside the app
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CleanupPlan {
+    pub items: Vec<PlanItem>,
+    /// Move items to the trash / Recycle Bin instead of deleting them
+    pub use_trash: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptFormat {
+    /// POSIX shell (macOS, Linux)
+    Sh,
+    PowerShell,
+}
+
+/// Quote a path for a POSIX shell (single quotes, with embedded quotes escaped)
+fn sh_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', "'\\''"))
+}
+
+/// Quote a path as a PowerShell literal string
+///
+/// PowerShell also ends such a string at typographic single quotes, so those are doubled too.
+fn powershell_quote(path: &Path) -> String {
+    let mut quoted = String::from("'");
+    for c in path.to_string_lossy().chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Characters that could end a line of the script, and with it a comment or a quoted path
+fn breaks_line(c: char) -> bool {
+    c.is_control() || matches!(c, '\u{2028}' | '\u{2029}')
+}
+
+/// `text` with line-breaking characters written as escapes, safe to put in a comment
+fn escape_for_comment(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if breaks_line(c) {
+                c.escape_default().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+// `--` so a path starting with `-` is never taken for an option
+const SH_DELETE: &str = "remove() {
+  rm -rf -- \"$1\"
+}
+";
+
+const SH_TRASH: &str = "remove() {
+  if command -v trash >/dev/null 2>&1; then
+    trash -- \"$1\"
+  elif command -v gio >/dev/null 2>&1; then
+    gio trash -- \"$1\"
+  else
+    echo \"No trash command found; skipped $1\" >&2
+  fi
+}
+";
+
+const POWERSHELL_DELETE: &str = "function Remove-CleanupItem([string]$Path) {
+    Remove-Item -LiteralPath $Path -Recurse -Force
+}
+";
+
+const POWERSHELL_TRASH: &str = "Add-Type -AssemblyName Microsoft.VisualBasic
+function Remove-CleanupItem([string]$Path) {
+    if (Test-Path -LiteralPath $Path -PathType Container) {
+        [Microsoft.VisualBasic.FileIO.FileSystem]::DeleteDirectory($Path, 'OnlyErrorDialogs', 'SendToRecycleBin')
+    } else {
+        [Microsoft.VisualBasic.FileIO.FileSystem]::DeleteFile($Path, 'OnlyErrorDialogs', 'SendToRecycleBin')
+    }
+}
+";
+
+/// Render a plan as a script; protected and never-suggest items are left in as comments
+///
+/// Paths and reasons never carry a line break into the script: an item whose path holds one
+/// is left in as a comment with it escaped.
+fn render_script(
+    plan: &CleanupPlan,
+    format: ScriptFormat,
+    never_suggest: &[NeverSuggestEntry],
+    sizes: &SizeFormatter,
+    generated_at: &str,
+) -> String {
+    let mut script = String::new();
+    let total: u64 = plan.items.iter().map(|i| i.size).sum();
+
+    if format == ScriptFormat::Sh {
+        script.push_str("#!/bin/sh\n");
+    }
+    let _ = writeln!(
+        script,
+        "# Cleanup script generated by Disk Analyser on {}",
+        generated_at
+    );
+    script.push_str(
+        "# Nothing has been deleted yet. Review every line before running this script.\n",
+    );
+    let _ = writeln!(script, "# Sizes are in {}", sizes.describe());
+    let _ = writeln!(
+        script,
+        "# {} item(s), {} total\n",
+        plan.items.len(),
+        sizes.format(total)
+    );
+
+    script.push_str(match (format, plan.use_trash) {
+        (ScriptFormat::Sh, false) => SH_DELETE,
+        (ScriptFormat::Sh, true) => SH_TRASH,
+        (ScriptFormat::PowerShell, false) => POWERSHELL_DELETE,
+        (ScriptFormat::PowerShell, true) => POWERSHELL_TRASH,
+    });
+
+    for item in &plan.items {
+        script.push('\n');
+        let _ = write!(script, "# {}", sizes.format(item.size));
+        if let Some(reason) = &item.reason {
+            let _ = write!(script, " - {}", escape_for_comment(reason));
+        }
+        script.push('\n');
+
+        let command = match format {
+            ScriptFormat::Sh => format!("remove {}", sh_quote(&item.path)),
+            ScriptFormat::PowerShell => {
+                format!("Remove-CleanupItem {}", powershell_quote(&item.path))
+            }
+        };
+
+        let path = item.path.to_string_lossy();
+        if path.chars().any(breaks_line) {
+            let _ = writeln!(
+                script,
+                "# Skipped, name has control characters: {}",
+                escape_for_comment(&path)
+            );
+        } else if safety::is_protected_path(&item.path) {
+            let _ = writeln!(script, "# Skipped, protected system path: {}", command);
+        } else if is_app_data_path(&item.path) {
+            let _ = writeln!(script, "# Skipped, Disk Analyser's own data: {}", command);
+        } else if is_never_suggested(never_suggest, &item.path) {
+            let _ = writeln!(script, "# Skipped, on the never-suggest list: {}", command);
+        } else {
+            script.push_str(&command);
+            script.push('\n');
+        }
+    }
+
+    script
+}
+
+// Tauri commands
+
+/// Write a reviewed cleanup plan as a script instead of deleting anything from the app
+///
+/// Returns the path of the written script.
+#[tauri::command]
+pub async fn export_cleanup_script_command(
+    plan: CleanupPlan,
+    format: ScriptFormat,
+) -> Result<PathBuf, String> {
+    let never_suggest = never_suggest::load_never_suggest()?;
+    let now = chrono::Local::now();
+    let script = render_script(
+        &plan,
+        format,
+        &never_suggest,
+        &SizeFormatter::current(),
+        &now.to_rfc3339(),
+    );
+
+    let dir = app_data_dir()?.join("scripts");
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let extension = match format {
+        ScriptFormat::Sh => "sh",
+        ScriptFormat::PowerShell => "ps1",
+    };
+    let file = dir.join(format!("cleanup-{}.{}", now.timestamp(), extension));
+    write_guarded(&file, script.as_bytes())?;
+
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sizes::UnitSystem;
+
+    fn binary() -> SizeFormatter {
+        SizeFormatter::new(UnitSystem::Binary, '.')
+    }
+
+    fn plan(use_trash: bool) -> CleanupPlan {
+        CleanupPlan {
+            items: vec![
+                PlanItem {
+                    path: PathBuf::from("/home/user/Downloads/it's big.iso"),
+                    size: 3 * 1024 * 1024 * 1024,
+                    reason: Some("Older than 90 days".to_string()),
+                },
+                PlanItem {
+                    path: PathBuf::from("/home/user/Photos/dup.jpg"),
+                    size: 2048,
+                    reason: None,
+                },
+            ],
+            use_trash,
+        }
+    }
+
+    #[test]
+    fn test_render_sh_script() {
+        let never_suggest = vec![NeverSuggestEntry {
+            path: PathBuf::from("/home/user/Photos"),
+            note: None,
+            added_at: 0,
+        }];
+
+        let script = render_script(
+            &plan(false),
+            ScriptFormat::Sh,
+            &never_suggest,
+            &binary(),
+            "today",
+        );
+
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("# Sizes are in binary units (1 KiB = 1024 bytes)"));
+        assert!(script.contains("# 2 item(s), 3.0 GiB total"));
+        assert!(script.contains(
+            "# 3.0 GiB - Older than 90 days\nremove '/home/user/Downloads/it'\\''s big.iso'\n"
+        ));
+        assert!(script
+            .contains("# Skipped, on the never-suggest list: remove '/home/user/Photos/dup.jpg'"));
+        assert!(script.contains("rm -rf -- \"$1\""));
+    }
+
+    #[test]
+    fn test_render_powershell_trash_script() {
+        let script = render_script(
+            &plan(true),
+            ScriptFormat::PowerShell,
+            &[],
+            &binary(),
+            "today",
+        );
+
+        assert!(script.contains("SendToRecycleBin"));
+        assert!(script.contains("Remove-CleanupItem '/home/user/Downloads/it''s big.iso'"));
+        assert!(script.contains("# 2.0 KiB\nRemove-CleanupItem '/home/user/Photos/dup.jpg'"));
+    }
+
+    #[test]
+    fn test_control_characters_stay_in_comments() {
+        let plan = CleanupPlan {
+            items: vec![
+                PlanItem {
+                    path: PathBuf::from("/tmp/x\nrm -rf ~\n"),
+                    size: 1,
+                    reason: Some("Old\r\nrm -rf /".to_string()),
+                },
+                PlanItem {
+                    path: PathBuf::from("/tmp/a\u{2019};calc;\u{2018}"),
+                    size: 1,
+                    reason: None,
+                },
+            ],
+            use_trash: false,
+        };
+
+        let sh = render_script(&plan, ScriptFormat::Sh, &[], &binary(), "today");
+        assert!(sh.contains("# 1 B - Old\\r\\nrm -rf /\n"));
+        assert!(sh.contains("# Skipped, name has control characters: /tmp/x\\nrm -rf ~\\n\n"));
+        assert!(!sh.lines().any(|line| line.starts_with("rm -rf ~")));
+
+        let ps = render_script(&plan, ScriptFormat::PowerShell, &[], &binary(), "today");
+        assert!(ps.contains("Remove-CleanupItem '/tmp/a\u{2019}\u{2019};calc;\u{2018}\u{2018}'"));
+    }
+}
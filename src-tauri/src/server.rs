@@ -0,0 +1,360 @@
+use crate::access;
+use crate::api;
+use crate::events::EventSink;
+use crate::{breadcrumbs, reports, safety, scanner, storage};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path as UrlPath, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Command-line flag that runs the engine headless behind an HTTP/WebSocket API;
+/// `--serve=ADDR` listens somewhere other than the default
+const SERVE_FLAG: &str = "--serve";
+
+/// Loopback only unless an address is given, so nothing is reachable from the network
+/// by accident
+const DEFAULT_ADDR: &str = "127.0.0.1:7878";
+
+/// Needed alongside a non-loopback `--serve=ADDR`: the API can delete files and is served
+/// over plain HTTP, so exposing it should be a deliberate choice (ideally behind a TLS proxy)
+const ALLOW_REMOTE_FLAG: &str = "--allow-remote";
+
+/// WebSocket subprotocol carrying the token as `bearer.<token>`, for browsers, which can't
+/// set an `Authorization` header on a WebSocket
+const TOKEN_PROTOCOL_PREFIX: &str = "bearer.";
+
+/// Fixed token for scripted setups; otherwise a new one is generated on every start
+const TOKEN_ENV: &str = "DISK_ANALYSER_TOKEN";
+
+/// Events kept for a slow WebSocket client before it starts missing some
+const EVENT_BUFFER: usize = 4096;
+
+/// Commands a server client can invoke
+const COMMANDS: &[&str] = &[
+    "get_api_version_command",
+    "scan_directory_streaming_command",
+    "cancel_scan_command",
+    "get_children_command",
+    "get_ancestors_command",
+    "get_storage_locations_command",
+    "get_access_mode_command",
+    "get_path_extremes_command",
+    "get_most_crowded_directories_command",
+    "get_tiny_files_report_command",
+    "get_cluster_overhead_command",
+    "get_other_files_report_command",
+    "get_largest_files_command",
+    "check_deletion_safety_command",
+    "delete_items_command",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServeConfig {
+    pub addr: SocketAddr,
+    pub token: String,
+}
+
+/// An event as sent to WebSocket clients: the name the desktop app listens for, and its payload
+#[derive(Serialize)]
+struct ServerEvent<'a, S> {
+    event: &'a str,
+    payload: S,
+}
+
+/// Sends events to every connected WebSocket client
+#[derive(Clone)]
+struct Broadcast(broadcast::Sender<String>);
+
+impl EventSink for Broadcast {
+    fn send<S: Serialize + Clone>(&self, event: &str, payload: &S) {
+        if let Ok(text) = serde_json::to_string(&ServerEvent { event, payload }) {
+            // No receivers just means nobody is listening yet
+            let _ = self.0.send(text);
+        }
+    }
+}
+
+struct ServerState {
+    token: String,
+    events: Broadcast,
+}
+
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 24];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "Cannot generate an access token".to_string())?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// `Some` when the command line asks for server mode
+///
+/// Refuses a non-loopback address unless `--allow-remote` is given too.
+fn serve_config(
+    args: impl Iterator<Item = String>,
+    token: Option<String>,
+) -> Result<Option<ServeConfig>, String> {
+    let args: Vec<String> = args.collect();
+    let Some(arg) = args
+        .iter()
+        .find(|a| *a == SERVE_FLAG || a.starts_with("--serve="))
+    else {
+        return Ok(None);
+    };
+    let addr: SocketAddr = arg
+        .strip_prefix("--serve=")
+        .unwrap_or(DEFAULT_ADDR)
+        .parse()
+        .map_err(|e| format!("Invalid {} address: {}", SERVE_FLAG, e))?;
+    if !addr.ip().is_loopback() && !args.iter().any(|a| a == ALLOW_REMOTE_FLAG) {
+        return Err(format!(
+            "Refusing to serve on {}: the API can delete files and is not encrypted. \
+             Pass {} to expose it anyway, preferably behind a TLS proxy",
+            addr, ALLOW_REMOTE_FLAG
+        ));
+    }
+    let token = match token.filter(|t| !t.is_empty()) {
+        Some(token) => token,
+        None => generate_token()?,
+    };
+    Ok(Some(ServeConfig { addr, token }))
+}
+
+/// Read the command line; `None` means start the desktop app as usual
+pub fn config_from_args() -> Result<Option<ServeConfig>, String> {
+    serve_config(std::env::args(), std::env::var(TOKEN_ENV).ok())
+}
+
+/// Compare without stopping at the first difference, so timing doesn't leak the token
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// The token from an `Authorization: Bearer` header, or from a `bearer.<token>` WebSocket
+/// subprotocol; never from the URL, where it would end up in logs and history
+fn authorized(headers: &HeaderMap, expected: &str) -> bool {
+    let from_header = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let from_protocol = headers
+        .get_all("sec-websocket-protocol")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .find_map(|p| p.trim().strip_prefix(TOKEN_PROTOCOL_PREFIX));
+    from_header
+        .or(from_protocol)
+        .is_some_and(|given| tokens_match(given, expected))
+}
+
+fn arg<T: DeserializeOwned>(args: &Value, name: &str) -> Result<T, String> {
+    serde_json::from_value(args.get(name).cloned().unwrap_or(Value::Null))
+        .map_err(|e| format!("Invalid argument {}: {}", name, e))
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+/// Run a command the way the desktop app invokes it; arguments use the same camelCase names
+async fn dispatch(command: &str, args: &Value, events: &Broadcast) -> Result<Value, String> {
+    // The one place served commands are checked against the managed policy
+    access::ensure_command_allowed(command)?;
+    match command {
+        "get_api_version_command" => to_json(api::api_version()),
+        "scan_directory_streaming_command" => to_json(
+            scanner::scan_directory_async(
+                arg(args, "path")?,
+                scanner::TreeShape::new(arg(args, "childLimit")?, arg(args, "aggregateBelow")?),
+                arg::<Option<Vec<String>>>(args, "excludePatterns")?.unwrap_or_default(),
+                arg(args, "dropFilesBelow")?,
+                events.clone(),
+            )
+            .await?,
+        ),
+        "cancel_scan_command" => to_json(scanner::cancel_scan().await?),
+        "get_children_command" => {
+            let path = arg(args, "path")?;
+            let offset = arg::<Option<usize>>(args, "offset")?.unwrap_or(0);
+            let limit =
+                arg::<Option<usize>>(args, "limit")?.unwrap_or(scanner::DEFAULT_CHILD_LIMIT);
+            to_json(match arg::<Option<u64>>(args, "scanId")? {
+                Some(scan_id) => scanner::get_children(scan_id, path, offset, limit).await?,
+                None => scanner::find_children(path, offset, limit).await?,
+            })
+        }
+        "get_ancestors_command" => {
+            to_json(breadcrumbs::get_ancestors_command(arg(args, "path")?).await?)
+        }
+        "get_storage_locations_command" => to_json(storage::get_storage_locations()?),
+        "get_access_mode_command" => to_json(access::access_mode()),
+        "get_path_extremes_command" => {
+            to_json(reports::get_path_extremes_command(arg(args, "scanId")?).await?)
+        }
+        "get_most_crowded_directories_command" => to_json(
+            reports::get_most_crowded_directories_command(arg(args, "scanId")?, arg(args, "n")?)
+                .await?,
+        ),
+        "get_tiny_files_report_command" => to_json(
+            reports::get_tiny_files_report_command(arg(args, "scanId")?, arg(args, "minFiles")?)
+                .await?,
+        ),
+        "get_cluster_overhead_command" => to_json(
+            reports::get_cluster_overhead_command(arg(args, "scanId")?, arg(args, "n")?).await?,
+        ),
+        "get_other_files_report_command" => to_json(
+            reports::get_other_files_report_command(arg(args, "scanId")?, arg(args, "n")?).await?,
+        ),
+        "get_largest_files_command" => to_json(
+            reports::get_largest_files_command(arg(args, "path")?, arg(args, "limit")?).await?,
+        ),
+        "check_deletion_safety_command" => {
+            to_json(safety::check_deletion_safety_command(arg(args, "paths")?).await?)
+        }
+        "delete_items_command" => {
+            let paths: Vec<PathBuf> = arg(args, "paths")?;
+            let use_trash = arg::<Option<bool>>(args, "useTrash")?.unwrap_or(false);
+            to_json(safety::delete_and_patch_scan(paths, use_trash, events).await?)
+        }
+        _ => Err(format!("Unknown command {}", command)),
+    }
+}
+
+/// `POST /api/invoke/{command}` with the command's arguments as a JSON object
+async fn invoke(
+    State(state): State<Arc<ServerState>>,
+    UrlPath(command): UrlPath<String>,
+    headers: HeaderMap,
+    body: Option<Json<Value>>,
+) -> Response {
+    if !authorized(&headers, &state.token) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let args = body.map(|Json(v)| v).unwrap_or(Value::Null);
+    api::warn_deprecated(&state.events, &command);
+    let command = api::current_name(&command).to_string();
+    if !COMMANDS.contains(&command.as_str()) {
+        let error = format!("Unknown command {}", command);
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": error }))).into_response();
+    }
+    match dispatch(&command, &args, &state.events).await {
+        Ok(value) => Json(value).into_response(),
+        Err(error) => (StatusCode::BAD_REQUEST, Json(json!({ "error": error }))).into_response(),
+    }
+}
+
+/// `GET /api/events` upgrades to a WebSocket carrying every event as `{event, payload}`
+async fn events(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if !authorized(&headers, &state.token) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let receiver = state.events.0.subscribe();
+    // Browsers drop the connection unless the subprotocol they offered is accepted
+    ws.protocols([format!("{}{}", TOKEN_PROTOCOL_PREFIX, state.token)])
+        .on_upgrade(move |socket| forward_events(socket, receiver))
+}
+
+async fn forward_events(mut socket: WebSocket, mut receiver: broadcast::Receiver<String>) {
+    loop {
+        match receiver.recv().await {
+            Ok(text) => {
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            // A slow client skips what it missed rather than holding up the scan
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Serve the API until the process is stopped
+pub fn serve(config: ServeConfig) -> Result<(), String> {
+    let (sender, _) = broadcast::channel(EVENT_BUFFER);
+    let state = Arc::new(ServerState {
+        token: config.token.clone(),
+        events: Broadcast(sender),
+    });
+    let app = Router::new()
+        .route("/api/invoke/{command}", post(invoke))
+        .route("/api/events", get(events))
+        .with_state(state);
+
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    runtime.block_on(async move {
+        let listener = tokio::net::TcpListener::bind(config.addr)
+            .await
+            .map_err(|e| format!("Cannot listen on {}: {}", config.addr, e))?;
+        println!("Serving on http://{}", config.addr);
+        println!("Access token: {}", config.token);
+        axum::serve(listener, app).await.map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serve_config_and_auth() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            serve_config(args(&["disk-analyser"]).into_iter(), None).unwrap(),
+            None
+        );
+        let config = serve_config(args(&["disk-analyser", "--serve"]).into_iter(), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.addr, DEFAULT_ADDR.parse().unwrap());
+        assert_eq!(config.token.len(), 48);
+        assert!(serve_config(
+            args(&["disk-analyser", "--serve=0.0.0.0:9000"]).into_iter(),
+            None
+        )
+        .is_err());
+        let config = serve_config(
+            args(&["disk-analyser", "--serve=0.0.0.0:9000", "--allow-remote"]).into_iter(),
+            Some("secret".to_string()),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.addr.port(), 9000);
+        assert_eq!(config.token, "secret");
+        assert!(serve_config(
+            args(&["disk-analyser", "--serve=nowhere"]).into_iter(),
+            None
+        )
+        .is_err());
+
+        let mut headers = HeaderMap::new();
+        assert!(!authorized(&headers, "secret"));
+        headers.insert("sec-websocket-protocol", "bearer.secret".parse().unwrap());
+        assert!(authorized(&headers, "secret"));
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer wrong!".parse().unwrap());
+        assert!(!authorized(&headers, "secret"));
+        headers.insert("authorization", "Bearer secret".parse().unwrap());
+        assert!(authorized(&headers, "secret"));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::history::HistoryRetention;
+use crate::persistence::{data_file, read_json_or_default, write_json};
+use crate::sizes::UnitSystem;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// User preferences the backend needs when it produces text for the user
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(default)]
+pub struct AppSettings {
+    pub unit_system: UnitSystem,
+    /// Decimal separator for sizes; `None` follows the system locale
+    pub decimal_separator: Option<char>,
+    /// Let incremental rescans reuse listings of directories whose mtime hasn't changed
+    pub trust_directory_mtimes: bool,
+    /// Encrypt saved scans, caches and other files naming the user's files with a key kept in
+    /// the OS keychain
+    pub encrypt_saved_data: bool,
+    /// Replace names with pseudonyms in exported scans unless the export says otherwise
+    pub anonymize_exports: bool,
+    /// Refuse every action that changes user files; `--read-only` forces this on
+    pub read_only: bool,
+    /// Language tag for backend messages, e.g. `de-DE`; `None` follows the system locale
+    pub language: Option<String>,
+    /// Sort rankings of a million entries or more in memory-mapped temporary files (on a
+    /// RAM disk where the OS has one) instead of on the heap
+    pub stage_large_sorts: bool,
+    /// On Linux, batch a scan's metadata reads through io_uring (kernel 5.6 or later);
+    /// faster on NVMe with a cold cache, and falls back to plain reads where unavailable
+    pub use_io_uring: bool,
+    /// Where caches, temporary files and saved scans go when their usual place is on a
+    /// nearly full volume being scanned; `None` picks the roomiest other local, fixed volume
+    pub cache_location: Option<PathBuf>,
+    /// How many saved scans to keep per volume
+    pub history_retention: HistoryRetention,
+}
+
+/// Load settings, falling back to defaults when none were saved
+pub fn load_settings() -> Result<AppSettings, String> {
+    read_json_or_default(&data_file(SETTINGS_FILE)?)
+}
+
+/// Replace the saved settings
+pub(crate) fn save_settings(settings: &AppSettings) -> Result<(), String> {
+    write_json(&data_file(SETTINGS_FILE)?, settings)
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn get_settings_command() -> Result<AppSettings, String> {
+    load_settings()
+}
+
+#[tauri::command]
+pub async fn save_settings_command(settings: AppSettings) -> Result<AppSettings, String> {
+    save_settings(&settings)?;
+    Ok(settings)
+}
@@ -0,0 +1,140 @@
+use crate::settings::{self, AppSettings};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+const BINARY_UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+const DECIMAL_UNITS: [&str; 6] = ["B", "KB", "MB", "GB", "TB", "PB"];
+
+/// Languages that write decimals with a comma
+const COMMA_DECIMAL_LANGUAGES: [&str; 20] = [
+    "bg", "cs", "da", "de", "el", "es", "et", "fi", "fr", "hr", "hu", "id", "it", "lt", "nb", "nl",
+    "pl", "pt", "ru", "sv",
+];
+
+/// Whether sizes are counted in powers of 1024 or 1000
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitSystem {
+    /// 1 KiB = 1024 bytes (Windows Explorer's figures, with IEC labels)
+    #[default]
+    Binary,
+    /// 1 KB = 1000 bytes (what Finder and drive vendors show)
+    Decimal,
+}
+
+/// Formats byte counts for every string the backend shows or exports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeFormatter {
+    pub unit_system: UnitSystem,
+    pub decimal_separator: char,
+}
+
+impl SizeFormatter {
+    pub fn new(unit_system: UnitSystem, decimal_separator: char) -> Self {
+        Self {
+            unit_system,
+            decimal_separator,
+        }
+    }
+
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self::new(
+            settings.unit_system,
+            settings
+                .decimal_separator
+                .unwrap_or_else(locale_decimal_separator),
+        )
+    }
+
+    /// Formatter for the saved settings, or the defaults if they can't be read
+    pub fn current() -> Self {
+        Self::from_settings(&settings::load_settings().unwrap_or_default())
+    }
+
+    /// The convention in words, for export headers
+    pub fn describe(&self) -> &'static str {
+        match self.unit_system {
+            UnitSystem::Binary => "binary units (1 KiB = 1024 bytes)",
+            UnitSystem::Decimal => "decimal units (1 KB = 1000 bytes)",
+        }
+    }
+
+    /// Human-readable size with one decimal, e.g. `1.5 GiB` or `1,6 GB`
+    pub fn format(&self, bytes: u64) -> String {
+        let (base, units) = match self.unit_system {
+            UnitSystem::Binary => (1024.0, BINARY_UNITS),
+            UnitSystem::Decimal => (1000.0, DECIMAL_UNITS),
+        };
+
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= base && unit < units.len() - 1 {
+            value /= base;
+            unit += 1;
+        }
+
+        if unit == 0 {
+            format!("{} B", bytes)
+        } else {
+            let number = format!("{:.1}", value).replace('.', &self.decimal_separator.to_string());
+            format!("{} {}", number, units[unit])
+        }
+    }
+}
+
+/// `part` as a percentage of `whole`, rounded to one decimal; every percentage the backend
+/// sends goes through this so views agree on the rounding
+pub(crate) fn percent_of(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    (part as f64 * 1000.0 / whole as f64).round() / 10.0
+}
+
+/// Decimal separator of the process locale (`LC_ALL`, `LC_NUMERIC`, then `LANG`)
+fn locale_decimal_separator() -> char {
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    let language = locale
+        .split(['_', '-', '.'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    if COMMA_DECIMAL_LANGUAGES.contains(&language.as_str()) {
+        ','
+    } else {
+        '.'
+    }
+}
+
+// Tauri commands
+
+/// Format a size the way backend reports and exports do, so the UI can match them
+#[tauri::command]
+pub async fn format_size_command(bytes: u64) -> Result<String, String> {
+    Ok(SizeFormatter::current().format(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_sizes() {
+        let binary = SizeFormatter::new(UnitSystem::Binary, '.');
+        assert_eq!(binary.format(512), "512 B");
+        assert_eq!(binary.format(1536), "1.5 KiB");
+        assert_eq!(binary.format(3 << 30), "3.0 GiB");
+
+        let decimal = SizeFormatter::new(UnitSystem::Decimal, ',');
+        assert_eq!(decimal.format(1500), "1,5 KB");
+        assert_eq!(decimal.format(3 << 30), "3,2 GB");
+
+        assert_eq!(percent_of(1, 3), 33.3);
+        assert_eq!(percent_of(5, 0), 0.0);
+    }
+}
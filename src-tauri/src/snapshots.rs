@@ -0,0 +1,175 @@
+use crate::access;
+use crate::reconcile::{self, HiddenSpaceSource};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A local APFS snapshot taken by Time Machine
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LocalSnapshot {
+    pub name: String,
+    /// Date label `tmutil` uses to address the snapshot, e.g. `2024-05-01-093015`
+    pub date_label: String,
+    /// Unix timestamp (seconds); the label carries no time zone, so it is read as UTC
+    pub created_at: Option<i64>,
+    /// Rough share of the space the snapshots hold; APFS doesn't report per-snapshot sizes
+    pub approximate_size: Option<u64>,
+}
+
+/// List Time Machine local snapshots on a volume with `tmutil listlocalsnapshots`
+#[cfg(target_os = "macos")]
+pub fn list_local_snapshots(volume: &Path) -> Result<Vec<LocalSnapshot>, String> {
+    let output = run_tmutil(&["listlocalsnapshots", &volume.to_string_lossy()])?;
+    Ok(parse_local_snapshots(&output))
+}
+
+/// Local snapshots are an APFS/Time Machine feature
+#[cfg(not(target_os = "macos"))]
+pub fn list_local_snapshots(_volume: &Path) -> Result<Vec<LocalSnapshot>, String> {
+    Ok(Vec::new())
+}
+
+/// Ask Time Machine to purge snapshots until `bytes` are free (urgency 1-4, 4 most aggressive)
+#[cfg(target_os = "macos")]
+pub fn thin_local_snapshots(volume: &Path, bytes: u64, urgency: u8) -> Result<(), String> {
+    run_tmutil(&[
+        "thinlocalsnapshots",
+        &volume.to_string_lossy(),
+        &bytes.to_string(),
+        &urgency.clamp(1, 4).to_string(),
+    ])
+    .map(|_| ())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn thin_local_snapshots(_volume: &Path, _bytes: u64, _urgency: u8) -> Result<(), String> {
+    Err("Local snapshots are only available on macOS".to_string())
+}
+
+/// Delete the local snapshot with the given date label
+#[cfg(target_os = "macos")]
+pub fn delete_local_snapshot(date_label: &str) -> Result<(), String> {
+    run_tmutil(&["deletelocalsnapshots", date_label]).map(|_| ())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn delete_local_snapshot(_date_label: &str) -> Result<(), String> {
+    Err("Local snapshots are only available on macOS".to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn run_tmutil(args: &[&str]) -> Result<String, String> {
+    let output = crate::tools::run("tmutil", args)?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "tmutil {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parse `tmutil listlocalsnapshots` output (one `com.apple.TimeMachine.<date>.local` per line)
+#[cfg(any(target_os = "macos", test))]
+fn parse_local_snapshots(output: &str) -> Vec<LocalSnapshot> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter_map(|name| {
+            let date_label = name
+                .strip_prefix("com.apple.TimeMachine.")?
+                .trim_end_matches(".local");
+            let created_at = chrono::NaiveDateTime::parse_from_str(date_label, "%Y-%m-%d-%H%M%S")
+                .ok()
+                .map(|d| d.and_utc().timestamp());
+            Some(LocalSnapshot {
+                name: name.to_string(),
+                date_label: date_label.to_string(),
+                created_at,
+                approximate_size: None,
+            })
+        })
+        .collect()
+}
+
+/// Spread the reconciliation's snapshot estimate evenly across the snapshots
+pub(crate) fn apportion(snapshots: &mut [LocalSnapshot], total: u64) {
+    let share = total.checked_div(snapshots.len() as u64).unwrap_or(0);
+    for snapshot in snapshots {
+        snapshot.approximate_size = Some(share);
+    }
+}
+
+// Tauri commands
+
+/// List local snapshots; with a `scan_id`, sizes are estimated from that scan's reconciliation
+#[tauri::command]
+pub async fn list_local_snapshots_command(
+    volume: String,
+    scan_id: Option<u64>,
+) -> Result<Vec<LocalSnapshot>, String> {
+    let mut snapshots = list_local_snapshots(Path::new(&volume))?;
+
+    if let Some(scan_id) = scan_id {
+        let report = reconcile::reconcile_scan(scan_id).await?;
+        if let Some(held) = report
+            .hidden
+            .iter()
+            .find(|h| h.source == HiddenSpaceSource::LocalSnapshots)
+        {
+            apportion(&mut snapshots, held.bytes);
+        }
+    }
+
+    Ok(snapshots)
+}
+
+/// Thin snapshots until `bytes` are reclaimable; the frontend must confirm first
+#[tauri::command]
+pub async fn thin_local_snapshots_command(
+    volume: String,
+    bytes: u64,
+    urgency: Option<u8>,
+    confirmed: bool,
+) -> Result<(), String> {
+    if !confirmed {
+        return Err("Thinning snapshots requires confirmation".to_string());
+    }
+    access::ensure_writable("Thinning snapshots")?;
+    thin_local_snapshots(Path::new(&volume), bytes, urgency.unwrap_or(4))
+}
+
+/// Delete one snapshot by date label; the frontend must confirm first
+#[tauri::command]
+pub async fn delete_local_snapshot_command(
+    date_label: String,
+    confirmed: bool,
+) -> Result<(), String> {
+    if !confirmed {
+        return Err("Deleting a snapshot requires confirmation".to_string());
+    }
+    access::ensure_writable("Deleting snapshots")?;
+    delete_local_snapshot(&date_label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_local_snapshots() {
+        let output = "Snapshots for disk /:\ncom.apple.TimeMachine.2024-05-01-093015.local\ncom.apple.TimeMachine.2024-05-02-101500.local\n";
+
+        let mut snapshots = parse_local_snapshots(output);
+
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].date_label, "2024-05-01-093015");
+        assert_eq!(snapshots[0].created_at, Some(1_714_555_815));
+
+        apportion(&mut snapshots, 300);
+        assert_eq!(snapshots[1].approximate_size, Some(150));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::classifier::classify_file;
+use crate::registry;
+use crate::types::FileType;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Files whose content is checked unless the caller asks for more; the largest go first
+const DEFAULT_SAMPLE: usize = 2000;
+/// Bytes read from the start of each file
+const HEADER_LEN: usize = 16;
+
+/// A file whose extension says one thing and whose content says another
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MisnamedFile {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Category the extension implies
+    pub extension_type: FileType,
+    /// Category the content was detected as
+    pub detected_type: FileType,
+    /// Detected format, e.g. "mp4" or "zip"
+    pub detected_format: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MisnamedReport {
+    pub scan_id: u64,
+    pub files_checked: u64,
+    /// Largest first
+    pub files: Vec<MisnamedFile>,
+}
+
+/// Format and category from a file's first bytes, for the formats worth telling apart
+fn sniff(header: &[u8]) -> Option<(&'static str, FileType)> {
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+
+    if at(4, b"ftyp") {
+        // ISO base media: the brand says whether it's a photo, audio or a movie
+        return Some(match header.get(8..12)? {
+            b"heic" | b"heix" | b"mif1" | b"avif" => ("heif", FileType::Image),
+            b"M4A " => ("m4a", FileType::Audio),
+            _ => ("mp4", FileType::Video),
+        });
+    }
+    if at(0, b"RIFF") {
+        return match header.get(8..12)? {
+            b"WEBP" => Some(("webp", FileType::Image)),
+            b"AVI " => Some(("avi", FileType::Video)),
+            b"WAVE" => Some(("wav", FileType::Audio)),
+            _ => None,
+        };
+    }
+
+    const SIGNATURES: &[(&[u8], &str, FileType)] = &[
+        (b"\xFF\xD8\xFF", "jpeg", FileType::Image),
+        (b"\x89PNG", "png", FileType::Image),
+        (b"GIF8", "gif", FileType::Image),
+        (b"\x1A\x45\xDF\xA3", "matroska", FileType::Video),
+        (b"ID3", "mp3", FileType::Audio),
+        (b"fLaC", "flac", FileType::Audio),
+        (b"OggS", "ogg", FileType::Audio),
+        (b"%PDF", "pdf", FileType::Document),
+        (b"PK\x03\x04", "zip", FileType::Archive),
+        (b"\x1F\x8B", "gzip", FileType::Archive),
+        (b"7z\xBC\xAF\x27\x1C", "7z", FileType::Archive),
+        (b"Rar!", "rar", FileType::Archive),
+        (b"\xFD7zXZ\x00", "xz", FileType::Archive),
+        (b"\x7FELF", "elf", FileType::Executable),
+        (b"MZ", "pe", FileType::Executable),
+        (b"\xCF\xFA\xED\xFE", "mach-o", FileType::Executable),
+        (b"\xCE\xFA\xED\xFE", "mach-o", FileType::Executable),
+    ];
+    SIGNATURES
+        .iter()
+        .find(|(magic, _, _)| at(0, magic))
+        .map(|(_, format, file_type)| (*format, file_type.clone()))
+}
+
+/// Whether a detected format is a legitimate body for the extension's category
+///
+/// Office documents are zip files and Ogg also carries video, so those never count as
+/// mismatches.
+fn compatible(extension_type: &FileType, format: &str, detected: &FileType) -> bool {
+    extension_type == detected
+        || (format == "zip" && matches!(extension_type, FileType::Document | FileType::Executable))
+        || (format == "ogg" && *extension_type == FileType::Video)
+}
+
+fn read_header(path: &Path) -> Option<Vec<u8>> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    File::open(path)
+        .ok()?
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut header)
+        .ok()?;
+    Some(header)
+}
+
+/// Check one file; `None` when its content matches its extension or can't be identified
+fn check_file(path: &Path, size: u64) -> Option<MisnamedFile> {
+    let extension_type = classify_file(path);
+    if extension_type == FileType::Other {
+        return None;
+    }
+    let (format, detected_type) = sniff(&read_header(path)?)?;
+    if compatible(&extension_type, format, &detected_type) {
+        return None;
+    }
+    Some(MisnamedFile {
+        path: path.to_path_buf(),
+        size,
+        extension_type,
+        detected_type,
+        detected_format: format.to_string(),
+    })
+}
+
+// Tauri commands
+
+/// Sniff the largest files of a retained scan and list those whose extension is wrong
+#[tauri::command]
+pub async fn find_misnamed_files_command(
+    scan_id: u64,
+    sample: Option<usize>,
+) -> Result<MisnamedReport, String> {
+    let mut candidates: Vec<(PathBuf, u64)> = registry::with_scan(scan_id, |nodes, _| {
+        nodes
+            .values()
+            .filter(|n| !n.is_directory && !n.is_symlink && n.file_type != FileType::Other)
+            .map(|n| (n.path.clone(), n.size))
+            .collect()
+    })
+    .await?;
+    candidates.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    candidates.truncate(sample.unwrap_or(DEFAULT_SAMPLE));
+
+    tokio::task::spawn_blocking(move || {
+        let mut files: Vec<MisnamedFile> = candidates
+            .iter()
+            .filter_map(|(path, size)| check_file(path, *size))
+            .collect();
+        files.sort_by_key(|f| std::cmp::Reverse(f.size));
+        MisnamedReport {
+            scan_id,
+            files_checked: candidates.len() as u64,
+            files,
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_misnamed_files() {
+        let dir = std::env::temp_dir().join("test_sniffing");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mp4 = b"\x00\x00\x00\x20ftypisom\x00\x00\x02\x00";
+        fs::write(dir.join("holiday.jpg"), mp4).unwrap();
+        fs::write(dir.join("holiday.mp4"), mp4).unwrap();
+        fs::write(dir.join("report.docx"), b"PK\x03\x04rest").unwrap();
+        fs::write(dir.join("notes.txt"), b"plain text").unwrap();
+
+        let misnamed = check_file(&dir.join("holiday.jpg"), 16);
+        let correct = check_file(&dir.join("holiday.mp4"), 16);
+        let office = check_file(&dir.join("report.docx"), 8);
+        let unknown = check_file(&dir.join("notes.txt"), 10);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let misnamed = misnamed.unwrap();
+        assert_eq!(misnamed.extension_type, FileType::Image);
+        assert_eq!(misnamed.detected_type, FileType::Video);
+        assert_eq!(misnamed.detected_format, "mp4");
+        assert!(correct.is_none());
+        assert!(office.is_none());
+        assert!(unknown.is_none());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::storage::get_available_space;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prefix of errors for writes refused because the destination is too full; the rest of
+/// the message is an `InsufficientSpace` as JSON
+pub const INSUFFICIENT_SPACE: &str = "InsufficientSpace";
+
+/// Left free on top of what an operation needs, for filesystem metadata and whatever
+/// else is writing to the volume meanwhile
+const SPACE_MARGIN: u64 = 16 << 20;
+
+/// Why a write was refused: how much it needs and how much the destination has
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct InsufficientSpace {
+    pub destination: PathBuf,
+    pub required_bytes: u64,
+    pub available_bytes: u64,
+}
+
+impl InsufficientSpace {
+    fn into_error(self) -> String {
+        let details = serde_json::to_string(&self).unwrap_or_default();
+        format!("{}: {}", INSUFFICIENT_SPACE, details)
+    }
+}
+
+fn check_space(destination: &Path, required: u64, available: u64) -> Result<(), String> {
+    if required.saturating_add(SPACE_MARGIN) > available {
+        return Err(InsufficientSpace {
+            destination: destination.to_path_buf(),
+            required_bytes: required,
+            available_bytes: available,
+        }
+        .into_error());
+    }
+    Ok(())
+}
+
+/// Refuse up front when `required` bytes won't fit at `destination`
+///
+/// A volume that doesn't report its free space is given the benefit of the doubt.
+pub(crate) fn ensure_space(destination: &Path, required: u64) -> Result<(), String> {
+    match get_available_space(destination) {
+        Some(available) => check_space(destination, required, available),
+        None => Ok(()),
+    }
+}
+
+/// An output being written next to its destination, deleted unless committed
+///
+/// Whatever goes wrong midway, the destination is either the complete new file or
+/// untouched, and no partial file is left behind.
+pub(crate) struct PartialOutput {
+    partial: PathBuf,
+    destination: PathBuf,
+    committed: bool,
+}
+
+impl PartialOutput {
+    /// Check there is room for `size` bytes, then reserve a partial file for them
+    pub(crate) fn new(destination: &Path, size: u64) -> Result<Self, String> {
+        ensure_space(destination, size)?;
+        let name = destination
+            .file_name()
+            .ok_or_else(|| format!("Not a file path: {}", destination.display()))?;
+        let partial = destination.with_file_name(format!(".{}.partial", name.to_string_lossy()));
+        Ok(Self {
+            partial,
+            destination: destination.to_path_buf(),
+            committed: false,
+        })
+    }
+
+    /// Where to write the output until it is committed
+    pub(crate) fn path(&self) -> &Path {
+        &self.partial
+    }
+
+    /// Move the finished output into place
+    pub(crate) fn commit(mut self) -> Result<(), String> {
+        fs::rename(&self.partial, &self.destination)
+            .map_err(|e| format!("Cannot write {}: {}", self.destination.display(), e))?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for PartialOutput {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.partial);
+        }
+    }
+}
+
+/// Write `contents` to `destination` if it fits, leaving nothing behind on failure
+pub(crate) fn write_guarded(destination: &Path, contents: &[u8]) -> Result<(), String> {
+    let output = PartialOutput::new(destination, contents.len() as u64)?;
+    fs::write(output.path(), contents)
+        .map_err(|e| format!("Cannot write {}: {}", destination.display(), e))?;
+    output.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_space_check_and_partial_cleanup() {
+        let destination = Path::new("/exports/scan.json");
+        assert!(check_space(destination, 1 << 20, 1 << 30).is_ok());
+        let error = check_space(destination, 1 << 30, 1 << 20).unwrap_err();
+        let details: InsufficientSpace = serde_json::from_str(
+            error
+                .strip_prefix(INSUFFICIENT_SPACE)
+                .and_then(|e| e.strip_prefix(": "))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(details.required_bytes, 1 << 30);
+        assert_eq!(details.available_bytes, 1 << 20);
+
+        let dir = std::env::temp_dir().join(format!("space-guard-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let destination = dir.join("out.txt");
+        {
+            let output = PartialOutput::new(&destination, 5).unwrap();
+            fs::write(output.path(), b"half").unwrap();
+            // Dropped without committing, as when a write fails midway
+        }
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+        write_guarded(&destination, b"whole").unwrap();
+        assert_eq!(fs::read(&destination).unwrap(), b"whole");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+use crate::persistence::cache_dir_avoiding;
+use crate::settings::load_settings;
+use memmap2::MmapMut;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use sysinfo::{Pid, System};
+
+/// Below this many entries a ranking is sorted on the heap even with staging on; the
+/// temporary file isn't worth it
+const STAGING_THRESHOLD: usize = 1_000_000;
+
+/// Per-process staging directories are named this plus the process id
+const STAGE_PREFIX: &str = "disk-analyser-stage-";
+
+/// A sort key and the position of its entry in the caller's list
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    key: u64,
+    index: u64,
+}
+
+static NEXT_FILE: AtomicU64 = AtomicU64::new(1);
+
+/// A RAM-backed directory when the OS has one, so staged sorts never touch a disk;
+/// elsewhere the temp directory, where the OS can page the mapping out instead of
+/// running out of memory, unless it is on the nearly full volume being scanned
+fn staging_root(scanned: &[&Path]) -> Result<PathBuf, String> {
+    #[cfg(target_os = "linux")]
+    {
+        let shm = Path::new("/dev/shm");
+        if shm.is_dir() {
+            return Ok(shm.to_path_buf());
+        }
+    }
+    cache_dir_avoiding(&std::env::temp_dir(), scanned)
+}
+
+fn process_dir(root: &Path, pid: u32) -> PathBuf {
+    root.join(format!("{}{}", STAGE_PREFIX, pid))
+}
+
+fn scan_dir(root: &Path, scan_id: u64) -> PathBuf {
+    process_dir(root, std::process::id()).join(format!("scan-{}", scan_id))
+}
+
+/// Records in a memory-mapped temporary file, deleted when dropped
+struct MappedRecords {
+    map: MmapMut,
+    len: usize,
+    path: PathBuf,
+}
+
+impl MappedRecords {
+    fn create(dir: &Path, len: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "sort-{}",
+            NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let mapped = file
+            .set_len((len.max(1) * std::mem::size_of::<Record>()) as u64)
+            .and_then(|_| unsafe { MmapMut::map_mut(&file) });
+        match mapped {
+            Ok(map) => Ok(Self { map, len, path }),
+            Err(e) => {
+                remove_staged(&path);
+                Err(e)
+            }
+        }
+    }
+
+    fn records(&mut self) -> &mut [Record] {
+        // The mapping is page-aligned and sized for `len` records
+        unsafe { std::slice::from_raw_parts_mut(self.map.as_mut_ptr().cast::<Record>(), self.len) }
+    }
+}
+
+impl Drop for MappedRecords {
+    fn drop(&mut self) {
+        remove_staged(&self.path);
+    }
+}
+
+/// Delete a staged file, then its scan and process directories once they are empty
+fn remove_staged(path: &Path) {
+    let _ = fs::remove_file(path);
+    for dir in path.ancestors().skip(1).take(2) {
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+/// Indices of the `limit` largest records, plus any tied with the last, largest first
+fn top(records: &mut [Record], limit: usize) -> Vec<usize> {
+    records.sort_unstable_by_key(|r| std::cmp::Reverse(r.key));
+    let Some(cutoff) = limit
+        .checked_sub(1)
+        .map(|i| records.get(i).map_or(0, |r| r.key))
+    else {
+        return Vec::new();
+    };
+    records
+        .iter()
+        .enumerate()
+        .take_while(|(i, r)| *i < limit || r.key == cutoff)
+        .map(|(_, r)| r.index as usize)
+        .collect()
+}
+
+/// Positions of the entries with the `limit` largest keys, largest first
+///
+/// Entries tied with the last one are included, so callers can break ties their own way
+/// before truncating. With `stage_large_sorts` on, big rankings are sorted in a
+/// memory-mapped temporary file for the scan instead of on the heap.
+pub(crate) fn largest(
+    scan_id: u64,
+    root: &Path,
+    keys: impl ExactSizeIterator<Item = u64>,
+    limit: usize,
+) -> Vec<usize> {
+    let len = keys.len();
+    let staged = len >= STAGING_THRESHOLD && load_settings().is_ok_and(|s| s.stage_large_sorts);
+    if staged {
+        let mapped = staging_root(&[root]).and_then(|dir| {
+            MappedRecords::create(&scan_dir(&dir, scan_id), len).map_err(|e| e.to_string())
+        });
+        if let Ok(mut mapped) = mapped {
+            let records = mapped.records();
+            for (index, key) in keys.enumerate() {
+                records[index] = Record {
+                    key,
+                    index: index as u64,
+                };
+            }
+            return top(records, limit);
+        }
+    }
+    let mut records: Vec<Record> = keys
+        .enumerate()
+        .map(|(index, key)| Record {
+            key,
+            index: index as u64,
+        })
+        .collect();
+    top(&mut records, limit)
+}
+
+/// Remove staging directories left by processes that are no longer running; called
+/// once at startup, since a crash skips the usual cleanup
+pub fn remove_stale_stages() {
+    let Ok(root) = staging_root(&[]) else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(&root) else {
+        return;
+    };
+    let mut system = System::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(pid) = name
+            .strip_prefix(STAGE_PREFIX)
+            .and_then(|p| p.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if pid != std::process::id() && !system.refresh_process(Pid::from_u32(pid)) {
+            let _ = fs::remove_dir_all(process_dir(&root, pid));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staged_ranking() {
+        let keys = [5u64, 9, 1, 7, 7, 3];
+        let dir = scan_dir(&std::env::temp_dir(), 0);
+        let mut mapped = MappedRecords::create(&dir, keys.len()).unwrap();
+        for (index, key) in keys.iter().enumerate() {
+            mapped.records()[index] = Record {
+                key: *key,
+                index: index as u64,
+            };
+        }
+        let mut ranked = top(mapped.records(), 2);
+        // 7 is tied at the cutoff, so both 7s are kept
+        ranked[1..].sort();
+        assert_eq!(ranked, [1, 3, 4]);
+
+        let path = mapped.path.clone();
+        drop(mapped);
+        assert!(!path.exists());
+        assert!(!dir.exists());
+
+        assert_eq!(largest(1, Path::new("/"), keys.into_iter(), 1), [1]);
+        assert!(largest(1, Path::new("/"), keys.into_iter(), 0).is_empty());
+    }
+}
@@ -1,21 +1,30 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum LocationType {
     Storage,
     Network,
     Folder,
+    /// A phone or camera attached over MTP or PTP; scanned read-only
+    Device,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct StorageLocation {
     pub name: String,
     pub path: PathBuf,
     pub location_type: LocationType,
     pub total_space: Option<u64>,
     pub available_space: Option<u64>,
+    /// Inode totals, where the filesystem reports them
+    pub inodes: Option<InodeUsage>,
+    /// Current user's quota on this volume, where one is configured
+    pub quota: Option<QuotaUsage>,
+    /// Volume Shadow Copy storage on this volume (Windows only)
+    pub shadow_storage: Option<ShadowStorage>,
 }
 
 #[cfg(target_os = "macos")]
@@ -32,6 +41,9 @@ pub fn get_storage_locations() -> Result<Vec<StorageLocation>, String> {
             location_type: LocationType::Storage,
             total_space: Some(stats.0),
             available_space: Some(stats.1),
+            inodes: get_inode_usage(Path::new("/")),
+            quota: None,
+            shadow_storage: None,
         });
     }
 
@@ -66,6 +78,9 @@ pub fn get_storage_locations() -> Result<Vec<StorageLocation>, String> {
                     },
                     total_space: if total > 0 { Some(total) } else { None },
                     available_space: if available > 0 { Some(available) } else { None },
+                    inodes: get_inode_usage(&path),
+                    quota: None,
+                    shadow_storage: None,
                 });
             }
         }
@@ -74,13 +89,12 @@ pub fn get_storage_locations() -> Result<Vec<StorageLocation>, String> {
     Ok(locations)
 }
 
+/// Without diskutil (missing or sandboxed away) disk images are listed as ordinary volumes
 #[cfg(target_os = "macos")]
 fn is_disk_image(path: &std::path::Path) -> bool {
-    use std::process::Command;
-
     // Use diskutil to check if this is a disk image
     // Disk images typically have "Disk Image" in their protocol
-    if let Ok(output) = Command::new("diskutil").arg("info").arg(path).output() {
+    if let Ok(output) = crate::tools::run("diskutil", &["info", &path.to_string_lossy()]) {
         if let Ok(info) = String::from_utf8(output.stdout) {
             // Check for disk image indicators
             return info.contains("Disk Image")
@@ -140,12 +154,18 @@ pub fn get_storage_locations() -> Result<Vec<StorageLocation>, String> {
             let name = get_windows_volume_name(&drive_str)
                 .unwrap_or_else(|| drive_str.trim_end_matches('\\').to_string());
 
+            // Needs administrator rights; without them no volume reports shadow storage
+            let shadow_storage = shadow_storage_for(drive_str.trim_end_matches('\\'));
+
             locations.push(StorageLocation {
                 name,
                 path: PathBuf::from(drive_str),
                 location_type,
                 total_space: total,
                 available_space: available,
+                inodes: None,
+                quota: None,
+                shadow_storage,
             });
 
             i = end + 1;
@@ -166,7 +186,7 @@ pub fn get_storage_locations() -> Result<Vec<StorageLocation>, String> {
     let file = File::open("/proc/mounts").map_err(|e| e.to_string())?;
     let reader = BufReader::new(file);
 
-    for line in reader.lines().flatten() {
+    for line in reader.lines().map_while(Result::ok) {
         let parts: Vec<&str> = line.split_whitespace().collect();
         if parts.len() < 3 {
             continue;
@@ -208,12 +228,22 @@ pub fn get_storage_locations() -> Result<Vec<StorageLocation>, String> {
             },
             total_space: if total > 0 { Some(total) } else { None },
             available_space: if available > 0 { Some(available) } else { None },
+            inodes: get_inode_usage(Path::new(mount_point)),
+            quota: get_user_quota(Path::new(mount_point)),
+            shadow_storage: None,
         });
     }
 
+    locations.extend(crate::devices::portable_devices());
     Ok(locations)
 }
 
+/// Capacity of the volume holding `path`, where it can be found
+pub(crate) fn volume_capacity(path: &Path) -> Option<u64> {
+    let locations = get_storage_locations().ok()?;
+    crate::reconcile::volume_for(path, &locations)?.total_space
+}
+
 #[cfg(target_os = "macos")]
 fn get_volume_stats(path: &std::path::Path) -> Result<(u64, u64), String> {
     use std::ffi::CString;
@@ -295,7 +325,7 @@ fn get_windows_volume_name(path: &str) -> Option<String> {
 }
 
 #[cfg(target_os = "linux")]
-fn get_linux_volume_stats(path: &str) -> Result<(u64, u64), String> {
+pub(crate) fn get_linux_volume_stats(path: &str) -> Result<(u64, u64), String> {
     use std::ffi::CString;
     use std::mem;
 
@@ -313,6 +343,477 @@ fn get_linux_volume_stats(path: &str) -> Result<(u64, u64), String> {
     }
 }
 
+/// Bytes the current user can still write on the volume holding `path`
+///
+/// `path` need not exist yet: the nearest existing ancestor is asked instead.
+pub fn get_available_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    available_on(existing)
+}
+
+#[cfg(target_os = "macos")]
+fn available_on(path: &Path) -> Option<u64> {
+    get_volume_stats(path).ok().map(|(_, available)| available)
+}
+
+#[cfg(target_os = "linux")]
+fn available_on(path: &Path) -> Option<u64> {
+    get_linux_volume_stats(&path.to_string_lossy())
+        .ok()
+        .map(|(_, available)| available)
+}
+
+#[cfg(target_os = "windows")]
+fn available_on(path: &Path) -> Option<u64> {
+    get_windows_volume_stats(&path.to_string_lossy())
+        .ok()
+        .and_then(|(_, available)| available)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn available_on(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Inode usage of the filesystem holding a path
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InodeUsage {
+    pub total: u64,
+    pub free: u64,
+    pub used: u64,
+}
+
+/// Query inode totals for the filesystem containing `path` (statvfs `f_files`/`f_ffree`)
+#[cfg(unix)]
+pub fn get_inode_usage(path: &Path) -> Option<InodeUsage> {
+    use std::ffi::CString;
+    use std::mem;
+
+    let path_cstr = CString::new(path.to_string_lossy().as_bytes()).ok()?;
+
+    unsafe {
+        let mut stats: libc::statvfs = mem::zeroed();
+        if libc::statvfs(path_cstr.as_ptr(), &mut stats) != 0 {
+            return None;
+        }
+
+        // fsfilcnt_t is 32-bit on macOS and 64-bit on Linux
+        #[allow(clippy::unnecessary_cast)]
+        let (total, free) = (stats.f_files as u64, stats.f_ffree as u64);
+
+        // Some filesystems (e.g. btrfs) allocate inodes dynamically and report zero
+        if total == 0 {
+            return None;
+        }
+
+        Some(InodeUsage {
+            total,
+            free,
+            used: total.saturating_sub(free),
+        })
+    }
+}
+
+/// Inodes are a Unix concept; other platforms report nothing
+#[cfg(not(unix))]
+pub fn get_inode_usage(_path: &Path) -> Option<InodeUsage> {
+    None
+}
+
+/// Allocation unit (cluster / fragment size) of the filesystem containing `path`
+#[cfg(unix)]
+pub fn get_cluster_size(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::mem;
+
+    let path_cstr = CString::new(path.to_string_lossy().as_bytes()).ok()?;
+
+    unsafe {
+        let mut stats: libc::statvfs = mem::zeroed();
+        if libc::statvfs(path_cstr.as_ptr(), &mut stats) != 0 {
+            return None;
+        }
+
+        // f_frsize is 32-bit on macOS and 64-bit on Linux
+        #[allow(clippy::unnecessary_cast)]
+        let size = stats.f_frsize as u64;
+        (size > 0).then_some(size)
+    }
+}
+
+/// Allocation unit of the volume containing `path` (sectors per cluster * bytes per sector)
+#[cfg(target_os = "windows")]
+pub fn get_cluster_size(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::fileapi::GetDiskFreeSpaceW;
+
+    let root = path.ancestors().last()?;
+    let root_wide: Vec<u16> = root.as_os_str().encode_wide().chain(Some(0)).collect();
+    let (mut sectors_per_cluster, mut bytes_per_sector, mut free, mut total) =
+        (0u32, 0u32, 0u32, 0u32);
+
+    unsafe {
+        if GetDiskFreeSpaceW(
+            root_wide.as_ptr(),
+            &mut sectors_per_cluster,
+            &mut bytes_per_sector,
+            &mut free,
+            &mut total,
+        ) == 0
+        {
+            return None;
+        }
+    }
+
+    let size = u64::from(sectors_per_cluster) * u64::from(bytes_per_sector);
+    (size > 0).then_some(size)
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+pub fn get_cluster_size(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// File system type (e.g. `apfs`, `ntfs`, `ext4`) of the mounted volume containing `path`
+pub fn get_filesystem_type(path: &Path) -> Option<String> {
+    sysinfo::Disks::new_with_refreshed_list()
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.file_system().to_string_lossy().to_lowercase())
+}
+
+/// File systems served over the network, whose reads can hang when the server goes away
+const NETWORK_FILESYSTEMS: [&str; 10] = [
+    "smbfs", "cifs", "smb2", "smb3", "nfs", "nfs4", "afpfs", "webdav", "davfs", "9p",
+];
+
+pub fn is_network_filesystem(file_system: &str) -> bool {
+    NETWORK_FILESYSTEMS.contains(&file_system)
+        || file_system == "fuse.sshfs"
+        || file_system == "fuse.rclone"
+}
+
+/// Network mount points a scan of `root` can reach: mounts below it, or the one holding it
+pub fn network_mount_points(root: &Path) -> Vec<PathBuf> {
+    sysinfo::Disks::new_with_refreshed_list()
+        .list()
+        .iter()
+        .filter(|disk| is_network_filesystem(&disk.file_system().to_string_lossy().to_lowercase()))
+        .map(|disk| disk.mount_point().to_path_buf())
+        .filter(|mount| mount.starts_with(root) || root.starts_with(mount))
+        .collect()
+}
+
+/// Per-user quota usage and limits on a volume (limits are `None` when unlimited)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QuotaUsage {
+    pub used_bytes: u64,
+    pub soft_limit_bytes: Option<u64>,
+    pub hard_limit_bytes: Option<u64>,
+    pub used_inodes: u64,
+    pub soft_limit_inodes: Option<u64>,
+    pub hard_limit_inodes: Option<u64>,
+}
+
+/// Query the current user's quota on the filesystem containing `path`
+///
+/// Returns `None` when quotas aren't enabled for the filesystem or the user has none.
+#[cfg(target_os = "linux")]
+pub fn get_user_quota(path: &Path) -> Option<QuotaUsage> {
+    use std::ffi::CString;
+    use std::mem;
+
+    // Quota block limits are expressed in 1 KiB blocks (QIF_DQBLKSIZE)
+    const QUOTA_BLOCK_SIZE: u64 = 1024;
+    const USRQUOTA: libc::c_int = 0;
+
+    let device = CString::new(mount_device_for(path)?).ok()?;
+
+    unsafe {
+        let mut quota: libc::dqblk = mem::zeroed();
+        let result = libc::quotactl(
+            libc::QCMD(libc::Q_GETQUOTA, USRQUOTA),
+            device.as_ptr(),
+            libc::getuid() as libc::c_int,
+            &mut quota as *mut libc::dqblk as *mut libc::c_char,
+        );
+        if result != 0 {
+            return None;
+        }
+
+        let limit = |value: u64| if value > 0 { Some(value) } else { None };
+        Some(QuotaUsage {
+            used_bytes: quota.dqb_curspace,
+            soft_limit_bytes: limit(quota.dqb_bsoftlimit * QUOTA_BLOCK_SIZE),
+            hard_limit_bytes: limit(quota.dqb_bhardlimit * QUOTA_BLOCK_SIZE),
+            used_inodes: quota.dqb_curinodes,
+            soft_limit_inodes: limit(quota.dqb_isoftlimit),
+            hard_limit_inodes: limit(quota.dqb_ihardlimit),
+        })
+    }
+}
+
+/// Quotas are only queried on Linux
+#[cfg(not(target_os = "linux"))]
+pub fn get_user_quota(_path: &Path) -> Option<QuotaUsage> {
+    None
+}
+
+/// Find the block device backing the mount that contains `path` (longest matching mount point)
+#[cfg(target_os = "linux")]
+pub(crate) fn mount_device_for(path: &Path) -> Option<String> {
+    let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            Some((parts.next()?, parts.next()?))
+        })
+        .filter(|(_, mount_point)| path.starts_with(mount_point))
+        .max_by_key(|(_, mount_point)| mount_point.len())
+        .map(|(device, _)| device.to_string())
+}
+
+/// Space reserved for Volume Shadow Copies (System Restore points, backups) on one volume
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ShadowStorage {
+    /// Drive the shadow copies protect, e.g. `C:`
+    pub volume: String,
+    pub used_bytes: u64,
+    pub allocated_bytes: u64,
+    /// `None` when the maximum is unbounded
+    pub max_bytes: Option<u64>,
+}
+
+/// How long one shadow storage query is reused; storage views refresh far more often than
+/// shadow storage changes, and each query starts a process
+#[cfg(target_os = "windows")]
+const SHADOW_STORAGE_TTL: std::time::Duration = std::time::Duration::from_secs(300);
+
+#[cfg(target_os = "windows")]
+type ShadowStorageByVolume = std::collections::HashMap<String, ShadowStorage>;
+
+/// Shadow storage by upper-cased drive letter from the last query (or why it failed), and
+/// when that query ran
+#[cfg(target_os = "windows")]
+type ShadowStorageCache = Option<(std::time::Instant, Result<ShadowStorageByVolume, String>)>;
+
+#[cfg(target_os = "windows")]
+static SHADOW_STORAGE: once_cell::sync::Lazy<std::sync::Mutex<ShadowStorageCache>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+/// Every volume's shadow storage, queried at most once per `SHADOW_STORAGE_TTL`
+#[cfg(target_os = "windows")]
+fn cached_shadow_storage() -> Result<ShadowStorageByVolume, String> {
+    let mut cache = SHADOW_STORAGE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((queried, result)) = cache.as_ref() {
+        if queried.elapsed() < SHADOW_STORAGE_TTL {
+            return result.clone();
+        }
+    }
+    let result = query_shadow_storage().map(|storages| {
+        storages
+            .into_iter()
+            .map(|s| (s.volume.to_uppercase(), s))
+            .collect()
+    });
+    *cache = Some((std::time::Instant::now(), result.clone()));
+    result
+}
+
+/// Shadow storage of one volume, e.g. `C:`
+#[cfg(target_os = "windows")]
+fn shadow_storage_for(volume: &str) -> Option<ShadowStorage> {
+    cached_shadow_storage().ok()?.remove(&volume.to_uppercase())
+}
+
+/// Shadow storage associations of every volume, reusing a recent query
+#[cfg(target_os = "windows")]
+pub fn get_shadow_storage() -> Result<Vec<ShadowStorage>, String> {
+    let mut storages: Vec<ShadowStorage> = cached_shadow_storage()?.into_values().collect();
+    storages.sort_by(|a, b| a.volume.cmp(&b.volume));
+    Ok(storages)
+}
+
+/// Query shadow storage associations from WMI, which reports exact byte counts
+///
+/// Both WMI and vssadmin need administrator rights. vssadmin is only used when PowerShell
+/// can't be run; its sizes are rounded and formatted for the user's locale.
+#[cfg(target_os = "windows")]
+fn query_shadow_storage() -> Result<Vec<ShadowStorage>, String> {
+    match get_shadow_storage_from_wmi() {
+        Err(e) if crate::tools::is_unavailable(&e) => {}
+        result => return result,
+    }
+    let output = crate::tools::run("vssadmin", &["list", "shadowstorage"])?;
+    if !output.status.success() {
+        return Err("vssadmin failed (administrator rights are required)".to_string());
+    }
+    Ok(parse_shadow_storage(
+        &String::from_utf8_lossy(&output.stdout),
+        user_decimal_separator(),
+    ))
+}
+
+/// Decimal separator of the user's Windows locale, which vssadmin formats sizes with
+#[cfg(target_os = "windows")]
+fn user_decimal_separator() -> char {
+    use winapi::um::winnls::{GetLocaleInfoEx, LOCALE_SDECIMAL};
+    let mut buffer = [0u16; 8];
+    let length = unsafe {
+        GetLocaleInfoEx(
+            std::ptr::null(),
+            LOCALE_SDECIMAL,
+            buffer.as_mut_ptr(),
+            buffer.len() as i32,
+        )
+    };
+    // The length counts the terminating null
+    if length < 2 {
+        return '.';
+    }
+    char::decode_utf16(buffer[..length as usize - 1].iter().copied())
+        .next()
+        .and_then(Result::ok)
+        .unwrap_or('.')
+}
+
+/// Win32_ShadowStorage with each volume resolved to its drive letter, as a JSON array
+#[cfg(target_os = "windows")]
+const WMI_SHADOW_STORAGE_SCRIPT: &str = "ConvertTo-Json -Compress -InputObject @(\
+    Get-CimInstance Win32_ShadowStorage | ForEach-Object { \
+        $id = $_.Volume.DeviceID; \
+        [pscustomobject]@{ \
+            volume = (Get-CimInstance Win32_Volume | Where-Object DeviceID -eq $id).DriveLetter; \
+            used = $_.UsedSpace; allocated = $_.AllocatedSpace; max = $_.MaxSpace } })";
+
+#[cfg(target_os = "windows")]
+fn get_shadow_storage_from_wmi() -> Result<Vec<ShadowStorage>, String> {
+    let output = crate::tools::run(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            WMI_SHADOW_STORAGE_SCRIPT,
+        ],
+    )?;
+    if !output.status.success() {
+        return Err(
+            "Reading shadow storage from WMI failed (administrator rights are required)"
+                .to_string(),
+        );
+    }
+    parse_wmi_shadow_storage(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the WMI fallback's output; WMI reports an unbounded maximum as `u64::MAX`
+#[cfg(any(target_os = "windows", test))]
+fn parse_wmi_shadow_storage(json: &str) -> Result<Vec<ShadowStorage>, String> {
+    #[derive(Deserialize)]
+    struct WmiShadowStorage {
+        volume: Option<String>,
+        used: u64,
+        allocated: u64,
+        max: u64,
+    }
+
+    let rows: Vec<WmiShadowStorage> =
+        serde_json::from_str(json).map_err(|e| format!("Unexpected WMI output: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .map(|row| ShadowStorage {
+            volume: row.volume.unwrap_or_default(),
+            used_bytes: row.used,
+            allocated_bytes: row.allocated,
+            max_bytes: (row.max != u64::MAX).then_some(row.max),
+        })
+        .collect())
+}
+
+/// Shadow copies only exist on Windows
+#[cfg(not(target_os = "windows"))]
+pub fn get_shadow_storage() -> Result<Vec<ShadowStorage>, String> {
+    Ok(Vec::new())
+}
+
+/// Parse `vssadmin list shadowstorage` output (English labels), with sizes written using
+/// `decimal` as the decimal separator
+#[cfg(any(target_os = "windows", test))]
+fn parse_shadow_storage(output: &str, decimal: char) -> Vec<ShadowStorage> {
+    let mut storages = Vec::new();
+    let mut current: Option<ShadowStorage> = None;
+
+    for line in output.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("For volume:") {
+            storages.extend(current.take());
+            // "(C:)\\?\Volume{...}\" -> "C:"
+            let rest = rest.trim();
+            let volume = rest
+                .strip_prefix('(')
+                .and_then(|r| r.split_once(')'))
+                .map(|(label, _)| label)
+                .unwrap_or(rest);
+            current = Some(ShadowStorage {
+                volume: volume.to_string(),
+                used_bytes: 0,
+                allocated_bytes: 0,
+                max_bytes: None,
+            });
+        } else if let Some(storage) = current.as_mut() {
+            if let Some(rest) = line.strip_prefix("Used Shadow Copy Storage space:") {
+                storage.used_bytes = parse_vssadmin_size(rest, decimal).unwrap_or(0);
+            } else if let Some(rest) = line.strip_prefix("Allocated Shadow Copy Storage space:") {
+                storage.allocated_bytes = parse_vssadmin_size(rest, decimal).unwrap_or(0);
+            } else if let Some(rest) = line.strip_prefix("Maximum Shadow Copy Storage space:") {
+                storage.max_bytes = parse_vssadmin_size(rest, decimal);
+            }
+        }
+    }
+
+    storages.extend(current);
+    storages
+}
+
+/// Unit of a vssadmin size as a multiplier, matched without regard to case
+#[cfg(any(target_os = "windows", test))]
+fn vssadmin_unit(unit: &str) -> Option<u64> {
+    match unit.to_uppercase().as_str() {
+        "B" | "BYTES" => Some(1),
+        "KB" => Some(1 << 10),
+        "MB" => Some(1 << 20),
+        "GB" => Some(1 << 30),
+        "TB" => Some(1 << 40),
+        "PB" => Some(1 << 50),
+        _ => None,
+    }
+}
+
+/// Parse a vssadmin size such as `1.23 GB (1%)` or `1 234,5 GB (1%)`; `UNBOUNDED` yields
+/// `None`
+///
+/// Everything before the unit is the number; any character in it other than digits and
+/// `decimal` is a digit group separator.
+#[cfg(any(target_os = "windows", test))]
+fn parse_vssadmin_size(text: &str, decimal: char) -> Option<u64> {
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    let unit_at = parts.iter().position(|p| vssadmin_unit(p).is_some())?;
+    let number: String = parts[..unit_at]
+        .concat()
+        .chars()
+        .filter_map(|c| match c {
+            '0'..='9' => Some(c),
+            c if c == decimal => Some('.'),
+            _ => None,
+        })
+        .collect();
+    let value: f64 = number.parse().ok()?;
+    Some((value * vssadmin_unit(parts[unit_at])? as f64) as u64)
+}
+
 pub fn get_quick_access_folders() -> Result<Vec<StorageLocation>, String> {
     let mut folders = Vec::new();
 
@@ -333,6 +834,9 @@ pub fn get_quick_access_folders() -> Result<Vec<StorageLocation>, String> {
                     location_type: LocationType::Folder,
                     total_space: None,
                     available_space: None,
+                    inodes: None,
+                    quota: None,
+                    shadow_storage: None,
                 });
             }
         }
@@ -341,6 +845,111 @@ pub fn get_quick_access_folders() -> Result<Vec<StorageLocation>, String> {
     Ok(folders)
 }
 
+/// How much of the machine a scan covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanScope {
+    /// The signed-in user's profile folder
+    CurrentUser,
+    /// Every profile under the users folder; other users' profiles need elevation
+    AllUsers,
+    /// The whole system volume
+    WholeVolume,
+}
+
+/// A profile folder under the users folder (`C:\Users` on Windows)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UserProfile {
+    pub name: String,
+    pub path: PathBuf,
+    /// Whether the profile's contents can be listed by this process
+    pub accessible: bool,
+    /// The signed-in user's own profile
+    pub current: bool,
+}
+
+/// Where to scan for a scope, and which profiles it won't be able to read
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScopeRoot {
+    pub scope: ScanScope,
+    pub root: PathBuf,
+    /// Profiles under the root that can't be read; only checked for `AllUsers`
+    pub inaccessible_profiles: Vec<UserProfile>,
+    /// Whether running elevated would let the scan reach everything it skips
+    pub requires_elevation: bool,
+}
+
+/// The folder holding every user's profile, found from the current user's
+fn users_dir(home: &Path) -> PathBuf {
+    home.parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from(if cfg!(windows) { r"C:\Users" } else { "/home" }))
+}
+
+/// Profile folders under `users_dir`
+///
+/// Junctions such as `All Users` and `Default User` point at other folders and are
+/// skipped, as are plain files like `desktop.ini`.
+pub fn list_user_profiles(users_dir: &Path, home: &Path) -> Result<Vec<UserProfile>, String> {
+    let entries = std::fs::read_dir(users_dir)
+        .map_err(|e| format!("Cannot list {}: {}", users_dir.display(), e))?;
+    let mut profiles: Vec<UserProfile> = entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_ok_and(|t| t.is_dir() && !t.is_symlink())
+        })
+        .map(|entry| {
+            let path = entry.path();
+            UserProfile {
+                name: entry.file_name().to_string_lossy().to_string(),
+                accessible: std::fs::read_dir(&path).is_ok(),
+                current: path == home,
+                path,
+            }
+        })
+        .collect();
+    profiles.sort_by_key(|p| p.name.to_lowercase());
+    Ok(profiles)
+}
+
+pub fn resolve_scan_scope(scope: ScanScope) -> Result<ScopeRoot, String> {
+    let home = dirs::home_dir().ok_or("Cannot find the current user's profile folder")?;
+    let (root, inaccessible_profiles) = match scope {
+        ScanScope::CurrentUser => (home, Vec::new()),
+        ScanScope::AllUsers => {
+            let users = users_dir(&home);
+            let inaccessible = list_user_profiles(&users, &home)?
+                .into_iter()
+                .filter(|p| !p.accessible)
+                .collect();
+            (users, inaccessible)
+        }
+        ScanScope::WholeVolume => {
+            let volume = home
+                .ancestors()
+                .last()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| PathBuf::from("/"));
+            (volume, Vec::new())
+        }
+    };
+    let elevated = crate::environment::elevated().unwrap_or(false);
+    let restricted = match scope {
+        ScanScope::CurrentUser => false,
+        ScanScope::AllUsers => !inaccessible_profiles.is_empty(),
+        // System folders outside every profile are restricted too
+        ScanScope::WholeVolume => true,
+    };
+    Ok(ScopeRoot {
+        scope,
+        requires_elevation: restricted && !elevated,
+        root,
+        inaccessible_profiles,
+    })
+}
+
 #[tauri::command]
 pub async fn get_storage_locations_command() -> Result<Vec<StorageLocation>, String> {
     get_storage_locations()
@@ -350,3 +959,81 @@ pub async fn get_storage_locations_command() -> Result<Vec<StorageLocation>, Str
 pub async fn get_quick_access_folders_command() -> Result<Vec<StorageLocation>, String> {
     get_quick_access_folders()
 }
+
+/// The folder to scan for a scope, with the user profiles it won't be able to read
+#[tauri::command]
+pub async fn resolve_scan_scope_command(scope: ScanScope) -> Result<ScopeRoot, String> {
+    tokio::task::spawn_blocking(move || resolve_scan_scope(scope))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shadow_storage() {
+        let output = r"vssadmin 1.1 - Volume Shadow Copy Service administrative command-line tool
+(C) Copyright 2001-2013 Microsoft Corp.
+
+Shadow Copy Storage association
+   For volume: (C:)\\?\Volume{1b2c3d4e-0000-0000-0000-100000000000}\
+   Shadow Copy Storage volume: (C:)\\?\Volume{1b2c3d4e-0000-0000-0000-100000000000}\
+   Used Shadow Copy Storage space: 1.5 GB (1%)
+   Allocated Shadow Copy Storage space: 2 GB (2%)
+   Maximum Shadow Copy Storage space: 10 GB (10%)
+
+Shadow Copy Storage association
+   For volume: (D:)\\?\Volume{1b2c3d4e-0000-0000-0000-200000000000}\
+   Shadow Copy Storage volume: (D:)\\?\Volume{1b2c3d4e-0000-0000-0000-200000000000}\
+   Used Shadow Copy Storage space: 512 MB (0%)
+   Allocated Shadow Copy Storage space: 640 MB (0%)
+   Maximum Shadow Copy Storage space: UNBOUNDED (100%)
+";
+
+        let storages = parse_shadow_storage(output, '.');
+
+        assert_eq!(storages.len(), 2);
+        assert_eq!(storages[0].volume, "C:");
+        assert_eq!(storages[0].used_bytes, 3 << 29);
+        assert_eq!(storages[0].allocated_bytes, 2 << 30);
+        assert_eq!(storages[0].max_bytes, Some(10 << 30));
+        assert_eq!(storages[1].volume, "D:");
+        assert_eq!(storages[1].allocated_bytes, 640 << 20);
+        assert_eq!(storages[1].max_bytes, None);
+
+        let expected = Some((1234.5 * (1u64 << 30) as f64) as u64);
+        assert_eq!(parse_vssadmin_size("1,234.5 GB (1%)", '.'), expected);
+        assert_eq!(parse_vssadmin_size("1.234,5 GB (1%)", ','), expected);
+        assert_eq!(parse_vssadmin_size("1\u{a0}234,5 GB (1%)", ','), expected);
+        assert_eq!(parse_vssadmin_size("1,5 GB (1%)", ','), Some(3 << 29));
+        assert_eq!(parse_vssadmin_size("UNBOUNDED (100%)", '.'), None);
+
+        let wmi = r#"[{"volume":"C:","used":1024,"allocated":2048,"max":18446744073709551615}]"#;
+        let from_wmi = parse_wmi_shadow_storage(wmi).unwrap();
+        assert_eq!(from_wmi[0].volume, "C:");
+        assert_eq!(from_wmi[0].used_bytes, 1024);
+        assert_eq!(from_wmi[0].max_bytes, None);
+    }
+
+    #[test]
+    fn test_list_user_profiles() {
+        let users = std::env::temp_dir().join("test_user_profiles");
+        let _ = std::fs::remove_dir_all(&users);
+        std::fs::create_dir_all(users.join("alice")).unwrap();
+        std::fs::create_dir_all(users.join("Bob")).unwrap();
+        std::fs::write(users.join("desktop.ini"), b"").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(users.join("alice"), users.join("All Users")).unwrap();
+
+        let profiles = list_user_profiles(&users, &users.join("Bob")).unwrap();
+        std::fs::remove_dir_all(&users).unwrap();
+
+        let names: Vec<&str> = profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["alice", "Bob"]);
+        assert!(profiles.iter().all(|p| p.accessible));
+        assert!(!profiles[0].current);
+        assert!(profiles[1].current);
+    }
+}
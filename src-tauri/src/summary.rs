@@ -0,0 +1,286 @@
+use crate::change_journal::JournalCursor;
+use crate::registry::{self, DiscoveredNode};
+use crate::storage::{InodeUsage, QuotaUsage};
+use crate::telemetry::IoStats;
+use crate::types::FileType;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Which scanner produced a result
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanStrategy {
+    /// Async top-down walk feeding the node registry
+    Progressive,
+    /// Rescan that reuses a previous scan's results where they are still valid
+    Incremental,
+    /// Random walks estimating sizes with confidence intervals; nothing is exact
+    Sampled,
+    /// Largest-first walk that stopped when its time ran out; unfinished directories are
+    /// marked partial
+    Budgeted,
+}
+
+/// Why a scan left a path out
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionReason {
+    /// The app's own data directory, which changes while a scan runs
+    AppData,
+    /// The user skipped it after the scan stalled reading it
+    Skipped,
+    /// On a network mount that stopped answering; nothing more was read from that mount
+    Unreachable,
+    /// Matched one of the scan's exclude patterns
+    Pattern,
+}
+
+/// A path skipped by the scanner, so the UI can label it instead of it silently vanishing
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExcludedPath {
+    pub path: String,
+    pub reason: ExclusionReason,
+}
+
+/// How much of a previous scan an incremental rescan was able to reuse
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IncrementalStats {
+    pub previous_scan_id: u64,
+    /// The previous scan was read from the on-disk cache, so its id is from an earlier session
+    #[serde(default)]
+    pub from_cache: bool,
+    /// Whether a change journal said which directories changed, instead of walking to find out
+    pub used_change_journal: bool,
+    /// Off when not requested, or when the volume's file system doesn't update them reliably
+    pub mtimes_trusted: bool,
+    /// Directories whose listing was taken from the previous scan because their mtime was unchanged
+    pub directories_reused: u64,
+    /// Directories that were listed again
+    pub directories_rewalked: u64,
+}
+
+/// A directory a time-limited scan never listed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UnexploredDir {
+    pub path: String,
+    /// Size in the previous scan covering it, when there was one
+    pub previous_size: Option<u64>,
+}
+
+/// How a time-limited scan spent its budget
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BudgetStats {
+    pub budget_ms: u64,
+    /// Whether time ran out before every directory was listed
+    pub exhausted: bool,
+    pub directories_listed: u64,
+    /// Directories found but never listed, most promising first; capped, see `unexplored_count`
+    pub unexplored: Vec<UnexploredDir>,
+    pub unexplored_count: u64,
+}
+
+/// A path paired with a size in bytes
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PathSize {
+    pub path: String,
+    pub size: u64,
+}
+
+/// A path paired with its nesting depth below the scan root
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PathDepth {
+    pub path: String,
+    pub depth: usize,
+}
+
+/// Statistics describing a completed scan, so the frontend doesn't recompute them from the tree
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScanSummary {
+    /// Id used to query the retained scan with later commands
+    pub scan_id: u64,
+    pub root: String,
+    pub strategy: ScanStrategy,
+    pub duration_ms: u64,
+    pub files: u64,
+    pub directories: u64,
+    pub symlinks: u64,
+    /// Entries that could not be read
+    pub errors: u64,
+    pub total_size: u64,
+    pub bytes_by_category: HashMap<FileType, u64>,
+    /// Bytes not double-counted because they belong to an already-seen hard link
+    pub hard_link_savings: u64,
+    pub largest_file: Option<PathSize>,
+    pub deepest_path: Option<PathDepth>,
+    /// Inode usage of the scanned volume (Unix only)
+    pub inodes: Option<InodeUsage>,
+    /// Current user's quota on the scanned volume, if one is configured
+    pub quota: Option<QuotaUsage>,
+    /// Allocation unit of the scanned volume, used to estimate slack space
+    pub cluster_size: Option<u64>,
+    pub excluded: Vec<ExcludedPath>,
+    /// Set for incremental rescans
+    pub incremental: Option<IncrementalStats>,
+    /// Set for time-limited scans
+    #[serde(default)]
+    pub budget: Option<BudgetStats>,
+    /// Change journal position from just before the walk, for the next incremental rescan
+    pub journal: Option<JournalCursor>,
+    /// File system call counts, latencies and throughput, for progressive scans
+    #[serde(default)]
+    pub io: Option<IoStats>,
+}
+
+/// Counters the scanner accumulates while walking
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ScanCounters {
+    pub directories: u64,
+    pub symlinks: u64,
+    pub errors: u64,
+    pub hard_link_bytes: u64,
+    pub excluded: Vec<ExcludedPath>,
+}
+
+/// Build a summary from the full registry (not the truncated tree) and the walk counters
+pub(crate) fn build_summary(
+    scan_id: u64,
+    nodes: &HashMap<PathBuf, DiscoveredNode>,
+    root: &Path,
+    counters: &ScanCounters,
+    duration: Duration,
+    strategy: ScanStrategy,
+) -> ScanSummary {
+    let mut files = 0u64;
+    let mut total_size = 0u64;
+    let mut bytes_by_category: HashMap<FileType, u64> = HashMap::new();
+    let mut largest_file: Option<&DiscoveredNode> = None;
+    let mut deepest_path: Option<(&DiscoveredNode, usize)> = None;
+
+    for node in nodes.values() {
+        let depth = node
+            .path
+            .strip_prefix(root)
+            .map(|p| p.components().count())
+            .unwrap_or(0);
+        if deepest_path.is_none_or(|(_, deepest)| depth > deepest) {
+            deepest_path = Some((node, depth));
+        }
+
+        if let Some(dropped) = &node.dropped {
+            files += dropped.files;
+            total_size += dropped.bytes;
+            for (category, bytes, _) in &dropped.categories {
+                *bytes_by_category.entry(category.clone()).or_insert(0) += bytes;
+            }
+        }
+        if node.is_directory || node.is_symlink {
+            continue;
+        }
+
+        files += 1;
+        total_size += node.size;
+        *bytes_by_category.entry(node.file_type.clone()).or_insert(0) += node.size;
+
+        if largest_file.is_none_or(|largest| node.size > largest.size) {
+            largest_file = Some(node);
+        }
+    }
+
+    ScanSummary {
+        scan_id,
+        root: root.to_string_lossy().to_string(),
+        strategy,
+        duration_ms: duration.as_millis() as u64,
+        files,
+        directories: counters.directories,
+        symlinks: counters.symlinks,
+        errors: counters.errors,
+        total_size,
+        bytes_by_category,
+        hard_link_savings: counters.hard_link_bytes,
+        largest_file: largest_file.map(|n| PathSize {
+            path: n.path.to_string_lossy().to_string(),
+            size: n.size,
+        }),
+        deepest_path: deepest_path.map(|(n, depth)| PathDepth {
+            path: n.path.to_string_lossy().to_string(),
+            depth,
+        }),
+        inodes: None,
+        quota: None,
+        cluster_size: None,
+        excluded: counters.excluded.clone(),
+        incremental: None,
+        budget: None,
+        journal: None,
+        io: None,
+    }
+}
+
+// Tauri commands
+
+/// Summary of the most recently completed scan
+#[tauri::command]
+pub async fn get_scan_summary_command() -> Result<ScanSummary, String> {
+    registry::active_summary()
+        .await
+        .ok_or_else(|| "No completed scan available".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(path: &str, size: u64, is_directory: bool, file_type: FileType) -> DiscoveredNode {
+        DiscoveredNode {
+            file_type,
+            ..registry::test_node(path, size, is_directory)
+        }
+    }
+
+    #[test]
+    fn test_build_summary() {
+        let nodes: HashMap<PathBuf, DiscoveredNode> = [
+            node("/scan", 0, true, FileType::Other),
+            node("/scan/a", 0, true, FileType::Other),
+            node("/scan/a/b", 0, true, FileType::Other),
+            node("/scan/a/b/clip.mp4", 900, false, FileType::Video),
+            node("/scan/a/notes.txt", 10, false, FileType::Document),
+            node("/scan/report.pdf", 90, false, FileType::Document),
+        ]
+        .into_iter()
+        .map(|n| (n.path.clone(), n))
+        .collect();
+
+        let counters = ScanCounters {
+            directories: 3,
+            symlinks: 1,
+            errors: 2,
+            hard_link_bytes: 64,
+            excluded: Vec::new(),
+        };
+        let summary = build_summary(
+            1,
+            &nodes,
+            Path::new("/scan"),
+            &counters,
+            Duration::from_millis(1500),
+            ScanStrategy::Progressive,
+        );
+
+        assert_eq!(summary.files, 3);
+        assert_eq!(summary.total_size, 1000);
+        assert_eq!(summary.duration_ms, 1500);
+        assert_eq!(summary.bytes_by_category[&FileType::Document], 100);
+        assert_eq!(summary.bytes_by_category[&FileType::Video], 900);
+        assert_eq!(summary.hard_link_savings, 64);
+        assert_eq!(summary.largest_file.unwrap().path, "/scan/a/b/clip.mp4");
+
+        let deepest = summary.deepest_path.unwrap();
+        assert_eq!(deepest.path, "/scan/a/b/clip.mp4");
+        assert_eq!(deepest.depth, 3);
+    }
+}
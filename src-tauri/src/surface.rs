@@ -0,0 +1,278 @@
+use crate::access;
+use crate::duplicates::{is_device_error, UnreadableFile};
+use crate::tools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Window};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+/// Bytes read per call; large enough to keep spinning disks streaming
+const READ_CHUNK: usize = 1024 * 1024;
+
+/// Minimum time between `surface-scan-progress` events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Cancellation token of the surface check in progress, if any
+static SURFACE_JOB: once_cell::sync::Lazy<Arc<Mutex<Option<CancellationToken>>>> =
+    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(None)));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SurfaceCheckMode {
+    /// Read every file on the volume end to end; needs no special rights
+    ReadFiles,
+    /// Ask the drive to run its own short SMART self-test through smartctl
+    SmartSelfTest,
+}
+
+/// Progress of a surface check, emitted as `surface-scan-progress`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SurfaceProgress {
+    pub bytes_read: u64,
+    /// Used space on the volume; the check is done when `bytes_read` reaches it
+    pub bytes_total: u64,
+    pub files_read: u64,
+    pub bad_files: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SurfaceCheckResult {
+    pub volume: PathBuf,
+    pub mode: SurfaceCheckMode,
+    /// True when the check was cancelled before reading everything
+    pub cancelled: bool,
+    pub bytes_read: u64,
+    pub files_read: u64,
+    /// Files the drive failed to read; any entry here is reason not to trust the drive
+    pub bad_files: Vec<UnreadableFile>,
+    /// What smartctl reported when starting the self-test
+    pub self_test: Option<String>,
+}
+
+/// Read one file to the end, counting bytes as they arrive; `Ok(false)` when cancelled
+fn read_through(
+    path: &Path,
+    buffer: &mut [u8],
+    token: &CancellationToken,
+    on_read: &mut dyn FnMut(u64),
+) -> io::Result<bool> {
+    let mut file = File::open(path)?;
+    loop {
+        if token.is_cancelled() {
+            return Ok(false);
+        }
+        match file.read(buffer)? {
+            0 => return Ok(true),
+            n => on_read(n as u64),
+        }
+    }
+}
+
+/// Read every file on the volume that holds `volume`, without crossing into other mounts
+fn read_volume(
+    volume: &Path,
+    bytes_total: u64,
+    token: &CancellationToken,
+    on_progress: &mut dyn FnMut(&SurfaceProgress),
+) -> SurfaceCheckResult {
+    let mut buffer = vec![0u8; READ_CHUNK];
+    let mut progress = SurfaceProgress {
+        bytes_read: 0,
+        bytes_total,
+        files_read: 0,
+        bad_files: 0,
+    };
+    let mut bad_files = Vec::new();
+    let mut last_emit = Instant::now();
+    let mut cancelled = false;
+
+    let files = walkdir::WalkDir::new(volume)
+        .follow_links(false)
+        .same_file_system(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file());
+    for entry in files {
+        let mut on_read = |n: u64| {
+            progress.bytes_read += n;
+            if last_emit.elapsed() >= PROGRESS_INTERVAL {
+                on_progress(&progress);
+                last_emit = Instant::now();
+            }
+        };
+        match read_through(entry.path(), &mut buffer, token, &mut on_read) {
+            Ok(true) => progress.files_read += 1,
+            Ok(false) => {
+                cancelled = true;
+                break;
+            }
+            // Permission errors and files deleted mid-check say nothing about the disk
+            Err(e) if is_device_error(&e) => {
+                progress.bad_files += 1;
+                bad_files.push(UnreadableFile {
+                    path: entry.path().to_path_buf(),
+                    size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+                    error: e.to_string(),
+                });
+            }
+            Err(_) => {}
+        }
+    }
+    on_progress(&progress);
+
+    SurfaceCheckResult {
+        volume: volume.to_path_buf(),
+        mode: SurfaceCheckMode::ReadFiles,
+        cancelled,
+        bytes_read: progress.bytes_read,
+        files_read: progress.files_read,
+        bad_files,
+        self_test: None,
+    }
+}
+
+/// Run `f` on the mounted disk that holds `volume`
+fn with_disk<T>(volume: &Path, f: impl FnOnce(&sysinfo::Disk) -> T) -> Option<T> {
+    sysinfo::Disks::new_with_refreshed_list()
+        .list()
+        .iter()
+        .filter(|disk| volume.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(f)
+}
+
+/// Device smartctl should address for the volume mounted at `volume`
+///
+/// smartctl takes drive letters on Windows and device nodes elsewhere.
+#[cfg(target_os = "windows")]
+fn smart_device(volume: &Path) -> Option<String> {
+    with_disk(volume, |disk| {
+        disk.mount_point()
+            .to_string_lossy()
+            .trim_end_matches('\\')
+            .to_string()
+    })
+}
+
+#[cfg(not(target_os = "windows"))]
+fn smart_device(volume: &Path) -> Option<String> {
+    with_disk(volume, |disk| disk.name().to_string_lossy().to_string())
+}
+
+/// Start the drive's short self-test; it runs inside the drive and the result lands in its log
+fn start_smart_self_test(volume: &Path) -> Result<SurfaceCheckResult, String> {
+    let device =
+        smart_device(volume).ok_or_else(|| format!("No drive found for {}", volume.display()))?;
+    let output = tools::run("smartctl", &["-t", "short", &device])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // smartctl's exit status is a bit mask where bits 0 and 1 mean the command itself failed
+    if output.status.code().is_none_or(|code| code & 0b11 != 0) {
+        let reason = stdout.lines().last().unwrap_or_default().trim().to_string();
+        return Err(format!("smartctl could not test {}: {}", device, reason));
+    }
+    let started = stdout
+        .lines()
+        .map(str::trim)
+        .find(|l| l.contains("self-test") || l.contains("Self-test"))
+        .unwrap_or("Self-test started")
+        .to_string();
+
+    Ok(SurfaceCheckResult {
+        volume: volume.to_path_buf(),
+        mode: SurfaceCheckMode::SmartSelfTest,
+        cancelled: false,
+        bytes_read: 0,
+        files_read: 0,
+        bad_files: Vec::new(),
+        self_test: Some(started),
+    })
+}
+
+// Tauri commands
+
+/// Check a volume's health before trusting it, emitting `surface-scan-progress` events
+///
+/// Both modes only read: `read_files` reads every file the user can open, which turns up
+/// bad sectors under used space; `smart_self_test` hands the job to the drive itself.
+#[tauri::command]
+pub async fn surface_check_command(
+    volume: String,
+    mode: SurfaceCheckMode,
+    window: Window,
+) -> Result<SurfaceCheckResult, String> {
+    let volume = PathBuf::from(volume);
+    access::ensure_scan_root_allowed(&volume)?;
+    if mode == SurfaceCheckMode::SmartSelfTest {
+        return tokio::task::spawn_blocking(move || start_smart_self_test(&volume))
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let token = CancellationToken::new();
+    {
+        let mut job = SURFACE_JOB.lock().await;
+        if job.is_some() {
+            return Err("A surface check is already running".to_string());
+        }
+        *job = Some(token.clone());
+    }
+
+    let used = with_disk(&volume, |disk| disk.total_space() - disk.available_space());
+    let result = tokio::task::spawn_blocking(move || {
+        read_volume(&volume, used.unwrap_or(0), &token, &mut |progress| {
+            let _ = window.emit("surface-scan-progress", progress);
+        })
+    })
+    .await
+    .map_err(|e| e.to_string());
+
+    *SURFACE_JOB.lock().await = None;
+    result
+}
+
+#[tauri::command]
+pub async fn cancel_surface_check_command() -> Result<(), String> {
+    match SURFACE_JOB.lock().await.as_ref() {
+        Some(token) => {
+            token.cancel();
+            Ok(())
+        }
+        None => Err("No surface check is currently running".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_read_volume() {
+        let dir = std::env::temp_dir().join("test_surface");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("a.bin"), vec![1u8; READ_CHUNK + 10]).unwrap();
+        fs::write(dir.join("nested/b.txt"), b"hello").unwrap();
+
+        let mut events = 0;
+        let result = read_volume(&dir, 0, &CancellationToken::new(), &mut |_| events += 1);
+        let token = CancellationToken::new();
+        token.cancel();
+        let cancelled = read_volume(&dir, 0, &token, &mut |_| {});
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!result.cancelled);
+        assert_eq!(result.files_read, 2);
+        assert_eq!(result.bytes_read, READ_CHUNK as u64 + 15);
+        assert!(result.bad_files.is_empty());
+        assert!(events >= 1);
+        assert!(cancelled.cancelled);
+        assert_eq!(cancelled.bytes_read, 0);
+    }
+}
@@ -0,0 +1,388 @@
+use crate::confidence::Confidence;
+use crate::i18n::{localize, LocalizedMessage};
+use crate::sizes::SizeFormatter;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::path::{Component, Path, PathBuf};
+
+/// Whether the volume holding the Windows directory ignores case, probed once
+static SYSTEM_ROOT_IGNORES_CASE: once_cell::sync::Lazy<bool> =
+    once_cell::sync::Lazy::new(|| system_root().is_some_and(|root| ignores_case(&root)));
+
+/// Windows-managed caches that look deletable but must be cleaned through supported tools
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemStoreKind {
+    /// `WinSxS`, mostly hard links into System32
+    ComponentStore,
+    /// `SoftwareDistribution\Download`
+    UpdateDownloads,
+    /// Delivery Optimization peer cache
+    DeliveryOptimization,
+}
+
+/// A supported way to reclaim space from a system store
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CleanupSuggestion {
+    pub title: LocalizedMessage,
+    pub description: LocalizedMessage,
+    /// How much the cleanup can reclaim, once the store has been measured
+    pub freeable: Option<LocalizedMessage>,
+    /// Command line to run; shown to the user, never run automatically
+    pub command: String,
+    pub requires_admin: bool,
+    /// Always high: the supported tool only removes what Windows no longer needs
+    pub confidence: Confidence,
+}
+
+/// A system store with its sizes measured so hard links are not double counted
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SystemStore {
+    pub kind: SystemStoreKind,
+    pub path: PathBuf,
+    /// Sum of file sizes as Explorer reports them
+    pub apparent_size: u64,
+    /// Bytes on disk, counting each hard-linked file once
+    pub actual_size: u64,
+    /// Bytes referenced only from inside this store (what cleanup can actually free)
+    pub exclusive_size: u64,
+    pub file_count: u64,
+    pub suggestion: CleanupSuggestion,
+}
+
+/// Hard-link-aware totals for a directory tree
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LinkAwareSize {
+    pub apparent: u64,
+    pub actual: u64,
+    pub exclusive: u64,
+    pub files: u64,
+}
+
+/// Windows directory (`%SystemRoot%`)
+#[cfg(target_os = "windows")]
+fn system_root() -> Option<PathBuf> {
+    Some(
+        std::env::var_os("SystemRoot")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("C:\\Windows")),
+    )
+}
+
+/// System stores only exist on Windows
+#[cfg(not(target_os = "windows"))]
+fn system_root() -> Option<PathBuf> {
+    None
+}
+
+/// Known store locations below a Windows directory
+fn store_paths(system_root: &Path) -> Vec<(SystemStoreKind, PathBuf)> {
+    vec![
+        (SystemStoreKind::ComponentStore, system_root.join("WinSxS")),
+        (
+            SystemStoreKind::UpdateDownloads,
+            system_root.join("SoftwareDistribution").join("Download"),
+        ),
+        (
+            SystemStoreKind::DeliveryOptimization,
+            system_root
+                .join("SoftwareDistribution")
+                .join("DeliveryOptimization"),
+        ),
+        // Location used before Windows 10 1803
+        (
+            SystemStoreKind::DeliveryOptimization,
+            system_root.join(
+                "ServiceProfiles\\NetworkService\\AppData\\Local\\Microsoft\\Windows\\DeliveryOptimization\\Cache",
+            ),
+        ),
+    ]
+}
+
+/// The supported cleanup action for a store
+fn suggestion_for(kind: SystemStoreKind, path: &Path) -> CleanupSuggestion {
+    let (code, command) = match kind {
+        SystemStoreKind::ComponentStore => (
+            "suggestion-component-store",
+            "Dism.exe /Online /Cleanup-Image /StartComponentCleanup".to_string(),
+        ),
+        SystemStoreKind::UpdateDownloads => {
+            let drive = path
+                .components()
+                .next()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .unwrap_or_else(|| "C:".to_string());
+            (
+                "suggestion-update-downloads",
+                format!("cleanmgr.exe /d {}", drive),
+            )
+        }
+        SystemStoreKind::DeliveryOptimization => (
+            "suggestion-delivery-optimization",
+            "powershell.exe -Command Delete-DeliveryOptimizationCache -Force".to_string(),
+        ),
+    };
+    CleanupSuggestion {
+        title: localize(&format!("{}-title", code), &[]),
+        description: localize(&format!("{}-description", code), &[]),
+        freeable: None,
+        command,
+        requires_admin: true,
+        confidence: Confidence::High,
+    }
+}
+
+/// Both paths name the same existing file or directory
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => (a.dev(), a.ino()) == (b.dev(), b.ino()),
+        _ => false,
+    }
+}
+
+/// Both paths name the same existing file or directory (canonical paths come back in the
+/// volume's own case)
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Whether the volume holding `dir` ignores case: `dir` with the case of its name flipped
+/// is the same directory
+fn ignores_case(dir: &Path) -> bool {
+    let Some(name) = dir.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    let flipped: String = name
+        .chars()
+        .flat_map(|c| {
+            if c.is_uppercase() {
+                c.to_lowercase().collect::<Vec<_>>()
+            } else {
+                c.to_uppercase().collect()
+            }
+        })
+        .collect();
+    flipped != name && same_file(dir, &dir.with_file_name(flipped))
+}
+
+/// Whether `path` is `base` or lies below it, comparing whole components
+fn path_within(path: &Path, base: &Path, ignore_case: bool) -> bool {
+    let fold = |c: Component| {
+        let text = c.as_os_str().to_string_lossy();
+        if ignore_case {
+            text.to_lowercase()
+        } else {
+            text.into_owned()
+        }
+    };
+    let mut parts = path.components();
+    base.components()
+        .all(|b| parts.next().is_some_and(|p| fold(p) == fold(b)))
+}
+
+/// The system store containing `path`, if any
+pub(crate) fn store_containing(path: &Path) -> Option<(SystemStoreKind, CleanupSuggestion)> {
+    let root = system_root()?;
+    let ignore_case = *SYSTEM_ROOT_IGNORES_CASE;
+
+    store_paths(&root).into_iter().find_map(|(kind, store)| {
+        path_within(path, &store, ignore_case).then(|| (kind, suggestion_for(kind, &store)))
+    })
+}
+
+/// File identity and hard link count
+#[cfg(unix)]
+fn link_info(_path: &Path, metadata: &Metadata) -> Option<((u64, u64), u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some(((metadata.dev(), metadata.ino()), metadata.nlink()))
+}
+
+/// File identity and hard link count (from `GetFileInformationByHandle`)
+#[cfg(target_os = "windows")]
+fn link_info(path: &Path, _metadata: &Metadata) -> Option<((u64, u64), u64)> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use winapi::um::fileapi::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+
+    // Access mode 0 only queries attributes, which works on TrustedInstaller-owned files
+    let file = std::fs::OpenOptions::new().access_mode(0).open(path).ok()?;
+
+    unsafe {
+        let mut info: BY_HANDLE_FILE_INFORMATION = std::mem::zeroed();
+        if GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) == 0 {
+            return None;
+        }
+        let index = (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+        Some((
+            (u64::from(info.dwVolumeSerialNumber), index),
+            u64::from(info.nNumberOfLinks),
+        ))
+    }
+}
+
+/// Measure a tree, counting each hard-linked file once and tracking links that point outside it
+pub(crate) fn measure_link_aware(path: &Path) -> LinkAwareSize {
+    let mut totals = LinkAwareSize::default();
+    // file id -> (size, total links, links seen inside the tree)
+    let mut seen: HashMap<(u64, u64), (u64, u64, u64)> = HashMap::new();
+
+    for entry in walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let size = metadata.len();
+        totals.apparent += size;
+        totals.files += 1;
+
+        match link_info(entry.path(), &metadata) {
+            Some((id, links)) if links > 1 => {
+                seen.entry(id).or_insert((size, links, 0)).2 += 1;
+            }
+            _ => {
+                totals.actual += size;
+                totals.exclusive += size;
+            }
+        }
+    }
+
+    for (size, links, inside) in seen.into_values() {
+        totals.actual += size;
+        if inside >= links {
+            totals.exclusive += size;
+        }
+    }
+
+    totals
+}
+
+/// Measure every system store present on this machine
+pub fn get_system_stores() -> Vec<SystemStore> {
+    let Some(root) = system_root() else {
+        return Vec::new();
+    };
+
+    let sizes = SizeFormatter::current();
+    store_paths(&root)
+        .into_iter()
+        .filter(|(_, path)| path.is_dir())
+        .map(|(kind, path)| {
+            let size = measure_link_aware(&path);
+            let mut suggestion = suggestion_for(kind, &path);
+            suggestion.freeable = Some(localize(
+                "suggestion-freeable",
+                &[
+                    ("size", json!(sizes.format(size.exclusive))),
+                    ("bytes", json!(size.exclusive)),
+                ],
+            ));
+            SystemStore {
+                kind,
+                suggestion,
+                path,
+                apparent_size: size.apparent,
+                actual_size: size.actual,
+                exclusive_size: size.exclusive,
+                file_count: size.files,
+            }
+        })
+        .collect()
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn get_system_stores_command() -> Result<Vec<SystemStore>, String> {
+    tokio::task::spawn_blocking(get_system_stores)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_store_paths() {
+        let stores = store_paths(Path::new("C:\\Windows"));
+        assert_eq!(stores[0].0, SystemStoreKind::ComponentStore);
+        assert!(stores[0].1.to_string_lossy().ends_with("WinSxS"));
+        let suggestion = suggestion_for(SystemStoreKind::ComponentStore, &stores[0].1);
+        assert!(suggestion.command.contains("StartComponentCleanup"));
+        assert_eq!(suggestion.title.code, "suggestion-component-store-title");
+    }
+
+    #[test]
+    fn test_path_within_compares_components() {
+        let store = Path::new("/Users/a/Library/Caches");
+        assert!(path_within(
+            Path::new("/Users/a/Library/Caches"),
+            store,
+            false
+        ));
+        assert!(path_within(
+            Path::new("/Users/a/Library/Caches/x"),
+            store,
+            false
+        ));
+        assert!(!path_within(
+            Path::new("/Users/a/Library/Caches2"),
+            store,
+            true
+        ));
+        assert!(!path_within(
+            Path::new("/users/a/library/caches/x"),
+            store,
+            false
+        ));
+        assert!(path_within(
+            Path::new("/users/a/library/caches/x"),
+            store,
+            true
+        ));
+        assert!(!path_within(Path::new("/Users/a/Library"), store, true));
+
+        let dir = std::env::temp_dir().join(format!("CaseProbe{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let lowered = dir.with_file_name(format!("caseprobe{}", std::process::id()));
+        assert_eq!(ignores_case(&dir), lowered.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_measure_link_aware() {
+        let temp_dir = std::env::temp_dir().join("test_system_stores");
+        let store = temp_dir.join("store");
+        fs::create_dir_all(&store).unwrap();
+
+        // Linked to a file outside the store: counted once, not exclusive
+        fs::write(temp_dir.join("outside.bin"), [0u8; 100]).unwrap();
+        fs::hard_link(temp_dir.join("outside.bin"), store.join("shared.bin")).unwrap();
+        // Both links inside the store: counted once, exclusive
+        fs::write(store.join("a.bin"), [0u8; 40]).unwrap();
+        fs::hard_link(store.join("a.bin"), store.join("b.bin")).unwrap();
+        fs::write(store.join("plain.bin"), [0u8; 7]).unwrap();
+
+        let size = measure_link_aware(&store);
+        fs::remove_dir_all(&temp_dir).unwrap();
+
+        assert_eq!(size.files, 4);
+        assert_eq!(size.apparent, 187);
+        assert_eq!(size.actual, 147);
+        assert_eq!(size.exclusive, 47);
+    }
+}
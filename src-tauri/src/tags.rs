@@ -0,0 +1,171 @@
+use crate::persistence::{data_file, read_sealed_json_or_default, write_sealed_json};
+use crate::registry::{self, DiscoveredNode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// A user's verdict on a node, kept across sessions
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeTag {
+    Keep,
+    Review,
+    ArchiveCandidate,
+}
+
+/// A stored tag, keyed by path and the fingerprint of the file it was given to
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagEntry {
+    pub path: String,
+    /// Fingerprint of the file's size and mtime (`None` for directories)
+    pub fingerprint: Option<u64>,
+    pub tag: NodeTag,
+    /// Unix timestamp (seconds) when the tag was set
+    pub tagged_at: i64,
+}
+
+/// A stored tag matched to a node of a scan
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TaggedNode {
+    pub id: u64,
+    pub path: String,
+    pub tag: NodeTag,
+}
+
+/// Tags keyed by path
+type TagStore = HashMap<String, TagEntry>;
+
+const TAGS_FILE: &str = "tags.json";
+
+/// Identify a file's contents cheaply, so a tag doesn't follow a path onto a different file
+///
+/// FNV-1a over size and mtime: stable across builds, unlike `DefaultHasher`.
+fn fingerprint(node: &DiscoveredNode) -> Option<u64> {
+    if node.is_directory {
+        return None;
+    }
+
+    let modified = node
+        .modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in node
+        .size
+        .to_le_bytes()
+        .into_iter()
+        .chain(modified.to_le_bytes())
+    {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    Some(hash)
+}
+
+/// Match stored tags against a scan's nodes; a file tag only applies while its fingerprint matches
+fn apply_tags(tags: &TagStore, nodes: &HashMap<PathBuf, DiscoveredNode>) -> Vec<TaggedNode> {
+    let mut tagged: Vec<TaggedNode> = tags
+        .values()
+        .filter_map(|entry| {
+            let node = nodes.get(Path::new(&entry.path))?;
+            (fingerprint(node) == entry.fingerprint).then(|| TaggedNode {
+                id: node.id,
+                path: entry.path.clone(),
+                tag: entry.tag,
+            })
+        })
+        .collect();
+    tagged.sort_by(|a, b| a.path.cmp(&b.path));
+    tagged
+}
+
+// Tauri commands
+
+/// Tag a node of a retained scan, or clear its tag when `tag` is `None`
+#[tauri::command]
+pub async fn tag_node_command(
+    scan_id: u64,
+    path: String,
+    tag: Option<NodeTag>,
+) -> Result<Option<TagEntry>, String> {
+    let fingerprint = registry::with_scan(scan_id, |nodes, _| {
+        nodes.get(Path::new(&path)).map(fingerprint)
+    })
+    .await?
+    .ok_or_else(|| format!("{} is not part of scan {}", path, scan_id))?;
+
+    let file = data_file(TAGS_FILE)?;
+    let mut tags: TagStore = read_sealed_json_or_default(&file)?;
+
+    let entry = tag.map(|tag| TagEntry {
+        path: path.clone(),
+        fingerprint,
+        tag,
+        tagged_at: chrono::Utc::now().timestamp(),
+    });
+    match &entry {
+        Some(entry) => tags.insert(path, entry.clone()),
+        None => tags.remove(&path),
+    };
+
+    write_sealed_json(&file, &tags)?;
+    Ok(entry)
+}
+
+/// Stored tags that still apply to a retained scan
+#[tauri::command]
+pub async fn get_scan_tags_command(scan_id: u64) -> Result<Vec<TaggedNode>, String> {
+    let tags: TagStore = read_sealed_json_or_default(&data_file(TAGS_FILE)?)?;
+    registry::with_scan(scan_id, |nodes, _| apply_tags(&tags, nodes)).await
+}
+
+#[tauri::command]
+pub async fn list_tags_command() -> Result<Vec<TagEntry>, String> {
+    let tags: TagStore = read_sealed_json_or_default(&data_file(TAGS_FILE)?)?;
+    let mut entries: Vec<TagEntry> = tags.into_values().collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_tags_follow_unchanged_files_only() {
+        let dir = registry::test_node("/scan/old", 0, true);
+        let file = registry::test_node("/scan/old/video.mp4", 500, false);
+        let mut tags = TagStore::new();
+        for (node, tag) in [(&dir, NodeTag::Review), (&file, NodeTag::Keep)] {
+            let path = node.path.to_string_lossy().to_string();
+            tags.insert(
+                path.clone(),
+                TagEntry {
+                    path,
+                    fingerprint: fingerprint(node),
+                    tag,
+                    tagged_at: 0,
+                },
+            );
+        }
+
+        let mut nodes: HashMap<PathBuf, DiscoveredNode> = [dir.clone(), file.clone()]
+            .into_iter()
+            .map(|n| (n.path.clone(), n))
+            .collect();
+        let tagged = apply_tags(&tags, &nodes);
+        assert_eq!(tagged.len(), 2);
+        assert_eq!(tagged[1].tag, NodeTag::Keep);
+
+        // The file was rewritten since it was tagged
+        nodes.get_mut(&file.path).unwrap().modified += Duration::from_secs(60);
+        let tagged = apply_tags(&tags, &nodes);
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].path, "/scan/old");
+    }
+}
@@ -0,0 +1,173 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Latency buckets; bucket `i` holds calls that took under 2^(i+1) microseconds
+const LATENCY_BUCKETS: usize = 32;
+
+/// Throughput samples kept per scan; older ones are thinned out so a long scan still fits
+const MAX_SAMPLES: usize = 120;
+
+/// File system activity over one slice of the scan
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ThroughputSample {
+    /// Time since the scan started, at the end of this slice
+    pub elapsed_ms: u64,
+    pub ops_per_sec: f64,
+    /// Bytes of file sizes discovered per second; the scan reads metadata, not contents
+    pub bytes_per_sec: f64,
+}
+
+/// How hard a scan worked the disk, so a slow scan can be blamed on the right party
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct IoStats {
+    /// Metadata lookups and directory reads issued
+    pub read_ops: u64,
+    /// Latency percentiles in microseconds, rounded up to a power of two
+    pub latency_p50_us: u64,
+    pub latency_p90_us: u64,
+    pub latency_p99_us: u64,
+    pub latency_max_us: u64,
+    pub throughput: Vec<ThroughputSample>,
+}
+
+/// Counters for the scan in progress; only one progressive scan runs at a time
+struct IoRecorder {
+    read_ops: AtomicU64,
+    latency_max_us: AtomicU64,
+    buckets: [AtomicU64; LATENCY_BUCKETS],
+    /// Samples so far, and the (elapsed, ops, bytes) the last one was taken at
+    samples: Mutex<(Vec<ThroughputSample>, (Duration, u64, u64))>,
+}
+
+static SCAN_IO: IoRecorder = IoRecorder {
+    read_ops: AtomicU64::new(0),
+    latency_max_us: AtomicU64::new(0),
+    buckets: [const { AtomicU64::new(0) }; LATENCY_BUCKETS],
+    samples: Mutex::new((Vec::new(), (Duration::ZERO, 0, 0))),
+};
+
+fn bucket_for(micros: u64) -> usize {
+    (u64::BITS - micros.leading_zeros()).saturating_sub(1) as usize
+}
+
+/// Smallest latency bound that covers `fraction` of the recorded calls
+fn percentile(buckets: &[u64], fraction: f64) -> u64 {
+    let total: u64 = buckets.iter().sum();
+    if total == 0 {
+        return 0;
+    }
+    let wanted = (total as f64 * fraction).ceil() as u64;
+    let mut seen = 0;
+    for (i, count) in buckets.iter().enumerate() {
+        seen += count;
+        if seen >= wanted {
+            return 1 << (i + 1);
+        }
+    }
+    1 << LATENCY_BUCKETS
+}
+
+/// Keep every other sample once the list is full, so samples stay spread over the whole scan
+fn push_sample(samples: &mut Vec<ThroughputSample>, sample: ThroughputSample) {
+    samples.push(sample);
+    if samples.len() > MAX_SAMPLES {
+        // Count from the newest so the latest sample always survives
+        let mut from_end = samples.len();
+        samples.retain(|_| {
+            from_end -= 1;
+            from_end.is_multiple_of(2)
+        });
+    }
+}
+
+/// Start counting for a new scan
+pub(crate) fn reset() {
+    SCAN_IO.read_ops.store(0, Ordering::Relaxed);
+    SCAN_IO.latency_max_us.store(0, Ordering::Relaxed);
+    for bucket in &SCAN_IO.buckets {
+        bucket.store(0, Ordering::Relaxed);
+    }
+    *SCAN_IO.samples.lock().unwrap() = (Vec::new(), (Duration::ZERO, 0, 0));
+}
+
+/// Count one file system call and how long it took
+pub(crate) fn record(latency: Duration) {
+    let micros = latency.as_micros() as u64;
+    SCAN_IO.read_ops.fetch_add(1, Ordering::Relaxed);
+    SCAN_IO.latency_max_us.fetch_max(micros, Ordering::Relaxed);
+    SCAN_IO.buckets[bucket_for(micros).min(LATENCY_BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Take a throughput sample; `bytes` is the running total of sizes discovered
+pub(crate) fn sample(elapsed: Duration, bytes: u64) {
+    let ops = SCAN_IO.read_ops.load(Ordering::Relaxed);
+    let mut samples = SCAN_IO.samples.lock().unwrap();
+    let (list, (last_elapsed, last_ops, last_bytes)) = &mut *samples;
+    let seconds = (elapsed - *last_elapsed).as_secs_f64();
+    if seconds <= 0.0 {
+        return;
+    }
+    push_sample(
+        list,
+        ThroughputSample {
+            elapsed_ms: elapsed.as_millis() as u64,
+            ops_per_sec: (ops - *last_ops) as f64 / seconds,
+            bytes_per_sec: bytes.saturating_sub(*last_bytes) as f64 / seconds,
+        },
+    );
+    *last_elapsed = elapsed;
+    *last_ops = ops;
+    *last_bytes = bytes;
+}
+
+/// Everything recorded since the last `reset`
+pub(crate) fn snapshot() -> IoStats {
+    let buckets: Vec<u64> = SCAN_IO
+        .buckets
+        .iter()
+        .map(|b| b.load(Ordering::Relaxed))
+        .collect();
+    IoStats {
+        read_ops: SCAN_IO.read_ops.load(Ordering::Relaxed),
+        latency_p50_us: percentile(&buckets, 0.5),
+        latency_p90_us: percentile(&buckets, 0.9),
+        latency_p99_us: percentile(&buckets, 0.99),
+        latency_max_us: SCAN_IO.latency_max_us.load(Ordering::Relaxed),
+        throughput: SCAN_IO.samples.lock().unwrap().0.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_and_sample_thinning() {
+        let mut buckets = vec![0u64; LATENCY_BUCKETS];
+        for micros in [3u64, 3, 3, 3, 3, 3, 3, 3, 40, 9000] {
+            buckets[bucket_for(micros)] += 1;
+        }
+        assert_eq!(percentile(&buckets, 0.5), 4);
+        assert_eq!(percentile(&buckets, 0.9), 64);
+        assert_eq!(percentile(&buckets, 0.99), 16384);
+        assert_eq!(percentile(&[0; LATENCY_BUCKETS], 0.5), 0);
+        assert_eq!(bucket_for(0), 0);
+
+        let mut samples = Vec::new();
+        for i in 0..=MAX_SAMPLES as u64 {
+            push_sample(
+                &mut samples,
+                ThroughputSample {
+                    elapsed_ms: i,
+                    ops_per_sec: 0.0,
+                    bytes_per_sec: 0.0,
+                },
+            );
+        }
+        assert_eq!(samples.len(), MAX_SAMPLES / 2 + 1);
+        assert_eq!(samples.last().unwrap().elapsed_ms, MAX_SAMPLES as u64);
+    }
+}
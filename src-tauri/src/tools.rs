@@ -0,0 +1,155 @@
+use crate::environment::find_tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::process::Output;
+use std::sync::Mutex;
+
+/// Prefix of errors returned when an external tool is missing or blocked, so the frontend
+/// can explain it instead of showing the raw error
+pub const TOOL_UNAVAILABLE: &str = "ToolUnavailable";
+
+/// Tools the OS refused to start (a sandbox, or an allow-list); remembered for the session
+static BLOCKED: once_cell::sync::Lazy<Mutex<HashSet<String>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolState {
+    Available,
+    /// Not found on `PATH`
+    Missing,
+    /// Found, but the OS refused to start it
+    Blocked,
+}
+
+/// An external tool, what depends on it, and what happens without it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ToolCapability {
+    pub tool: String,
+    pub state: ToolState,
+    pub features: Vec<String>,
+    /// How those features behave when the tool can't run; `None` when they are unavailable
+    pub fallback: Option<String>,
+}
+
+/// (tool, features that use it, fallback when it can't run)
+#[cfg(target_os = "macos")]
+const KNOWN_TOOLS: &[(&str, &[&str], Option<&str>)] = &[
+    (
+        "diskutil",
+        &["disk_image_detection", "eject"],
+        Some("Mounted disk images are listed as ordinary volumes"),
+    ),
+    ("tmutil", &["local_snapshots"], None),
+    ("hdiutil", &["disk_image_inspection"], None),
+    ("smartctl", &["smart_self_test"], None),
+    ("VBoxManage", &["vm_disk_compaction"], None),
+    ("prl_disk_tool", &["vm_disk_compaction"], None),
+];
+#[cfg(target_os = "windows")]
+const KNOWN_TOOLS: &[(&str, &[&str], Option<&str>)] = &[
+    (
+        "vssadmin",
+        &["shadow_storage"],
+        Some("Shadow storage is read through WMI with PowerShell"),
+    ),
+    (
+        "powershell",
+        &["disk_image_inspection", "vm_disk_compaction"],
+        None,
+    ),
+    ("icacls", &["ownership_repair"], None),
+    ("smartctl", &["smart_self_test"], None),
+    ("VBoxManage", &["vm_disk_compaction"], None),
+    ("vmware-vdiskmanager", &["vm_disk_compaction"], None),
+];
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const KNOWN_TOOLS: &[(&str, &[&str], Option<&str>)] = &[
+    ("smartctl", &["smart_self_test"], None),
+    ("udisksctl", &["eject"], None),
+    ("VBoxManage", &["vm_disk_compaction"], None),
+    ("vmware-vdiskmanager", &["vm_disk_compaction"], None),
+];
+
+fn tool_state(tool: &str) -> ToolState {
+    if BLOCKED.lock().unwrap().contains(tool) {
+        ToolState::Blocked
+    } else if find_tool(tool).is_some() {
+        ToolState::Available
+    } else {
+        ToolState::Missing
+    }
+}
+
+/// Run an external tool, turning "not installed" and "not allowed" into `ToolUnavailable`
+/// errors; the exit status is left to the caller
+pub(crate) fn run(tool: &str, args: &[&str]) -> Result<Output, String> {
+    let mut command = std::process::Command::new(tool);
+    command.args(args);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        // Keep console tools from flashing a window
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    command.output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => {
+            format!("{}: {} is not installed", TOOL_UNAVAILABLE, tool)
+        }
+        std::io::ErrorKind::PermissionDenied => {
+            BLOCKED.lock().unwrap().insert(tool.to_string());
+            format!(
+                "{}: {} was blocked by the sandbox or a security policy",
+                TOOL_UNAVAILABLE, tool
+            )
+        }
+        _ => format!("Failed to run {}: {}", tool, e),
+    })
+}
+
+/// Whether an error from `run` means the tool couldn't start, so a fallback applies
+#[cfg(any(target_os = "windows", test))]
+pub(crate) fn is_unavailable(error: &str) -> bool {
+    error.starts_with(TOOL_UNAVAILABLE)
+}
+
+pub fn tool_capabilities() -> Vec<ToolCapability> {
+    KNOWN_TOOLS
+        .iter()
+        .map(|(tool, features, fallback)| ToolCapability {
+            tool: tool.to_string(),
+            state: tool_state(tool),
+            features: features.iter().map(|f| f.to_string()).collect(),
+            fallback: fallback.map(str::to_string),
+        })
+        .collect()
+}
+
+// Tauri commands
+
+/// The external tools this platform's features use, and which of them can run
+#[tauri::command]
+pub async fn get_tool_capabilities_command() -> Result<Vec<ToolCapability>, String> {
+    tokio::task::spawn_blocking(tool_capabilities)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_tool_is_reported_as_unavailable() {
+        let error = run("no-such-tool-for-disk-analyser", &["--version"]).unwrap_err();
+        assert!(is_unavailable(&error));
+        assert_eq!(
+            tool_state("no-such-tool-for-disk-analyser"),
+            ToolState::Missing
+        );
+        assert!(!is_unavailable("tmutil thinlocalsnapshots failed: busy"));
+    }
+}
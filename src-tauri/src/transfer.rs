@@ -0,0 +1,139 @@
+use crate::types::FileNode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tauri::ipc::Response;
+use tokio::sync::Mutex;
+
+/// Size of each binary chunk handed to the frontend
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Encoded trees waiting to be fetched, keyed by handle
+type TransferStore = Arc<Mutex<HashMap<u64, Vec<u8>>>>;
+
+static TRANSFERS: once_cell::sync::Lazy<TransferStore> =
+    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(1);
+
+/// Describes an encoded tree the frontend can fetch chunk by chunk
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TreeHandle {
+    pub handle: u64,
+    /// Encoding of the payload (always "msgpack")
+    pub encoding: String,
+    pub total_bytes: u64,
+    pub chunk_size: u64,
+    pub chunk_count: u64,
+}
+
+/// Encode a tree as MessagePack and park it behind a handle
+///
+/// Field names are kept (`to_vec_named`) so the frontend decodes plain objects
+/// with the same shape as the JSON `FileNode`.
+pub async fn prepare_tree_transfer(tree: &FileNode) -> Result<TreeHandle, String> {
+    let bytes = rmp_serde::to_vec_named(tree).map_err(|e| format!("Cannot encode tree: {}", e))?;
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+
+    let info = TreeHandle {
+        handle,
+        encoding: "msgpack".to_string(),
+        total_bytes: bytes.len() as u64,
+        chunk_size: CHUNK_SIZE as u64,
+        chunk_count: bytes.len().div_ceil(CHUNK_SIZE) as u64,
+    };
+
+    TRANSFERS.lock().await.insert(handle, bytes);
+    Ok(info)
+}
+
+/// Read one chunk of a parked tree
+pub async fn read_chunk(handle: u64, index: u64) -> Result<Vec<u8>, String> {
+    let transfers = TRANSFERS.lock().await;
+    let bytes = transfers
+        .get(&handle)
+        .ok_or_else(|| format!("Unknown transfer handle: {}", handle))?;
+
+    let start = (index as usize).saturating_mul(CHUNK_SIZE);
+    if start >= bytes.len() {
+        return Err(format!("Chunk {} out of range", index));
+    }
+    let end = (start + CHUNK_SIZE).min(bytes.len());
+
+    Ok(bytes[start..end].to_vec())
+}
+
+/// Drop a parked tree once the frontend has finished reading it
+pub async fn release_transfer(handle: u64) -> bool {
+    TRANSFERS.lock().await.remove(&handle).is_some()
+}
+
+// Tauri commands
+
+/// Scan a directory and return a handle to the binary-encoded tree instead of the tree itself
+#[tauri::command]
+pub async fn scan_directory_binary_command(
+    path: String,
+    child_limit: Option<usize>,
+    aggregate_below: Option<u64>,
+    window: tauri::Window,
+) -> Result<TreeHandle, String> {
+    let shape = crate::scanner::TreeShape::new(child_limit, aggregate_below);
+    let tree = crate::scanner::scan_directory_async(path, shape, Vec::new(), None, window).await?;
+    prepare_tree_transfer(&tree).await
+}
+
+/// Fetch a chunk as a raw binary response (no JSON encoding on the way out)
+#[tauri::command]
+pub async fn read_tree_chunk_command(handle: u64, index: u64) -> Result<Response, String> {
+    read_chunk(handle, index).await.map(Response::new)
+}
+
+#[tauri::command]
+pub async fn release_tree_handle_command(handle: u64) -> Result<bool, String> {
+    Ok(release_transfer(handle).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FileType;
+    use std::path::PathBuf;
+    use std::time::SystemTime;
+
+    #[tokio::test]
+    async fn test_chunks_reassemble_to_tree() {
+        let tree = FileNode {
+            id: 0,
+            name: "root".to_string(),
+            path: PathBuf::from("/root"),
+            size: 7,
+            is_directory: true,
+            children: vec![],
+            file_type: FileType::Other,
+            modified: SystemTime::UNIX_EPOCH,
+            is_symlink: false,
+            symlink_target: None,
+            synthetic: None,
+            pct_of_parent: None,
+            pct_of_volume: None,
+            is_partial: false,
+        };
+
+        let info = prepare_tree_transfer(&tree).await.unwrap();
+        let mut bytes = Vec::new();
+        for index in 0..info.chunk_count {
+            bytes.extend(read_chunk(info.handle, index).await.unwrap());
+        }
+        assert_eq!(bytes.len() as u64, info.total_bytes);
+
+        let decoded: FileNode = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(decoded.size, 7);
+        assert!(read_chunk(info.handle, info.chunk_count).await.is_err());
+
+        assert!(release_transfer(info.handle).await);
+        assert!(read_chunk(info.handle, 0).await.is_err());
+    }
+}
@@ -1,9 +1,20 @@
+use crate::classifier::CategoryStats;
+use crate::estimate::EstimatedNode;
+use crate::summary::ScanSummary;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::time::SystemTime;
 
+/// Version of the serialized tree/event schema
+///
+/// Bump this whenever `FileNode` or `StreamingScanEvent` change in a way `#[serde(default)]`
+/// can't absorb, and add a matching migration step to the persistence module so older saved
+/// scans keep loading.
+pub const SCHEMA_VERSION: u32 = 2;
+
 /// Enum representing different file categories based on file extensions
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum FileType {
     Document,
     Image,
@@ -17,8 +28,11 @@ pub enum FileType {
 }
 
 /// Represents a file or directory node in the file system tree
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FileNode {
+    /// Stable identifier assigned when the node is first discovered
+    #[serde(default)]
+    pub id: u64,
     /// Name of the file or directory
     pub name: String,
     /// Full path to the file or directory
@@ -33,10 +47,58 @@ pub struct FileNode {
     pub file_type: FileType,
     /// Last modified timestamp
     pub modified: SystemTime,
+    /// Whether this node is a symlink (or a junction on Windows); links are reported, not followed
+    #[serde(default)]
+    pub is_symlink: bool,
+    /// Where the link points, if it could be read
+    #[serde(default)]
+    pub symlink_target: Option<PathBuf>,
+    /// Set on nodes that stand for several real entries rather than one; their `path` is
+    /// the directory the entries are in
+    #[serde(default)]
+    pub synthetic: Option<SyntheticNode>,
+    /// Percentage of the parent's size, rounded to one decimal; set by the tree and child
+    /// endpoints
+    #[serde(default)]
+    pub pct_of_parent: Option<f64>,
+    /// Percentage of the volume's capacity, rounded the same way
+    #[serde(default)]
+    pub pct_of_volume: Option<f64>,
+    /// Set on directories a time-limited scan didn't finish; their size is a lower bound
+    #[serde(default)]
+    pub is_partial: bool,
+}
+
+/// What a synthetic tree node stands for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SyntheticNode {
+    /// The smallest children left out past the child limit; page through them with
+    /// `get_children_command`
+    MoreItems { count: u64 },
+    /// Files smaller than `threshold` bytes, grouped so they don't crowd out the rest; the
+    /// real entries are still listed by `get_children_command`
+    SmallFiles { count: u64, threshold: u64 },
+    /// Files the retained scan keeps only as a count and total size, to save memory; they
+    /// can't be listed
+    Dropped { count: u64 },
+}
+
+/// A page of one directory's children from a retained scan, largest first
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChildrenPage {
+    /// 0 when the directory was in no retained scan and was listed from disk
+    pub scan_id: u64,
+    pub path: PathBuf,
+    pub offset: usize,
+    /// Children of `path` in all, not just on this page
+    pub total: usize,
+    /// Only the children themselves; their own children are left empty
+    pub children: Vec<FileNode>,
 }
 
 /// Tracks the progress of a directory scan operation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ScanProgress {
     /// Current path being scanned
     pub current_path: String,
@@ -47,7 +109,7 @@ pub struct ScanProgress {
 }
 
 /// Partial scan result emitted during progressive scanning
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PartialScanResult {
     /// The current state of the file tree
     pub tree: FileNode,
@@ -60,7 +122,7 @@ pub struct PartialScanResult {
 }
 
 /// Statistics for a scanned node
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
 pub struct NodeStats {
     /// Number of files in this node (1 for files, sum of children for directories)
     pub file_count: u64,
@@ -68,6 +130,12 @@ pub struct NodeStats {
     pub total_size: u64,
 }
 
+impl Default for NodeStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl NodeStats {
     pub fn new() -> Self {
         Self {
@@ -90,7 +158,7 @@ impl NodeStats {
 }
 
 /// Streaming scan event emitted during progressive scanning
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum StreamingScanEvent {
     /// Progress update with aggregated stats (lightweight, sent frequently)
@@ -99,6 +167,22 @@ pub enum StreamingScanEvent {
         files_scanned: u64,
         total_size: u64,
         current_path: String,
+        /// Directories found so far
+        #[serde(default)]
+        dirs_found: u64,
+        /// Directories whose whole subtree has been read
+        #[serde(default)]
+        dirs_completed: u64,
+        /// Share of the expected entries visited, when a pre-pass or earlier scan of the root
+        /// gave a count
+        #[serde(default)]
+        percent: Option<f64>,
+    },
+    /// Directory discovery phase, before any bytes have been counted
+    #[serde(rename = "discovering")]
+    Discovering {
+        dirs_found: u64,
+        current_path: String,
     },
     /// Partial tree snapshot (heavier, sent periodically for UI updates)
     #[serde(rename = "partial_tree")]
@@ -107,9 +191,13 @@ pub enum StreamingScanEvent {
         files_scanned: u64,
         total_size: u64,
     },
-    /// Node discovered - incremental update (lightweight, sent as nodes are found)
+    /// Node discovered - incremental update (lightweight, sent as nodes in the top two levels
+    /// are found)
     #[serde(rename = "node_update")]
     NodeUpdate {
+        /// Same as in the previous scan of the root for the same path
+        id: u64,
+        parent_id: Option<u64>,
         path: String,
         parent_path: Option<String>,
         name: String,
@@ -117,7 +205,51 @@ pub enum StreamingScanEvent {
         is_directory: bool,
         file_type: FileType,
     },
+    /// First-pass tree from sampled sizes, sent before the exact scan starts
+    #[serde(rename = "estimate")]
+    Estimate {
+        tree: EstimatedNode,
+        elapsed_ms: u64,
+    },
     /// Scan completed
     #[serde(rename = "complete")]
-    Complete { files_scanned: u64, total_size: u64 },
+    Complete {
+        files_scanned: u64,
+        total_size: u64,
+        schema_version: u32,
+        // Boxed: the summary dwarfs every other variant
+        summary: Box<ScanSummary>,
+    },
+    /// Size and file count per category so far, largest first
+    #[serde(rename = "category_progress")]
+    CategoryProgress { categories: Vec<CategoryStats> },
+    /// Large directories finished most recently, newest first; sent when a new one completes
+    #[serde(rename = "hot_paths")]
+    HotPaths { directories: Vec<HotPath> },
+    /// Node removed from the retained scan after a deletion
+    #[serde(rename = "node_removed")]
+    NodeRemoved {
+        scan_id: u64,
+        id: u64,
+        path: String,
+        parent_path: Option<String>,
+        size_removed: u64,
+        /// Ancestors from the immediate parent up to the scan root, with their new sizes
+        ancestors: Vec<AncestorSize>,
+    },
+}
+
+/// A large directory whose subtree was fully read during a scan
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HotPath {
+    pub path: String,
+    pub size: u64,
+}
+
+/// Updated size of an ancestor directory after a node was removed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AncestorSize {
+    pub id: u64,
+    pub path: String,
+    pub size: u64,
 }
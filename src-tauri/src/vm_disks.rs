@@ -0,0 +1,227 @@
+use crate::access;
+use crate::composition::{analyze_large_file, FileComposition, VmDiskFormat};
+use crate::environment::find_tool;
+use crate::i18n::{localize, LocalizedMessage};
+use crate::registry;
+use crate::tools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// Extensions of the VM disk formats `composition` recognizes
+const VM_DISK_EXTENSIONS: &[&str] = &["vdi", "vmdk", "qcow2", "hds", "vhd", "vhdx"];
+
+/// Where VMware Fusion keeps its disk tool, which isn't on `PATH`
+#[cfg(target_os = "macos")]
+const FUSION_VDISKMANAGER: &str =
+    "/Applications/VMware Fusion.app/Contents/Library/vmware-vdiskmanager";
+
+/// How to compact a disk with its vendor's tool
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CompactionPlan {
+    pub tool: String,
+    /// Whether the tool was found; when it wasn't, the plan is shown as instructions
+    pub available: bool,
+    /// Command line `compact_vm_disk_command` runs, shown to the user first
+    pub command_line: String,
+    /// What to do before compacting, e.g. shutting the VM down and zeroing free space
+    pub preparation: LocalizedMessage,
+}
+
+/// A VM disk with how much of its capacity it really takes up
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VmDisk {
+    pub path: PathBuf,
+    pub format: VmDiskFormat,
+    /// Capacity the guest sees, when the header says
+    pub provisioned: Option<u64>,
+    /// Bytes allocated on the host
+    pub actual: u64,
+    /// `None` for formats no vendor tool compacts in place (QEMU images)
+    pub compaction: Option<CompactionPlan>,
+}
+
+/// Vendor tool and arguments that compact a disk in place
+fn compaction_command(format: VmDiskFormat, path: &Path) -> Option<(&'static str, Vec<String>)> {
+    let path_arg = path.to_string_lossy().to_string();
+    match format {
+        VmDiskFormat::Vdi => Some((
+            "VBoxManage",
+            vec![
+                "modifymedium".into(),
+                "disk".into(),
+                path_arg,
+                "--compact".into(),
+            ],
+        )),
+        VmDiskFormat::Vmdk => Some(("vmware-vdiskmanager", vec!["-k".into(), path_arg])),
+        // The tool takes the `.hdd` bundle holding the `.hds` image
+        VmDiskFormat::Hds => {
+            let bundle = path.parent()?.to_string_lossy().to_string();
+            Some((
+                "prl_disk_tool",
+                vec!["compact".into(), "--hdd".into(), bundle],
+            ))
+        }
+        VmDiskFormat::Vhd | VmDiskFormat::Vhdx => Some((
+            "powershell",
+            vec![
+                "-NoProfile".into(),
+                "-Command".into(),
+                format!(
+                    "Optimize-VHD -Path '{}' -Mode Full",
+                    path_arg.replace('\'', "''")
+                ),
+            ],
+        )),
+        VmDiskFormat::Qcow2 => None,
+    }
+}
+
+/// Where a vendor tool is installed, looking in its usual folder when it isn't on `PATH`
+fn locate_tool(tool: &str) -> Option<PathBuf> {
+    let found = find_tool(tool);
+    #[cfg(target_os = "macos")]
+    if found.is_none() && tool == "vmware-vdiskmanager" {
+        return Some(PathBuf::from(FUSION_VDISKMANAGER)).filter(|p| p.is_file());
+    }
+    found
+}
+
+fn compaction_plan(format: VmDiskFormat, path: &Path) -> Option<CompactionPlan> {
+    let (tool, args) = compaction_command(format, path)?;
+    let quoted: Vec<String> = args
+        .iter()
+        .map(|arg| {
+            if arg.contains(' ') {
+                format!("\"{}\"", arg)
+            } else {
+                arg.clone()
+            }
+        })
+        .collect();
+    Some(CompactionPlan {
+        tool: tool.to_string(),
+        available: locate_tool(tool).is_some(),
+        command_line: format!("{} {}", tool, quoted.join(" ")),
+        preparation: localize("vm-compact-preparation", &[("tool", json!(tool))]),
+    })
+}
+
+/// Read a VM disk's header, or `None` when the file isn't one
+pub fn inspect_vm_disk(path: &Path) -> Option<VmDisk> {
+    let analysis = analyze_large_file(path).ok()?;
+    let Some(FileComposition::VmDisk {
+        format,
+        provisioned,
+    }) = analysis.composition
+    else {
+        return None;
+    };
+    Some(VmDisk {
+        path: path.to_path_buf(),
+        format,
+        provisioned,
+        actual: analysis.allocated,
+        compaction: compaction_plan(format, path),
+    })
+}
+
+fn is_vm_disk_candidate(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        VM_DISK_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+    })
+}
+
+// Tauri commands
+
+/// VirtualBox, VMware, Parallels, Hyper-V and QEMU disks in a retained scan, largest first
+#[tauri::command]
+pub async fn get_vm_disks_command(scan_id: u64) -> Result<Vec<VmDisk>, String> {
+    let mut candidates = registry::with_scan(scan_id, |nodes, _| {
+        nodes
+            .values()
+            .filter(|n| !n.is_directory && !n.is_symlink && is_vm_disk_candidate(&n.path))
+            .map(|n| n.path.clone())
+            .collect::<Vec<_>>()
+    })
+    .await?;
+    candidates.sort();
+
+    tokio::task::spawn_blocking(move || {
+        let mut disks: Vec<VmDisk> = candidates
+            .iter()
+            .filter_map(|path| inspect_vm_disk(path))
+            .collect();
+        disks.sort_by_key(|disk| std::cmp::Reverse(disk.actual));
+        disks
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Compact a VM disk with its vendor's tool; the frontend must show the plan and confirm first
+///
+/// The VM has to be shut down, and space the guest freed only comes back if it was zeroed.
+#[tauri::command]
+pub async fn compact_vm_disk_command(path: String, confirmed: bool) -> Result<VmDisk, String> {
+    if !confirmed {
+        return Err("Compacting a VM disk requires confirmation".to_string());
+    }
+    access::ensure_writable("Compacting VM disks")?;
+
+    tokio::task::spawn_blocking(move || {
+        let path = PathBuf::from(path);
+        let disk =
+            inspect_vm_disk(&path).ok_or_else(|| format!("{} is not a VM disk", path.display()))?;
+        let (tool, args) = compaction_command(disk.format, &path)
+            .ok_or_else(|| format!("No tool compacts {:?} disks in place", disk.format))?;
+        let program = locate_tool(tool).unwrap_or_else(|| PathBuf::from(tool));
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        let output = tools::run(&program.to_string_lossy(), &args)?;
+        if !output.status.success() {
+            return Err(format!(
+                "{} failed: {}",
+                tool,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        inspect_vm_disk(&path).ok_or_else(|| format!("Cannot read {}", path.display()))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compaction_uses_the_vendor_tool() {
+        let (tool, args) =
+            compaction_command(VmDiskFormat::Vdi, Path::new("/vms/win.vdi")).unwrap();
+        assert_eq!(tool, "VBoxManage");
+        assert_eq!(args, ["modifymedium", "disk", "/vms/win.vdi", "--compact"]);
+
+        let (tool, args) = compaction_command(
+            VmDiskFormat::Hds,
+            Path::new("/vms/Ubuntu.pvm/harddisk.hdd/harddisk.hdd.0.{5fbaabe3}.hds"),
+        )
+        .unwrap();
+        assert_eq!(tool, "prl_disk_tool");
+        assert_eq!(args[2], "/vms/Ubuntu.pvm/harddisk.hdd");
+
+        let (_, args) =
+            compaction_command(VmDiskFormat::Vhdx, Path::new("C:\\VMs\\Bob's.vhdx")).unwrap();
+        assert_eq!(
+            args[2],
+            "Optimize-VHD -Path 'C:\\VMs\\Bob''s.vhdx' -Mode Full"
+        );
+
+        assert!(compaction_command(VmDiskFormat::Qcow2, Path::new("/vms/a.qcow2")).is_none());
+        assert!(is_vm_disk_candidate(Path::new("/vms/A.VMDK")));
+        assert!(!is_vm_disk_candidate(Path::new("/vms/notes.txt")));
+    }
+}
@@ -0,0 +1,177 @@
+use crate::estimate::{estimate_tree, EstimatedNode};
+use crate::persistence::{app_cache_dir, read_sealed_json_or_default, write_sealed_json};
+use crate::storage::{get_storage_locations, LocationType, StorageLocation};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{Emitter, Window};
+
+/// Lives with the disposable caches, so clearing caches also clears it
+const ROOTS_CACHE_FILE: &str = "volume-roots.json";
+
+/// Entries older than this are re-estimated the next time the list is requested
+const REFRESH_AFTER_SECS: i64 = 6 * 60 * 60;
+
+/// Time allowed for estimating one volume
+const VOLUME_BUDGET: Duration = Duration::from_secs(2);
+
+/// Directory levels below each volume root that are kept; the estimate reads deeper
+const CACHED_DEPTH: usize = 2;
+
+/// Set while a background refresh runs, so repeated requests don't start another
+static REFRESHING: AtomicBool = AtomicBool::new(false);
+
+/// Approximate top levels of a volume, remembered between launches
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CachedVolumeRoot {
+    pub volume: PathBuf,
+    pub name: String,
+    /// Unix timestamp (seconds) of the estimate
+    pub refreshed_at: i64,
+    pub tree: EstimatedNode,
+}
+
+fn cache_file() -> Result<PathBuf, String> {
+    Ok(app_cache_dir()?.join(ROOTS_CACHE_FILE))
+}
+
+fn load_roots() -> Result<Vec<CachedVolumeRoot>, String> {
+    read_sealed_json_or_default(&cache_file()?)
+}
+
+fn save_roots(roots: &[CachedVolumeRoot]) -> Result<(), String> {
+    let file = cache_file()?;
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+    write_sealed_json(&file, &roots)
+}
+
+/// Local volumes with no cached estimate or an outdated one; network volumes are never
+/// walked in the background because their reads can hang
+fn stale_volumes(
+    roots: &[CachedVolumeRoot],
+    locations: Vec<StorageLocation>,
+    now: i64,
+) -> Vec<StorageLocation> {
+    locations
+        .into_iter()
+        .filter(|l| matches!(l.location_type, LocationType::Storage))
+        .filter(|l| {
+            roots
+                .iter()
+                .find(|r| r.volume == l.path)
+                .is_none_or(|r| now - r.refreshed_at >= REFRESH_AFTER_SECS)
+        })
+        .collect()
+}
+
+/// Drop everything more than `depth` levels below `node`
+fn truncate(node: &mut EstimatedNode, depth: usize) {
+    if depth == 0 {
+        node.children.clear();
+    }
+    for child in &mut node.children {
+        truncate(child, depth.saturating_sub(1));
+    }
+}
+
+/// Re-estimate `stale` volumes and merge them into `roots`, largest volume first
+fn refresh(
+    mut roots: Vec<CachedVolumeRoot>,
+    stale: Vec<StorageLocation>,
+    now: i64,
+) -> Vec<CachedVolumeRoot> {
+    for location in stale {
+        let mut tree = estimate_tree(&location.path, VOLUME_BUDGET);
+        truncate(&mut tree, CACHED_DEPTH);
+        roots.retain(|r| r.volume != location.path);
+        roots.push(CachedVolumeRoot {
+            volume: location.path,
+            name: location.name,
+            refreshed_at: now,
+            tree,
+        });
+    }
+    roots.sort_by_key(|r| std::cmp::Reverse(r.tree.size));
+    roots
+}
+
+// Tauri commands
+
+/// Cached top levels of every known volume, available before any scan
+///
+/// Returns at once with whatever is cached (possibly nothing on first launch). Outdated
+/// or missing volumes are then re-estimated in the background and the full list is sent
+/// as a `volume-roots-updated` event.
+#[tauri::command]
+pub async fn get_volume_roots_command(window: Window) -> Result<Vec<CachedVolumeRoot>, String> {
+    let roots = load_roots().unwrap_or_default();
+    let now = chrono::Utc::now().timestamp();
+    let stale = stale_volumes(&roots, get_storage_locations().unwrap_or_default(), now);
+    if !stale.is_empty() && !REFRESHING.swap(true, Ordering::SeqCst) {
+        let cached = roots.clone();
+        tokio::task::spawn_blocking(move || {
+            let refreshed = refresh(cached, stale, now);
+            if save_roots(&refreshed).is_ok() {
+                let _ = window.emit("volume-roots-updated", &refreshed);
+            }
+            REFRESHING.store(false, Ordering::SeqCst);
+        });
+    }
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn location(path: &Path, location_type: LocationType) -> StorageLocation {
+        StorageLocation {
+            name: "Test".to_string(),
+            path: path.to_path_buf(),
+            location_type,
+            total_space: None,
+            available_space: None,
+            inodes: None,
+            quota: None,
+            shadow_storage: None,
+        }
+    }
+
+    #[test]
+    fn test_refreshes_only_stale_local_volumes() {
+        let volume = std::env::temp_dir().join("test_volume_roots");
+        let _ = fs::remove_dir_all(&volume);
+        fs::create_dir_all(volume.join("Users/alice/Documents")).unwrap();
+        fs::write(volume.join("Users/alice/Documents/a.bin"), vec![1u8; 8192]).unwrap();
+
+        let share = PathBuf::from("/Volumes/share");
+        let locations = || {
+            vec![
+                location(&volume, LocationType::Storage),
+                location(&share, LocationType::Network),
+            ]
+        };
+        let stale = stale_volumes(&[], locations(), 1000);
+        let roots = refresh(Vec::new(), stale, 1000);
+        fs::remove_dir_all(&volume).unwrap();
+
+        assert_eq!(roots.len(), 1);
+        let users = &roots[0].tree.children[0];
+        assert_eq!(users.name, "Users");
+        assert_eq!(users.children[0].name, "alice");
+        // Sizes come from the deeper estimate, but only two levels are kept
+        assert!(users.children[0].size >= 8192);
+        assert!(users.children[0].children.is_empty());
+        assert!(stale_volumes(&roots, locations(), 1000 + 60).is_empty());
+        assert_eq!(
+            stale_volumes(&roots, locations(), 1000 + REFRESH_AFTER_SECS).len(),
+            1
+        );
+    }
+}
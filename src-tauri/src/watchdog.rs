@@ -0,0 +1,158 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// How long an operation may go without progress before it is reported as stalled
+pub(crate) const STALL_AFTER: Duration = Duration::from_secs(20);
+
+/// Emitted as `operation-stalled` when a long-running operation stops making progress
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OperationStalled {
+    /// Which operation stalled, e.g. "scan"
+    pub operation: String,
+    /// The path that has been in progress longest; usually a hanging network mount
+    pub path: String,
+    pub stalled_for_secs: u64,
+    /// Whether `skip_stalled_path_command` can abandon `path`
+    pub can_skip: bool,
+}
+
+/// A file system call in progress, which the user can abandon
+struct Pending {
+    started: Instant,
+    skip: CancellationToken,
+}
+
+/// Directory reads currently in progress, keyed by path
+static IN_FLIGHT: once_cell::sync::Lazy<Mutex<HashMap<PathBuf, Pending>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Removes its path from the in-flight set when the read finishes or is abandoned
+pub(crate) struct InFlightGuard {
+    path: PathBuf,
+    /// Cancelled when the user skips this path
+    pub skip: CancellationToken,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT.lock().unwrap().remove(&self.path);
+    }
+}
+
+/// Record that `path` is being read until the returned guard is dropped
+pub(crate) fn begin(path: &Path) -> InFlightGuard {
+    let skip = CancellationToken::new();
+    IN_FLIGHT.lock().unwrap().insert(
+        path.to_path_buf(),
+        Pending {
+            started: Instant::now(),
+            skip: skip.clone(),
+        },
+    );
+    InFlightGuard {
+        path: path.to_path_buf(),
+        skip,
+    }
+}
+
+/// Paths with a read in progress
+pub(crate) fn in_flight_paths() -> Vec<PathBuf> {
+    IN_FLIGHT.lock().unwrap().keys().cloned().collect()
+}
+
+/// The read that has been in progress longest, and for how long
+pub(crate) fn oldest_in_flight() -> Option<(PathBuf, Duration)> {
+    IN_FLIGHT
+        .lock()
+        .unwrap()
+        .iter()
+        .min_by_key(|(_, pending)| pending.started)
+        .map(|(path, pending)| (path.clone(), pending.started.elapsed()))
+}
+
+/// Abandon the read of `path`; returns false when nothing is reading it
+pub(crate) fn skip(path: &Path) -> bool {
+    match IN_FLIGHT.lock().unwrap().get(path) {
+        Some(pending) => {
+            pending.skip.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Tracks a progress counter and decides when to report a stall, once per stall
+pub(crate) struct StallDetector {
+    last_marker: u64,
+    last_change: Instant,
+    reported: bool,
+}
+
+impl StallDetector {
+    pub(crate) fn new() -> Self {
+        Self {
+            last_marker: 0,
+            last_change: Instant::now(),
+            reported: false,
+        }
+    }
+
+    /// Feed the current counter; returns how long it has been stuck when a stall starts
+    pub(crate) fn observe(&mut self, marker: u64, now: Instant) -> Option<Duration> {
+        if marker != self.last_marker {
+            self.last_marker = marker;
+            self.last_change = now;
+            self.reported = false;
+            return None;
+        }
+        let stuck = now.duration_since(self.last_change);
+        if stuck >= STALL_AFTER && !self.reported {
+            self.reported = true;
+            return Some(stuck);
+        }
+        None
+    }
+}
+
+// Tauri commands
+
+/// Skip a subtree the scan stalled on; it is listed as skipped in the scan summary
+#[tauri::command]
+pub async fn skip_stalled_path_command(path: String) -> Result<(), String> {
+    if skip(Path::new(&path)) {
+        Ok(())
+    } else {
+        Err(format!("{} is no longer being read", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stall_detection_and_skip() {
+        let start = Instant::now();
+        let mut detector = StallDetector::new();
+        assert_eq!(detector.observe(5, start), None);
+        assert_eq!(detector.observe(5, start + Duration::from_secs(1)), None);
+        let stalled = detector.observe(5, start + STALL_AFTER + Duration::from_secs(1));
+        assert_eq!(stalled, Some(STALL_AFTER + Duration::from_secs(1)));
+        // Reported once per stall, and again after progress resumes and stops
+        assert_eq!(detector.observe(5, start + STALL_AFTER * 2), None);
+        assert_eq!(detector.observe(6, start + STALL_AFTER * 2), None);
+        assert!(detector.observe(6, start + STALL_AFTER * 4).is_some());
+
+        let path = Path::new("/Volumes/dead-share/test_watchdog");
+        let guard = begin(path);
+        assert!(skip(path));
+        assert!(guard.skip.is_cancelled());
+        drop(guard);
+        assert!(!skip(path));
+    }
+}
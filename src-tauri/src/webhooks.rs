@@ -0,0 +1,388 @@
+use crate::i18n::{localize, LocalizedMessage};
+use crate::persistence::{data_file, read_json_or_default, write_json};
+use crate::sizes::SizeFormatter;
+use crate::storage::{get_storage_locations, LocationType};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const WEBHOOKS_FILE: &str = "webhooks.json";
+
+/// How often volumes are checked for low free space
+const LOW_SPACE_TICK: Duration = Duration::from_secs(5 * 60);
+
+/// Give up on a receiver that doesn't answer, so a dead endpoint can't pile up threads
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How a webhook's request body is shaped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The notification itself, for scripts and automation tools
+    Json,
+    /// A Slack incoming-webhook message
+    Slack,
+    /// A Discord webhook message
+    Discord,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    ScanCompleted,
+    CleanupExecuted,
+    LowSpace,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Webhook {
+    /// Assigned when the webhook is first saved (send 0 for a new one)
+    pub id: u64,
+    pub name: String,
+    pub url: String,
+    pub format: WebhookFormat,
+    pub events: Vec<WebhookEvent>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct WebhookSettings {
+    pub webhooks: Vec<Webhook>,
+    /// A volume is low on space below this percentage free
+    pub low_space_percent: u8,
+    next_id: u64,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            webhooks: Vec::new(),
+            low_space_percent: 10,
+            next_id: 0,
+        }
+    }
+}
+
+/// What happened; the `json` format sends this as is
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Notification {
+    ScanCompleted {
+        root: String,
+        files_scanned: u64,
+        total_size: u64,
+    },
+    CleanupExecuted {
+        deleted: u64,
+        failed: u64,
+        space_freed: u64,
+        /// Size of what went to the trash instead, which frees nothing until it is emptied
+        #[serde(default)]
+        space_trashed: u64,
+    },
+    LowSpace {
+        volume: PathBuf,
+        available_bytes: u64,
+        total_bytes: u64,
+    },
+    /// Sent by `test_webhook_command` only
+    Test,
+}
+
+impl Notification {
+    fn event(&self) -> Option<WebhookEvent> {
+        match self {
+            Notification::ScanCompleted { .. } => Some(WebhookEvent::ScanCompleted),
+            Notification::CleanupExecuted { .. } => Some(WebhookEvent::CleanupExecuted),
+            Notification::LowSpace { .. } => Some(WebhookEvent::LowSpace),
+            Notification::Test => None,
+        }
+    }
+
+    /// One line for chat formats
+    fn message(&self, formatter: &SizeFormatter) -> LocalizedMessage {
+        match self {
+            Notification::ScanCompleted {
+                root,
+                files_scanned,
+                total_size,
+            } => localize(
+                "notification-scan-completed",
+                &[
+                    ("root", json!(root)),
+                    ("files", json!(files_scanned)),
+                    ("size", json!(formatter.format(*total_size))),
+                ],
+            ),
+            Notification::CleanupExecuted {
+                deleted,
+                failed,
+                space_freed,
+                space_trashed,
+            } => localize(
+                match (*space_trashed > 0, *failed > 0) {
+                    (false, false) => "notification-cleanup-executed",
+                    (false, true) => "notification-cleanup-partial",
+                    (true, false) => "notification-cleanup-trashed",
+                    (true, true) => "notification-cleanup-trashed-partial",
+                },
+                &[
+                    ("deleted", json!(deleted)),
+                    ("failed", json!(failed)),
+                    ("size", json!(formatter.format(*space_freed))),
+                    ("trashed", json!(formatter.format(*space_trashed))),
+                ],
+            ),
+            Notification::LowSpace {
+                volume,
+                available_bytes,
+                total_bytes,
+            } => localize(
+                "notification-low-space",
+                &[
+                    ("volume", json!(volume.display().to_string())),
+                    ("available", json!(formatter.format(*available_bytes))),
+                    ("total", json!(formatter.format(*total_bytes))),
+                ],
+            ),
+            Notification::Test => localize("notification-test", &[]),
+        }
+    }
+}
+
+pub(crate) fn load_webhooks() -> Result<WebhookSettings, String> {
+    read_json_or_default(&data_file(WEBHOOKS_FILE)?)
+}
+
+pub(crate) fn save_webhooks(settings: &WebhookSettings) -> Result<(), String> {
+    write_json(&data_file(WEBHOOKS_FILE)?, settings)
+}
+
+fn request_body(
+    format: WebhookFormat,
+    notification: &Notification,
+    formatter: &SizeFormatter,
+) -> Value {
+    match format {
+        WebhookFormat::Json => json!({
+            "source": "disk-analyser",
+            "sent_at": chrono::Utc::now().timestamp(),
+            "notification": notification,
+        }),
+        WebhookFormat::Slack => json!({ "text": notification.message(formatter).text }),
+        WebhookFormat::Discord => json!({ "content": notification.message(formatter).text }),
+    }
+}
+
+fn post(
+    webhook: &Webhook,
+    notification: &Notification,
+    formatter: &SizeFormatter,
+) -> Result<(), String> {
+    ureq::post(&webhook.url)
+        .timeout(REQUEST_TIMEOUT)
+        .send_json(request_body(webhook.format, notification, formatter))
+        .map(|_| ())
+        .map_err(|e| format!("Webhook {} failed: {}", webhook.name, e))
+}
+
+/// Send `notification` to every enabled webhook subscribed to it, in the background
+///
+/// Delivery is best effort: a receiver that is down never holds up or fails the operation
+/// that triggered it.
+pub(crate) fn notify(notification: Notification) {
+    let Some(event) = notification.event() else {
+        return;
+    };
+    let Ok(settings) = load_webhooks() else {
+        return;
+    };
+    let targets: Vec<Webhook> = settings
+        .webhooks
+        .into_iter()
+        .filter(|w| w.enabled && w.events.contains(&event))
+        .collect();
+    if targets.is_empty() {
+        return;
+    }
+    std::thread::spawn(move || {
+        let formatter = SizeFormatter::current();
+        for webhook in &targets {
+            let _ = post(webhook, &notification, &formatter);
+        }
+    });
+}
+
+/// Volumes below the threshold that weren't already, updating `alerted` so each volume
+/// alerts once until it recovers
+fn newly_low(
+    volumes: &[(PathBuf, u64, u64)],
+    low_space_percent: u8,
+    alerted: &mut HashSet<PathBuf>,
+) -> Vec<Notification> {
+    let mut notifications = Vec::new();
+    for (volume, available, total) in volumes {
+        if *total == 0 {
+            continue;
+        }
+        let low = available * 100 < total * low_space_percent as u64;
+        if !low {
+            alerted.remove(volume);
+        } else if alerted.insert(volume.clone()) {
+            notifications.push(Notification::LowSpace {
+                volume: volume.clone(),
+                available_bytes: *available,
+                total_bytes: *total,
+            });
+        }
+    }
+    notifications
+}
+
+/// Watch local volumes and send `low_space` notifications; runs for the life of the process
+pub fn start_low_space_monitor() {
+    std::thread::spawn(|| {
+        let mut alerted = HashSet::new();
+        loop {
+            std::thread::sleep(LOW_SPACE_TICK);
+
+            let Ok(settings) = load_webhooks() else {
+                continue;
+            };
+            let subscribed = settings
+                .webhooks
+                .iter()
+                .any(|w| w.enabled && w.events.contains(&WebhookEvent::LowSpace));
+            if !subscribed {
+                continue;
+            }
+            let volumes: Vec<(PathBuf, u64, u64)> = get_storage_locations()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|l| matches!(l.location_type, LocationType::Storage))
+                .filter_map(|l| Some((l.path, l.available_space?, l.total_space?)))
+                .collect();
+            for notification in newly_low(&volumes, settings.low_space_percent, &mut alerted) {
+                notify(notification);
+            }
+        }
+    });
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn get_webhook_settings_command() -> Result<WebhookSettings, String> {
+    load_webhooks()
+}
+
+/// Create (id 0) or update a webhook
+#[tauri::command]
+pub async fn save_webhook_command(mut webhook: Webhook) -> Result<Webhook, String> {
+    if !webhook.url.starts_with("https://") && !webhook.url.starts_with("http://") {
+        return Err(format!("Not a web address: {}", webhook.url));
+    }
+    let mut settings = load_webhooks()?;
+
+    if webhook.id == 0 {
+        settings.next_id += 1;
+        webhook.id = settings.next_id;
+        settings.webhooks.push(webhook.clone());
+    } else {
+        let existing = settings
+            .webhooks
+            .iter_mut()
+            .find(|w| w.id == webhook.id)
+            .ok_or_else(|| format!("Unknown webhook: {}", webhook.id))?;
+        *existing = webhook.clone();
+    }
+
+    save_webhooks(&settings)?;
+    Ok(webhook)
+}
+
+#[tauri::command]
+pub async fn delete_webhook_command(id: u64) -> Result<(), String> {
+    let mut settings = load_webhooks()?;
+    settings.webhooks.retain(|w| w.id != id);
+    save_webhooks(&settings)
+}
+
+#[tauri::command]
+pub async fn set_low_space_threshold_command(percent: u8) -> Result<(), String> {
+    if !(1..=99).contains(&percent) {
+        return Err("The threshold must be between 1 and 99 percent".to_string());
+    }
+    let mut settings = load_webhooks()?;
+    settings.low_space_percent = percent;
+    save_webhooks(&settings)
+}
+
+/// Send a test message and report whether the receiver accepted it
+#[tauri::command]
+pub async fn test_webhook_command(id: u64) -> Result<(), String> {
+    let webhook = load_webhooks()?
+        .webhooks
+        .into_iter()
+        .find(|w| w.id == id)
+        .ok_or_else(|| format!("Unknown webhook: {}", id))?;
+    tokio::task::spawn_blocking(move || {
+        post(&webhook, &Notification::Test, &SizeFormatter::current())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sizes::UnitSystem;
+
+    #[test]
+    fn test_request_bodies_and_low_space_alerts() {
+        let formatter = SizeFormatter::new(UnitSystem::Binary, '.');
+        let notification = Notification::CleanupExecuted {
+            deleted: 3,
+            failed: 0,
+            space_freed: 3 << 30,
+            space_trashed: 0,
+        };
+        assert_eq!(
+            request_body(WebhookFormat::Slack, &notification, &formatter)["text"],
+            "Cleanup deleted 3 items and freed 3.0 GiB"
+        );
+        assert!(
+            request_body(WebhookFormat::Discord, &notification, &formatter)["content"].is_string()
+        );
+        let body = request_body(WebhookFormat::Json, &notification, &formatter);
+        assert_eq!(body["notification"]["event"], "cleanup_executed");
+        assert_eq!(body["notification"]["space_freed"], 3u64 << 30);
+
+        let trashed = Notification::CleanupExecuted {
+            deleted: 2,
+            failed: 1,
+            space_freed: 0,
+            space_trashed: 1 << 30,
+        };
+        assert_eq!(
+            request_body(WebhookFormat::Slack, &trashed, &formatter)["text"],
+            "Cleanup moved 2 items (1.0 GiB) to the trash; 1 could not be moved"
+        );
+        let body = request_body(WebhookFormat::Json, &trashed, &formatter);
+        assert_eq!(body["notification"]["space_trashed"], 1u64 << 30);
+
+        let volume = PathBuf::from("/Volumes/Data");
+        let mut alerted = HashSet::new();
+        let low = [(volume.clone(), 5, 100)];
+        assert_eq!(newly_low(&low, 10, &mut alerted).len(), 1);
+        // Still low: no repeat alert
+        assert!(newly_low(&low, 10, &mut alerted).is_empty());
+        // Recovers, then drops again
+        assert!(newly_low(&[(volume.clone(), 50, 100)], 10, &mut alerted).is_empty());
+        assert_eq!(newly_low(&low, 10, &mut alerted).len(), 1);
+    }
+}
@@ -339,7 +339,7 @@ export function DeletionDialog({ isOpen, onClose }: DeletionDialogProps) {
                                       className={`text-xs mt-2 ${style.color}`}
                                       role="alert"
                                     >
-                                      {check.message}
+                                      {check.message.text}
                                     </p>
                                   )}
                               </div>
@@ -33,7 +33,7 @@ export async function checkPathPermissions(path: string): Promise<boolean> {
  */
 export async function openFullDiskAccessSettings(): Promise<void> {
   try {
-    await invoke('open_full_disk_access_settings');
+    await invoke('open_full_disk_access_settings_command');
   } catch (error) {
     console.error('Failed to open settings:', error);
   }
@@ -13,6 +13,7 @@ export enum FileType {
 }
 
 export interface FileNode {
+  id: number;
   name: string;
   path: string;
   size: number;
@@ -20,6 +21,77 @@ export interface FileNode {
   children: FileNode[];
   file_type: FileType;
   modified: number | { secs_since_epoch: number; nanos_since_epoch: number }; // Rust SystemTime
+  is_symlink: boolean;
+  symlink_target: string | null;
+  // Set on nodes that stand for several real entries; their path is the directory they're in
+  synthetic: SyntheticNode | null;
+  // Rounded to one decimal; set by the tree and child endpoints
+  pct_of_parent?: number | null;
+  pct_of_volume?: number | null;
+  // Set on directories a time-limited scan didn't finish; their size is a lower bound
+  is_partial?: boolean;
+}
+
+export type SyntheticNode =
+  | {
+      // The smallest children left out past the child limit; page through them with
+      // get_children_command
+      kind: 'more_items';
+      count: number;
+    }
+  | {
+      // Files smaller than threshold bytes, grouped so they don't crowd out the rest; the
+      // real entries are still listed by get_children_command
+      kind: 'small_files';
+      count: number;
+      threshold: number;
+    }
+  | {
+      // Files the retained scan keeps only as a count and total size, to save memory;
+      // they can't be listed
+      kind: 'dropped';
+      count: number;
+    };
+
+// A page of one directory's children from a retained scan, largest first
+// What a folder on a breadcrumb trail is, for its icon
+export type DirKind =
+  | 'volume'
+  | 'users'
+  | 'home'
+  | 'system'
+  | 'downloads'
+  | 'cache'
+  | 'trash'
+  | 'bundle'
+  | 'folder';
+
+export interface Ancestor {
+  path: string;
+  name: string;
+  // The volume's used space for the volume itself; null above the scanned root
+  size: number | null;
+  pct_of_volume: number | null;
+  dir_kind: DirKind;
+}
+
+export interface ChildrenPage {
+  // 0 when the directory was in no retained scan and was listed from disk
+  scan_id: number;
+  path: string;
+  offset: number;
+  // Children of path in all, not just on this page
+  total: number;
+  // Only the children themselves; their own children are left empty
+  children: FileNode[];
+}
+
+// How many entries a scan of root is expected to visit
+export interface EntryCount {
+  root: string;
+  // Files, folders and links below root, plus root itself
+  entries: number;
+  counted_at: number;
 }
 
 export interface ScanProgress {
@@ -46,6 +118,16 @@ export type StreamingScanEvent =
       files_scanned: number;
       total_size: number;
       current_path: string;
+      dirs_found: number;
+      // Directories whose whole subtree has been read
+      dirs_completed: number;
+      // Share of the expected entries visited; null without an entry count for the root
+      percent?: number | null;
+    }
+  | {
+      type: 'discovering';
+      dirs_found: number;
+      current_path: string;
     }
   | {
       type: 'partial_tree';
@@ -55,6 +137,8 @@ export type StreamingScanEvent =
     }
   | {
       type: 'node_update';
+      id: number;
+      parent_id: number | null;
       path: string;
       parent_path: string | null;
       name: string;
@@ -62,12 +146,50 @@ export type StreamingScanEvent =
       is_directory: boolean;
       file_type: FileType;
     }
+  | {
+      // First-pass tree from sampled sizes, sent before the exact scan starts
+      type: 'estimate';
+      tree: EstimatedNode;
+      elapsed_ms: number;
+    }
   | {
       type: 'complete';
       files_scanned: number;
       total_size: number;
+      schema_version: number;
+      summary: ScanSummary;
+    }
+  | {
+      // Size and file count per category so far, largest first
+      type: 'category_progress';
+      categories: CategoryStats[];
+    }
+  | {
+      // Large directories finished most recently, newest first
+      type: 'hot_paths';
+      directories: HotPath[];
+    }
+  | {
+      type: 'node_removed';
+      scan_id: number;
+      id: number;
+      path: string;
+      parent_path: string | null;
+      size_removed: number;
+      ancestors: AncestorSize[];
     };
 
+export interface HotPath {
+  path: string;
+  size: number;
+}
+
+export interface AncestorSize {
+  id: number;
+  path: string;
+  size: number;
+}
+
 export interface CategoryStats {
   category: FileType;
   total_size: number;
@@ -80,16 +202,29 @@ export interface FailedDeletion {
 }
 
 export interface DeletionResult {
+  // Every item gone from where it was, trashed or not
   deleted: string[];
+  // Those of deleted moved to the trash; the rest were removed for good
+  trashed: string[];
   failed: FailedDeletion[];
   space_freed: number;
+  // Still takes space until the trash is emptied
+  space_trashed: number;
+}
+
+// A backend message: render code + params from the frontend's translations, or show text,
+// which the backend rendered in the user's language
+export interface LocalizedMessage {
+  code: string;
+  params: Record<string, string | number>;
+  text: string;
 }
 
 export type SafetyCheck =
   | { type: 'Safe' }
-  | { type: 'Protected'; message: string }
-  | { type: 'InUse'; message: string }
-  | { type: 'RequiresConfirmation'; message: string };
+  | { type: 'Protected'; message: LocalizedMessage }
+  | { type: 'InUse'; message: LocalizedMessage }
+  | { type: 'RequiresConfirmation'; message: LocalizedMessage };
 
 export type SortField = 'size' | 'name' | 'type' | 'modified';
 export type SortOrder = 'asc' | 'desc';
@@ -105,6 +240,8 @@ export enum LocationType {
   Storage = 'storage',
   Network = 'network',
   Folder = 'folder',
+  // A phone or camera attached over MTP or PTP; scanned read-only
+  Device = 'device',
 }
 
 export interface StorageLocation {
@@ -113,4 +250,1261 @@ export interface StorageLocation {
   location_type: LocationType;
   total_space?: number;
   available_space?: number;
+  inodes?: InodeUsage;
+  quota?: QuotaUsage;
+  shadow_storage?: ShadowStorage;
+}
+
+// How much of the machine a scan covers; all_users needs elevation for other profiles
+export type ScanScope = 'current_user' | 'all_users' | 'whole_volume';
+
+export interface UserProfile {
+  name: string;
+  path: string;
+  accessible: boolean;
+  current: boolean;
+}
+
+export interface ScopeRoot {
+  scope: ScanScope;
+  root: string;
+  // Profiles the scan won't be able to read; only checked for all_users
+  inaccessible_profiles: UserProfile[];
+  requires_elevation: boolean;
+}
+
+// A scope offered on first run; the estimates come from a quick sample
+export interface FirstRunScope {
+  scope: ScanScope;
+  root: string;
+  estimated_size: number;
+  estimated_files: number;
+  estimated_duration_ms: number;
+  requires_elevation: boolean;
+}
+
+// What the user picked on first run; later launches scan the roots automatically
+export interface FirstRunChoice {
+  // Unix seconds; null until the flow is finished
+  completed_at: number | null;
+  scopes: ScanScope[];
+  roots: string[];
+  permissions: GrantedPermissions | null;
+  auto_scan: boolean;
+}
+
+export interface ShadowStorage {
+  volume: string;
+  used_bytes: number;
+  allocated_bytes: number;
+  max_bytes: number | null;
+}
+
+export interface QuotaUsage {
+  used_bytes: number;
+  soft_limit_bytes: number | null;
+  hard_limit_bytes: number | null;
+  used_inodes: number;
+  soft_limit_inodes: number | null;
+  hard_limit_inodes: number | null;
+}
+
+export interface SavedScan {
+  schema_version: number;
+  saved_at: number;
+  // Size convention chosen when the scan was saved
+  unit_system: UnitSystem;
+  // Host name of the machine that scanned; null in anonymized exports
+  machine?: string | null;
+  tree: FileNode;
+}
+
+export interface SavedScanInfo {
+  file: string;
+  root: string;
+  saved_at: number;
+  total_size: number;
+}
+
+// A scan imported from another machine into the workspace
+export interface FleetScan {
+  id: number;
+  machine: string;
+  root: string;
+  saved_at: number;
+  total_size: number;
+  file: string;
+}
+
+// The newest scan of one root on a machine
+export interface FleetVolume {
+  root: string;
+  used: number;
+  saved_at: number;
+  // null for scans saved on this machine
+  fleet_scan_id: number | null;
+}
+
+export interface FleetMachine {
+  machine: string;
+  is_local: boolean;
+  // Roots nested in another scanned root aren't counted twice
+  used: number;
+  last_saved_at: number;
+  volumes: FleetVolume[];
+}
+
+export interface FleetOverview {
+  machines: FleetMachine[];
+  total_used: number;
+}
+
+// How a child's size changed between two scans
+export type SizeChange = 'new' | 'grew' | 'shrank' | 'removed';
+
+// One bar of the waterfall: a child and what it added to or took from its parent
+export interface WaterfallStep {
+  name: string;
+  path: string;
+  change: SizeChange;
+  is_directory: boolean;
+  old_size: number;
+  new_size: number;
+  delta: number;
+}
+
+export interface WaterfallBucket {
+  change: SizeChange;
+  count: number;
+  delta: number;
+}
+
+// Steps run from old_size to new_size: new children first, then those that grew,
+// shrank and were removed, each largest change first
+export interface DiffWaterfall {
+  path: string;
+  old_size: number;
+  new_size: number;
+  delta: number;
+  steps: WaterfallStep[];
+  // One per kind of change that occurred, in step order
+  buckets: WaterfallBucket[];
+  // Children present in both scans at the same size
+  unchanged_count: number;
+}
+
+// How much of one kind of data there was in each scan; listed largest change first
+export interface CategoryChange {
+  category: FileType;
+  old_size: number;
+  new_size: number;
+  delta: number;
+  old_file_count: number;
+  new_file_count: number;
+}
+
+export interface TreeHandle {
+  handle: number;
+  encoding: 'msgpack';
+  total_bytes: number;
+  chunk_size: number;
+  chunk_count: number;
+}
+
+export type ScanStrategy = 'progressive' | 'incremental' | 'sampled' | 'budgeted';
+
+export interface PathSize {
+  path: string;
+  size: number;
+}
+
+export interface PathDepth {
+  path: string;
+  depth: number;
+}
+
+// Where a volume's change journal stood when a scan started
+export type JournalCursor =
+  | {
+      kind: 'usn';
+      volume: string;
+      journal_id: number;
+      next_usn: number;
+    }
+  | {
+      kind: 'fs_events';
+      event_id: number;
+    };
+
+export interface IncrementalStats {
+  previous_scan_id: number;
+  // The previous scan came from the on-disk cache; its id is from an earlier session
+  from_cache: boolean;
+  // A change journal listed what changed, so nothing had to be walked to find out
+  used_change_journal: boolean;
+  mtimes_trusted: boolean;
+  // Directories whose listing was reused because their mtime was unchanged
+  directories_reused: number;
+  directories_rewalked: number;
+}
+
+export interface ScanSummary {
+  scan_id: number;
+  root: string;
+  strategy: ScanStrategy;
+  duration_ms: number;
+  files: number;
+  directories: number;
+  symlinks: number;
+  errors: number;
+  total_size: number;
+  bytes_by_category: Partial<Record<FileType, number>>;
+  hard_link_savings: number;
+  largest_file: PathSize | null;
+  deepest_path: PathDepth | null;
+  inodes: InodeUsage | null;
+  quota: QuotaUsage | null;
+  cluster_size: number | null;
+  excluded: ExcludedPath[];
+  // Set for incremental rescans
+  incremental: IncrementalStats | null;
+  // Set for time-limited scans
+  budget?: BudgetStats | null;
+  // Change journal position from just before the walk
+  journal: JournalCursor | null;
+  // Set for progressive scans
+  io: IoStats | null;
+}
+
+// A directory a time-limited scan never listed
+export interface UnexploredDir {
+  path: string;
+  // Size in the previous scan covering it, when there was one
+  previous_size: number | null;
+}
+
+export interface BudgetStats {
+  budget_ms: number;
+  // Whether time ran out before every directory was listed
+  exhausted: boolean;
+  directories_listed: number;
+  // Most promising first; capped, see unexplored_count
+  unexplored: UnexploredDir[];
+  unexplored_count: number;
+}
+
+export interface ThroughputSample {
+  elapsed_ms: number;
+  ops_per_sec: number;
+  // File sizes discovered per second; the scan reads metadata, not contents
+  bytes_per_sec: number;
+}
+
+export interface IoStats {
+  read_ops: number;
+  // Latency percentiles in microseconds, rounded up to a power of two
+  latency_p50_us: number;
+  latency_p90_us: number;
+  latency_p99_us: number;
+  latency_max_us: number;
+  throughput: ThroughputSample[];
+}
+
+// skipped: the user skipped it after the scan stalled reading it
+// unreachable: on a network mount that stopped answering
+// pattern: matched one of the scan's exclude patterns
+export type ExclusionReason = 'app_data' | 'skipped' | 'unreachable' | 'pattern';
+
+export interface ExcludedPath {
+  path: string;
+  reason: ExclusionReason;
+}
+
+export interface PathLength {
+  path: string;
+  length: number;
+}
+
+export interface PathExtremes {
+  scan_id: number;
+  max_depth: number;
+  deepest_directories: PathDepth[];
+  longest_paths: PathLength[];
+  paths_over_max_path: number;
+}
+
+export interface InodeUsage {
+  total: number;
+  free: number;
+  used: number;
+}
+
+export interface CrowdedDirectory {
+  path: string;
+  file_count: number;
+  direct_file_count: number;
+  size: number;
+  average_file_size: number;
+  share_of_used_inodes: number | null;
+}
+
+export interface CrowdedDirectories {
+  scan_id: number;
+  directories: CrowdedDirectory[];
+  inodes: InodeUsage | null;
+}
+
+export interface TinyFileDirectory {
+  path: string;
+  tiny_files: number;
+  logical_size: number;
+  allocated_size: number;
+}
+
+export interface TinyFilesReport {
+  scan_id: number;
+  cluster_size: number;
+  zero_byte_files: number;
+  zero_byte_paths: string[];
+  tiny_files: number;
+  tiny_logical_size: number;
+  tiny_allocated_size: number;
+  directories: TinyFileDirectory[];
+}
+
+export interface DirectorySlack {
+  path: string;
+  logical_size: number;
+  allocated_size: number;
+  slack: number;
+}
+
+export interface ClusterOverhead {
+  scan_id: number;
+  cluster_size: number;
+  logical_size: number;
+  allocated_size: number;
+  wasted: number;
+  directories: DirectorySlack[];
+}
+
+export interface OtherDirectory {
+  path: string;
+  size: number;
+  file_count: number;
+}
+
+// Other files sharing an extension
+export interface OtherGroup {
+  // Lowercased, without the dot; null for files with no extension
+  extension: string | null;
+  size: number;
+  file_count: number;
+  directories: OtherDirectory[];
+}
+
+export interface OtherFilesReport {
+  scan_id: number;
+  other_size: number;
+  other_files: number;
+  groups: OtherGroup[];
+}
+
+export interface LargestFile {
+  path: string;
+  size: number;
+  file_type: FileType;
+  // Unix timestamp (seconds)
+  modified: number;
+}
+
+// The largest files beneath path, however deep
+export interface LargestFiles {
+  scan_id: number;
+  path: string;
+  files: LargestFile[];
+}
+
+export type HiddenSpaceSource = 'shadow_copies' | 'local_snapshots';
+
+export interface HiddenSpace {
+  source: HiddenSpaceSource;
+  bytes: number;
+  description: string;
+  estimated: boolean;
+  reclaim_command: string | null;
+}
+
+export interface SpaceReconciliation {
+  scan_id: number;
+  volume: string;
+  total_space: number;
+  used_space: number;
+  scanned_bytes: number;
+  covers_volume: boolean;
+  hidden: HiddenSpace[];
+  unaccounted_bytes: number;
+}
+
+// How safe a cleanup suggestion is to act on without reviewing it: high for caches, the
+// trash and system stores; medium for old downloads, duplicate copies and snapshots; low
+// for anything else
+export type Confidence = 'low' | 'medium' | 'high';
+
+// Which suggestion engine a recommendation came from
+export type RecommendationCategory =
+  | 'trash'
+  | 'caches'
+  | 'downloads'
+  | 'duplicates'
+  | 'large_unused_files'
+  | 'snapshots'
+  // Databases to vacuum; nothing is deleted
+  | 'databases';
+
+export interface Recommendation {
+  category: RecommendationCategory;
+  title: LocalizedMessage;
+  description: LocalizedMessage;
+  // What the cleanup would remove; empty for snapshots, which are deleted by date label
+  paths: string[];
+  // Date label of the snapshot, for delete_local_snapshot_command
+  snapshot: string | null;
+  bytes: number;
+  confidence: Confidence;
+}
+
+export interface CategorySavings {
+  category: RecommendationCategory;
+  count: number;
+  bytes: number;
+}
+
+// Every suggestion for a volume, largest saving first
+export interface Recommendations {
+  volume: string;
+  items: Recommendation[];
+  // One entry per category with at least one recommendation
+  categories: CategorySavings[];
+  total_bytes: number;
+}
+
+export interface Contributor {
+  path: string;
+  size: number;
+  is_directory: boolean;
+}
+
+export interface CategoryShare {
+  category: FileType;
+  bytes: number;
+  percent: number;
+}
+
+export type AgeRange = 'last_month' | 'last_year' | 'older';
+
+export interface AgeBucket {
+  range: AgeRange;
+  bytes: number;
+  files: number;
+}
+
+// bundle: an app, library or other package macOS shows as one file
+export type KnownKind = 'cache' | 'trash' | 'bundle';
+
+export interface DuplicatesInside {
+  groups: number;
+  reclaimable: number;
+}
+
+// Why a file or folder is as big as it is, for explain_node_command
+export interface NodeExplanation {
+  path: string;
+  size: number;
+  file_count: number;
+  // One sentence naming the main reason
+  summary: LocalizedMessage;
+  largest_children: Contributor[];
+  largest_files: Contributor[];
+  dominant_category: CategoryShare | null;
+  // Every range, newest first
+  age: AgeBucket[];
+  // From the last duplicate search
+  duplicates: DuplicatesInside;
+  known_kind: KnownKind | null;
+}
+
+export type SystemStoreKind = 'component_store' | 'update_downloads' | 'delivery_optimization';
+
+export interface CleanupSuggestion {
+  title: LocalizedMessage;
+  description: LocalizedMessage;
+  // How much the cleanup can reclaim, once the store has been measured
+  freeable: LocalizedMessage | null;
+  command: string;
+  requires_admin: boolean;
+  confidence: Confidence;
+}
+
+export interface SystemStore {
+  kind: SystemStoreKind;
+  path: string;
+  apparent_size: number;
+  actual_size: number;
+  exclusive_size: number;
+  file_count: number;
+  suggestion: CleanupSuggestion;
+}
+
+export interface LocalSnapshot {
+  name: string;
+  date_label: string;
+  created_at: number | null;
+  approximate_size: number | null;
+}
+
+export type NodeTag = 'keep' | 'review' | 'archive_candidate';
+
+export interface TagEntry {
+  path: string;
+  fingerprint: number | null;
+  tag: NodeTag;
+  tagged_at: number;
+}
+
+export interface TaggedNode {
+  id: number;
+  path: string;
+  tag: NodeTag;
+}
+
+export interface NeverSuggestEntry {
+  path: string;
+  note: string | null;
+  added_at: number;
+}
+
+export interface SizeSample {
+  // Unix seconds
+  at: number;
+  size: number;
+}
+
+// A pinned path with the size the latest scan covering it saw
+export interface PinnedPathStatus {
+  path: string;
+  pinned_at: number;
+  // null until a scan has covered the path
+  size: number | null;
+  last_scanned_at: number | null;
+  // Bytes gained (negative when shrunk) since the previous sample
+  trend: number | null;
+  // Oldest first
+  samples: SizeSample[];
+}
+
+export type PolicyRule =
+  | { kind: 'older_than'; min_age_days: number; min_size: number }
+  | { kind: 'keep_latest'; suffix: string; keep: number };
+
+export interface CleanupPolicy {
+  id: number;
+  name: string;
+  root: string;
+  rule: PolicyRule;
+  schedule_hours: number | null;
+  enabled: boolean;
+  last_evaluated_at: number | null;
+  // Leave out matches less safe than this, so unattended runs only touch e.g. caches
+  min_confidence: Confidence | null;
+}
+
+export interface PolicyMatch {
+  path: string;
+  size: number;
+  modified: number;
+  confidence: Confidence;
+}
+
+export interface PolicyReport {
+  report_id: number;
+  policy_id: number;
+  generated_at: number;
+  matches: PolicyMatch[];
+  total_bytes: number;
+  skipped_never_suggest: number;
+  skipped_low_confidence: number;
+}
+
+export interface PlanItem {
+  path: string;
+  size: number;
+  reason: string | null;
+}
+
+export interface CleanupPlan {
+  items: PlanItem[];
+  use_trash: boolean;
+}
+
+export type ScriptFormat = 'sh' | 'powershell';
+
+export type HashPhase = 'partial' | 'full';
+
+export interface DuplicateProgress {
+  phase: HashPhase;
+  files_done: number;
+  files_total: number;
+}
+
+export interface DuplicateFile {
+  path: string;
+  modified: number;
+  never_suggest: boolean;
+}
+
+export interface VolumeCopies {
+  volume: string;
+  files: DuplicateFile[];
+}
+
+export interface DuplicateGroup {
+  hash: string;
+  size: number;
+  volumes: VolumeCopies[];
+  cross_volume: boolean;
+  reclaimable: number;
+}
+
+export interface DuplicateDirectory {
+  path: string;
+  volume: string;
+  never_suggest: boolean;
+}
+
+export interface DuplicateDirectoryGroup {
+  signature: string;
+  size: number;
+  file_count: number;
+  directories: DuplicateDirectory[];
+  reclaimable: number;
+}
+
+export interface DuplicateReport {
+  scan_ids: number[];
+  paused: boolean;
+  groups: DuplicateGroup[];
+  directory_groups: DuplicateDirectoryGroup[];
+  total_reclaimable: number;
+  files_hashed: number;
+  files_from_cache: number;
+  // Files the disk failed to read while hashing; an early sign of a failing drive
+  possibly_corrupt: UnreadableFile[];
+}
+
+export interface UnreadableFile {
+  path: string;
+  size: number;
+  error: string;
+}
+
+export type SurfaceCheckMode = 'read_files' | 'smart_self_test';
+
+// Emitted as 'surface-scan-progress'
+export interface SurfaceProgress {
+  bytes_read: number;
+  // Used space on the volume
+  bytes_total: number;
+  files_read: number;
+  bad_files: number;
+}
+
+export interface SurfaceCheckResult {
+  volume: string;
+  mode: SurfaceCheckMode;
+  cancelled: boolean;
+  bytes_read: number;
+  files_read: number;
+  // Files the drive failed to read
+  bad_files: UnreadableFile[];
+  // What smartctl reported when starting the self-test
+  self_test: string | null;
+}
+
+export interface FamilyMember {
+  id: number;
+  path: string;
+  name: string;
+  size: number;
+  modified: number;
+  never_suggest: boolean;
+}
+
+export interface VersionFamily {
+  directory: string;
+  base_name: string;
+  members: FamilyMember[];
+  reclaimable: number;
+}
+
+export type BrokenLinkKind =
+  | 'dangling_symlink'
+  | 'broken_shortcut'
+  | 'partial_download'
+  | 'temp_file';
+
+export interface BrokenLink {
+  path: string;
+  kind: BrokenLinkKind;
+  size: number;
+  target: string | null;
+  modified: number;
+}
+
+export type ArchiveFormat = 'zip' | 'tar' | 'tar_gz' | 'seven_zip';
+
+export interface ArchiveEntry {
+  name: string;
+  is_directory: boolean;
+  size: number;
+  compressed_size: number | null;
+  file_count: number;
+}
+
+export interface ArchiveListing {
+  path: string;
+  format: ArchiveFormat;
+  archive_size: number;
+  uncompressed_size: number;
+  file_count: number;
+  entries: ArchiveEntry[];
+}
+
+export interface ArchiveOptions {
+  // Store symlinks as links; otherwise they are left out and reported as skipped
+  symlinks: boolean;
+  permissions: boolean;
+  // Unix only
+  xattrs: boolean;
+}
+
+export interface ArchiveResult {
+  // Id of the run in the operation journal
+  operation_id: number;
+  archive: string;
+  // Manifest written next to the archive
+  manifest: string;
+  files: number;
+  bytes: number;
+  // Entries written as Zip64
+  zip64_entries: number;
+  skipped: string[];
+  // Set when the originals were deleted after the archive checked out
+  deletion: DeletionResult | null;
+}
+
+export type EntryKind = 'file' | 'directory' | 'symlink';
+
+export interface ManifestEntry {
+  // Name inside the archive; directories end in '/'
+  name: string;
+  // Where it was archived from
+  source: string;
+  kind: EntryKind;
+  size: number;
+  // BLAKE3 of the contents, for files
+  hash: string | null;
+  modified: number;
+}
+
+export interface ArchiveManifest {
+  archive: string;
+  created_at: number;
+  entries: ManifestEntry[];
+}
+
+export interface ArchiveOperation {
+  id: number;
+  archive: string;
+  manifest: string;
+  created_at: number;
+  sources: string[];
+  files: number;
+  bytes: number;
+  originals_deleted: boolean;
+}
+
+export interface RestoreFailure {
+  path: string;
+  error: string;
+}
+
+export interface RestoreResult {
+  restored: string[];
+  // Files whose contents matched the manifest
+  verified: number;
+  failed: RestoreFailure[];
+}
+
+export type DiskImageFormat = 'dmg' | 'iso' | 'vhd' | 'vhdx';
+
+export interface DiskImageEntry {
+  name: string;
+  is_directory: boolean;
+  size: number | null;
+}
+
+export interface DiskImageInfo {
+  path: string;
+  format: DiskImageFormat;
+  file_size: number;
+  virtual_size: number | null;
+  volume_name: string | null;
+  creator: string | null;
+  entries: DiskImageEntry[];
+  listing_error: string | null;
+}
+
+export type VmDiskFormat = 'vdi' | 'vmdk' | 'qcow2' | 'hds' | 'vhd' | 'vhdx';
+
+export interface ArchiveLayer {
+  name: string;
+  size: number;
+}
+
+// What a large file is made of, for formats readable without a helper tool
+export type FileComposition =
+  | { kind: 'vm_disk'; format: VmDiskFormat; provisioned: number | null }
+  | { kind: 'sqlite'; page_size: number; page_count: number; free_pages: number }
+  // Largest layer first
+  | { kind: 'docker_archive'; layers: ArchiveLayer[] };
+
+export interface LargeFileAnalysis {
+  path: string;
+  size: number;
+  // Less than size for sparse files
+  allocated: number;
+  composition: FileComposition | null;
+  // Known for SQLite free pages; null where it depends on the guest or contents
+  reclaimable_by_compaction: number | null;
+}
+
+// How to compact a VM disk with its vendor's tool
+export interface CompactionPlan {
+  tool: string;
+  // When false, show the plan as instructions
+  available: boolean;
+  command_line: string;
+  preparation: LocalizedMessage;
+}
+
+export interface VmDisk {
+  path: string;
+  format: VmDiskFormat;
+  provisioned: number | null;
+  // Bytes allocated on the host
+  actual: number;
+  // null for QEMU images, which no tool compacts in place
+  compaction: CompactionPlan | null;
+}
+
+export type DataBucket =
+  | 'saved_scans'
+  | 'hash_cache'
+  | 'scripts'
+  | 'logs'
+  | 'caches'
+  | 'scan_cache'
+  | 'settings';
+
+export interface BucketUsage {
+  bucket: DataBucket;
+  path: string;
+  size: number;
+  file_count: number;
+}
+
+export interface AppDataUsage {
+  total_size: number;
+  buckets: BucketUsage[];
+}
+
+export interface PruneOptions {
+  keep_saved_scans: number | null;
+  script_max_age_days: number | null;
+  clear_logs: boolean;
+  // Also clears the scan cache
+  clear_caches: boolean;
+  compact_hash_cache: boolean;
+}
+
+export interface PruneResult {
+  freed_bytes: number;
+  files_removed: number;
+  hash_entries_dropped: number;
+  errors: string[];
+}
+
+export interface MemoryUsage {
+  scan_id: number;
+  nodes: number;
+  // Files counted only in their folder's totals, after compacting
+  dropped_files: number;
+  estimated_bytes: number;
+}
+
+export interface CompactResult {
+  scan_id: number;
+  files_dropped: number;
+  bytes_before: number;
+  bytes_after: number;
+}
+
+// Payload of csv-export-progress events
+export interface CsvExportProgress {
+  rows_written: number;
+  rows_total: number;
+  // After compression
+  bytes_written: number;
+  eta_secs: number;
+}
+
+export interface CsvExportResult {
+  destination: string;
+  rows_written: number;
+  bytes_written: number;
+  gzip: boolean;
+}
+
+// Whether sizes are counted in powers of 1024 (KiB, MiB) or 1000 (KB, MB)
+export type UnitSystem = 'binary' | 'decimal';
+
+export interface AppSettings {
+  unit_system: UnitSystem;
+  // null follows the system locale
+  decimal_separator: string | null;
+  trust_directory_mtimes: boolean;
+  // Saved scans and the hash cache are encrypted with a key kept in the OS keychain
+  encrypt_saved_data: boolean;
+  // Exported scans replace names with pseudonyms, keeping extensions and sizes
+  anonymize_exports: boolean;
+  // Refuse every action that changes user files; `--read-only` forces this on
+  read_only: boolean;
+  // Language tag for backend messages, e.g. 'de-DE'; null follows the system locale
+  language: string | null;
+  // Sort rankings of a million entries or more in memory-mapped temporary files
+  // (on a RAM disk where the OS has one) instead of on the heap
+  stage_large_sorts: boolean;
+  // Linux only: batch metadata reads through io_uring, falling back where unavailable
+  use_io_uring: boolean;
+  // Where caches, temporary files and saved scans go when their usual place is on a
+  // nearly full volume being scanned; null picks the roomiest other local, fixed volume
+  cache_location: string | null;
+  history_retention: HistoryRetention;
+}
+
+// Settings, protected paths, policies, webhooks and pins in one importable file
+export interface ConfigBundle {
+  version: number;
+  exported_at: number;
+  app_version: string;
+  settings: AppSettings;
+  never_suggest: NeverSuggestEntry[];
+  // Ids are reassigned on import
+  policies: CleanupPolicy[];
+  webhooks: WebhookSettings;
+  pinned_paths: string[];
+}
+
+// Nothing is pruned until keep_per_volume is set
+export interface HistoryRetention {
+  // Newest saved scans kept per volume; null keeps everything
+  keep_per_volume: number | null;
+  // Also keep the first scan of every month, so long-term trends survive pruning
+  keep_monthly_baselines: boolean;
+  // Prune after every saved scan instead of only on request
+  auto_compact: boolean;
+}
+
+// Saved scans of one volume
+export interface VolumeHistory {
+  volume: string;
+  scan_count: number;
+  bytes: number;
+  oldest_saved_at: number;
+  newest_saved_at: number;
+  // Scans the retention settings would delete
+  prunable_count: number;
+  prunable_bytes: number;
+}
+
+export interface HistoryStorageUsage {
+  scan_count: number;
+  bytes: number;
+  volumes: VolumeHistory[];
+  retention: HistoryRetention;
+}
+
+export interface HistoryPruneResult {
+  scans_removed: number;
+  freed_bytes: number;
+  errors: string[];
+}
+
+// Errors from refused actions start with this
+export const POLICY_DENIED = 'PolicyDenied';
+
+// Errors for folders a sandboxed build hasn't been granted start with this;
+// pick the folder with the open panel, then call grant_folder_command
+export const OUTSIDE_GRANTED_SCOPE = 'OutsideGrantedScope';
+
+// Errors for exports and saves that won't fit start with this, followed by ': ' and an
+// InsufficientSpace as JSON; nothing was written
+export const INSUFFICIENT_SPACE = 'InsufficientSpace';
+
+export interface InsufficientSpace {
+  destination: string;
+  required_bytes: number;
+  available_bytes: number;
+}
+
+// A folder kept accessible across launches in the sandboxed build
+export interface GrantedFolder {
+  path: string;
+  // Opaque security-scoped bookmark data
+  bookmark: number[];
+}
+
+export interface AccessMode {
+  read_only: boolean;
+  // Forced with `--read-only`, so the setting can't turn it off
+  forced_by_cli: boolean;
+}
+
+// An external program and where it was found on PATH (null when missing)
+export interface Integration {
+  name: string;
+  path: string | null;
+}
+
+export interface MountedFileSystem {
+  mount_point: string;
+  file_system: string;
+  removable: boolean;
+}
+
+// null where the question doesn't apply to this platform
+export interface GrantedPermissions {
+  full_disk_access: boolean | null;
+  elevated: boolean | null;
+}
+
+export interface FeatureAvailability {
+  feature: string;
+  available: boolean;
+  reason: string | null;
+}
+
+export interface EnvironmentReport {
+  app_version: string;
+  os: string;
+  kernel: string | null;
+  arch: string;
+  file_systems: MountedFileSystem[];
+  integrations: Integration[];
+  permissions: GrantedPermissions;
+  access: AccessMode;
+  features: FeatureAvailability[];
+}
+
+// Errors from external tools that are missing or blocked start with this
+export const TOOL_UNAVAILABLE = 'ToolUnavailable';
+
+// blocked: found, but the OS refused to start it
+export type ToolState = 'available' | 'missing' | 'blocked';
+
+export interface ToolCapability {
+  tool: string;
+  state: ToolState;
+  features: string[];
+  // How those features behave without the tool; null when they are unavailable
+  fallback: string | null;
+}
+
+// Restrictions set by an administrator on managed installs; empty lists restrict nothing
+export interface ManagedPolicy {
+  // Command names as invoked, e.g. 'delete_items_command'
+  disabled_commands: string[];
+  allowed_scan_roots: string[];
+}
+
+export interface EstimatedNode {
+  name: string;
+  path: string;
+  size: number;
+  is_directory: boolean;
+  entry_count: number;
+  // False when any part of size was extrapolated from samples
+  exact: boolean;
+  children: EstimatedNode[];
+}
+
+// Approximate top two levels of a volume, cached between launches;
+// refreshed lists arrive as 'volume-roots-updated' events
+export interface CachedVolumeRoot {
+  volume: string;
+  name: string;
+  // Unix timestamp (seconds) of the estimate
+  refreshed_at: number;
+  tree: EstimatedNode;
+}
+
+// Every figure in a sampled scan is an estimate
+export interface SampledDirectory {
+  path: string;
+  estimated_size: number;
+  size_low: number;
+  size_high: number;
+  estimated_files: number;
+}
+
+export interface SampledScan {
+  root: string;
+  strategy: ScanStrategy;
+  walks_per_directory: number;
+  confidence: number;
+  root_files_size: number;
+  estimated_total: number;
+  directories: SampledDirectory[];
+  duration_ms: number;
+}
+
+export interface FailedRepair {
+  path: string;
+  error: string;
+}
+
+// Outcome of take_ownership_command or fix_permissions_command
+export interface RepairResult {
+  // Entries that were already fine aren't counted
+  changed: number;
+  failed: FailedRepair[];
+}
+
+// Payload of the operation-stalled event
+export interface OperationStalled {
+  operation: string;
+  // The read in progress longest; usually a hanging network mount
+  path: string;
+  stalled_for_secs: number;
+  // Whether skip_stalled_path_command can abandon path
+  can_skip: boolean;
+}
+
+// A message on the --serve WebSocket (/api/events): the event name the desktop app
+// listens for, and its payload
+export interface ServerEvent<T = unknown> {
+  event: string;
+  payload: T;
+}
+
+export type WebhookFormat = 'json' | 'slack' | 'discord';
+
+export type WebhookEvent = 'scan_completed' | 'cleanup_executed' | 'low_space';
+
+export interface Webhook {
+  // 0 for a new webhook; assigned by save_webhook_command
+  id: number;
+  name: string;
+  url: string;
+  format: WebhookFormat;
+  events: WebhookEvent[];
+  enabled: boolean;
+}
+
+export interface WebhookSettings {
+  webhooks: Webhook[];
+  // A volume is low on space below this percentage free
+  low_space_percent: number;
+}
+
+// Body of a 'json' webhook is { source, sent_at, notification }
+export type Notification =
+  | { event: 'scan_completed'; root: string; files_scanned: number; total_size: number }
+  | {
+      event: 'cleanup_executed';
+      deleted: number;
+      failed: number;
+      space_freed: number;
+      // Moved to the trash instead, which frees nothing until it is emptied
+      space_trashed: number;
+    }
+  | { event: 'low_space'; volume: string; available_bytes: number; total_bytes: number }
+  | { event: 'test' };
+
+// API version this frontend is built against; compare with get_api_version_command
+export const API_VERSION = 2;
+
+// Payload of the api-deprecation event, sent the first time a deprecated command is used
+export interface DeprecationWarning {
+  command: string;
+  replacement: string | null;
+  deprecated_in: number;
+  // The first API version without the command
+  removed_in: number;
+  message: string;
+}
+
+export interface ApiVersion {
+  api_version: number;
+  min_supported: number;
+  app_version: string;
+  schema_version: number;
+  deprecations: DeprecationWarning[];
+}
+
+// A file whose extension disagrees with its content
+export interface MisnamedFile {
+  path: string;
+  size: number;
+  extension_type: FileType;
+  detected_type: FileType;
+  // e.g. 'mp4' or 'zip'
+  detected_format: string;
+}
+
+export interface MisnamedReport {
+  scan_id: number;
+  files_checked: number;
+  files: MisnamedFile[];
+}
+
+// Copy leaves the sources in place; move deletes them once every copy is verified
+export type MoveMode = 'copy' | 'move';
+
+// A resumable copy or move; also the payload of 'move-progress' events
+export interface MoveStatus {
+  id: number;
+  mode: MoveMode;
+  sources: string[];
+  destination: string;
+  files_total: number;
+  files_done: number;
+  bytes_total: number;
+  bytes_done: number;
+  created_at: number;
+  finished: boolean;
+  // Sources a finished move didn't delete: changed since they were copied, not empty, or refused
+  left_behind: string[];
+  // Set while the job runs
+  current: string | null;
+}
+
+export interface EjectResult {
+  volume: string;
+  // Pending writes were flushed to the drive
+  flushed: boolean;
+  ejected: boolean;
+  // In-app operations still using the volume; it isn't ejected while there are any unless forced
+  busy: LocalizedMessage[];
 }